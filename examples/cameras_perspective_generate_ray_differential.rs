@@ -50,8 +50,10 @@ fn main() {
         filter,
         35.0,
         filename,
+        8,
         1.0,
         std::f32::INFINITY,
+        Vec::new(),
     ));
     let pos = Point3f {
         x: 2.0,
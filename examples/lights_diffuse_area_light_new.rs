@@ -31,8 +31,8 @@ fn main() {
     let inner_radius: Float = 0.0;
     let phi_max: Float = 360.0;
     let shape: Arc<Shape + Send + Sync> = Arc::new(Disk::new(
-        light_to_world,
-        inverse,
+        Arc::new(light_to_world),
+        Arc::new(inverse),
         false,
         false,
         height,
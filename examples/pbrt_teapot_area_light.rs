@@ -3,6 +3,7 @@ extern crate pbrt;
 use pbrt::accelerators::bvh::{BVHAccel, SplitMethod};
 use pbrt::cameras::perspective::PerspectiveCamera;
 use pbrt::core::camera::Camera;
+use pbrt::core::checkpoint::Checkpoint;
 use pbrt::core::film::Film;
 use pbrt::core::filter::Filter;
 use pbrt::core::geometry::{
@@ -74,8 +75,8 @@ impl SceneDescriptionBuilder {
         uv: Vec<Point2f>,
     ) -> &mut SceneDescriptionBuilder {
         let triangle_mesh = Arc::new(TriangleMesh::new(
-            object_to_world,
-            world_to_object,
+            Arc::new(object_to_world),
+            Arc::new(world_to_object),
             false,
             false,
             n_triangles,
@@ -99,8 +100,8 @@ impl SceneDescriptionBuilder {
         phi_max: Float,
     ) -> &mut SceneDescriptionBuilder {
         let disk = Arc::new(Disk::new(
-            object_to_world,
-            world_to_object,
+            Arc::new(object_to_world),
+            Arc::new(world_to_object),
             false,
             false,
             height,
@@ -147,8 +148,8 @@ impl RenderOptions {
             // create individual triangles
             for id in 0..mesh.n_triangles {
                 let triangle = Arc::new(Triangle::new(
-                    mesh.object_to_world,
-                    mesh.world_to_object,
+                    mesh.object_to_world.clone(),
+                    mesh.world_to_object.clone(),
                     mesh.transform_swaps_handedness,
                     mesh.clone(),
                     id,
@@ -2032,8 +2033,10 @@ fn main() {
         filter,
         35.0,
         filename,
+        8,
         1.0,
         std::f32::INFINITY,
+        Vec::new(),
     ));
     let camera: Arc<Camera + Send + Sync> = Arc::new(PerspectiveCamera::new(
         animated_cam_to_world,
@@ -2051,5 +2054,12 @@ fn main() {
     let mut integrator: Box<SamplerIntegrator + Send + Sync> = Box::new(
         DirectLightingIntegrator::new(LightStrategy::UniformSampleAll, 10, sample_bounds),
     );
-    render(&scene, &camera, &mut sampler, &mut integrator, 0_u8);
+    render(
+        &scene,
+        &camera,
+        &mut sampler,
+        &mut integrator,
+        0_u8,
+        &Checkpoint::default(),
+    );
 }
@@ -0,0 +1,173 @@
+extern crate pbrt;
+
+// pbrt
+use pbrt::core::api::RenderSetup;
+use pbrt::core::geometry::{Point2f, Point3f, Vector3f};
+use pbrt::core::pbrt::Spectrum;
+use pbrt::core::scenebuilder::{CameraKind, FilmSettings, IntegratorKind, LightKind};
+use pbrt::core::scenebuilder::{MaterialKind, SamplerKind, SceneBuilder, ShapeKind};
+use pbrt::core::transform::Transform;
+// std
+use std::process;
+
+fn main() {
+    let mut builder: SceneBuilder = SceneBuilder::new(0_u8);
+    let result = build_scene(&mut builder);
+    if let Err(e) = result {
+        println!("ERROR: {}", e);
+        process::exit(1);
+    }
+    match builder.build() {
+        Ok(mut render_setup) => {
+            let setup: &mut RenderSetup = &mut render_setup;
+            setup.render();
+        }
+        Err(e) => {
+            println!("ERROR: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn build_scene(builder: &mut SceneBuilder) -> Result<(), pbrt::core::api::ApiError> {
+    // render options
+    let world_to_camera: Transform = Transform::look_at(
+        &Point3f {
+            x: 0.0,
+            y: 2.0,
+            z: 8.0,
+        },
+        &Point3f {
+            x: 0.0,
+            y: 0.5,
+            z: 0.0,
+        },
+        &Vector3f {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+    );
+    builder.camera(CameraKind::Perspective { fov: 40.0 }, &world_to_camera)?;
+    builder.film(FilmSettings {
+        x_resolution: 400,
+        y_resolution: 300,
+        filename: String::from("scene_builder.png"),
+    })?;
+    builder.sampler(SamplerKind::Sobol { pixel_samples: 16 })?;
+    builder.integrator(IntegratorKind::Path { max_depth: 5 })?;
+    // lights
+    builder.light(
+        &Transform::default(),
+        LightKind::Infinite {
+            radiance: Spectrum::new(0.2),
+            n_samples: 4,
+            map_name: None,
+        },
+    )?;
+    builder.area_light(
+        &Transform::translate(&Vector3f {
+            x: 0.0,
+            y: 6.0,
+            z: 2.0,
+        }),
+        ShapeKind::Sphere { radius: 0.5 },
+        MaterialKind::Matte {
+            kd: Spectrum::new(0.0),
+            sigma: 0.0,
+        },
+        Spectrum::new(20.0),
+        false,
+    )?;
+    // a ground plane made of two triangles
+    builder.named_material(
+        "ground",
+        MaterialKind::Matte {
+            kd: Spectrum::new(0.4),
+            sigma: 0.0,
+        },
+    )?;
+    builder.shape(
+        &Transform::default(),
+        ShapeKind::TriangleMesh {
+            indices: vec![0, 1, 2, 0, 2, 3],
+            p: vec![
+                Point3f {
+                    x: -10.0,
+                    y: 0.0,
+                    z: -10.0,
+                },
+                Point3f {
+                    x: 10.0,
+                    y: 0.0,
+                    z: -10.0,
+                },
+                Point3f {
+                    x: 10.0,
+                    y: 0.0,
+                    z: 10.0,
+                },
+                Point3f {
+                    x: -10.0,
+                    y: 0.0,
+                    z: 10.0,
+                },
+            ],
+            n: Vec::new(),
+            uv: vec![
+                Point2f { x: 0.0, y: 0.0 },
+                Point2f { x: 1.0, y: 0.0 },
+                Point2f { x: 1.0, y: 1.0 },
+                Point2f { x: 0.0, y: 1.0 },
+            ],
+        },
+        MaterialKind::Named(String::from("ground")),
+    )?;
+    // three instances of a glass sphere resting on a plastic disk
+    builder.object(
+        "ball",
+        vec![
+            (
+                Transform::translate(&Vector3f {
+                    x: 0.0,
+                    y: 0.75,
+                    z: 0.0,
+                }),
+                ShapeKind::Sphere { radius: 0.75 },
+                MaterialKind::Glass {
+                    kr: Spectrum::new(1.0),
+                    kt: Spectrum::new(1.0),
+                    eta: 1.5,
+                },
+            ),
+            (
+                Transform::translate(&Vector3f {
+                    x: 0.0,
+                    y: 0.01,
+                    z: 0.0,
+                }) * Transform::rotate_x(-90.0),
+                ShapeKind::Disk {
+                    radius: 1.0,
+                    height: 0.0,
+                },
+                MaterialKind::Plastic {
+                    kd: Spectrum::new(0.1),
+                    ks: Spectrum::new(0.5),
+                    roughness: 0.05,
+                },
+            ),
+        ],
+    )?;
+    for i in 0..3 {
+        let x: f32 = (i as f32 - 1.0) * 2.5;
+        builder.object_instance(
+            &Transform::translate(&Vector3f {
+                x: x,
+                y: 0.0,
+                z: 0.0,
+            }),
+            "ball",
+        )?;
+    }
+    Ok(())
+}
@@ -4,6 +4,7 @@ use pbrt::core::geometry::Vector3f;
 use pbrt::core::pbrt::Float;
 use pbrt::core::transform::Transform;
 use pbrt::shapes::disk::Disk;
+use std::sync::Arc;
 
 fn main() {
     let translate: Transform = Transform::translate(&Vector3f {
@@ -17,8 +18,8 @@ fn main() {
     let inner_radius: Float = 0.0;
     let phi_max: Float = 360.0;
     let disk = Disk::new(
-        translate,
-        inverse,
+        Arc::new(translate),
+        Arc::new(inverse),
         false,
         false,
         height,
@@ -4,6 +4,7 @@ use pbrt::core::geometry::Vector3f;
 use pbrt::core::pbrt::Float;
 use pbrt::core::transform::Transform;
 use pbrt::shapes::sphere::Sphere;
+use std::sync::Arc;
 
 fn main() {
     let translate: Transform = Transform::translate(&Vector3f {
@@ -17,7 +18,14 @@ fn main() {
     let z_max: Float = 1.0;
     let phi_max: Float = 360.0;
     let sphere = Sphere::new(
-        translate, inverse, false, false, radius, z_min, z_max, phi_max,
+        Arc::new(translate),
+        Arc::new(inverse),
+        false,
+        false,
+        radius,
+        z_min,
+        z_max,
+        phi_max,
     );
     println!("translate = {:?}", translate);
     println!("inverse = {:?}", inverse);
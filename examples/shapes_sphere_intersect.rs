@@ -5,6 +5,7 @@ use pbrt::core::pbrt::Float;
 use pbrt::core::shape::Shape;
 use pbrt::core::transform::Transform;
 use pbrt::shapes::sphere::Sphere;
+use std::sync::Arc;
 
 fn main() {
     // see CreateSphereShape() in sphere.cpp
@@ -19,7 +20,14 @@ fn main() {
     });
     let inverse: Transform = Transform::inverse(&translate);
     let sphere: Sphere = Sphere::new(
-        translate, inverse, false, false, radius, z_min, z_max, phi_max,
+        Arc::new(translate),
+        Arc::new(inverse),
+        false,
+        false,
+        radius,
+        z_min,
+        z_max,
+        phi_max,
     );
     // see Sphere::Intersect() in sphere.cpp
     let o: Point3f = Point3f {
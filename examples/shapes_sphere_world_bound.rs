@@ -5,6 +5,7 @@ use pbrt::core::pbrt::Float;
 use pbrt::core::shape::Shape;
 use pbrt::core::transform::Transform;
 use pbrt::shapes::sphere::Sphere;
+use std::sync::Arc;
 
 fn main() {
     let radius: Float = 1.0;
@@ -18,7 +19,14 @@ fn main() {
     });
     let inverse: Transform = Transform::inverse(&translate);
     let sphere: Sphere = Sphere::new(
-        translate, inverse, false, false, radius, z_min, z_max, phi_max,
+        Arc::new(translate),
+        Arc::new(inverse),
+        false,
+        false,
+        radius,
+        z_min,
+        z_max,
+        phi_max,
     );
     let world_bound: Bounds3f = sphere.world_bound(); // Primitive
                                                       // println!("sphere() = {:?}", sphere);
@@ -30,7 +38,14 @@ fn main() {
     });
     let inverse: Transform = Transform::inverse(&translate);
     let sphere: Sphere = Sphere::new(
-        translate, inverse, false, false, radius, z_min, z_max, phi_max,
+        Arc::new(translate),
+        Arc::new(inverse),
+        false,
+        false,
+        radius,
+        z_min,
+        z_max,
+        phi_max,
     );
     let world_bound: Bounds3f = sphere.world_bound(); // Primitive
                                                       // println!("sphere() = {:?}", sphere);
@@ -46,8 +46,8 @@ fn main() {
         Point2f { x: 1.0, y: 1.0 },
     ];
     let triangle_mesh: TriangleMesh = TriangleMesh::new(
-        translate,
-        inverse,
+        Arc::new(translate),
+        Arc::new(inverse),
         false,
         false,
         n_triangles,
@@ -63,8 +63,8 @@ fn main() {
     println!("triangle_mesh = {:?}", triangle_mesh);
     for id in 0..triangle_mesh.n_triangles {
         let triangle = Triangle::new(
-            triangle_mesh.object_to_world,
-            triangle_mesh.world_to_object,
+            triangle_mesh.object_to_world.clone(),
+            triangle_mesh.world_to_object.clone(),
             triangle_mesh.transform_swaps_handedness,
             Arc::new(triangle_mesh.clone()),
             id,
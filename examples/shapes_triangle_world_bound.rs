@@ -37,12 +37,12 @@ fn main() {
         Point2f { x: 0.0, y: 1.0 },
         Point2f { x: 1.0, y: 1.0 },
     ];
-    let object_to_world: Transform = Transform::translate(&Vector3f {
+    let object_to_world: Arc<Transform> = Arc::new(Transform::translate(&Vector3f {
         x: 0.25,
         y: 0.0,
         z: 0.0,
-    });
-    let world_to_object: Transform = Transform::inverse(&object_to_world);
+    }));
+    let world_to_object: Arc<Transform> = Arc::new(Transform::inverse(&object_to_world));
     let mut p_ws: Vec<Point3f> = Vec::new();
     let n_vertices: usize = p.len();
     for i in 0..n_vertices {
@@ -51,8 +51,8 @@ fn main() {
     let s: Vec<Vector3f> = Vec::new();
     let n: Vec<Normal3f> = Vec::new();
     let triangle_mesh = Arc::new(TriangleMesh::new(
-        object_to_world,
-        world_to_object,
+        object_to_world.clone(),
+        world_to_object.clone(),
         false,
         false,
         n_triangles,
@@ -66,8 +66,8 @@ fn main() {
     let mut tris: Vec<Arc<Triangle>> = Vec::new();
     for i in 0..n_triangles {
         let triangle = Arc::new(Triangle::new(
-            object_to_world,
-            world_to_object,
+            object_to_world.clone(),
+            world_to_object.clone(),
             false,
             triangle_mesh.clone(),
             i,
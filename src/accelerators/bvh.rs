@@ -13,6 +13,7 @@ use core::material::Material;
 use core::paramset::ParamSet;
 use core::pbrt::Float;
 use core::primitive::Primitive;
+use core::profiler::{Prof, ProfilePhase};
 
 // see bvh.h
 
@@ -125,6 +126,7 @@ impl BVHAccel {
         max_prims_in_node: usize,
         split_method: SplitMethod,
     ) -> Self {
+        let _p = ProfilePhase::new(Prof::AccelConstruction);
         let bvh = Arc::new(BVHAccel {
             max_prims_in_node: std::cmp::min(max_prims_in_node, 255),
             split_method: split_method.clone(),
@@ -421,7 +423,7 @@ impl Primitive for BVHAccel {
         if self.nodes.len() == 0 {
             return None;
         }
-        // TODO: ProfilePhase p(Prof::AccelIntersect);
+        let _p = ProfilePhase::new(Prof::AccelIntersect);
         let mut hit: bool = false;
         let inv_dir: Vector3f = Vector3f {
             x: 1.0 / ray.d.x,
@@ -489,7 +491,7 @@ impl Primitive for BVHAccel {
         if self.nodes.len() == 0 {
             return false;
         }
-        // TODO: ProfilePhase p(Prof::AccelIntersectP);
+        let _p = ProfilePhase::new(Prof::AccelIntersectP);
         let inv_dir: Vector3f = Vector3f {
             x: 1.0 / ray.d.x,
             y: 1.0 / ray.d.y,
@@ -0,0 +1,576 @@
+// std
+use std::sync::Arc;
+// others
+use time::PreciseTime;
+// pbrt
+use core::geometry::bnd3_union_bnd3;
+use core::geometry::{Bounds3f, Ray, Vector3f};
+use core::interaction::SurfaceInteraction;
+use core::light::AreaLight;
+use core::material::Material;
+use core::paramset::ParamSet;
+use core::pbrt::log_2_int_i32;
+use core::pbrt::Float;
+use core::primitive::Primitive;
+use core::profiler::{Prof, ProfilePhase};
+
+// see kdtreeaccel.h
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct KdAccelNode {
+    // in C++ a union { Float split;                   // interior
+    //                  int onePrimitive;              // leaf
+    //                  int primitiveIndicesOffset; }; // leaf
+    split: Float,
+    one_primitive: usize,
+    primitive_indices_offset: usize,
+    // in C++ a union { int flags;       // both
+    //                  int nPrims;      // leaf
+    //                  int aboveChild; }; // interior
+    flags: u8, // 0, 1, 2 for the split axis, 3 for leaves
+    n_prims: usize,
+    above_child: usize,
+}
+
+impl KdAccelNode {
+    pub fn init_leaf(
+        &mut self,
+        prim_nums: &[usize],
+        np: usize,
+        primitive_indices: &mut Vec<usize>,
+    ) {
+        self.flags = 3;
+        self.n_prims = np;
+        // store primitive ids for leaf node
+        if np == 0 {
+            self.one_primitive = 0;
+        } else if np == 1 {
+            self.one_primitive = prim_nums[0];
+        } else {
+            self.primitive_indices_offset = primitive_indices.len();
+            for i in 0..np {
+                primitive_indices.push(prim_nums[i]);
+            }
+        }
+    }
+    pub fn init_interior(&mut self, axis: u8, ac: usize, s: Float) {
+        self.split = s;
+        self.flags = axis;
+        self.above_child = ac;
+    }
+    pub fn split_pos(&self) -> Float {
+        self.split
+    }
+    pub fn n_primitives(&self) -> usize {
+        self.n_prims
+    }
+    pub fn split_axis(&self) -> u8 {
+        self.flags
+    }
+    pub fn is_leaf(&self) -> bool {
+        self.flags == 3
+    }
+    pub fn above_child(&self) -> usize {
+        self.above_child
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum EdgeType {
+    Start = 0,
+    End = 1,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct BoundEdge {
+    t: Float,
+    prim_num: usize,
+    edge_type: EdgeType,
+}
+
+impl Default for BoundEdge {
+    fn default() -> Self {
+        BoundEdge {
+            t: 0.0,
+            prim_num: 0_usize,
+            edge_type: EdgeType::Start,
+        }
+    }
+}
+
+impl BoundEdge {
+    pub fn new(t: Float, prim_num: usize, starting: bool) -> Self {
+        BoundEdge {
+            t: t,
+            prim_num: prim_num,
+            edge_type: if starting {
+                EdgeType::Start
+            } else {
+                EdgeType::End
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+struct KdToDo {
+    node: usize,
+    t_min: Float,
+    t_max: Float,
+}
+
+/// Size of the stack of nodes still to visit during traversal, which
+/// limits the depth of the tree.
+pub const MAX_TODO: usize = 64;
+
+// KdTreeAccel -> Aggregate -> Primitive
+pub struct KdTreeAccel {
+    isect_cost: i32,
+    traversal_cost: i32,
+    max_prims: i32,
+    empty_bonus: Float,
+    pub primitives: Vec<Arc<Primitive + Sync + Send>>,
+    primitive_indices: Vec<usize>,
+    pub nodes: Vec<KdAccelNode>,
+    bounds: Bounds3f,
+}
+
+impl KdTreeAccel {
+    pub fn new(
+        p: Vec<Arc<Primitive + Sync + Send>>,
+        isect_cost: i32,
+        traversal_cost: i32,
+        empty_bonus: Float,
+        max_prims: i32,
+        max_depth: i32,
+    ) -> Self {
+        let num_prims: usize = p.len();
+        let mut kd_tree: KdTreeAccel = KdTreeAccel {
+            isect_cost: isect_cost,
+            traversal_cost: traversal_cost,
+            max_prims: max_prims,
+            empty_bonus: empty_bonus,
+            primitives: p,
+            primitive_indices: Vec::new(),
+            nodes: Vec::new(),
+            bounds: Bounds3f::default(),
+        };
+        if num_prims == 0 {
+            return kd_tree;
+        }
+        // build kd-tree for accelerator
+        let _p = ProfilePhase::new(Prof::AccelConstruction);
+        let max_depth: i32 = if max_depth <= 0 {
+            (8.0 as Float + 1.3 as Float * log_2_int_i32(num_prims as i32) as Float).round() as i32
+        } else {
+            max_depth
+        }
+        .min(MAX_TODO as i32);
+        // compute bounds for kd-tree construction
+        let mut prim_bounds: Vec<Bounds3f> = Vec::with_capacity(num_prims);
+        for (i, prim) in kd_tree.primitives.iter().enumerate() {
+            let b: Bounds3f = prim.world_bound();
+            if i == 0 {
+                kd_tree.bounds = b;
+            } else {
+                kd_tree.bounds = bnd3_union_bnd3(&kd_tree.bounds, &b);
+            }
+            prim_bounds.push(b);
+        }
+        // allocate working memory for kd-tree construction
+        let mut edges: [Vec<BoundEdge>; 3] = [
+            vec![BoundEdge::default(); 2 * num_prims],
+            vec![BoundEdge::default(); 2 * num_prims],
+            vec![BoundEdge::default(); 2 * num_prims],
+        ];
+        // initialize _prim_nums_ for kd-tree construction
+        let prim_nums: Vec<usize> = (0..num_prims).collect();
+        // start recursive construction of kd-tree
+        println!("KdTreeAccel::build_tree(..., {}, ...)", num_prims);
+        let start = PreciseTime::now();
+        let bounds: Bounds3f = kd_tree.bounds;
+        kd_tree.build_tree(
+            0,
+            &bounds,
+            &prim_bounds,
+            &prim_nums,
+            max_depth,
+            &mut edges,
+            0,
+        );
+        let end = PreciseTime::now();
+        println!("{} seconds for building kd-tree ...", start.to(end));
+        kd_tree
+    }
+    pub fn create(prims: Vec<Arc<Primitive + Send + Sync>>, ps: &ParamSet) -> Arc<KdTreeAccel> {
+        let isect_cost: i32 = ps.find_one_int("intersectcost", 80);
+        let trav_cost: i32 = ps.find_one_int("traversalcost", 1);
+        let empty_bonus: Float = ps.find_one_float("emptybonus", 0.5);
+        let max_prims: i32 = ps.find_one_int("maxprims", 1);
+        let max_depth: i32 = ps.find_one_int("maxdepth", -1);
+        Arc::new(KdTreeAccel::new(
+            prims.clone(),
+            isect_cost,
+            trav_cost,
+            empty_bonus,
+            max_prims,
+            max_depth,
+        ))
+    }
+    fn build_tree(
+        &mut self,
+        node_num: usize,
+        node_bounds: &Bounds3f,
+        all_prim_bounds: &Vec<Bounds3f>,
+        prim_nums: &[usize],
+        depth: i32,
+        edges: &mut [Vec<BoundEdge>; 3],
+        bad_refines: i32,
+    ) {
+        assert_eq!(node_num, self.nodes.len());
+        // get next free node from _nodes_ array
+        self.nodes.push(KdAccelNode::default());
+        let n_primitives: usize = prim_nums.len();
+        // initialize leaf node if termination criteria met
+        if n_primitives <= self.max_prims as usize || depth == 0 {
+            self.nodes[node_num].init_leaf(prim_nums, n_primitives, &mut self.primitive_indices);
+            return;
+        }
+        // initialize interior node and continue recursion
+
+        // choose split axis position for interior node
+        let mut best_axis: Option<u8> = None;
+        let mut best_offset: usize = 0;
+        let mut best_cost: Float = std::f32::INFINITY as Float;
+        let old_cost: Float = self.isect_cost as Float * n_primitives as Float;
+        let total_sa: Float = node_bounds.surface_area();
+        let inv_total_sa: Float = 1.0 as Float / total_sa;
+        let d: Vector3f = node_bounds.p_max - node_bounds.p_min;
+        // choose which axis to split along
+        let mut axis: u8 = node_bounds.maximum_extent();
+        let mut retries: usize = 0;
+        loop {
+            // initialize edges for _axis_
+            let axis_edges: &mut Vec<BoundEdge> = &mut edges[axis as usize];
+            for (i, pn) in prim_nums.iter().enumerate() {
+                let bounds: &Bounds3f = &all_prim_bounds[*pn];
+                axis_edges[2 * i] = BoundEdge::new(bounds.p_min[axis], *pn, true);
+                axis_edges[2 * i + 1] = BoundEdge::new(bounds.p_max[axis], *pn, false);
+            }
+            // sort _edges_ for _axis_
+            axis_edges[0..2 * n_primitives].sort_by(|e0, e1| {
+                if e0.t == e1.t {
+                    (e0.edge_type as u8).cmp(&(e1.edge_type as u8))
+                } else {
+                    e0.t.partial_cmp(&e1.t).unwrap()
+                }
+            });
+            // compute cost of all splits for _axis_ to find best
+            let mut n_below: usize = 0;
+            let mut n_above: usize = n_primitives;
+            for i in 0..2 * n_primitives {
+                if axis_edges[i].edge_type == EdgeType::End {
+                    n_above -= 1;
+                }
+                let edge_t: Float = axis_edges[i].t;
+                if edge_t > node_bounds.p_min[axis] && edge_t < node_bounds.p_max[axis] {
+                    // compute cost for split at _i_th edge
+
+                    // compute child surface areas for split at _edge_t_
+                    let other_axis_0: u8 = (axis + 1) % 3;
+                    let other_axis_1: u8 = (axis + 2) % 3;
+                    let below_sa: Float = 2.0
+                        * (d[other_axis_0] * d[other_axis_1]
+                            + (edge_t - node_bounds.p_min[axis])
+                                * (d[other_axis_0] + d[other_axis_1]));
+                    let above_sa: Float = 2.0
+                        * (d[other_axis_0] * d[other_axis_1]
+                            + (node_bounds.p_max[axis] - edge_t)
+                                * (d[other_axis_0] + d[other_axis_1]));
+                    let p_below: Float = below_sa * inv_total_sa;
+                    let p_above: Float = above_sa * inv_total_sa;
+                    let eb: Float = if n_above == 0 || n_below == 0 {
+                        self.empty_bonus
+                    } else {
+                        0.0
+                    };
+                    let cost: Float = self.traversal_cost as Float
+                        + self.isect_cost as Float
+                            * (1.0 - eb)
+                            * (p_below * n_below as Float + p_above * n_above as Float);
+                    // update best split if this is lowest cost so far
+                    if cost < best_cost {
+                        best_cost = cost;
+                        best_axis = Some(axis);
+                        best_offset = i;
+                    }
+                }
+                if axis_edges[i].edge_type == EdgeType::Start {
+                    n_below += 1;
+                }
+            }
+            assert!(n_below == n_primitives && n_above == 0);
+            // create leaf if no good splits were found
+            if best_axis.is_none() && retries < 2 {
+                retries += 1;
+                axis = (axis + 1) % 3;
+                continue;
+            }
+            break;
+        }
+        let bad_refines: i32 = if best_cost > old_cost {
+            bad_refines + 1
+        } else {
+            bad_refines
+        };
+        let best_axis: u8 = match best_axis {
+            Some(best_axis)
+                if !((best_cost > 4.0 * old_cost && n_primitives < 16) || bad_refines == 3) =>
+            {
+                best_axis
+            }
+            _ => {
+                self.nodes[node_num].init_leaf(
+                    prim_nums,
+                    n_primitives,
+                    &mut self.primitive_indices,
+                );
+                return;
+            }
+        };
+        // classify primitives with respect to split
+        let mut prims0: Vec<usize> = Vec::with_capacity(n_primitives);
+        let mut prims1: Vec<usize> = Vec::with_capacity(n_primitives);
+        {
+            let axis_edges: &Vec<BoundEdge> = &edges[best_axis as usize];
+            for i in 0..best_offset {
+                if axis_edges[i].edge_type == EdgeType::Start {
+                    prims0.push(axis_edges[i].prim_num);
+                }
+            }
+            for i in (best_offset + 1)..(2 * n_primitives) {
+                if axis_edges[i].edge_type == EdgeType::End {
+                    prims1.push(axis_edges[i].prim_num);
+                }
+            }
+        }
+        // recursively initialize children nodes
+        let t_split: Float = edges[best_axis as usize][best_offset].t;
+        let mut bounds0: Bounds3f = *node_bounds;
+        let mut bounds1: Bounds3f = *node_bounds;
+        bounds0.p_max[best_axis] = t_split;
+        bounds1.p_min[best_axis] = t_split;
+        self.build_tree(
+            node_num + 1,
+            &bounds0,
+            all_prim_bounds,
+            &prims0,
+            depth - 1,
+            edges,
+            bad_refines,
+        );
+        let above_child: usize = self.nodes.len();
+        self.nodes[node_num].init_interior(best_axis, above_child, t_split);
+        self.build_tree(
+            above_child,
+            &bounds1,
+            all_prim_bounds,
+            &prims1,
+            depth - 1,
+            edges,
+            bad_refines,
+        );
+    }
+    /// Returns the parametric range of the ray overlapping the
+    /// kd-tree's bounds (if any).
+    fn ray_range(&self, ray: &Ray) -> Option<(Float, Float)> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut t_min: Float = 0.0;
+        let mut t_max: Float = 0.0;
+        if self.bounds.intersect_b(ray, &mut t_min, &mut t_max) {
+            Some((t_min, t_max))
+        } else {
+            None
+        }
+    }
+}
+
+impl Primitive for KdTreeAccel {
+    fn world_bound(&self) -> Bounds3f {
+        self.bounds
+    }
+    fn intersect(&self, ray: &mut Ray) -> Option<SurfaceInteraction> {
+        let _p = ProfilePhase::new(Prof::AccelIntersect);
+        // compute initial parametric range of ray inside kd-tree extent
+        let (mut t_min, mut t_max) = match self.ray_range(ray) {
+            Some(range) => range,
+            None => return None,
+        };
+        // prepare to traverse kd-tree for ray
+        let inv_dir: Vector3f = Vector3f {
+            x: 1.0 / ray.d.x,
+            y: 1.0 / ray.d.y,
+            z: 1.0 / ray.d.z,
+        };
+        let mut todo: [KdToDo; MAX_TODO] = [KdToDo::default(); MAX_TODO];
+        let mut todo_pos: usize = 0;
+        // traverse kd-tree nodes in order for ray
+        let mut si: Option<SurfaceInteraction> = None;
+        let mut node_index: usize = 0;
+        loop {
+            // bail out if we found a hit closer than the current node
+            if ray.t_max < t_min {
+                break;
+            }
+            let node: &KdAccelNode = &self.nodes[node_index];
+            if !node.is_leaf() {
+                // process kd-tree interior node
+
+                // compute parametric distance along ray to split plane
+                let axis: u8 = node.split_axis();
+                let t_plane: Float = (node.split_pos() - ray.o[axis]) * inv_dir[axis];
+                // get node children pointers for ray
+                let below_first: bool = (ray.o[axis] < node.split_pos())
+                    || (ray.o[axis] == node.split_pos() && ray.d[axis] <= 0.0);
+                let (first_child, second_child) = if below_first {
+                    (node_index + 1, node.above_child())
+                } else {
+                    (node.above_child(), node_index + 1)
+                };
+                // advance to next child node, possibly enqueue other child
+                if t_plane > t_max || t_plane <= 0.0 {
+                    node_index = first_child;
+                } else if t_plane < t_min {
+                    node_index = second_child;
+                } else {
+                    // enqueue _second_child_ in todo list
+                    todo[todo_pos] = KdToDo {
+                        node: second_child,
+                        t_min: t_plane,
+                        t_max: t_max,
+                    };
+                    todo_pos += 1;
+                    node_index = first_child;
+                    t_max = t_plane;
+                }
+            } else {
+                // check for intersections inside leaf node
+                let n_primitives: usize = node.n_primitives();
+                if n_primitives == 1 {
+                    let p: &Arc<Primitive + Sync + Send> = &self.primitives[node.one_primitive];
+                    // check one primitive inside leaf node
+                    if let Some(isect) = p.intersect(ray) {
+                        si = Some(isect);
+                    }
+                } else {
+                    for i in 0..n_primitives {
+                        let index: usize =
+                            self.primitive_indices[node.primitive_indices_offset + i];
+                        let p: &Arc<Primitive + Sync + Send> = &self.primitives[index];
+                        // check one primitive inside leaf node
+                        if let Some(isect) = p.intersect(ray) {
+                            si = Some(isect);
+                        }
+                    }
+                }
+                // grab next node to process from todo list
+                if todo_pos > 0 {
+                    todo_pos -= 1;
+                    node_index = todo[todo_pos].node;
+                    t_min = todo[todo_pos].t_min;
+                    t_max = todo[todo_pos].t_max;
+                } else {
+                    break;
+                }
+            }
+        }
+        si
+    }
+    fn intersect_p(&self, ray: &Ray) -> bool {
+        let _p = ProfilePhase::new(Prof::AccelIntersectP);
+        // compute initial parametric range of ray inside kd-tree extent
+        let (mut t_min, mut t_max) = match self.ray_range(ray) {
+            Some(range) => range,
+            None => return false,
+        };
+        // prepare to traverse kd-tree for ray
+        let inv_dir: Vector3f = Vector3f {
+            x: 1.0 / ray.d.x,
+            y: 1.0 / ray.d.y,
+            z: 1.0 / ray.d.z,
+        };
+        let mut todo: [KdToDo; MAX_TODO] = [KdToDo::default(); MAX_TODO];
+        let mut todo_pos: usize = 0;
+        let mut node_index: usize = 0;
+        loop {
+            let node: &KdAccelNode = &self.nodes[node_index];
+            if node.is_leaf() {
+                // check for shadow ray intersections inside leaf node
+                let n_primitives: usize = node.n_primitives();
+                if n_primitives == 1 {
+                    let p: &Arc<Primitive + Sync + Send> = &self.primitives[node.one_primitive];
+                    if p.intersect_p(ray) {
+                        return true;
+                    }
+                } else {
+                    for i in 0..n_primitives {
+                        let index: usize =
+                            self.primitive_indices[node.primitive_indices_offset + i];
+                        let p: &Arc<Primitive + Sync + Send> = &self.primitives[index];
+                        if p.intersect_p(ray) {
+                            return true;
+                        }
+                    }
+                }
+                // grab next node to process from todo list
+                if todo_pos > 0 {
+                    todo_pos -= 1;
+                    node_index = todo[todo_pos].node;
+                    t_min = todo[todo_pos].t_min;
+                    t_max = todo[todo_pos].t_max;
+                } else {
+                    break;
+                }
+            } else {
+                // process kd-tree interior node
+
+                // compute parametric distance along ray to split plane
+                let axis: u8 = node.split_axis();
+                let t_plane: Float = (node.split_pos() - ray.o[axis]) * inv_dir[axis];
+                // get node children pointers for ray
+                let below_first: bool = (ray.o[axis] < node.split_pos())
+                    || (ray.o[axis] == node.split_pos() && ray.d[axis] <= 0.0);
+                let (first_child, second_child) = if below_first {
+                    (node_index + 1, node.above_child())
+                } else {
+                    (node.above_child(), node_index + 1)
+                };
+                // advance to next child node, possibly enqueue other child
+                if t_plane > t_max || t_plane <= 0.0 {
+                    node_index = first_child;
+                } else if t_plane < t_min {
+                    node_index = second_child;
+                } else {
+                    // enqueue _second_child_ in todo list
+                    todo[todo_pos] = KdToDo {
+                        node: second_child,
+                        t_min: t_plane,
+                        t_max: t_max,
+                    };
+                    todo_pos += 1;
+                    node_index = first_child;
+                    t_max = t_plane;
+                }
+            }
+        }
+        false
+    }
+    fn get_material(&self) -> Option<Arc<Material + Send + Sync>> {
+        None
+    }
+    fn get_area_light(&self) -> Option<Arc<AreaLight + Send + Sync>> {
+        None
+    }
+}
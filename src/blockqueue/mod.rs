@@ -51,6 +51,11 @@ impl BlockQueue {
             next: AtomicUsize::new(0),
         }
     }
+    /// Remove the blocks which are `finished` already (when resuming
+    /// an interrupted render)
+    pub fn remove_blocks(&mut self, finished: &[(u32, u32)]) {
+        self.blocks.retain(|block| !finished.contains(block));
+    }
     /// Get the dimensions of an individual block in the queue
     pub fn block_dim(&self) -> (u32, u32) {
         self.dimensions
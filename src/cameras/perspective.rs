@@ -15,6 +15,7 @@ use core::medium::{Medium, MediumInterface};
 use core::paramset::ParamSet;
 use core::pbrt::lerp;
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::sampling::concentric_sample_disk;
 use core::transform::{AnimatedTransform, Transform};
 
@@ -179,7 +180,7 @@ impl PerspectiveCamera {
 
 impl Camera for PerspectiveCamera {
     fn generate_ray_differential(&self, sample: &CameraSample, ray: &mut Ray) -> Float {
-        // TODO: ProfilePhase prof(Prof::GenerateCameraRay);
+        let _p = ProfilePhase::new(Prof::GenerateCameraRay);
         // compute raster and camera sample positions
         let p_film: Point3f = Point3f {
             x: sample.p_film.x,
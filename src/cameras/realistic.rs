@@ -4,7 +4,6 @@ extern crate num_cpus;
 // std
 use std;
 use std::path::PathBuf;
-use std::sync::mpsc;
 use std::sync::Arc;
 // pbrt
 use core::camera::{Camera, CameraSample};
@@ -19,6 +18,7 @@ use core::medium::Medium;
 use core::paramset::ParamSet;
 use core::pbrt::{lerp, quadratic};
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::reflection::refract;
 use core::transform::{AnimatedTransform, Transform};
 
@@ -108,10 +108,8 @@ impl RealisticCamera {
             let camera = &camera;
             let film = &film;
             crossbeam::scope(|scope| {
-                let (band_tx, band_rx) = mpsc::channel();
                 // spawn worker threads
                 for (b, band) in bands.into_iter().enumerate() {
-                    let band_tx = band_tx.clone();
                     scope.spawn(move |_| {
                         for (index, bound) in band.into_iter().enumerate() {
                             let i: usize = (b * chunk_size) + index;
@@ -122,15 +120,7 @@ impl RealisticCamera {
                             *bound = camera.bound_exit_pupil(r0, r1);
                         }
                     });
-                    // send progress through the channel to main thread
-                    band_tx.send(b).expect(&format!("Failed to send progress"));
                 }
-                // spawn thread to report progress
-                scope.spawn(move |_| {
-                    for _ in pbr::PbIter::new(0..num_cores) {
-                        band_rx.recv().unwrap();
-                    }
-                });
             }).unwrap();
         }
         camera.exit_pupil_bounds = exit_pupil_bounds;
@@ -194,7 +184,7 @@ impl RealisticCamera {
         camera
     }
     pub fn generate_ray(&self, sample: &CameraSample, ray: &mut Ray) -> Float {
-        // TODO: ProfilePhase prof(Prof::GenerateCameraRay);
+        let _p = ProfilePhase::new(Prof::GenerateCameraRay);
         // ++totalRays;
         // find point on film, _p_film_, corresponding to _sample.p_film_
         let s: Point2f = Point2f {
@@ -0,0 +1,317 @@
+//! Arbitrary output variables (AOVs) are extra per-pixel channels
+//! the film can record next to the rendered image, e.g. for
+//! compositing. They get requested via the film's "aovs" parameter,
+//! like `"string aovs" [ "depth" "normal" "albedo" ]`.
+//!
+//! The geometric AOVs (depth, position, normal, albedo, uv) describe
+//! the first intersection of the camera rays and get averaged over
+//! the samples which hit something, the ids keep the value of the
+//! first sample which hit something, and the radiance AOVs (direct,
+//! indirect, diffuse, specular) get averaged over all samples of a
+//! pixel. Unlike the image itself they are not filtered.
+
+// std
+use std::f32::consts::PI;
+use std::io::{Read, Result, Write};
+// others
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+// pbrt
+use core::checkpoint::{read_float, write_float};
+use core::geometry::{nrm_faceforward_vec3, Normal3f, Point2f, Point3f, Vector3f};
+use core::interaction::SurfaceInteraction;
+use core::pbrt::{Float, Spectrum};
+use core::reflection::BxdfType;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AovType {
+    /// Distance from the camera to the first intersection
+    Depth,
+    /// World space position of the first intersection
+    Position,
+    /// Shading normal at the first intersection
+    Normal,
+    /// Reflectance of the non-specular parts of the BSDF at the first intersection
+    Albedo,
+    /// Surface (u, v) coordinates at the first intersection
+    Uv,
+    /// Counts **Shape** statements, starting at 1
+    PrimitiveId,
+    /// Counts materials used by shapes, starting at 1
+    MaterialId,
+    /// Light emitted by or arriving directly at the first intersection
+    Direct,
+    /// Light arriving at the first intersection after more bounces
+    Indirect,
+    /// Light scattered by non-specular parts of the first BSDF
+    Diffuse,
+    /// Light scattered by specular parts of the first BSDF
+    Specular,
+    /// Number of camera samples taken for a pixel
+    SampleCount,
+}
+
+impl AovType {
+    pub fn from_name(name: &str) -> Option<AovType> {
+        match name {
+            "depth" => Some(AovType::Depth),
+            "position" => Some(AovType::Position),
+            "normal" => Some(AovType::Normal),
+            "albedo" => Some(AovType::Albedo),
+            "uv" => Some(AovType::Uv),
+            "primid" => Some(AovType::PrimitiveId),
+            "materialid" => Some(AovType::MaterialId),
+            "direct" => Some(AovType::Direct),
+            "indirect" => Some(AovType::Indirect),
+            "diffuse" => Some(AovType::Diffuse),
+            "specular" => Some(AovType::Specular),
+            "samplecount" => Some(AovType::SampleCount),
+            _ => None,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match *self {
+            AovType::Depth => "depth",
+            AovType::Position => "position",
+            AovType::Normal => "normal",
+            AovType::Albedo => "albedo",
+            AovType::Uv => "uv",
+            AovType::PrimitiveId => "primid",
+            AovType::MaterialId => "materialid",
+            AovType::Direct => "direct",
+            AovType::Indirect => "indirect",
+            AovType::Diffuse => "diffuse",
+            AovType::Specular => "specular",
+            AovType::SampleCount => "samplecount",
+        }
+    }
+    /// Names of the channels (e.g. within an OpenEXR layer).
+    pub fn channels(&self) -> &'static [&'static str] {
+        match *self {
+            AovType::Depth => &["Z"],
+            AovType::Position | AovType::Normal => &["X", "Y", "Z"],
+            AovType::Uv => &["U", "V"],
+            AovType::PrimitiveId | AovType::MaterialId => &["id"],
+            AovType::SampleCount => &["count"],
+            _ => &["R", "G", "B"],
+        }
+    }
+    /// Colors (albedo and radiance) can be written like the image
+    /// itself, all other AOVs need linear floats.
+    pub fn is_color(&self) -> bool {
+        *self == AovType::Albedo || self.is_radiance()
+    }
+    fn is_geometric(&self) -> bool {
+        match *self {
+            AovType::Depth
+            | AovType::Position
+            | AovType::Normal
+            | AovType::Albedo
+            | AovType::Uv => true,
+            _ => false,
+        }
+    }
+    fn is_radiance(&self) -> bool {
+        match *self {
+            AovType::Direct | AovType::Indirect | AovType::Diffuse | AovType::Specular => true,
+            _ => false,
+        }
+    }
+}
+
+/// The AOV values of a single camera sample, filled in by the
+/// integrators.
+#[derive(Debug, Default, Clone)]
+pub struct AovSample {
+    pub hit: bool,
+    pub depth: Float,
+    pub position: Point3f,
+    pub normal: Normal3f,
+    pub albedo: Spectrum,
+    pub uv: Point2f,
+    pub primitive_id: u32,
+    pub material_id: u32,
+    pub direct: Spectrum,
+    pub indirect: Spectrum,
+    pub diffuse: Spectrum,
+    pub specular: Spectrum,
+}
+
+impl AovSample {
+    /// Records the first intersection of a camera ray starting at
+    /// *origin*. The albedo needs the BSDF, so call this after
+    /// **compute_scattering_functions()**.
+    pub fn record_hit(&mut self, origin: &Point3f, isect: &SurfaceInteraction) {
+        self.hit = true;
+        self.depth = (isect.p - *origin).length();
+        self.position = isect.p;
+        self.normal = isect.shading.n;
+        self.uv = isect.uv;
+        if let Some(primitive) = isect.primitive {
+            self.primitive_id = primitive.primitive_id;
+            self.material_id = primitive.material_id;
+        }
+        if let Some(ref bsdf) = isect.bsdf {
+            // reflectance towards the shading normal, which is exact
+            // for Lambertian reflection
+            let wi: Vector3f = Vector3f::from(nrm_faceforward_vec3(&isect.shading.n, &isect.wo));
+            self.albedo = bsdf.f(&isect.wo, &wi, BxdfType::BsdfAll as u8) * Spectrum::new(PI);
+        }
+    }
+    /// Splits the radiance *l* arriving along a camera ray into the
+    /// part *l_direct* (emitted by or arriving directly at the first
+    /// intersection) and the rest. The part *l_emitted* (emitted by
+    /// the first intersection itself) is neither diffuse nor
+    /// specular, light arriving directly counts as diffuse (specular
+    /// lobes can't sample lights), the rest according to
+    /// *first_specular*, the lobe sampled at the first intersection.
+    pub fn record_radiance(
+        &mut self,
+        l: &Spectrum,
+        l_emitted: &Spectrum,
+        l_direct: &Spectrum,
+        first_specular: bool,
+    ) {
+        let indirect: Spectrum = *l - *l_direct;
+        self.direct = *l_direct;
+        self.indirect = indirect;
+        self.diffuse = *l_direct - *l_emitted;
+        if first_specular {
+            self.specular = indirect;
+        } else {
+            self.diffuse += indirect;
+        }
+    }
+}
+
+/// Sums of the AOV values of all samples taken for one pixel.
+#[derive(Debug, Default, Clone)]
+pub struct AovPixel {
+    values: Vec<Float>,
+    n_samples: u32,
+    n_hits: u32,
+}
+
+impl AovPixel {
+    pub fn new(aovs: &[AovType]) -> Self {
+        let n_channels: usize = aovs.iter().map(|aov| aov.channels().len()).sum();
+        AovPixel {
+            values: vec![0.0 as Float; n_channels],
+            n_samples: 0_u32,
+            n_hits: 0_u32,
+        }
+    }
+    pub fn add_sample(&mut self, aovs: &[AovType], sample: &AovSample) {
+        self.n_samples += 1;
+        if sample.hit {
+            self.n_hits += 1;
+        }
+        let mut offset: usize = 0;
+        for aov in aovs {
+            let n: usize = aov.channels().len();
+            if !aov.is_geometric() || sample.hit {
+                let values: &mut [Float] = &mut self.values[offset..offset + n];
+                match *aov {
+                    AovType::Depth => values[0] += sample.depth,
+                    AovType::Position => {
+                        values[0] += sample.position.x;
+                        values[1] += sample.position.y;
+                        values[2] += sample.position.z;
+                    }
+                    AovType::Normal => {
+                        values[0] += sample.normal.x;
+                        values[1] += sample.normal.y;
+                        values[2] += sample.normal.z;
+                    }
+                    AovType::Albedo => add_spectrum(values, &sample.albedo),
+                    AovType::Uv => {
+                        values[0] += sample.uv.x;
+                        values[1] += sample.uv.y;
+                    }
+                    AovType::PrimitiveId => {
+                        if values[0] == 0.0 as Float {
+                            values[0] = sample.primitive_id as Float;
+                        }
+                    }
+                    AovType::MaterialId => {
+                        if values[0] == 0.0 as Float {
+                            values[0] = sample.material_id as Float;
+                        }
+                    }
+                    AovType::Direct => add_spectrum(values, &sample.direct),
+                    AovType::Indirect => add_spectrum(values, &sample.indirect),
+                    AovType::Diffuse => add_spectrum(values, &sample.diffuse),
+                    AovType::Specular => add_spectrum(values, &sample.specular),
+                    AovType::SampleCount => {}
+                }
+            }
+            offset += n;
+        }
+    }
+    pub fn merge(&mut self, aovs: &[AovType], other: &AovPixel) {
+        let mut offset: usize = 0;
+        for aov in aovs {
+            for i in offset..offset + aov.channels().len() {
+                match *aov {
+                    AovType::PrimitiveId | AovType::MaterialId => {
+                        if self.values[i] == 0.0 as Float {
+                            self.values[i] = other.values[i];
+                        }
+                    }
+                    _ => self.values[i] += other.values[i],
+                }
+            }
+            offset += aov.channels().len();
+        }
+        self.n_samples += other.n_samples;
+        self.n_hits += other.n_hits;
+    }
+    /// Appends the final values of *aov* (which is at channel
+    /// *offset*) to *values*, radiance gets multiplied by *scale*.
+    pub fn resolve(&self, aov: &AovType, offset: usize, scale: Float, values: &mut Vec<Float>) {
+        for i in offset..offset + aov.channels().len() {
+            let value: Float = if *aov == AovType::SampleCount {
+                self.n_samples as Float
+            } else if aov.is_geometric() {
+                if self.n_hits > 0 {
+                    self.values[i] / self.n_hits as Float
+                } else {
+                    0.0 as Float
+                }
+            } else if aov.is_radiance() {
+                if self.n_samples > 0 {
+                    scale * self.values[i] / self.n_samples as Float
+                } else {
+                    0.0 as Float
+                }
+            } else {
+                self.values[i]
+            };
+            values.push(value);
+        }
+    }
+    /// Writes the sums to a checkpoint (see **core::checkpoint**).
+    pub fn write(&self, w: &mut Write) -> Result<()> {
+        for v in &self.values {
+            write_float(w, *v)?;
+        }
+        w.write_u32::<LittleEndian>(self.n_samples)?;
+        w.write_u32::<LittleEndian>(self.n_hits)
+    }
+    /// Reads the sums written by **write()**.
+    pub fn read(&mut self, r: &mut Read) -> Result<()> {
+        for v in self.values.iter_mut() {
+            *v = read_float(r)?;
+        }
+        self.n_samples = r.read_u32::<LittleEndian>()?;
+        self.n_hits = r.read_u32::<LittleEndian>()?;
+        Ok(())
+    }
+}
+
+fn add_spectrum(values: &mut [Float], s: &Spectrum) {
+    let mut rgb: [Float; 3] = [0.0 as Float; 3];
+    s.to_rgb(&mut rgb);
+    values[0] += rgb[0];
+    values[1] += rgb[1];
+    values[2] += rgb[2];
+}
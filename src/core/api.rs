@@ -4,34 +4,48 @@
 
 // std
 use std;
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::mem;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 // pbrt
 use accelerators::bvh::{BVHAccel, SplitMethod};
+use accelerators::kdtree::{KdTreeAccel, MAX_TODO};
 use cameras::environment::EnvironmentCamera;
 use cameras::orthographic::OrthographicCamera;
 use cameras::perspective::PerspectiveCamera;
 use cameras::realistic::RealisticCamera;
+use core::aov::AovType;
 use core::camera::Camera;
+use core::checkpoint::Checkpoint;
 use core::film::Film;
 use core::filter::Filter;
-use core::geometry::{vec3_coordinate_system, vec3_cross_vec3};
+use core::geometry::{bnd2_intersect_bnd2, vec3_coordinate_system, vec3_cross_vec3};
 use core::geometry::{Bounds2f, Bounds2i, Normal3f, Point2f, Point2i, Point3f, Vector3f};
+use core::imageio::check_image_file;
 use core::integrator::SamplerIntegrator;
 use core::light::Light;
 use core::material::Material;
 use core::medium::get_medium_scattering_properties;
 use core::medium::{Medium, MediumInterface};
 use core::mipmap::ImageWrap;
-use core::paramset::{ParamSet, TextureParams};
+use core::paramset::{suggest_parameter, ParamSet, TextureParams};
 use core::pbrt::{clamp_t, lerp};
 use core::pbrt::{Float, Spectrum};
 use core::primitive::{GeometricPrimitive, Primitive, TransformedPrimitive};
+use core::profiler::{clear_profiler, init_profiler, report_profiler_results};
 use core::reflection::FourierBSDFTable;
 use core::sampler::Sampler;
 use core::scene::Scene;
+use core::scenewriter::{ResolvedStatement, SceneWriter};
 use core::shape::Shape;
+use core::stats::{clear_stats, print_stats};
+use core::stats::{StatCounter, StatIntDistribution, StatMemoryCounter, StatPercent};
 use core::texture::{
     IdentityMapping3D, PlanarMapping2D, Texture, TextureMapping2D, TextureMapping3D, UVMapping2D,
 };
@@ -78,6 +92,7 @@ use shapes::disk::Disk;
 use shapes::loopsubdiv::loop_subdivide;
 use shapes::nurbs::nurbs_evaluate_surface;
 use shapes::nurbs::Homogeneous3;
+use shapes::objmesh::{create_obj_mesh, ObjMeshPart};
 use shapes::plymesh::create_ply_mesh;
 use shapes::sphere::Sphere;
 use shapes::triangle::{Triangle, TriangleMesh};
@@ -90,6 +105,142 @@ use textures::windy::WindyTexture;
 
 // see api.cpp
 
+static N_OBJECT_INSTANCES_CREATED: StatCounter = StatCounter::new("Scene/Object instances created");
+static N_OBJECT_INSTANCES_USED: StatCounter = StatCounter::new("Scene/Object instances used");
+static N_OBJECT_INSTANCES_TOTAL: StatCounter =
+    StatCounter::new("Scene/Object instances (including nested ones)");
+static TRANSFORM_CACHE_BYTES: StatMemoryCounter = StatMemoryCounter::new("Memory/TransformCache");
+static TRANSFORM_CACHE_SAVED_BYTES: StatMemoryCounter =
+    StatMemoryCounter::new("Memory/TransformCache savings");
+static N_TRANSFORM_CACHE_HITS: StatPercent = StatPercent::new("Scene/TransformCache hits");
+static TRANSFORM_CACHE_PROBES: StatIntDistribution =
+    StatIntDistribution::new("Scene/Probes per TransformCache lookup");
+
+/// Errors reported by the *pbrt_* API functions, e.g. for lights,
+/// materials, shapes, etc. which are unknown or not supported (yet).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ApiError {
+    /// The type is known from the C++ version, but not implemented
+    /// (yet).
+    Unsupported { directive: String, name: String },
+    /// Nobody knows about a type (or named object) with this name.
+    Unknown { directive: String, name: String },
+    /// A parameter is missing or has an invalid value.
+    InvalidParameter { directive: String, message: String },
+    /// The directive can't be used at this point of the scene
+    /// description.
+    InvalidState { directive: String, message: String },
+    /// A parameter was never looked up, most likely because it is
+    /// misspelled or doesn't belong to the type (reported as warning
+    /// only, even in strict mode).
+    UnusedParameter {
+        location: String,
+        directive: String,
+        name: String,
+        parameter: String,
+        suggestion: Option<String>,
+    },
+    /// The scene writer failed to write a statement (or PLY file).
+    WriteFailed { directive: String, message: String },
+}
+
+impl ApiError {
+    fn unsupported(directive: &str, name: &str) -> Self {
+        ApiError::Unsupported {
+            directive: String::from(directive),
+            name: String::from(name),
+        }
+    }
+    fn unknown(directive: &str, name: &str) -> Self {
+        ApiError::Unknown {
+            directive: String::from(directive),
+            name: String::from(name),
+        }
+    }
+    fn invalid_parameter(directive: &str, message: String) -> Self {
+        ApiError::InvalidParameter {
+            directive: String::from(directive),
+            message: message,
+        }
+    }
+    fn invalid_state(directive: &str, message: String) -> Self {
+        ApiError::InvalidState {
+            directive: String::from(directive),
+            message: message,
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApiError::Unsupported {
+                ref directive,
+                ref name,
+            } => write!(f, "{} \"{}\" not supported (yet)", directive, name),
+            ApiError::Unknown {
+                ref directive,
+                ref name,
+            } => write!(f, "{} \"{}\" unknown", directive, name),
+            ApiError::InvalidParameter {
+                ref directive,
+                ref message,
+            } => write!(f, "{}: {}", directive, message),
+            ApiError::InvalidState {
+                ref directive,
+                ref message,
+            } => write!(f, "{}: {}", directive, message),
+            ApiError::UnusedParameter {
+                ref location,
+                ref directive,
+                ref name,
+                ref parameter,
+                ref suggestion,
+            } => {
+                if location != "" {
+                    write!(f, "{}: ", location)?;
+                }
+                write!(
+                    f,
+                    "{} \"{}\": parameter \"{}\" not used",
+                    directive, name, parameter
+                )?;
+                if let Some(ref suggestion) = *suggestion {
+                    write!(f, " (did you mean \"{}\"?)", suggestion)?;
+                }
+                Ok(())
+            }
+            ApiError::WriteFailed {
+                ref directive,
+                ref message,
+            } => write!(f, "{}: {}", directive, message),
+        }
+    }
+}
+
+impl Error for ApiError {
+    fn description(&self) -> &str {
+        match *self {
+            ApiError::Unsupported { .. } => "unsupported directive",
+            ApiError::Unknown { .. } => "unknown directive",
+            ApiError::InvalidParameter { .. } => "invalid parameter",
+            ApiError::InvalidState { .. } => "invalid state",
+            ApiError::UnusedParameter { .. } => "unused parameter",
+            ApiError::WriteFailed { .. } => "write failed",
+        }
+    }
+}
+
+/// How the API deals with unsupported or unknown directives.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ErrorMode {
+    /// Fail on the first problem.
+    Strict,
+    /// Print a warning, skip the directive (or use a default), and
+    /// collect all warnings for a final report.
+    Lenient,
+}
+
 pub struct BsdfState {
     pub loaded_bsdfs: HashMap<String, Arc<FourierBSDFTable>>,
 }
@@ -105,7 +256,32 @@ impl Default for BsdfState {
 pub struct ApiState {
     number_of_threads: u8,
     pub search_directory: Option<Box<PathBuf>>,
+    pub error_mode: ErrorMode,
+    /// If set, statements get written instead of executed (see
+    /// **core::scenewriter**).
+    pub scene_writer: Option<SceneWriter>,
+    /// If set, the world block gets recorded while the scene is built
+    /// (see **SceneWriter::write_state()**).
+    pub scene_record: Option<Vec<ResolvedStatement>>,
+    /// Reduces image resolution, sample counts, and SPPM iterations
+    /// for a fast preview.
+    pub quick_render: bool,
+    /// Overrides the film's "cropwindow" (x0 x1 y0 y1, in NDC space).
+    pub crop_window: Option<[Float; 4]>,
+    /// Overrides the film's "filename".
+    pub image_file: Option<String>,
+    /// Time between two checkpoints of the render (see
+    /// **core::checkpoint**).
+    pub checkpoint_interval: Option<Duration>,
+    /// Continues an interrupted render from its checkpoint.
+    pub resume: bool,
+    warnings: Vec<ApiError>,
+    // unused parameters already warned about
+    unused_warned: HashSet<ApiError>,
+    // number of problems reported, to avoid follow-up warnings
+    reported: usize,
     cur_transform: TransformSet,
+    transform_cache: TransformCache,
     active_transform_bits: u8,
     named_coordinate_systems: HashMap<&'static str, TransformSet>,
     render_options: RenderOptions,
@@ -121,6 +297,17 @@ impl Default for ApiState {
         ApiState {
             number_of_threads: 0_u8,
             search_directory: None,
+            error_mode: ErrorMode::Lenient,
+            scene_writer: None,
+            scene_record: None,
+            quick_render: false,
+            crop_window: None,
+            image_file: None,
+            checkpoint_interval: None,
+            resume: false,
+            warnings: Vec::new(),
+            unused_warned: HashSet::new(),
+            reported: 0,
             cur_transform: TransformSet {
                 t: [Transform {
                     m: Matrix4x4 {
@@ -141,6 +328,7 @@ impl Default for ApiState {
                     },
                 }; 2],
             },
+            transform_cache: TransformCache::default(),
             named_coordinate_systems: HashMap::new(),
             active_transform_bits: 3_u8, // 0x11 for MaxTransforms = 2
             render_options: RenderOptions::default(),
@@ -153,6 +341,124 @@ impl Default for ApiState {
     }
 }
 
+impl ApiState {
+    /// All problems reported so far in lenient mode.
+    pub fn warnings(&self) -> &Vec<ApiError> {
+        &self.warnings
+    }
+    pub fn render_options(&self) -> &RenderOptions {
+        &self.render_options
+    }
+    /// In strict mode the error is returned, in lenient mode it gets
+    /// printed as a warning and is collected for the final report.
+    pub fn report(&mut self, error: ApiError) -> Result<(), ApiError> {
+        self.reported += 1;
+        match self.error_mode {
+            ErrorMode::Strict => Err(error),
+            ErrorMode::Lenient => {
+                println!("WARNING: {}", error);
+                self.warnings.push(error);
+                Ok(())
+            }
+        }
+    }
+    /// Warns about parameters of a statement which were never looked
+    /// up (see ParamSet::ReportUnused() in paramset.cpp).
+    pub fn warn_unused(&mut self, unused: Vec<ApiError>) {
+        for warning in unused {
+            // shared parameters (e.g. of an area light) get looked at
+            // once per shape
+            if self.unused_warned.insert(warning.clone()) {
+                println!("WARNING: {}", warning);
+                self.warnings.push(warning);
+            }
+        }
+    }
+    fn record(&mut self, statement: ResolvedStatement) {
+        if let Some(ref mut statements) = self.scene_record {
+            statements.push(statement);
+        }
+    }
+}
+
+/// Copies **params** including the names, to be recorded (see
+/// **ApiState::scene_record**).
+fn copy_params(params: &ParamSet) -> ParamSet {
+    let mut copy: ParamSet = ParamSet::default();
+    copy.copy_from(params);
+    copy.name = params.name.clone();
+    copy.tex_type = params.tex_type.clone();
+    copy.tex_name = params.tex_name.clone();
+    copy
+}
+
+/// Records a **Shape** statement with the material it gets, and the
+/// rest of the graphics state it depends on.
+fn shape_statement(api_state: &ApiState, params: &ParamSet) -> ResolvedStatement {
+    let graphics_state: &GraphicsState = &api_state.graphics_state;
+    let mut material: ParamSet;
+    if graphics_state.current_material != "" {
+        match graphics_state
+            .named_material_params
+            .get(&graphics_state.current_material)
+        {
+            Some(named_params) => {
+                material = copy_params(named_params);
+                material.name = material.find_one_string("type", String::new());
+            }
+            None => {
+                // the default material (see create_material())
+                material = ParamSet::default();
+                material.name = String::from("matte");
+            }
+        }
+    } else {
+        material = copy_params(&graphics_state.material_params);
+        material.name = graphics_state.material.clone();
+    }
+    let area_light: Option<ParamSet> = if graphics_state.area_light != "" {
+        Some(copy_params(&graphics_state.area_light_params))
+    } else {
+        None
+    };
+    ResolvedStatement::Shape {
+        params: copy_params(params),
+        transform: api_state.cur_transform,
+        material: material,
+        area_light: area_light,
+        inside_medium: graphics_state.current_inside_medium.clone(),
+        outside_medium: graphics_state.current_outside_medium.clone(),
+        reverse_orientation: graphics_state.reverse_orientation,
+    }
+}
+
+fn unused_parameters(params: &ParamSet) -> Vec<ApiError> {
+    unused_parameter_errors(params, params.unused(), params.lookups())
+}
+
+fn unused_texture_params(tp: &TextureParams) -> Vec<ApiError> {
+    unused_parameter_errors(&tp.material_params, tp.unused(), tp.lookups())
+}
+
+fn unused_parameter_errors(
+    params: &ParamSet,
+    unused: Vec<String>,
+    lookups: Vec<String>,
+) -> Vec<ApiError> {
+    let mut errors: Vec<ApiError> = Vec::new();
+    for parameter in unused {
+        let suggestion: Option<String> = suggest_parameter(&parameter, &lookups);
+        errors.push(ApiError::UnusedParameter {
+            location: params.location.clone(),
+            directive: params.key_word.clone(),
+            name: params.name.clone(),
+            parameter: parameter,
+            suggestion: suggestion,
+        });
+    }
+    errors
+}
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct TransformSet {
     pub t: [Transform; 2],
@@ -173,6 +479,113 @@ impl TransformSet {
     }
 }
 
+/// Hands out one shared copy of each distinct _Transform_, so that
+/// the many shapes created with the same transformation (e.g. all
+/// triangles of a mesh, all segments of a curve, or thousands of
+/// small shapes placed by an exporter) don't each store their own
+/// matrices (see TransformCache in api.cpp).
+pub struct TransformCache {
+    // open addressing with quadratic probing, the size is a power of 2
+    hash_table: Vec<Option<Arc<Transform>>>,
+    hash_table_occupancy: usize,
+}
+
+impl Default for TransformCache {
+    fn default() -> Self {
+        TransformCache {
+            hash_table: vec![None; 512],
+            hash_table_occupancy: 0_usize,
+        }
+    }
+}
+
+impl TransformCache {
+    /// Returns the cached copy of **t**, which gets added to the
+    /// cache if it wasn't there yet.
+    pub fn lookup(&mut self, t: &Transform) -> Arc<Transform> {
+        let mask: usize = self.hash_table.len() - 1;
+        let mut offset: usize = TransformCache::hash(t) as usize & mask;
+        let mut step: usize = 1;
+        loop {
+            // keep looking until we find the _Transform_ or determine
+            // that it's not present
+            match self.hash_table[offset] {
+                Some(ref t_cached) if **t_cached != *t => {}
+                _ => break,
+            }
+            // advance using quadratic probing
+            offset = (offset + step * step) & mask;
+            step += 1;
+        }
+        TRANSFORM_CACHE_PROBES.report_value(step as i64);
+        if let Some(ref t_cached) = self.hash_table[offset] {
+            N_TRANSFORM_CACHE_HITS.add(1, 1);
+            return t_cached.clone();
+        }
+        N_TRANSFORM_CACHE_HITS.add(0, 1);
+        let t_cached: Arc<Transform> = Arc::new(*t);
+        // the transform plus the reference counts of the _Arc_
+        TRANSFORM_CACHE_BYTES.add(mem::size_of::<Transform>() + 2 * mem::size_of::<usize>());
+        self.insert(t_cached.clone());
+        t_cached
+    }
+    /// Drops all cached transforms (the ones still in use by shapes
+    /// stay alive).
+    pub fn clear(&mut self) {
+        self.hash_table = vec![None; 512];
+        self.hash_table_occupancy = 0_usize;
+    }
+    fn insert(&mut self, t_new: Arc<Transform>) {
+        self.hash_table_occupancy += 1;
+        if self.hash_table_occupancy == self.hash_table.len() / 2 {
+            self.grow();
+        }
+        let mask: usize = self.hash_table.len() - 1;
+        let mut offset: usize = TransformCache::hash(&t_new) as usize & mask;
+        let mut step: usize = 1;
+        while self.hash_table[offset].is_some() {
+            offset = (offset + step * step) & mask;
+            step += 1;
+        }
+        self.hash_table[offset] = Some(t_new);
+    }
+    fn grow(&mut self) {
+        let old_table: Vec<Option<Arc<Transform>>> =
+            mem::replace(&mut self.hash_table, vec![None; 0]);
+        let new_size: usize = 2 * old_table.len();
+        self.hash_table = vec![None; new_size];
+        let mask: usize = new_size - 1;
+        // insert the old entries into the new hash table
+        for t_old in old_table.into_iter() {
+            if let Some(t_old) = t_old {
+                let mut offset: usize = TransformCache::hash(&t_old) as usize & mask;
+                let mut step: usize = 1;
+                while self.hash_table[offset].is_some() {
+                    offset = (offset + step * step) & mask;
+                    step += 1;
+                }
+                self.hash_table[offset] = Some(t_old);
+            }
+        }
+    }
+    fn hash(t: &Transform) -> u64 {
+        let mut hasher: DefaultHasher = DefaultHasher::new();
+        for matrix in [t.m, t.m_inv].iter() {
+            for row in matrix.m.iter() {
+                for value in row.iter() {
+                    // treat -0.0 and 0.0 the same, they compare equal
+                    if *value == 0.0 as Float {
+                        0_u32.hash(&mut hasher);
+                    } else {
+                        value.to_bits().hash(&mut hasher);
+                    }
+                }
+            }
+        }
+        hasher.finish()
+    }
+}
+
 pub struct RenderOptions {
     pub transform_start_time: Float,
     pub transform_end_time: Float,
@@ -193,8 +606,16 @@ pub struct RenderOptions {
     pub lights: Vec<Arc<Light + Sync + Send>>,
     pub primitives: Vec<Arc<Primitive + Sync + Send>>,
     pub instances: HashMap<String, Vec<Arc<Primitive + Sync + Send>>>,
+    /// Emissive shapes of each instance definition
+    pub instance_area_lights: HashMap<String, Vec<InstanceAreaLight>>,
+    /// Number of (nested) instances used within each instance definition
+    pub nested_instances: HashMap<String, u64>,
     pub current_instance: String,
     pub have_scattering_media: bool, // false
+    /// Number of **Shape** statements so far, for the "primid" AOV
+    pub n_shapes: u32,
+    /// Maps materials (by address) to ids for the "materialid" AOV
+    pub material_ids: HashMap<usize, u32>,
 }
 
 impl Default for RenderOptions {
@@ -238,8 +659,12 @@ impl Default for RenderOptions {
             lights: Vec::new(),
             primitives: Vec::new(),
             instances: HashMap::new(),
+            instance_area_lights: HashMap::new(),
+            nested_instances: HashMap::new(),
             current_instance: String::from(""),
             have_scattering_media: false,
+            n_shapes: 0_u32,
+            material_ids: HashMap::new(),
         }
     }
 }
@@ -253,6 +678,9 @@ pub struct GraphicsState {
     pub material_params: ParamSet,
     pub material: String,
     pub named_materials: HashMap<String, Option<Arc<Material + Send + Sync>>>,
+    /// Parameters of the named materials, only kept while the scene
+    /// gets recorded (see **ApiState::scene_record**).
+    pub named_material_params: HashMap<String, Arc<ParamSet>>,
     pub current_material: String,
     pub area_light_params: ParamSet,
     pub area_light: String,
@@ -283,6 +711,7 @@ impl GraphicsState {
             material_params: ParamSet::default(),
             material: String::from(""),
             named_materials: named_materials,
+            named_material_params: HashMap::new(),
             current_material: current_material,
             area_light_params: ParamSet::default(),
             area_light: String::from(""),
@@ -291,13 +720,74 @@ impl GraphicsState {
     }
 }
 
+impl Clone for GraphicsState {
+    fn clone(&self) -> Self {
+        let mut material_param_set: ParamSet = ParamSet::default();
+        material_param_set.copy_from(&self.material_params);
+        material_param_set.name = self.material_params.name.clone();
+        let mut area_light_param_set: ParamSet = ParamSet::default();
+        area_light_param_set.copy_from(&self.area_light_params);
+        area_light_param_set.name = self.area_light_params.name.clone();
+        GraphicsState {
+            current_inside_medium: self.current_inside_medium.clone(),
+            current_outside_medium: self.current_outside_medium.clone(),
+            float_textures: self.float_textures.clone(),
+            spectrum_textures: self.spectrum_textures.clone(),
+            material_params: material_param_set,
+            material: self.material.clone(),
+            named_materials: self.named_materials.clone(),
+            named_material_params: self.named_material_params.clone(),
+            current_material: self.current_material.clone(),
+            area_light_params: area_light_param_set,
+            area_light: self.area_light.clone(),
+            reverse_orientation: self.reverse_orientation,
+        }
+    }
+}
+
+/// An emissive shape of an object instance definition. Area lights
+/// have to live in world space, so the shape gets created again for
+/// each use of the instance (see **pbrt_object_instance()**).
+pub struct InstanceAreaLight {
+    param_set: ParamSet,
+    graphics_state: GraphicsState,
+    // the shape's transform relative to the instance
+    transform: Transform,
+    primitive_id: u32,
+}
+
+impl InstanceAreaLight {
+    /// Returns a copy placed by **instance_to_world**, e.g. when an
+    /// instance gets used within another instance definition.
+    fn transformed(&self, instance_to_world: &Transform) -> InstanceAreaLight {
+        let mut area_light: InstanceAreaLight = self.clone();
+        area_light.transform = *instance_to_world * self.transform;
+        area_light
+    }
+}
+
+impl Clone for InstanceAreaLight {
+    fn clone(&self) -> Self {
+        let mut param_set: ParamSet = ParamSet::default();
+        param_set.copy_from(&self.param_set);
+        param_set.name = self.param_set.name.clone();
+        InstanceAreaLight {
+            param_set: param_set,
+            graphics_state: self.graphics_state.clone(),
+            transform: self.transform,
+            primitive_id: self.primitive_id,
+        }
+    }
+}
+
 fn create_material(
-    api_state: &ApiState,
+    api_state: &mut ApiState,
     bsdf_state: &mut BsdfState,
-) -> Option<Arc<Material + Send + Sync>> {
+) -> Result<Option<Arc<Material + Send + Sync>>, ApiError> {
     // CreateMaterial
     let mut material_params = ParamSet::default();
     material_params.copy_from(&api_state.graphics_state.material_params);
+    material_params.name = api_state.graphics_state.material_params.name.clone();
     let mut mp: TextureParams = TextureParams {
         float_textures: api_state.graphics_state.float_textures.clone(),
         spectrum_textures: api_state.graphics_state.spectrum_textures.clone(),
@@ -311,95 +801,116 @@ fn create_material(
             .get(api_state.graphics_state.current_material.as_str())
         {
             Some(named_material) => {
-                return named_material.clone();
-            }
-            None => {
-                println!(
-                    "WARNING: Named material \"{}\" not defined. Using \"matte\".",
-                    api_state.graphics_state.current_material
-                );
+                return Ok(named_material.clone());
             }
+            None => {}
         }
+        // using "matte" in lenient mode
+        api_state.report(ApiError::unknown(
+            "NamedMaterial",
+            &api_state.graphics_state.current_material,
+        ))?;
+        Ok(Some(default_material()))
     } else {
         // MakeMaterial
-        if api_state.graphics_state.material == "" || api_state.graphics_state.material == "none" {
-            return None;
-        } else if api_state.graphics_state.material == "matte" {
-            return Some(MatteMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "plastic" {
-            return Some(PlasticMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "translucent" {
-            println!("TODO: CreateTranslucentMaterial");
-        } else if api_state.graphics_state.material == "glass" {
-            return Some(GlassMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "mirror" {
-            return Some(MirrorMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "hair" {
-            return Some(HairMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "mix" {
-            let m1: String = mp.find_string("namedmaterial1", String::from(""));
-            let m2: String = mp.find_string("namedmaterial2", String::from(""));
-            let mat1 = match api_state.graphics_state.named_materials.get(&m1) {
-                Some(named_material) => named_material,
-                None => {
-                    panic!("Material \"{}\" unknown.", m1);
-                }
-            };
-            let mat2 = match api_state.graphics_state.named_materials.get(&m2) {
-                Some(named_material) => named_material,
-                None => {
-                    panic!("Material \"{}\" unknown.", m2);
-                }
-            };
-            let scale: Arc<Texture<Spectrum> + Send + Sync> =
-                mp.get_spectrum_texture("amount", Spectrum::new(0.5));
-            if let Some(m1) = mat1 {
-                if let Some(m2) = mat2 {
-                    let mix = Arc::new(MixMaterial::new(m1.clone(), m2.clone(), scale));
-                    return Some(mix);
+        let reported: usize = api_state.reported;
+        if mp.material_params.key_word == "MakeNamedMaterial" {
+            // the material type was already found by pbrt_make_named_material()
+            mp.find_string("type", String::new());
+        }
+        let mtl: Option<Arc<Material + Send + Sync>> =
+            make_material(api_state, bsdf_state, &mut mp)?;
+        if api_state.reported == reported {
+            api_state.warn_unused(unused_texture_params(&mp));
+        }
+        Ok(mtl)
+    }
+}
+
+fn make_material(
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+    mp: &mut TextureParams,
+) -> Result<Option<Arc<Material + Send + Sync>>, ApiError> {
+    if api_state.graphics_state.material == "" || api_state.graphics_state.material == "none" {
+        return Ok(None);
+    } else if api_state.graphics_state.material == "matte" {
+        return Ok(Some(MatteMaterial::create(mp)));
+    } else if api_state.graphics_state.material == "plastic" {
+        return Ok(Some(PlasticMaterial::create(mp)));
+    } else if api_state.graphics_state.material == "translucent" {
+        api_state.report(ApiError::unsupported("Material", "translucent"))?;
+    } else if api_state.graphics_state.material == "glass" {
+        return Ok(Some(GlassMaterial::create(mp)));
+    } else if api_state.graphics_state.material == "mirror" {
+        return Ok(Some(MirrorMaterial::create(mp)));
+    } else if api_state.graphics_state.material == "hair" {
+        return Ok(Some(HairMaterial::create(mp)));
+    } else if api_state.graphics_state.material == "mix" {
+        let m1: String = mp.find_string("namedmaterial1", String::from(""));
+        let m2: String = mp.find_string("namedmaterial2", String::from(""));
+        let mat1 = api_state.graphics_state.named_materials.get(&m1).cloned();
+        let mat2 = api_state.graphics_state.named_materials.get(&m2).cloned();
+        match (mat1, mat2) {
+            (Some(mat1), Some(mat2)) => {
+                let scale: Arc<Texture<Spectrum> + Send + Sync> =
+                    mp.get_spectrum_texture("amount", Spectrum::new(0.5));
+                if let Some(m1) = mat1 {
+                    if let Some(m2) = mat2 {
+                        let mix = Arc::new(MixMaterial::new(m1.clone(), m2.clone(), scale));
+                        return Ok(Some(mix));
+                    }
                 }
+                return Ok(None);
             }
-            return None;
-        } else if api_state.graphics_state.material == "metal" {
-            return Some(MetalMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "substrate" {
-            return Some(SubstrateMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "uber" {
-            return Some(UberMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "subsurface" {
-            return Some(SubsurfaceMaterial::create(&mut mp));
-        } else if api_state.graphics_state.material == "kdsubsurface" {
-            println!("TODO: CreateKdsubsurfaceMaterial");
-        } else if api_state.graphics_state.material == "fourier" {
-            return Some(FourierMaterial::create(&mut mp, bsdf_state));
-        } else if api_state.graphics_state.material == "disney" {
-            return Some(DisneyMaterial::create(&mut mp));
-        } else {
-            panic!(
-                "Material \"{}\" unknown.",
-                api_state.graphics_state.material
-            );
+            (None, _) => api_state.report(ApiError::unknown("Material", &m1))?,
+            (_, None) => api_state.report(ApiError::unknown("Material", &m2))?,
         }
+    } else if api_state.graphics_state.material == "metal" {
+        return Ok(Some(MetalMaterial::create(mp)));
+    } else if api_state.graphics_state.material == "substrate" {
+        return Ok(Some(SubstrateMaterial::create(mp)));
+    } else if api_state.graphics_state.material == "uber" {
+        return Ok(Some(UberMaterial::create(mp)));
+    } else if api_state.graphics_state.material == "subsurface" {
+        return Ok(Some(SubsurfaceMaterial::create(mp)));
+    } else if api_state.graphics_state.material == "kdsubsurface" {
+        api_state.report(ApiError::unsupported("Material", "kdsubsurface"))?;
+    } else if api_state.graphics_state.material == "fourier" {
+        return Ok(Some(FourierMaterial::create(mp, bsdf_state)));
+    } else if api_state.graphics_state.material == "disney" {
+        return Ok(Some(DisneyMaterial::create(mp)));
+    } else {
+        // using "matte" in lenient mode
+        api_state.report(ApiError::unknown(
+            "Material",
+            &api_state.graphics_state.material,
+        ))?;
     }
+    Ok(Some(default_material()))
+}
+
+fn default_material() -> Arc<Material + Send + Sync> {
     let kd = Arc::new(ConstantTexture::new(Spectrum::new(0.5)));
     let sigma = Arc::new(ConstantTexture::new(0.0 as Float));
-    Some(Arc::new(MatteMaterial::new(kd, sigma, None)))
+    Arc::new(MatteMaterial::new(kd, sigma, None))
 }
 
-fn create_medium_interface(api_state: &ApiState) -> MediumInterface {
+fn create_medium_interface(api_state: &mut ApiState) -> Result<MediumInterface, ApiError> {
     let mut m: MediumInterface = MediumInterface::default();
     if api_state.graphics_state.current_inside_medium != String::from("") {
         match api_state
             .render_options
             .named_media
             .get(&api_state.graphics_state.current_inside_medium)
+            .cloned()
         {
-            Some(inside_medium_arc) => m.inside = Some(inside_medium_arc.clone()),
+            Some(inside_medium_arc) => m.inside = Some(inside_medium_arc),
             None => {
-                panic!(
-                    "ERROR: Named medium \"{:?}\" undefined.",
-                    api_state.graphics_state.current_inside_medium
-                );
+                api_state.report(ApiError::unknown(
+                    "MediumInterface",
+                    &api_state.graphics_state.current_inside_medium,
+                ))?;
             }
         }
     }
@@ -408,20 +919,24 @@ fn create_medium_interface(api_state: &ApiState) -> MediumInterface {
             .render_options
             .named_media
             .get(&api_state.graphics_state.current_outside_medium)
+            .cloned()
         {
-            Some(outside_medium_arc) => m.outside = Some(outside_medium_arc.clone()),
+            Some(outside_medium_arc) => m.outside = Some(outside_medium_arc),
             None => {
-                panic!(
-                    "ERROR: Named medium \"{:?}\" undefined.",
-                    api_state.graphics_state.current_outside_medium
-                );
+                api_state.report(ApiError::unknown(
+                    "MediumInterface",
+                    &api_state.graphics_state.current_outside_medium,
+                ))?;
             }
         }
     }
-    m
+    Ok(m)
 }
 
-fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
+fn make_light(
+    api_state: &mut ApiState,
+    medium_interface: &MediumInterface,
+) -> Result<(), ApiError> {
     // MakeLight (api.cpp:591)
     if api_state.param_set.name == "point" {
         let i: Spectrum = api_state
@@ -496,9 +1011,9 @@ fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
         ));
         api_state.render_options.lights.push(spot_light);
     } else if api_state.param_set.name == "goniometric" {
-        println!("TODO: CreateGoniometricLight");
+        api_state.report(ApiError::unsupported("LightSource", "goniometric"))?;
     } else if api_state.param_set.name == "projection" {
-        println!("TODO: CreateProjectionLight");
+        api_state.report(ApiError::unsupported("LightSource", "projection"))?;
     } else if api_state.param_set.name == "distant" {
         // CreateDistantLight
         let l: Spectrum = api_state
@@ -550,8 +1065,10 @@ fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
                 texmap = String::from(path_buf.to_str().unwrap());
             }
         }
-        let n_samples: i32 = api_state.param_set.find_one_int("nsamples", 1 as i32);
-        // TODO: if (PbrtOptions.quickRender) nSamples = std::max(1, nSamples / 4);
+        let mut n_samples: i32 = api_state.param_set.find_one_int("nsamples", 1 as i32);
+        if api_state.quick_render {
+            n_samples = std::cmp::max(1, n_samples / 4);
+        }
 
         // return std::make_shared<InfiniteAreaLight>(light2world, L * sc, nSamples, texmap);
         let infinte_light = Arc::new(InfiniteAreaLight::new(
@@ -562,14 +1079,18 @@ fn make_light(api_state: &mut ApiState, medium_interface: &MediumInterface) {
         ));
         api_state.render_options.lights.push(infinte_light);
     } else {
-        panic!("MakeLight: unknown name {}", api_state.param_set.name);
+        api_state.report(ApiError::unknown("LightSource", &api_state.param_set.name))?;
     }
+    Ok(())
 }
 
-fn make_medium(api_state: &mut ApiState) {
+fn make_medium(api_state: &mut ApiState) -> Result<(), ApiError> {
     let medium_type: String = api_state.param_set.find_one_string("type", String::new());
     if medium_type == "" {
-        panic!("ERROR: No parameter string \"type\" found in MakeNamedMedium");
+        return api_state.report(ApiError::invalid_parameter(
+            "MakeNamedMedium",
+            String::from("no parameter string \"type\" found"),
+        ));
     }
     // MakeMedium (api.cpp:685)
     let sig_a_rgb: [Float; 3] = [0.0011, 0.0024, 0.014];
@@ -592,9 +1113,9 @@ fn make_medium(api_state: &mut ApiState) {
     if medium_type == "homogeneous" {
         some_medium = Some(Arc::new(HomogeneousMedium::new(&sig_a, &sig_s, g)));
     } else if medium_type == "heterogeneous" {
-        panic!("TODO: make_medium(\"heterogeneous\")");
+        return api_state.report(ApiError::unsupported("MakeNamedMedium", &medium_type));
     } else {
-        panic!("MakeMedium: unknown name {}", medium_type);
+        return api_state.report(ApiError::unknown("MakeNamedMedium", &medium_type));
     }
     if let Some(medium) = some_medium {
         api_state
@@ -602,20 +1123,59 @@ fn make_medium(api_state: &mut ApiState) {
             .named_media
             .insert(api_state.param_set.name.clone(), medium);
     }
+    Ok(())
 }
 
-fn make_texture(api_state: &mut ApiState) {
-    // pbrtTexture (api.cpp:1049)
-    let mut geom_params: ParamSet = ParamSet::default();
-    let mut material_params: ParamSet = ParamSet::default();
-    geom_params.copy_from(&api_state.param_set);
-    material_params.copy_from(&api_state.param_set);
-    let mut tp: TextureParams = TextureParams {
-        float_textures: api_state.graphics_state.float_textures.clone(),
-        spectrum_textures: api_state.graphics_state.spectrum_textures.clone(),
-        geom_params: geom_params,
-        material_params: material_params,
+/// Creates the aggregate selected by the **Accelerator** statement,
+/// the only place which needs to know about all accelerators.
+fn make_accelerator(
+    name: &str,
+    prims: Vec<Arc<Primitive + Sync + Send>>,
+    params: &ParamSet,
+) -> Result<Arc<Primitive + Sync + Send>, ApiError> {
+    let accelerator: Arc<Primitive + Sync + Send> = match name {
+        "bvh" => BVHAccel::create(prims, params),
+        "kdtree" => KdTreeAccel::create(prims, params),
+        _ => return Err(ApiError::unknown("Accelerator", name)),
     };
+    Ok(accelerator)
+}
+
+/// Restricts the film's sample bounds to the integrator's optional
+/// "pixelbounds" parameter (x0 x1 y0 y1), to rerender part of an image.
+fn make_pixel_bounds(params: &ParamSet, sample_bounds: Bounds2i) -> Result<Bounds2i, ApiError> {
+    let pb: Vec<i32> = params.find_int("pixelbounds");
+    let np: usize = pb.len();
+    if np == 0 as usize {
+        return Ok(sample_bounds);
+    }
+    if np != 4 as usize {
+        return Err(ApiError::invalid_parameter(
+            "Integrator",
+            format!(
+                "Expected four values for \"pixelbounds\" parameter. Got {}.",
+                np
+            ),
+        ));
+    }
+    let pixel_bounds: Bounds2i = bnd2_intersect_bnd2(
+        &sample_bounds,
+        &Bounds2i {
+            p_min: Point2i { x: pb[0], y: pb[2] },
+            p_max: Point2i { x: pb[1], y: pb[3] },
+        },
+    );
+    if pixel_bounds.p_max.x <= pixel_bounds.p_min.x || pixel_bounds.p_max.y <= pixel_bounds.p_min.y
+    {
+        return Err(ApiError::invalid_parameter(
+            "Integrator",
+            String::from("Degenerate \"pixelbounds\" specified."),
+        ));
+    }
+    Ok(pixel_bounds)
+}
+
+fn make_texture(api_state: &mut ApiState, tp: &mut TextureParams) -> Result<(), ApiError> {
     if api_state.param_set.tex_type == "float" {
         match api_state
             .graphics_state
@@ -630,7 +1190,10 @@ fn make_texture(api_state: &mut ApiState) {
         // TODO: WARN_IF_ANIMATED_TRANSFORM("Texture");
         // MakeFloatTexture(texname, curTransform[0], tp);
         if api_state.param_set.tex_name == "constant" {
-            println!("TODO: CreateConstantFloatTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "scale" {
             let ft = Arc::new(ScaleTexture::<Float>::new(
                 tp.get_float_texture("tex1", 1.0 as Float),
@@ -641,9 +1204,15 @@ fn make_texture(api_state: &mut ApiState) {
                 .float_textures
                 .insert(api_state.param_set.name.clone(), ft);
         } else if api_state.param_set.tex_name == "mix" {
-            println!("TODO: CreateMixFloatTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "bilerp" {
-            println!("TODO: CreateBilerpFloatTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "imagemap" {
             // CreateImageFloatTexture
             let mut map: Option<Box<TextureMapping2D + Send + Sync>> = None;
@@ -660,9 +1229,9 @@ fn make_texture(api_state: &mut ApiState) {
                     dv: dv,
                 }));
             } else if mapping == "spherical" {
-                println!("TODO: SphericalMapping2D");
+                api_state.report(ApiError::unsupported("Texture mapping", &mapping))?;
             } else if mapping == "cylindrical" {
-                println!("TODO: CylindricalMapping2D");
+                api_state.report(ApiError::unsupported("Texture mapping", &mapping))?;
             } else if mapping == "planar" {
                 map = Some(Box::new(PlanarMapping2D {
                     vs: tp.find_vector3f(
@@ -685,7 +1254,7 @@ fn make_texture(api_state: &mut ApiState) {
                     dt: tp.find_float("vdelta", 0.0),
                 }));
             } else {
-                panic!("2D texture mapping \"{}\" unknown", mapping);
+                api_state.report(ApiError::unknown("Texture mapping", &mapping))?;
             }
             // initialize _ImageTexture_ parameters
             let max_aniso: Float = tp.find_float("maxanisotropy", 8.0);
@@ -730,17 +1299,35 @@ fn make_texture(api_state: &mut ApiState) {
                     .insert(api_state.param_set.name.clone(), ft);
             }
         } else if api_state.param_set.tex_name == "uv" {
-            println!("TODO: CreateUVFloatTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "checkerboard" {
-            println!("TODO: CreateCheckerboardFloatTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "dots" {
-            println!("TODO: CreateDotsFloatTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "fbm" {
-            println!("TODO: CreateFBmFloatTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "wrinkled" {
-            println!("TODO: CreateWrinkledFloatTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "marble" {
-            println!("TODO: CreateMarbleFloatTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "windy" {
             let tex_2_world: Transform = Transform {
                 m: api_state.cur_transform.t[0].m,
@@ -754,12 +1341,12 @@ fn make_texture(api_state: &mut ApiState) {
                 .float_textures
                 .insert(api_state.param_set.name.clone(), wt);
         } else if api_state.param_set.tex_name == "ptex" {
-            println!("TODO: CreatePtexFloatTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else {
-            println!(
-                "Float texture \"{}\" unknown.",
-                api_state.param_set.tex_name
-            );
+            api_state.report(ApiError::unknown("Texture", &api_state.param_set.tex_name))?;
         }
     } else if api_state.param_set.tex_type == "color" || api_state.param_set.tex_type == "spectrum"
     {
@@ -794,9 +1381,15 @@ fn make_texture(api_state: &mut ApiState) {
                 .spectrum_textures
                 .insert(api_state.param_set.name.clone(), st);
         } else if api_state.param_set.tex_name == "mix" {
-            println!("TODO: CreateMixSpectrumTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "bilerp" {
-            println!("TODO: CreateBilerpSpectrumTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "imagemap" {
             // CreateImageSpectrumTexture
             let mut map: Option<Box<TextureMapping2D + Send + Sync>> = None;
@@ -813,9 +1406,9 @@ fn make_texture(api_state: &mut ApiState) {
                     dv: dv,
                 }));
             } else if mapping == "spherical" {
-                println!("TODO: SphericalMapping2D");
+                api_state.report(ApiError::unsupported("Texture mapping", &mapping))?;
             } else if mapping == "cylindrical" {
-                println!("TODO: CylindricalMapping2D");
+                api_state.report(ApiError::unsupported("Texture mapping", &mapping))?;
             } else if mapping == "planar" {
                 map = Some(Box::new(PlanarMapping2D {
                     vs: tp.find_vector3f(
@@ -838,7 +1431,7 @@ fn make_texture(api_state: &mut ApiState) {
                     dt: tp.find_float("vdelta", 0.0),
                 }));
             } else {
-                panic!("2D texture mapping \"{}\" unknown", mapping);
+                api_state.report(ApiError::unknown("Texture mapping", &mapping))?;
             }
             // initialize _ImageTexture_ parameters
             let max_aniso: Float = tp.find_float("maxanisotropy", 8.0);
@@ -883,12 +1476,18 @@ fn make_texture(api_state: &mut ApiState) {
                     .insert(api_state.param_set.name.clone(), st);
             }
         } else if api_state.param_set.tex_name == "uv" {
-            println!("TODO: CreateUVSpectrumTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "checkerboard" {
             // CreateCheckerboardSpectrumTexture
             let dim: i32 = tp.find_int("dimension", 2);
             if dim != 2 && dim != 3 {
-                panic!("{} dimensional checkerboard texture not supported", dim);
+                return api_state.report(ApiError::invalid_parameter(
+                    "Texture",
+                    format!("{} dimensional checkerboard texture not supported", dim),
+                ));
             }
             let tex1: Arc<Texture<Spectrum> + Send + Sync> =
                 tp.get_spectrum_texture("tex1", Spectrum::new(1.0));
@@ -909,9 +1508,9 @@ fn make_texture(api_state: &mut ApiState) {
                         dv: dv,
                     }));
                 } else if mapping == "spherical" {
-                    println!("TODO: SphericalMapping2D");
+                    api_state.report(ApiError::unsupported("Texture mapping", &mapping))?;
                 } else if mapping == "cylindrical" {
-                    println!("TODO: CylindricalMapping2D");
+                    api_state.report(ApiError::unsupported("Texture mapping", &mapping))?;
                 } else if mapping == "planar" {
                     map = Some(Box::new(PlanarMapping2D {
                         vs: tp.find_vector3f(
@@ -934,7 +1533,7 @@ fn make_texture(api_state: &mut ApiState) {
                         dt: tp.find_float("vdelta", 0.0),
                     }));
                 } else {
-                    panic!("2D texture mapping \"{}\" unknown", mapping);
+                    api_state.report(ApiError::unknown("Texture mapping", &mapping))?;
                 }
                 // TODO: aamode
                 if let Some(mapping) = map {
@@ -946,60 +1545,72 @@ fn make_texture(api_state: &mut ApiState) {
                 }
             } else {
                 // dim == 3
-                println!("TODO: TextureMapping3D");
+                api_state.report(ApiError::unsupported("Texture", "checkerboard (3D)"))?;
             }
         } else if api_state.param_set.tex_name == "dots" {
-            println!("TODO: CreateDotsSpectrumTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "fbm" {
-            println!("TODO: CreateFBmSpectrumTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "wrinkled" {
-            println!("TODO: CreateWrinkledSpectrumTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "marble" {
-            println!("TODO: CreateMarbleSpectrumTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else if api_state.param_set.tex_name == "windy" {
-            println!("TODO: CreateWindySpectrumTexture");
+            api_state.report(ApiError::unsupported(
+                "Texture",
+                &api_state.param_set.tex_name,
+            ))?;
         } else {
-            println!(
-                "Spectrum texture \"{}\" unknown.",
-                api_state.param_set.tex_name
-            );
+            api_state.report(ApiError::unknown("Texture", &api_state.param_set.tex_name))?;
         }
     } else {
-        panic!("Texture type \"{}\" unknown.", api_state.param_set.tex_type);
+        api_state.report(ApiError::unknown(
+            "Texture type",
+            &api_state.param_set.tex_type,
+        ))?;
     }
     // MakeFloatTexture(texname, curTransform[0], tp);
     // or
     // MakeSpectrumTexture(texname, curTransform[0], tp);
+    Ok(())
 }
 
 fn get_shapes_and_materials(
-    api_state: &ApiState,
+    api_state: &mut ApiState,
     bsdf_state: &mut BsdfState,
-) -> (
-    Vec<Arc<Shape + Send + Sync>>,
-    Vec<Option<Arc<Material + Send + Sync>>>,
-) {
+) -> Result<
+    (
+        Vec<Arc<Shape + Send + Sync>>,
+        Vec<Option<Arc<Material + Send + Sync>>>,
+    ),
+    ApiError,
+> {
     let mut shapes: Vec<Arc<Shape + Send + Sync>> = Vec::new();
     let mut materials: Vec<Option<Arc<Material + Send + Sync>>> = Vec::new();
     // pbrtShape (api.cpp:1153)
-    // TODO: if (!curTransform.IsAnimated()) { ... }
-    // TODO: transformCache.Lookup(curTransform[0], &ObjToWorld, &WorldToObj);
-    let mut obj_to_world: Transform = Transform {
-        m: api_state.cur_transform.t[0].m,
-        m_inv: api_state.cur_transform.t[0].m_inv,
-    };
-    let mut world_to_obj: Transform = Transform {
-        m: api_state.cur_transform.t[0].m_inv,
-        m_inv: api_state.cur_transform.t[0].m,
-    };
+    let obj_to_world: Arc<Transform>;
+    let world_to_obj: Arc<Transform>;
     if api_state.cur_transform.is_animated() {
-        if api_state.graphics_state.area_light != String::from("") {
-            println!("WARNING: Ignoring currently set area light when creating animated shape",);
-        }
-        // WORK
-        // set both transforms to identity
-        obj_to_world = Transform::default();
-        world_to_obj = Transform::default();
+        // animated shapes are created in object space,
+        // pbrt_shape() moves them with a _TransformedPrimitive_
+        obj_to_world = api_state.transform_cache.lookup(&Transform::default());
+        world_to_obj = obj_to_world.clone();
+    } else {
+        let t: Transform = api_state.cur_transform.t[0];
+        obj_to_world = api_state.transform_cache.lookup(&t);
+        world_to_obj = api_state.transform_cache.lookup(&Transform::inverse(&t));
     }
     // MakeShapes (api.cpp:296)
     if api_state.param_set.name == "sphere" {
@@ -1009,8 +1620,8 @@ fn get_shapes_and_materials(
         let z_max: Float = api_state.param_set.find_one_float("zmax", radius);
         let phi_max: Float = api_state.param_set.find_one_float("phimax", 360.0 as Float);
         let sphere = Arc::new(Sphere::new(
-            obj_to_world,
-            world_to_obj,
+            obj_to_world.clone(),
+            world_to_obj.clone(),
             false,
             false,
             radius,
@@ -1018,7 +1629,7 @@ fn get_shapes_and_materials(
             z_max,
             phi_max,
         ));
-        let mtl: Option<Arc<Material + Send + Sync>> = create_material(&api_state, bsdf_state);
+        let mtl: Option<Arc<Material + Send + Sync>> = create_material(api_state, bsdf_state)?;
         shapes.push(sphere.clone());
         materials.push(mtl);
     } else if api_state.param_set.name == "cylinder" {
@@ -1027,15 +1638,15 @@ fn get_shapes_and_materials(
         let z_max: Float = api_state.param_set.find_one_float("zmax", radius);
         let phi_max: Float = api_state.param_set.find_one_float("phimax", 360.0 as Float);
         let cylinder = Arc::new(Cylinder::new(
-            obj_to_world,
-            world_to_obj,
+            obj_to_world.clone(),
+            world_to_obj.clone(),
             false,
             radius,
             z_min,
             z_max,
             phi_max,
         ));
-        let mtl: Option<Arc<Material + Send + Sync>> = create_material(&api_state, bsdf_state);
+        let mtl: Option<Arc<Material + Send + Sync>> = create_material(api_state, bsdf_state)?;
         shapes.push(cylinder.clone());
         materials.push(mtl.clone());
     } else if api_state.param_set.name == "disk" {
@@ -1044,8 +1655,8 @@ fn get_shapes_and_materials(
         let inner_radius: Float = api_state.param_set.find_one_float("innerradius", 0.0);
         let phi_max: Float = api_state.param_set.find_one_float("phimax", 360.0);
         let disk = Arc::new(Disk::new(
-            obj_to_world,
-            world_to_obj,
+            obj_to_world.clone(),
+            world_to_obj.clone(),
             false,
             false,
             height,
@@ -1053,17 +1664,17 @@ fn get_shapes_and_materials(
             inner_radius,
             phi_max,
         ));
-        let mtl: Option<Arc<Material + Send + Sync>> = create_material(&api_state, bsdf_state);
+        let mtl: Option<Arc<Material + Send + Sync>> = create_material(api_state, bsdf_state)?;
         shapes.push(disk.clone());
         materials.push(mtl.clone());
     } else if api_state.param_set.name == "cone" {
-        println!("TODO: CreateConeShape");
+        api_state.report(ApiError::unsupported("Shape", &api_state.param_set.name))?;
     } else if api_state.param_set.name == "paraboloid" {
-        println!("TODO: CreateParaboloidShape");
+        api_state.report(ApiError::unsupported("Shape", &api_state.param_set.name))?;
     } else if api_state.param_set.name == "hyperboloid" {
-        println!("TODO: CreateHyperboloidShape");
+        api_state.report(ApiError::unsupported("Shape", &api_state.param_set.name))?;
     } else if api_state.param_set.name == "curve" {
-        let mtl: Option<Arc<Material + Send + Sync>> = create_material(&api_state, bsdf_state);
+        let mtl: Option<Arc<Material + Send + Sync>> = create_material(api_state, bsdf_state)?;
         let curve_shapes: Vec<Arc<Shape + Send + Sync>> = create_curve_shape(
             &obj_to_world,
             &world_to_obj,
@@ -1128,11 +1739,15 @@ fn get_shapes_and_materials(
         }
         for i in 0..vi.len() {
             if vi[i] as usize >= p.len() {
-                panic!(
-                    "trianglemesh has out of-bounds vertex index {} ({} \"P\" values were given)",
-                    vi[i],
-                    p.len()
-                );
+                api_state.report(ApiError::invalid_parameter(
+                    "Shape",
+                    format!(
+                        "trianglemesh has out of-bounds vertex index {} ({} \"P\" values were given)",
+                        vi[i],
+                        p.len()
+                    ),
+                ))?;
+                return Ok((shapes, materials));
             }
         }
         // TODO: alpha
@@ -1148,9 +1763,9 @@ fn get_shapes_and_materials(
         for i in 0..vi.len() {
             vertex_indices.push(vi[i] as usize);
         }
-        let mesh = Arc::new(TriangleMesh::new(
-            obj_to_world,
-            world_to_obj,
+        let mut mesh: TriangleMesh = TriangleMesh::new(
+            obj_to_world.clone(),
+            world_to_obj.clone(),
             api_state.graphics_state.reverse_orientation,
             false,        // transform_swaps_handedness
             vi.len() / 3, // n_triangles
@@ -1160,12 +1775,57 @@ fn get_shapes_and_materials(
             s_ws, // in world space
             n_ws, // in world space
             uvs,
-        ));
-        let mtl: Option<Arc<Material + Send + Sync>> = create_material(&api_state, bsdf_state);
+        );
+        // deformation motion blur: "P1" (and "N1") at shutter close
+        let p1 = api_state.param_set.find_point3f("P1");
+        if !p1.is_empty() {
+            let n1 = api_state.param_set.find_normal3f("N1");
+            if p1.len() != p.len() {
+                api_state.report(ApiError::invalid_parameter(
+                    "Shape",
+                    format!(
+                        "trianglemesh has {} \"P1\" values but {} \"P\" values, ignoring \"P1\"",
+                        p1.len(),
+                        p.len()
+                    ),
+                ))?;
+            } else if !n1.is_empty() && n1.len() != mesh.n.len() {
+                api_state.report(ApiError::invalid_parameter(
+                    "Shape",
+                    format!(
+                        "trianglemesh has {} \"N1\" values but {} \"N\" values, ignoring \"P1\"",
+                        n1.len(),
+                        mesh.n.len()
+                    ),
+                ))?;
+            } else {
+                let mut p1_ws: Vec<Point3f> = Vec::new();
+                for i in 0..p1.len() {
+                    p1_ws.push(obj_to_world.transform_point(&p1[i]));
+                }
+                let mut n1_ws: Vec<Normal3f> = Vec::new();
+                for i in 0..n1.len() {
+                    n1_ws.push(obj_to_world.transform_normal(&n1[i]));
+                }
+                mesh.set_motion(
+                    p1_ws,
+                    n1_ws,
+                    api_state.render_options.transform_start_time,
+                    api_state.render_options.transform_end_time,
+                );
+            }
+        } else if !api_state.param_set.find_normal3f("N1").is_empty() {
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                String::from("ignoring \"N1\" of trianglemesh without \"P1\""),
+            ))?;
+        }
+        let mesh = Arc::new(mesh);
+        let mtl: Option<Arc<Material + Send + Sync>> = create_material(api_state, bsdf_state)?;
         for id in 0..mesh.n_triangles {
             let triangle = Arc::new(Triangle::new(
-                mesh.object_to_world,
-                mesh.world_to_object,
+                mesh.object_to_world.clone(),
+                mesh.world_to_object.clone(),
                 mesh.reverse_orientation,
                 mesh.clone(),
                 id,
@@ -1174,26 +1834,82 @@ fn get_shapes_and_materials(
             materials.push(mtl.clone());
         }
     } else if api_state.param_set.name == "plymesh" {
-        if let Some(ref search_directory) = api_state.search_directory {
-            let mtl: Option<Arc<Material + Send + Sync>> = create_material(&api_state, bsdf_state);
-            let ply_shapes: Vec<Arc<Shape + Send + Sync>> = create_ply_mesh(
+        let mtl: Option<Arc<Material + Send + Sync>> = create_material(api_state, bsdf_state)?;
+        if let Some(search_directory) = api_state.search_directory.clone() {
+            let mut warnings: Vec<String> = Vec::new();
+            let result = create_ply_mesh(
                 &obj_to_world,
                 &world_to_obj,
                 false, // reverse_orientation
                 &api_state.param_set,
                 api_state.graphics_state.float_textures.clone(),
                 // additional parameters:
-                Some(search_directory),
+                Some(&search_directory),
+                api_state.render_options.transform_start_time,
+                api_state.render_options.transform_end_time,
+                &mut warnings,
+            );
+            for warning in warnings {
+                api_state.report(ApiError::invalid_parameter("Shape", warning))?;
+            }
+            match result {
+                Ok(ply_shapes) => {
+                    for shape in ply_shapes {
+                        shapes.push(shape.clone());
+                        materials.push(mtl.clone());
+                    }
+                }
+                Err(message) => {
+                    return Err(ApiError::invalid_parameter("Shape", message));
+                }
+            }
+        } else {
+            return Err(ApiError::invalid_state(
+                "Shape",
+                String::from("no search directory for plymesh"),
+            ));
+        }
+    } else if api_state.param_set.name == "objmesh" {
+        let mtl: Option<Arc<Material + Send + Sync>> = create_material(api_state, bsdf_state)?;
+        if let Some(search_directory) = api_state.search_directory.clone() {
+            let mut warnings: Vec<ApiError> = Vec::new();
+            let result: Result<Vec<ObjMeshPart>, String> = create_obj_mesh(
+                &obj_to_world,
+                &world_to_obj,
+                api_state.graphics_state.reverse_orientation,
+                &api_state.param_set,
+                Some(&search_directory),
+                &mut warnings,
             );
-            for shape in ply_shapes {
-                shapes.push(shape.clone());
-                materials.push(mtl.clone());
+            for warning in warnings {
+                api_state.report(warning)?;
+            }
+            match result {
+                Ok(parts) => {
+                    // materials of the MTL files replace the current one
+                    for part in parts {
+                        let part_mtl: Option<Arc<Material + Send + Sync>> = match part.material {
+                            Some(material) => Some(material),
+                            None => mtl.clone(),
+                        };
+                        for shape in part.shapes {
+                            shapes.push(shape);
+                            materials.push(part_mtl.clone());
+                        }
+                    }
+                }
+                Err(message) => {
+                    return Err(ApiError::invalid_parameter("Shape", message));
+                }
             }
         } else {
-            panic!("No search directory for plymesh.");
+            return Err(ApiError::invalid_state(
+                "Shape",
+                String::from("no search directory for objmesh"),
+            ));
         }
     } else if api_state.param_set.name == "heightfield" {
-        println!("TODO: CreateHeightfield");
+        api_state.report(ApiError::unsupported("Shape", &api_state.param_set.name))?;
     } else if api_state.param_set.name == "loopsubdiv" {
         // CreateLoopSubdiv
         let n_levels: i32 = api_state
@@ -1203,10 +1919,18 @@ fn get_shapes_and_materials(
         let vertex_indices: Vec<i32> = api_state.param_set.find_int("indices");
         let p = api_state.param_set.find_point3f("P");
         if vertex_indices.is_empty() {
-            panic!("Vertex indices \"indices\" not provided for LoopSubdiv shape.");
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                String::from("Vertex indices \"indices\" not provided for LoopSubdiv shape."),
+            ))?;
+            return Ok((shapes, materials));
         }
         if p.is_empty() {
-            panic!("Vertex positions \"P\" not provided for LoopSubdiv shape.");
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                String::from("Vertex positions \"P\" not provided for LoopSubdiv shape."),
+            ))?;
+            return Ok((shapes, materials));
         }
         // don't actually use this for now...
         let _scheme: String = api_state
@@ -1220,11 +1944,11 @@ fn get_shapes_and_materials(
             &vertex_indices,
             &p,
         );
-        let mtl: Option<Arc<Material + Send + Sync>> = create_material(&api_state, bsdf_state);
+        let mtl: Option<Arc<Material + Send + Sync>> = create_material(api_state, bsdf_state)?;
         for id in 0..mesh.n_triangles {
             let triangle = Arc::new(Triangle::new(
-                mesh.object_to_world,
-                mesh.world_to_object,
+                mesh.object_to_world.clone(),
+                mesh.world_to_object.clone(),
                 mesh.reverse_orientation,
                 mesh.clone(),
                 id,
@@ -1236,19 +1960,39 @@ fn get_shapes_and_materials(
         // CreateNURBS
         let nu: i32 = api_state.param_set.find_one_int("nu", -1);
         if nu == -1_i32 {
-            panic!("Must provide number of control points \"nu\" with NURBS shape.");
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                String::from("Must provide number of control points \"nu\" with NURBS shape."),
+            ))?;
+            return Ok((shapes, materials));
         }
         let uorder: i32 = api_state.param_set.find_one_int("uorder", -1);
         if uorder == -1_i32 {
-            panic!("Must provide u order \"uorder\" with NURBS shape.");
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                String::from("Must provide u order \"uorder\" with NURBS shape."),
+            ))?;
+            return Ok((shapes, materials));
         }
         let uknots: Vec<Float> = api_state.param_set.find_float("uknots");
         if uknots.is_empty() {
-            panic!("Must provide u knot vector \"uknots\" with NURBS shape.");
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                String::from("Must provide u knot vector \"uknots\" with NURBS shape."),
+            ))?;
+            return Ok((shapes, materials));
         }
         if uknots.len() != (nu + uorder) as usize {
-            panic!("Number of knots in u knot vector {} doesn't match sum of number of u control points {} and u order {}.",
-                   uknots.len(), nu, uorder);
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                format!(
+                    "Number of knots in u knot vector {} doesn't match sum of number of u control points {} and u order {}.",
+                    uknots.len(),
+                    nu,
+                    uorder
+                ),
+            ))?;
+            return Ok((shapes, materials));
         }
         let u0: Float = api_state
             .param_set
@@ -1258,19 +2002,39 @@ fn get_shapes_and_materials(
             .find_one_float("u1", uknots[nu as usize]);
         let nv: i32 = api_state.param_set.find_one_int("nv", -1);
         if nv == -1_i32 {
-            panic!("Must provide number of control points \"nv\" with NURBS shape.");
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                String::from("Must provide number of control points \"nv\" with NURBS shape."),
+            ))?;
+            return Ok((shapes, materials));
         }
         let vorder: i32 = api_state.param_set.find_one_int("vorder", -1);
         if vorder == -1_i32 {
-            panic!("Must provide u order \"vorder\" with NURBS shape.");
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                String::from("Must provide u order \"vorder\" with NURBS shape."),
+            ))?;
+            return Ok((shapes, materials));
         }
         let vknots: Vec<Float> = api_state.param_set.find_float("vknots");
         if vknots.is_empty() {
-            panic!("Must provide u knot vector \"vknots\" with NURBS shape.");
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                String::from("Must provide u knot vector \"vknots\" with NURBS shape."),
+            ))?;
+            return Ok((shapes, materials));
         }
         if vknots.len() != (nv + vorder) as usize {
-            panic!("Number of knots in v knot vector {} doesn't match sum of number of v control points {} and v order {}.",
-                   vknots.len(), nv, vorder);
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                format!(
+                    "Number of knots in v knot vector {} doesn't match sum of number of v control points {} and v order {}.",
+                    vknots.len(),
+                    nv,
+                    vorder
+                ),
+            ))?;
+            return Ok((shapes, materials));
         }
         let v0: Float = api_state
             .param_set
@@ -1285,22 +2049,38 @@ fn get_shapes_and_materials(
         if p.is_empty() {
             pw = api_state.param_set.find_float("Pw");
             if pw.is_empty() {
-                panic!("Must provide control points via \"P\" or \"Pw\" parameter to NURBS shape.");
+                api_state.report(ApiError::invalid_parameter(
+                    "Shape",
+                    String::from(
+                        "Must provide control points via \"P\" or \"Pw\" parameter to NURBS shape.",
+                    ),
+                ))?;
+                return Ok((shapes, materials));
             }
             if pw.len() % 4 != 0 {
-                panic!("Number of \"Pw\" control points provided to NURBS shape must be multiple of four");
+                api_state.report(ApiError::invalid_parameter(
+                    "Shape",
+                    String::from(
+                        "Number of \"Pw\" control points provided to NURBS shape must be multiple of four",
+                    ),
+                ))?;
+                return Ok((shapes, materials));
             }
             npts = pw.len() / 4_usize;
             is_homogeneous = true;
         }
         if npts != (nu * nv) as usize {
-            panic!(
-                "NURBS shape was expecting {}x{}={} control points, was given {}",
-                nu,
-                nv,
-                nu * nv,
-                npts
-            );
+            api_state.report(ApiError::invalid_parameter(
+                "Shape",
+                format!(
+                    "NURBS shape was expecting {}x{}={} control points, was given {}",
+                    nu,
+                    nv,
+                    nu * nv,
+                    npts
+                ),
+            ))?;
+            return Ok((shapes, materials));
         }
         // compute NURBS dicing rates
         let diceu: usize = 30;
@@ -1394,8 +2174,8 @@ fn get_shapes_and_materials(
             n_ws.push(obj_to_world.transform_normal(&eval_ns[i]));
         }
         let mesh = Arc::new(TriangleMesh::new(
-            obj_to_world,
-            world_to_obj,
+            obj_to_world.clone(),
+            world_to_obj.clone(),
             api_state.graphics_state.reverse_orientation,
             false,  // transform_swaps_handedness
             n_tris, // n_triangles
@@ -1406,11 +2186,11 @@ fn get_shapes_and_materials(
             n_ws,       // in world space
             uvs,
         ));
-        let mtl: Option<Arc<Material + Send + Sync>> = create_material(&api_state, bsdf_state);
+        let mtl: Option<Arc<Material + Send + Sync>> = create_material(api_state, bsdf_state)?;
         for id in 0..mesh.n_triangles {
             let triangle = Arc::new(Triangle::new(
-                mesh.object_to_world,
-                mesh.world_to_object,
+                mesh.object_to_world.clone(),
+                mesh.world_to_object.clone(),
                 mesh.reverse_orientation,
                 mesh.clone(),
                 id,
@@ -1419,9 +2199,9 @@ fn get_shapes_and_materials(
             materials.push(mtl.clone());
         }
     } else {
-        panic!("Shape \"{}\" unknown.", api_state.param_set.name);
+        api_state.report(ApiError::unknown("Shape", &api_state.param_set.name))?;
     }
-    (shapes, materials)
+    Ok((shapes, materials))
 }
 
 fn print_params(params: &ParamSet) {
@@ -1514,25 +2294,107 @@ fn print_params(params: &ParamSet) {
     }
 }
 
+/// The integrators don't share a common trait (yet), each kind comes
+/// with its own render function.
+pub enum RenderIntegrator {
+    Sampler(Box<SamplerIntegrator + Sync + Send>),
+    BDPT(Box<BDPTIntegrator>),
+    MLT(Box<MLTIntegrator>),
+    SPPM(Box<SPPMIntegrator>),
+}
+
+/// Everything needed to render an image, as created by
+/// **pbrt_world_end()** at the end of the scene description.
+pub struct RenderSetup {
+    pub scene: Scene,
+    pub camera: Arc<Camera + Sync + Send>,
+    pub sampler: Box<Sampler + Sync + Send>,
+    pub integrator: RenderIntegrator,
+    pub number_of_threads: u8,
+    pub checkpoint: Checkpoint,
+}
+
+impl RenderSetup {
+    pub fn render(&mut self) {
+        let num_threads: u8 = self.number_of_threads;
+        match self.integrator {
+            RenderIntegrator::Sampler(ref mut integrator) => render(
+                &self.scene,
+                &self.camera,
+                &mut self.sampler,
+                integrator,
+                num_threads,
+                &self.checkpoint,
+            ),
+            RenderIntegrator::BDPT(ref mut integrator) => render_bdpt(
+                &self.scene,
+                &self.camera,
+                &mut self.sampler,
+                integrator,
+                num_threads,
+                &self.checkpoint,
+            ),
+            RenderIntegrator::MLT(ref mut integrator) => render_mlt(
+                &self.scene,
+                &self.camera,
+                &mut self.sampler,
+                integrator,
+                num_threads,
+            ),
+            RenderIntegrator::SPPM(ref mut integrator) => render_sppm(
+                &self.scene,
+                &self.camera,
+                &mut self.sampler,
+                integrator,
+                num_threads,
+            ),
+        }
+    }
+}
+
 pub fn pbrt_init(number_of_threads: u8) -> (ApiState, BsdfState) {
     let mut api_state: ApiState = ApiState::default();
     let bsdf_state: BsdfState = BsdfState::default();
     api_state.number_of_threads = number_of_threads;
+    init_profiler();
     (api_state, bsdf_state)
 }
 
-pub fn pbrt_cleanup(api_state: &ApiState) {
+/// Ends the scene description and renders the image. If a scene
+/// writer is set, only the final `WorldEnd` gets written.
+pub fn pbrt_cleanup(api_state: &mut ApiState) -> Result<(), ApiError> {
     // println!("WorldEnd");
-    assert!(
-        api_state.pushed_graphics_states.len() == 0_usize,
-        "Missing end to pbrtAttributeBegin()"
-    );
-    assert!(
-        api_state.pushed_transforms.len() == 0_usize,
-        "Missing end to pbrtTransformBegin()"
-    );
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement("WorldEnd")?;
+        return writer.flush();
+    }
+    let mut render_setup: RenderSetup = pbrt_world_end(api_state)?;
+    render_setup.render();
+    print_stats();
+    report_profiler_results();
+    clear_stats();
+    clear_profiler();
+    Ok(())
+}
+
+/// Creates the scene, camera, sampler, and integrator from everything
+/// collected so far, without rendering (see pbrtWorldEnd() in
+/// api.cpp).
+pub fn pbrt_world_end(api_state: &mut ApiState) -> Result<RenderSetup, ApiError> {
+    if api_state.pushed_graphics_states.len() != 0_usize {
+        return Err(ApiError::invalid_state(
+            "WorldEnd",
+            String::from("missing end to AttributeBegin"),
+        ));
+    }
+    if api_state.pushed_transforms.len() != 0_usize {
+        return Err(ApiError::invalid_state(
+            "WorldEnd",
+            String::from("missing end to TransformBegin"),
+        ));
+    }
     // MakeFilter
-    let mut some_filter: Option<Arc<Filter + Sync + Send>> = None;
+    let some_filter: Option<Arc<Filter + Sync + Send>>;
     if api_state.render_options.filter_name == "box" {
         some_filter = Some(BoxFilter::create(&api_state.render_options.filter_params));
     } else if api_state.render_options.filter_name == "gaussian" {
@@ -1544,52 +2406,84 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
             &api_state.render_options.filter_params,
         ));
     } else if api_state.render_options.filter_name == "sinc" {
-        println!("TODO: CreateSincFilter");
+        return Err(ApiError::unsupported("PixelFilter", "sinc"));
     } else if api_state.render_options.filter_name == "triangle" {
         some_filter = Some(TriangleFilter::create(
             &api_state.render_options.filter_params,
         ));
     } else {
-        panic!(
-            "Filter \"{}\" unknown.",
-            api_state.render_options.filter_name
-        );
+        return Err(ApiError::unknown(
+            "PixelFilter",
+            &api_state.render_options.filter_name,
+        ));
     }
     // MakeFilm
     if api_state.render_options.film_name == "image" {
-        let filename: String = api_state
+        let mut filename: String = api_state
             .render_options
             .film_params
             .find_one_string("filename", String::new());
-        let xres: i32 = api_state
+        if let Some(ref image_file) = api_state.image_file {
+            if filename != "" {
+                println!(
+                    "WARNING: Output filename supplied on command line, \"{}\" is overriding filename provided in scene description file, \"{}\".",
+                    image_file, filename
+                );
+            }
+            filename = image_file.clone();
+        }
+        if filename == "" {
+            filename = String::from("pbrt.png");
+        }
+        if let Err(e) = check_image_file(&filename) {
+            return Err(ApiError::invalid_parameter("Film", format!("{}.", e)));
+        }
+        let bit_depth: i32 = api_state
+            .render_options
+            .film_params
+            .find_one_int("bitdepth", 8);
+        if bit_depth != 8 && bit_depth != 16 {
+            return Err(ApiError::invalid_parameter(
+                "Film",
+                format!("\"bitdepth\" must be 8 or 16, not {}.", bit_depth),
+            ));
+        }
+        let mut xres: i32 = api_state
             .render_options
             .film_params
             .find_one_int("xresolution", 1280);
-        let yres: i32 = api_state
+        let mut yres: i32 = api_state
             .render_options
             .film_params
             .find_one_int("yresolution", 720);
-        // TODO: if (PbrtOptions.quickRender) xres = std::max(1, xres / 4);
-        // TODO: if (PbrtOptions.quickRender) yres = std::max(1, yres / 4);
+        if api_state.quick_render {
+            xres = std::cmp::max(1, xres / 4);
+            yres = std::cmp::max(1, yres / 4);
+        }
         let mut crop: Bounds2f = Bounds2f {
             p_min: Point2f { x: 0.0, y: 0.0 },
             p_max: Point2f { x: 1.0, y: 1.0 },
         };
-        // TODO: const Float *cr = params.FindFloat("cropwindow", &cwi);
-        let cr: Vec<Float> = api_state
+        let mut cr: Vec<Float> = api_state
             .render_options
             .film_params
             .find_float("cropwindow");
+        if let Some(crop_window) = api_state.crop_window {
+            cr = crop_window.to_vec();
+        }
         if cr.len() == 4 {
             crop.p_min.x = clamp_t(cr[0].min(cr[1]), 0.0, 1.0);
             crop.p_max.x = clamp_t(cr[0].max(cr[1]), 0.0, 1.0);
             crop.p_min.y = clamp_t(cr[2].min(cr[3]), 0.0, 1.0);
             crop.p_max.y = clamp_t(cr[2].max(cr[3]), 0.0, 1.0);
         } else if cr.len() != 0 {
-            panic!(
-                "{:?} values supplied for \"cropwindow\". Expected 4.",
-                cr.len()
-            );
+            return Err(ApiError::invalid_parameter(
+                "Film",
+                format!(
+                    "{:?} values supplied for \"cropwindow\". Expected 4.",
+                    cr.len()
+                ),
+            ));
         }
         let scale: Float = api_state
             .render_options
@@ -1603,6 +2497,38 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
             .render_options
             .film_params
             .find_one_float("maxsampleluminance", std::f32::INFINITY);
+        let mut aovs: Vec<AovType> = Vec::new();
+        for name in api_state.render_options.film_params.find_string("aovs") {
+            match AovType::from_name(&name) {
+                Some(aov) => {
+                    if aovs.contains(&aov) {
+                        api_state.report(ApiError::invalid_parameter(
+                            "Film",
+                            format!("AOV \"{}\" is given more than once.", name),
+                        ))?;
+                    } else {
+                        aovs.push(aov);
+                    }
+                }
+                None => {
+                    return Err(ApiError::invalid_parameter(
+                        "Film",
+                        format!("Unknown AOV \"{}\".", name),
+                    ));
+                }
+            }
+        }
+        if !aovs.is_empty() {
+            let integrator_name: String = api_state.render_options.integrator_name.clone();
+            if integrator_name == "bdpt" || integrator_name == "mlt" || integrator_name == "sppm" {
+                // don't allocate (or write) layers which stay empty
+                api_state.report(ApiError::unsupported(
+                    "AOVs of Integrator",
+                    &integrator_name,
+                ))?;
+                aovs.clear();
+            }
+        }
         if let Some(filter) = some_filter {
             let film: Arc<Film> = Arc::new(Film::new(
                 Point2i { x: xres, y: yres },
@@ -1610,13 +2536,15 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                 filter,
                 diagonal,
                 filename,
+                bit_depth as u8,
                 scale,
                 max_sample_luminance,
+                aovs,
             ));
             // MakeCamera
             // TODO: let mut some_camera: Option<Arc<Camera + Sync + Send>> = None;
             let mut some_camera: Option<Arc<Camera + Sync + Send>>;
-            let medium_interface: MediumInterface = create_medium_interface(&api_state);
+            let medium_interface: MediumInterface = create_medium_interface(api_state)?;
             let animated_cam_to_world: AnimatedTransform = AnimatedTransform::new(
                 &api_state.render_options.camera_to_world.t[0],
                 api_state.render_options.transform_start_time,
@@ -1670,18 +2598,18 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                 );
                 some_camera = Some(camera);
             } else {
-                panic!(
-                    "Camera \"{}\" unknown.",
-                    api_state.render_options.camera_name
-                );
+                return Err(ApiError::unknown(
+                    "Camera",
+                    &api_state.render_options.camera_name,
+                ));
             }
             if let Some(camera) = some_camera {
                 // MakeSampler
-                let mut some_sampler: Option<Box<Sampler + Sync + Send>> = None;
+                let some_sampler: Option<Box<Sampler + Sync + Send>>;
                 if api_state.render_options.sampler_name == "lowdiscrepancy"
                     || api_state.render_options.sampler_name == "02sequence"
                 {
-                    let nsamp: i32 = api_state
+                    let mut nsamp: i32 = api_state
                         .render_options
                         .sampler_params
                         .find_one_int("pixelsamples", 16);
@@ -1689,17 +2617,21 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                         .render_options
                         .sampler_params
                         .find_one_int("dimensions", 4);
-                    // TODO: if (PbrtOptions.quickRender) nsamp = 1;
+                    if api_state.quick_render {
+                        nsamp = 1;
+                    }
                     let sampler = Box::new(ZeroTwoSequenceSampler::new(nsamp as i64, sd as i64));
                     some_sampler = Some(sampler);
                 } else if api_state.render_options.sampler_name == "maxmindist" {
-                    println!("TODO: CreateMaxMinDistSampler");
+                    return Err(ApiError::unsupported("Sampler", "maxmindist"));
                 } else if api_state.render_options.sampler_name == "halton" {
-                    let nsamp: i32 = api_state
+                    let mut nsamp: i32 = api_state
                         .render_options
                         .sampler_params
                         .find_one_int("pixelsamples", 16);
-                    // TODO: if (PbrtOptions.quickRender) nsamp = 1;
+                    if api_state.quick_render {
+                        nsamp = 1;
+                    }
                     let sample_at_center: bool = api_state
                         .render_options
                         .integrator_params
@@ -1712,29 +2644,35 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                     ));
                     some_sampler = Some(sampler);
                 } else if api_state.render_options.sampler_name == "sobol" {
-                    let nsamp: i32 = api_state
+                    let mut nsamp: i32 = api_state
                         .render_options
                         .sampler_params
                         .find_one_int("pixelsamples", 16);
+                    if api_state.quick_render {
+                        nsamp = 1;
+                    }
                     let sample_bounds: Bounds2i = camera.get_film().get_sample_bounds();
                     let sampler = Box::new(SobolSampler::new(nsamp as i64, sample_bounds));
                     some_sampler = Some(sampler);
                 } else if api_state.render_options.sampler_name == "random" {
-                    let nsamp: i32 = api_state
+                    let mut nsamp: i32 = api_state
                         .render_options
                         .sampler_params
                         .find_one_int("pixelsamples", 4);
+                    if api_state.quick_render {
+                        nsamp = 1;
+                    }
                     let sampler = Box::new(RandomSampler::new(nsamp as i64));
                     some_sampler = Some(sampler);
                 } else if api_state.render_options.sampler_name == "stratified" {
-                    println!("TODO: CreateStratifiedSampler");
+                    return Err(ApiError::unsupported("Sampler", "stratified"));
                 } else {
-                    panic!(
-                        "Sampler \"{}\" unknown.",
-                        api_state.render_options.sampler_name
-                    );
+                    return Err(ApiError::unknown(
+                        "Sampler",
+                        &api_state.render_options.sampler_name,
+                    ));
                 }
-                if let Some(mut sampler) = some_sampler {
+                if let Some(sampler) = some_sampler {
                     // MakeIntegrator
                     // if let Some(mut sampler) = some_sampler {
                     let mut some_integrator: Option<Box<SamplerIntegrator + Sync + Send>> = None;
@@ -1742,7 +2680,7 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                     let mut some_mlt_integrator: Option<Box<MLTIntegrator>> = None;
                     let mut some_sppm_integrator: Option<Box<SPPMIntegrator>> = None;
                     if api_state.render_options.integrator_name == "whitted" {
-                        println!("TODO: CreateWhittedIntegrator");
+                        return Err(ApiError::unsupported("Integrator", "whitted"));
                     } else if api_state.render_options.integrator_name == "directlighting" {
                         // CreateDirectLightingIntegrator
                         let max_depth: i32 = api_state
@@ -1759,13 +2697,15 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                         } else if st == "all" {
                             strategy = LightStrategy::UniformSampleAll;
                         } else {
-                            panic!("Strategy \"{}\" for direct lighting unknown.", st);
+                            return Err(ApiError::invalid_parameter(
+                                "Integrator",
+                                format!("Strategy \"{}\" for direct lighting unknown.", st),
+                            ));
                         }
-                        // TODO: const int *pb = params.FindInt("pixelbounds", &np);
-                        let pixel_bounds: Bounds2i = Bounds2i {
-                            p_min: Point2i { x: 0, y: 0 },
-                            p_max: Point2i { x: xres, y: yres },
-                        };
+                        let pixel_bounds: Bounds2i = make_pixel_bounds(
+                            &api_state.render_options.integrator_params,
+                            camera.get_film().get_sample_bounds(),
+                        )?;
                         let integrator = Box::new(DirectLightingIntegrator::new(
                             strategy,
                             max_depth as i64,
@@ -1778,26 +2718,10 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                             .render_options
                             .integrator_params
                             .find_one_int("maxdepth", 5);
-                        let pb: Vec<i32> = api_state
-                            .render_options
-                            .integrator_params
-                            .find_int("pixelbounds");
-                        let np: usize = pb.len();
-                        let pixel_bounds: Bounds2i = camera.get_film().get_sample_bounds();
-                        if np > 0 as usize {
-                            if np != 4 as usize {
-                                panic!(
-                                    "Expected four values for \"pixelbounds\" parameter. Got {}.",
-                                    np
-                                );
-                            } else {
-                                println!("TODO: pixelBounds = Intersect(...)");
-                                // pixelBounds = Intersect(pixelBounds,
-                                //                         Bounds2i{{pb[0], pb[2]}, {pb[1], pb[3]}});
-                                // if (pixelBounds.Area() == 0)
-                                //     Error("Degenerate \"pixelbounds\" specified.");
-                            }
-                        }
+                        let pixel_bounds: Bounds2i = make_pixel_bounds(
+                            &api_state.render_options.integrator_params,
+                            camera.get_film().get_sample_bounds(),
+                        )?;
                         let rr_threshold: Float = api_state
                             .render_options
                             .integrator_params
@@ -1816,7 +2740,7 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                         ));
                         some_integrator = Some(integrator);
                     } else if api_state.render_options.integrator_name == "volpath" {
-                        println!("TODO: CreateVolPathIntegrator");
+                        return Err(ApiError::unsupported("Integrator", "volpath"));
                     } else if api_state.render_options.integrator_name == "bdpt" {
                         // CreateBDPTIntegrator
                         let mut max_depth: i32 = api_state
@@ -1835,7 +2759,10 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                             println!("WARNING: visualizestrategies/visualizeweights was enabled, limiting maxdepth to 5");
                             max_depth = 5;
                         }
-                        let pixel_bounds: Bounds2i = camera.get_film().get_sample_bounds();
+                        let pixel_bounds: Bounds2i = make_pixel_bounds(
+                            &api_state.render_options.integrator_params,
+                            camera.get_film().get_sample_bounds(),
+                        )?;
                         let light_strategy: String = api_state
                             .render_options
                             .integrator_params
@@ -1874,6 +2801,10 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                             .render_options
                             .integrator_params
                             .find_one_float("sigma", 0.01 as Float);
+                        if api_state.quick_render {
+                            mutations_per_pixel = std::cmp::max(1, mutations_per_pixel / 16);
+                            n_bootstrap = std::cmp::max(1, n_bootstrap / 16);
+                        }
                         let mut integrator = Box::new(MLTIntegrator::new(
                             camera.clone(),
                             max_depth as u32,
@@ -1886,26 +2817,10 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                         some_mlt_integrator = Some(integrator);
                     } else if api_state.render_options.integrator_name == "ambientocclusion" {
                         // CreateAOIntegrator
-                        let pb: Vec<i32> = api_state
-                            .render_options
-                            .integrator_params
-                            .find_int("pixelbounds");
-                        let np: usize = pb.len();
-                        let pixel_bounds: Bounds2i = camera.get_film().get_sample_bounds();
-                        if np > 0 as usize {
-                            if np != 4 as usize {
-                                panic!(
-                                    "Expected four values for \"pixelbounds\" parameter. Got {}.",
-                                    np
-                                );
-                            } else {
-                                println!("TODO: pixelBounds = Intersect(...)");
-                                // pixelBounds = Intersect(pixelBounds,
-                                //                         Bounds2i{{pb[0], pb[2]}, {pb[1], pb[3]}});
-                                // if (pixelBounds.Area() == 0)
-                                //     Error("Degenerate \"pixelbounds\" specified.");
-                            }
-                        }
+                        let pixel_bounds: Bounds2i = make_pixel_bounds(
+                            &api_state.render_options.integrator_params,
+                            camera.get_film().get_sample_bounds(),
+                        )?;
                         let cos_sample: bool = api_state
                             .render_options
                             .integrator_params
@@ -1943,7 +2858,9 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                             .render_options
                             .integrator_params
                             .find_one_float("radius", 1.0 as Float);
-                        // TODO: if (PbrtOptions.quickRender) nIterations = std::max(1, nIterations / 16);
+                        if api_state.quick_render {
+                            n_iterations = std::cmp::max(1, n_iterations / 16);
+                        }
                         let mut integrator = Box::new(SPPMIntegrator::new(
                             camera.clone(),
                             n_iterations,
@@ -1954,10 +2871,10 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                         ));
                         some_sppm_integrator = Some(integrator);
                     } else {
-                        panic!(
-                            "Integrator \"{}\" unknown.",
-                            api_state.render_options.integrator_name
-                        );
+                        return Err(ApiError::unknown(
+                            "Integrator",
+                            &api_state.render_options.integrator_name,
+                        ));
                     }
                     if api_state.render_options.have_scattering_media
                         && api_state.render_options.integrator_name != String::from("volpath")
@@ -1969,357 +2886,113 @@ pub fn pbrt_cleanup(api_state: &ApiState) {
                         print!("volume scattering. Consider using \"volpath\", \"bdpt\", or ");
                         println!("\"mlt\".");
                     }
-                    if let Some(mut integrator) = some_integrator {
-                        // MakeIntegrator
-                        // TODO: if (renderOptions->haveScatteringMedia && ...)
-                        if api_state.render_options.lights.is_empty() {
-                            // warn if no light sources are defined
-                            println!("WARNING: No light sources defined in scene; rendering a black image.",);
-                        }
-                        // MakeAccelerator
-                        if api_state.render_options.accelerator_name == "bvh" {
-                            //  CreateBVHAccelerator
-                            let split_method_name: String = api_state
-                                .render_options
-                                .accelerator_params
-                                .find_one_string("splitmethod", String::from("sah"));
-                            let split_method;
-                            if split_method_name == "sah" {
-                                split_method = SplitMethod::SAH;
-                            } else if split_method_name == "hlbvh" {
-                                split_method = SplitMethod::HLBVH;
-                            } else if split_method_name == "middle" {
-                                split_method = SplitMethod::Middle;
-                            } else if split_method_name == "equal" {
-                                split_method = SplitMethod::EqualCounts;
-                            } else {
-                                println!(
-                                    "WARNING: BVH split method \"{}\" unknown.  Using \"sah\".",
-                                    split_method_name
-                                );
-                                split_method = SplitMethod::SAH;
-                            }
-                            let max_prims_in_node: i32 = api_state
-                                .render_options
-                                .accelerator_params
-                                .find_one_int("maxnodeprims", 4);
-                            let accelerator = Arc::new(BVHAccel::new(
-                                api_state.render_options.primitives.clone(),
-                                max_prims_in_node as usize,
-                                split_method,
-                            ));
-                            // MakeScene
-                            let scene: Scene = Scene::new(
-                                accelerator.clone(),
-                                api_state.render_options.lights.clone(),
-                            );
-                            // TODO: primitives.erase(primitives.begin(), primitives.end());
-                            // TODO: lights.erase(lights.begin(), lights.end());
-                            let num_threads: u8 = api_state.number_of_threads;
-                            render(
-                                &scene,
-                                &camera.clone(),
-                                &mut sampler,
-                                &mut integrator,
-                                num_threads,
-                            );
-                        } else if api_state.render_options.accelerator_name == "kdtree" {
-                            // println!("TODO: CreateKdTreeAccelerator");
-                            // WARNING: Use BVHAccel for now !!!
-                            let accelerator = Arc::new(BVHAccel::new(
-                                api_state.render_options.primitives.clone(),
-                                4,
-                                SplitMethod::SAH,
-                            ));
-                            // MakeScene
-                            let scene: Scene = Scene::new(
-                                accelerator.clone(),
-                                api_state.render_options.lights.clone(),
-                            );
-                            // TODO: primitives.erase(primitives.begin(), primitives.end());
-                            // TODO: lights.erase(lights.begin(), lights.end());
-                            let num_threads: u8 = api_state.number_of_threads;
-                            render(&scene, &camera, &mut sampler, &mut integrator, num_threads);
-                        } else {
-                            panic!(
-                                "Accelerator \"{}\" unknown.",
-                                api_state.render_options.accelerator_name
-                            );
-                        }
-                    } else if let Some(mut integrator) = some_bdpt_integrator {
-                        // because we can't call
-                        // integrator.render() yet,
-                        // let us repeat some code and
-                        // call render_bdpt(...)
-                        // instead:
-
-                        // MakeIntegrator
-                        // TODO: if (renderOptions->haveScatteringMedia && ...)
-                        if api_state.render_options.lights.is_empty() {
-                            // warn if no light sources are defined
-                            println!("WARNING: No light sources defined in scene; rendering a black image.",);
-                        }
-                        // MakeAccelerator
-                        if api_state.render_options.accelerator_name == "bvh" {
-                            //  CreateBVHAccelerator
-                            let split_method_name: String = api_state
-                                .render_options
-                                .accelerator_params
-                                .find_one_string("splitmethod", String::from("sah"));
-                            let split_method;
-                            if split_method_name == "sah" {
-                                split_method = SplitMethod::SAH;
-                            } else if split_method_name == "hlbvh" {
-                                split_method = SplitMethod::HLBVH;
-                            } else if split_method_name == "middle" {
-                                split_method = SplitMethod::Middle;
-                            } else if split_method_name == "equal" {
-                                split_method = SplitMethod::EqualCounts;
-                            } else {
-                                println!(
-                                    "WARNING: BVH split method \"{}\" unknown.  Using \"sah\".",
-                                    split_method_name
-                                );
-                                split_method = SplitMethod::SAH;
-                            }
-                            let max_prims_in_node: i32 = api_state
-                                .render_options
-                                .accelerator_params
-                                .find_one_int("maxnodeprims", 4);
-                            let accelerator = Arc::new(BVHAccel::new(
-                                api_state.render_options.primitives.clone(),
-                                max_prims_in_node as usize,
-                                split_method,
-                            ));
-                            // MakeScene
-                            let scene: Scene = Scene::new(
-                                accelerator.clone(),
-                                api_state.render_options.lights.clone(),
-                            );
-                            // TODO: primitives.erase(primitives.begin(), primitives.end());
-                            // TODO: lights.erase(lights.begin(), lights.end());
-                            let num_threads: u8 = api_state.number_of_threads;
-                            render_bdpt(
-                                &scene,
-                                &camera,
-                                &mut sampler,
-                                &mut integrator,
-                                num_threads,
-                            );
-                        } else if api_state.render_options.accelerator_name == "kdtree" {
-                            // println!("TODO: CreateKdTreeAccelerator");
-                            // WARNING: Use BVHAccel for now !!!
-                            let accelerator = Arc::new(BVHAccel::new(
-                                api_state.render_options.primitives.clone(),
-                                4,
-                                SplitMethod::SAH,
-                            ));
-                            // MakeScene
-                            let scene: Scene = Scene::new(
-                                accelerator.clone(),
-                                api_state.render_options.lights.clone(),
-                            );
-                            // TODO: primitives.erase(primitives.begin(), primitives.end());
-                            // TODO: lights.erase(lights.begin(), lights.end());
-                            let num_threads: u8 = api_state.number_of_threads;
-                            render_bdpt(
-                                &scene,
-                                &camera,
-                                &mut sampler,
-                                &mut integrator,
-                                num_threads,
-                            );
-                        } else {
-                            panic!(
-                                "Accelerator \"{}\" unknown.",
-                                api_state.render_options.accelerator_name
-                            );
-                        }
-                    } else if let Some(mut integrator) = some_mlt_integrator {
-                        // because we can't call
-                        // integrator.render() yet,
-                        // let us repeat some code and
-                        // call render_mlt(...)
-                        // instead:
-
-                        // MakeIntegrator
-                        // TODO: if (renderOptions->haveScatteringMedia && ...)
-                        if api_state.render_options.lights.is_empty() {
-                            // warn if no light sources are defined
-                            println!("WARNING: No light sources defined in scene; rendering a black image.",);
-                        }
-                        // MakeAccelerator
-                        if api_state.render_options.accelerator_name == "bvh" {
-                            //  CreateBVHAccelerator
-                            let split_method_name: String = api_state
-                                .render_options
-                                .accelerator_params
-                                .find_one_string("splitmethod", String::from("sah"));
-                            let split_method;
-                            if split_method_name == "sah" {
-                                split_method = SplitMethod::SAH;
-                            } else if split_method_name == "hlbvh" {
-                                split_method = SplitMethod::HLBVH;
-                            } else if split_method_name == "middle" {
-                                split_method = SplitMethod::Middle;
-                            } else if split_method_name == "equal" {
-                                split_method = SplitMethod::EqualCounts;
-                            } else {
-                                println!(
-                                    "WARNING: BVH split method \"{}\" unknown.  Using \"sah\".",
-                                    split_method_name
-                                );
-                                split_method = SplitMethod::SAH;
-                            }
-                            let max_prims_in_node: i32 = api_state
-                                .render_options
-                                .accelerator_params
-                                .find_one_int("maxnodeprims", 4);
-                            let accelerator = Arc::new(BVHAccel::new(
-                                api_state.render_options.primitives.clone(),
-                                max_prims_in_node as usize,
-                                split_method,
-                            ));
-                            // MakeScene
-                            let scene: Scene = Scene::new(
-                                accelerator.clone(),
-                                api_state.render_options.lights.clone(),
-                            );
-                            // TODO: primitives.erase(primitives.begin(), primitives.end());
-                            // TODO: lights.erase(lights.begin(), lights.end());
-                            let num_threads: u8 = api_state.number_of_threads;
-                            render_mlt(&scene, &camera, &mut sampler, &mut integrator, num_threads);
-                        } else if api_state.render_options.accelerator_name == "kdtree" {
-                            // println!("TODO: CreateKdTreeAccelerator");
-                            // WARNING: Use BVHAccel for now !!!
-                            let accelerator = Arc::new(BVHAccel::new(
-                                api_state.render_options.primitives.clone(),
-                                4,
-                                SplitMethod::SAH,
-                            ));
-                            // MakeScene
-                            let scene: Scene = Scene::new(
-                                accelerator.clone(),
-                                api_state.render_options.lights.clone(),
-                            );
-                            // TODO: primitives.erase(primitives.begin(), primitives.end());
-                            // TODO: lights.erase(lights.begin(), lights.end());
-                            let num_threads: u8 = api_state.number_of_threads;
-                            render_mlt(&scene, &camera, &mut sampler, &mut integrator, num_threads);
-                        } else {
-                            panic!(
-                                "Accelerator \"{}\" unknown.",
-                                api_state.render_options.accelerator_name
-                            );
-                        }
-                    } else if let Some(mut integrator) = some_sppm_integrator {
-                        // because we can't call
-                        // integrator.render() yet,
-                        // let us repeat some code and
-                        // call render_sppm(...)
-                        // instead:
-
-                        // MakeIntegrator
-                        // TODO: if (renderOptions->haveScatteringMedia && ...)
-                        if api_state.render_options.lights.is_empty() {
-                            // warn if no light sources are defined
-                            println!("WARNING: No light sources defined in scene; rendering a black image.",);
-                        }
-                        // MakeAccelerator
-                        if api_state.render_options.accelerator_name == "bvh" {
-                            //  CreateBVHAccelerator
-                            let split_method_name: String = api_state
-                                .render_options
-                                .accelerator_params
-                                .find_one_string("splitmethod", String::from("sah"));
-                            let split_method;
-                            if split_method_name == "sah" {
-                                split_method = SplitMethod::SAH;
-                            } else if split_method_name == "hlbvh" {
-                                split_method = SplitMethod::HLBVH;
-                            } else if split_method_name == "middle" {
-                                split_method = SplitMethod::Middle;
-                            } else if split_method_name == "equal" {
-                                split_method = SplitMethod::EqualCounts;
-                            } else {
-                                println!(
-                                    "WARNING: BVH split method \"{}\" unknown.  Using \"sah\".",
-                                    split_method_name
-                                );
-                                split_method = SplitMethod::SAH;
-                            }
-                            let max_prims_in_node: i32 = api_state
-                                .render_options
-                                .accelerator_params
-                                .find_one_int("maxnodeprims", 4);
-                            let accelerator = Arc::new(BVHAccel::new(
-                                api_state.render_options.primitives.clone(),
-                                max_prims_in_node as usize,
-                                split_method,
-                            ));
-                            // MakeScene
-                            let scene: Scene = Scene::new(
-                                accelerator.clone(),
-                                api_state.render_options.lights.clone(),
-                            );
-                            // TODO: primitives.erase(primitives.begin(), primitives.end());
-                            // TODO: lights.erase(lights.begin(), lights.end());
-                            let num_threads: u8 = api_state.number_of_threads;
-                            render_sppm(
-                                &scene,
-                                &camera,
-                                &mut sampler,
-                                &mut integrator,
-                                num_threads,
-                            );
-                        } else if api_state.render_options.accelerator_name == "kdtree" {
-                            // println!("TODO: CreateKdTreeAccelerator");
-                            // WARNING: Use BVHAccel for now !!!
-                            let accelerator = Arc::new(BVHAccel::new(
-                                api_state.render_options.primitives.clone(),
-                                4,
-                                SplitMethod::SAH,
-                            ));
-                            // MakeScene
-                            let scene: Scene = Scene::new(
-                                accelerator.clone(),
-                                api_state.render_options.lights.clone(),
-                            );
-                            // TODO: primitives.erase(primitives.begin(), primitives.end());
-                            // TODO: lights.erase(lights.begin(), lights.end());
-                            let num_threads: u8 = api_state.number_of_threads;
-                            render_sppm(
-                                &scene,
-                                &camera,
-                                &mut sampler,
-                                &mut integrator,
-                                num_threads,
-                            );
-                        } else {
-                            panic!(
-                                "Accelerator \"{}\" unknown.",
-                                api_state.render_options.accelerator_name
-                            );
-                        }
+                    let mut unused: Vec<ApiError> = Vec::new();
+                    for params in &[
+                        &api_state.render_options.filter_params,
+                        &api_state.render_options.film_params,
+                        &api_state.render_options.camera_params,
+                        &api_state.render_options.sampler_params,
+                        &api_state.render_options.integrator_params,
+                    ] {
+                        unused.append(&mut unused_parameters(params));
+                    }
+                    api_state.warn_unused(unused);
+                    let integrator: RenderIntegrator;
+                    if let Some(sampler_integrator) = some_integrator {
+                        integrator = RenderIntegrator::Sampler(sampler_integrator);
+                    } else if let Some(bdpt_integrator) = some_bdpt_integrator {
+                        integrator = RenderIntegrator::BDPT(bdpt_integrator);
+                    } else if let Some(mlt_integrator) = some_mlt_integrator {
+                        integrator = RenderIntegrator::MLT(mlt_integrator);
+                    } else if let Some(sppm_integrator) = some_sppm_integrator {
+                        integrator = RenderIntegrator::SPPM(sppm_integrator);
                     } else {
-                        panic!("Unable to create integrator.");
+                        return Err(ApiError::invalid_state(
+                            "WorldEnd",
+                            String::from("unable to create integrator"),
+                        ));
+                    }
+                    // TODO: if (renderOptions->haveScatteringMedia && ...)
+                    if api_state.render_options.lights.is_empty() {
+                        // warn if no light sources are defined
+                        println!(
+                            "WARNING: No light sources defined in scene; rendering a black image.",
+                        );
+                    }
+                    // MakeAccelerator
+                    let accelerator: Arc<Primitive + Sync + Send> = make_accelerator(
+                        &api_state.render_options.accelerator_name,
+                        api_state.render_options.primitives.clone(),
+                        &api_state.render_options.accelerator_params,
+                    )?;
+                    let unused: Vec<ApiError> =
+                        unused_parameters(&api_state.render_options.accelerator_params);
+                    api_state.warn_unused(unused);
+                    // MakeScene
+                    let scene: Scene =
+                        Scene::new(accelerator, api_state.render_options.lights.clone());
+                    // TODO: primitives.erase(primitives.begin(), primitives.end());
+                    // TODO: lights.erase(lights.begin(), lights.end());
+                    // the shapes keep their transforms alive
+                    api_state.transform_cache.clear();
+                    let checkpoint: Checkpoint = Checkpoint::new(
+                        &camera.get_film().filename,
+                        api_state.checkpoint_interval,
+                        api_state.resume,
+                    );
+                    let integrator_name: &str = api_state.render_options.integrator_name.as_str();
+                    if checkpoint.is_enabled()
+                        && (integrator_name == "mlt" || integrator_name == "sppm")
+                    {
+                        println!(
+                            "WARNING: Checkpoints are not supported by the \"{}\" integrator.",
+                            integrator_name
+                        );
                     }
+                    Ok(RenderSetup {
+                        scene: scene,
+                        camera: camera,
+                        sampler: sampler,
+                        integrator: integrator,
+                        number_of_threads: api_state.number_of_threads,
+                        checkpoint: checkpoint,
+                    })
                 } else {
-                    panic!("Unable to create sampler.");
+                    return Err(ApiError::invalid_state(
+                        "WorldEnd",
+                        String::from("unable to create sampler"),
+                    ));
                 }
             } else {
-                panic!("Unable to create camera.");
+                return Err(ApiError::invalid_state(
+                    "WorldEnd",
+                    String::from("unable to create camera"),
+                ));
             }
         } else {
-            panic!("Unable to create film.");
+            return Err(ApiError::invalid_state(
+                "WorldEnd",
+                String::from("unable to create film"),
+            ));
         }
     } else {
-        panic!("Film \"{}\" unknown.", api_state.render_options.film_name);
+        return Err(ApiError::unknown(
+            "Film",
+            &api_state.render_options.film_name,
+        ));
     }
 }
 
-pub fn pbrt_translate(api_state: &mut ApiState, dx: Float, dy: Float, dz: Float) {
+pub fn pbrt_translate(
+    api_state: &mut ApiState,
+    dx: Float,
+    dy: Float,
+    dz: Float,
+) -> Result<(), ApiError> {
     // println!("Translate {} {} {}", dx, dy, dz);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement(&format!("Translate {} {} {}", dx, dy, dz));
+    }
     let translate: Transform = Transform::translate(&Vector3f {
         x: dx,
         y: dy,
@@ -2333,10 +3006,14 @@ pub fn pbrt_translate(api_state: &mut ApiState, dx: Float, dy: Float, dz: Float)
         // 0x1?
         api_state.cur_transform.t[1] = api_state.cur_transform.t[1] * translate;
     }
+    Ok(())
 }
 
-pub fn pbrt_transform(api_state: &mut ApiState, tr: &Transform) {
+pub fn pbrt_transform(api_state: &mut ApiState, tr: &Transform) -> Result<(), ApiError> {
     // println!("{:?}", tr);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.transform("Transform", tr);
+    }
     if api_state.active_transform_bits & 1_u8 > 0_u8 {
         // 0x?1
         api_state.cur_transform.t[0] = *tr;
@@ -2345,10 +3022,14 @@ pub fn pbrt_transform(api_state: &mut ApiState, tr: &Transform) {
         // 0x1?
         api_state.cur_transform.t[1] = *tr;
     }
+    Ok(())
 }
 
-pub fn pbrt_concat_transform(api_state: &mut ApiState, tr: &Transform) {
+pub fn pbrt_concat_transform(api_state: &mut ApiState, tr: &Transform) -> Result<(), ApiError> {
     // println!("Concat{:?}", tr);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.transform("ConcatTransform", tr);
+    }
     if api_state.active_transform_bits & 1_u8 > 0_u8 {
         // 0x?1
         api_state.cur_transform.t[0] = api_state.cur_transform.t[0] * *tr;
@@ -2357,10 +3038,20 @@ pub fn pbrt_concat_transform(api_state: &mut ApiState, tr: &Transform) {
         // 0x1?
         api_state.cur_transform.t[1] = api_state.cur_transform.t[1] * *tr;
     }
+    Ok(())
 }
 
-pub fn pbrt_rotate(api_state: &mut ApiState, angle: Float, dx: Float, dy: Float, dz: Float) {
+pub fn pbrt_rotate(
+    api_state: &mut ApiState,
+    angle: Float,
+    dx: Float,
+    dy: Float,
+    dz: Float,
+) -> Result<(), ApiError> {
     // println!("Rotate {} {} {} {}", angle, dx, dy, dz);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement(&format!("Rotate {} {} {} {}", angle, dx, dy, dz));
+    }
     let rotate: Transform = Transform::rotate(
         angle,
         &Vector3f {
@@ -2377,10 +3068,19 @@ pub fn pbrt_rotate(api_state: &mut ApiState, angle: Float, dx: Float, dy: Float,
         // 0x1?
         api_state.cur_transform.t[1] = api_state.cur_transform.t[1] * rotate;
     }
+    Ok(())
 }
 
-pub fn pbrt_scale(api_state: &mut ApiState, sx: Float, sy: Float, sz: Float) {
+pub fn pbrt_scale(
+    api_state: &mut ApiState,
+    sx: Float,
+    sy: Float,
+    sz: Float,
+) -> Result<(), ApiError> {
     // println!("Scale {} {} {}", sx, sy, sz);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement(&format!("Scale {} {} {}", sx, sy, sz));
+    }
     let scale: Transform = Transform::scale(sx, sy, sz);
     if api_state.active_transform_bits & 1_u8 > 0_u8 {
         // 0x?1
@@ -2390,6 +3090,7 @@ pub fn pbrt_scale(api_state: &mut ApiState, sx: Float, sy: Float, sz: Float) {
         // 0x1?
         api_state.cur_transform.t[1] = api_state.cur_transform.t[1] * scale;
     }
+    Ok(())
 }
 
 pub fn pbrt_look_at(
@@ -2403,11 +3104,17 @@ pub fn pbrt_look_at(
     ux: Float,
     uy: Float,
     uz: Float,
-) {
+) -> Result<(), ApiError> {
     // println!(
     //     "LookAt {} {} {} {} {} {} {} {} {}",
     //     ex, ey, ez, lx, ly, lz, ux, uy, uz
     // );
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement(&format!(
+            "LookAt {} {} {} {} {} {} {} {} {}",
+            ex, ey, ez, lx, ly, lz, ux, uy, uz
+        ));
+    }
     let pos: Point3f = Point3f {
         x: ex,
         y: ey,
@@ -2432,10 +3139,17 @@ pub fn pbrt_look_at(
         // 0x1?
         api_state.cur_transform.t[1] = api_state.cur_transform.t[1] * look_at;
     }
+    Ok(())
 }
 
-pub fn pbrt_coord_sys_transform(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_coord_sys_transform(
+    api_state: &mut ApiState,
+    params: ParamSet,
+) -> Result<(), ApiError> {
     // println!("CoordSysTransform \"{}\"", params.name);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement(&format!("CoordSysTransform \"{}\"", params.name));
+    }
     api_state.param_set = params;
     match api_state
         .named_coordinate_systems
@@ -2446,37 +3160,61 @@ pub fn pbrt_coord_sys_transform(api_state: &mut ApiState, params: ParamSet) {
             api_state.cur_transform.t[1] = transform_set.t[1];
         }
         None => {
-            println!(
-                "Couldn't find named coordinate system \"{}\"",
-                api_state.param_set.name
-            );
+            api_state.report(ApiError::unknown(
+                "CoordSysTransform",
+                &api_state.param_set.name,
+            ))?;
         }
     };
+    Ok(())
 }
 
-pub fn pbrt_active_transform_all(api_state: &mut ApiState) {
+pub fn pbrt_active_transform_all(api_state: &mut ApiState) -> Result<(), ApiError> {
     // println!("ActiveTransform All");
-    api_state.active_transform_bits = 3_u8 // 0x11
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement("ActiveTransform All");
+    }
+    api_state.active_transform_bits = 3_u8; // 0x11
+    Ok(())
 }
 
-pub fn pbrt_active_transform_end_time(api_state: &mut ApiState) {
+pub fn pbrt_active_transform_end_time(api_state: &mut ApiState) -> Result<(), ApiError> {
     // println!("ActiveTransform EndTime");
-    api_state.active_transform_bits = 2_u8 // 0x10
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement("ActiveTransform EndTime");
+    }
+    api_state.active_transform_bits = 2_u8; // 0x10
+    Ok(())
 }
 
-pub fn pbrt_active_transform_start_time(api_state: &mut ApiState) {
+pub fn pbrt_active_transform_start_time(api_state: &mut ApiState) -> Result<(), ApiError> {
     // println!("ActiveTransform StartTime");
-    api_state.active_transform_bits = 1_u8 // 0x01
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement("ActiveTransform StartTime");
+    }
+    api_state.active_transform_bits = 1_u8; // 0x01
+    Ok(())
 }
 
-pub fn pbrt_transform_times(api_state: &mut ApiState, start: Float, end: Float) {
+pub fn pbrt_transform_times(
+    api_state: &mut ApiState,
+    start: Float,
+    end: Float,
+) -> Result<(), ApiError> {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement(&format!("TransformTimes {} {}", start, end));
+    }
     println!("TransformTimes {} {}", start, end);
     api_state.render_options.transform_start_time = start;
     api_state.render_options.transform_end_time = end;
+    Ok(())
 }
 
-pub fn pbrt_pixel_filter(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_pixel_filter(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
     // println!("PixelFilter \"{}\"", params.name);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("PixelFilter", &params);
+    }
     print_params(&params);
     api_state.render_options.filter_name = params.name.clone();
     api_state.param_set = params;
@@ -2484,9 +3222,14 @@ pub fn pbrt_pixel_filter(api_state: &mut ApiState, params: ParamSet) {
         .render_options
         .filter_params
         .copy_from(&api_state.param_set);
+    api_state.render_options.filter_params.name = api_state.param_set.name.clone();
+    Ok(())
 }
 
-pub fn pbrt_film(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_film(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("Film", &params);
+    }
     println!("Film \"{}\"", params.name);
     print_params(&params);
     api_state.render_options.film_name = params.name.clone();
@@ -2495,9 +3238,14 @@ pub fn pbrt_film(api_state: &mut ApiState, params: ParamSet) {
         .render_options
         .film_params
         .copy_from(&api_state.param_set);
+    api_state.render_options.film_params.name = api_state.param_set.name.clone();
+    Ok(())
 }
 
-pub fn pbrt_sampler(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_sampler(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("Sampler", &params);
+    }
     println!("Sampler \"{}\"", params.name);
     print_params(&params);
     api_state.render_options.sampler_name = params.name.clone();
@@ -2506,9 +3254,43 @@ pub fn pbrt_sampler(api_state: &mut ApiState, params: ParamSet) {
         .render_options
         .sampler_params
         .copy_from(&api_state.param_set);
+    api_state.render_options.sampler_params.name = api_state.param_set.name.clone();
+    Ok(())
+}
+
+pub fn pbrt_accelerator(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("Accelerator", &params);
+    }
+    println!("Accelerator \"{}\"", params.name);
+    print_params(&params);
+    if params.name == "kdtree" {
+        // the traversal stack limits the depth of the tree
+        let max_depth: i32 = params.find_one_int("maxdepth", -1);
+        if max_depth > MAX_TODO as i32 {
+            api_state.report(ApiError::invalid_parameter(
+                "Accelerator",
+                format!(
+                    "kd-tree \"maxdepth\" {} is too large, using {}",
+                    max_depth, MAX_TODO
+                ),
+            ))?;
+        }
+    }
+    api_state.render_options.accelerator_name = params.name.clone();
+    api_state.param_set = params;
+    api_state
+        .render_options
+        .accelerator_params
+        .copy_from(&api_state.param_set);
+    api_state.render_options.accelerator_params.name = api_state.param_set.name.clone();
+    Ok(())
 }
 
-pub fn pbrt_integrator(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_integrator(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("Integrator", &params);
+    }
     println!("Integrator \"{}\"", params.name);
     print_params(&params);
     api_state.render_options.integrator_name = params.name.clone();
@@ -2517,11 +3299,16 @@ pub fn pbrt_integrator(api_state: &mut ApiState, params: ParamSet) {
         .render_options
         .integrator_params
         .copy_from(&api_state.param_set);
+    api_state.render_options.integrator_params.name = api_state.param_set.name.clone();
+    Ok(())
 }
 
-pub fn pbrt_camera(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_camera(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
     // println!("Camera \"{}\"", params.name);
     // print_params(&params);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("Camera", &params);
+    }
     api_state.render_options.camera_name = params.name.clone();
     api_state.param_set = params;
     api_state.render_options.camera_to_world.t[0] =
@@ -2541,28 +3328,57 @@ pub fn pbrt_camera(api_state: &mut ApiState, params: ParamSet) {
         .render_options
         .camera_params
         .copy_from(&api_state.param_set);
+    api_state.render_options.camera_params.name = api_state.param_set.name.clone();
+    Ok(())
 }
 
-pub fn pbrt_make_named_medium(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_make_named_medium(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
     // println!("MakeNamedMedium \"{}\"", params.name);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("MakeNamedMedium", &params);
+    }
+    if api_state.scene_record.is_some() {
+        let statement: ResolvedStatement = ResolvedStatement::MakeNamedMedium {
+            params: copy_params(&params),
+            transform: api_state.cur_transform.t[0],
+        };
+        api_state.record(statement);
+    }
     print_params(&api_state.param_set);
     api_state.param_set = params;
-    make_medium(api_state);
+    let reported: usize = api_state.reported;
+    make_medium(api_state)?;
+    if api_state.reported == reported {
+        let unused: Vec<ApiError> = unused_parameters(&api_state.param_set);
+        api_state.warn_unused(unused);
+    }
+    Ok(())
 }
 
 pub fn pbrt_medium_interface(
     api_state: &mut ApiState,
     inside_name: &String,
     outside_name: &String,
-) {
+) -> Result<(), ApiError> {
     // println!("MediumInterface \"{}\" \"{}\"", inside_name, outside_name);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement(&format!(
+            "MediumInterface \"{}\" \"{}\"",
+            inside_name, outside_name
+        ));
+    }
     api_state.graphics_state.current_inside_medium = inside_name.clone();
     api_state.graphics_state.current_outside_medium = outside_name.clone();
     api_state.render_options.have_scattering_media = true;
+    Ok(())
 }
 
-pub fn pbrt_world_begin(api_state: &mut ApiState) {
+pub fn pbrt_world_begin(api_state: &mut ApiState) -> Result<(), ApiError> {
     // println!("WorldBegin");
+    if let Some(ref mut writer) = api_state.scene_writer {
+        writer.statement("")?;
+        return writer.statement("WorldBegin");
+    }
     api_state.cur_transform.t[0] = Transform::default();
     api_state.cur_transform.t[1] = Transform::default();
     api_state.active_transform_bits = 3_u8; // 0x11
@@ -2572,27 +3388,17 @@ pub fn pbrt_world_begin(api_state: &mut ApiState) {
             t: [Transform::default(); 2],
         },
     );
+    Ok(())
 }
 
-pub fn pbrt_attribute_begin(api_state: &mut ApiState) {
+pub fn pbrt_attribute_begin(api_state: &mut ApiState) -> Result<(), ApiError> {
     // println!("AttributeBegin");
-    let mut material_param_set: ParamSet = ParamSet::default();
-    material_param_set.copy_from(&api_state.graphics_state.material_params);
-    let mut area_light_param_set: ParamSet = ParamSet::default();
-    area_light_param_set.copy_from(&api_state.graphics_state.area_light_params);
-    api_state.pushed_graphics_states.push(GraphicsState {
-        current_inside_medium: api_state.graphics_state.current_inside_medium.clone(),
-        current_outside_medium: api_state.graphics_state.current_outside_medium.clone(),
-        float_textures: api_state.graphics_state.float_textures.clone(),
-        spectrum_textures: api_state.graphics_state.spectrum_textures.clone(),
-        material_params: material_param_set,
-        material: String::from(api_state.graphics_state.material.as_ref()),
-        named_materials: api_state.graphics_state.named_materials.clone(),
-        current_material: String::from(api_state.graphics_state.current_material.as_ref()),
-        area_light_params: area_light_param_set,
-        area_light: String::from(api_state.graphics_state.area_light.as_ref()),
-        reverse_orientation: api_state.graphics_state.reverse_orientation,
-    });
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.begin_block("AttributeBegin");
+    }
+    api_state
+        .pushed_graphics_states
+        .push(api_state.graphics_state.clone());
     api_state.pushed_transforms.push(TransformSet {
         t: [
             Transform {
@@ -2608,12 +3414,19 @@ pub fn pbrt_attribute_begin(api_state: &mut ApiState) {
     api_state
         .pushed_active_transform_bits
         .push(api_state.active_transform_bits);
+    Ok(())
 }
 
-pub fn pbrt_attribute_end(api_state: &mut ApiState) {
+pub fn pbrt_attribute_end(api_state: &mut ApiState) -> Result<(), ApiError> {
     // println!("AttributeEnd");
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.end_block("AttributeEnd");
+    }
     if !(api_state.pushed_graphics_states.len() >= 1_usize) {
-        panic!("Unmatched pbrtAttributeEnd() encountered.")
+        return api_state.report(ApiError::invalid_state(
+            "AttributeEnd",
+            String::from("unmatched AttributeEnd encountered"),
+        ));
     }
     let pgs: GraphicsState = api_state.pushed_graphics_states.pop().unwrap();
     // current_inside_medium
@@ -2633,6 +3446,7 @@ pub fn pbrt_attribute_end(api_state: &mut ApiState) {
         .graphics_state
         .material_params
         .copy_from(&pgs.material_params);
+    api_state.graphics_state.material_params.name = pgs.material_params.name.clone();
     // material
     api_state.graphics_state.material = String::from(pgs.material.as_ref());
     // area_light_params
@@ -2646,6 +3460,7 @@ pub fn pbrt_attribute_end(api_state: &mut ApiState) {
         .graphics_state
         .area_light_params
         .copy_from(&pgs.area_light_params);
+    api_state.graphics_state.area_light_params.name = pgs.area_light_params.name.clone();
     // area_light
     api_state.graphics_state.area_light = String::from(pgs.area_light.as_ref());
     // reverse_orientation
@@ -2655,10 +3470,14 @@ pub fn pbrt_attribute_end(api_state: &mut ApiState) {
     api_state.cur_transform.t[1] = popped_transform_set.t[1];
     let active_transform_bits: u8 = api_state.pushed_active_transform_bits.pop().unwrap();
     api_state.active_transform_bits = active_transform_bits;
+    Ok(())
 }
 
-pub fn pbrt_transform_begin(api_state: &mut ApiState) {
+pub fn pbrt_transform_begin(api_state: &mut ApiState) -> Result<(), ApiError> {
     // println!("TransformBegin");
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.begin_block("TransformBegin");
+    }
     api_state.pushed_transforms.push(TransformSet {
         t: [
             Transform {
@@ -2674,58 +3493,160 @@ pub fn pbrt_transform_begin(api_state: &mut ApiState) {
     api_state
         .pushed_active_transform_bits
         .push(api_state.active_transform_bits);
+    Ok(())
 }
 
-pub fn pbrt_transform_end(api_state: &mut ApiState) {
+pub fn pbrt_transform_end(api_state: &mut ApiState) -> Result<(), ApiError> {
     // println!("TransformEnd");
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.end_block("TransformEnd");
+    }
+    if api_state.pushed_transforms.is_empty() {
+        return api_state.report(ApiError::invalid_state(
+            "TransformEnd",
+            String::from("unmatched TransformEnd encountered"),
+        ));
+    }
     let popped_transform_set: TransformSet = api_state.pushed_transforms.pop().unwrap();
     api_state.cur_transform.t[0] = popped_transform_set.t[0];
     api_state.cur_transform.t[1] = popped_transform_set.t[1];
     let active_transform_bits: u8 = api_state.pushed_active_transform_bits.pop().unwrap();
     api_state.active_transform_bits = active_transform_bits;
+    Ok(())
 }
 
-pub fn pbrt_texture(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_texture(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
     // println!(
     //     "Texture \"{}\" \"{}\" \"{}\"",
     //     params.name, params.tex_type, params.tex_name
     // );
     // print_params(&params);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("Texture", &params);
+    }
+    if api_state.scene_record.is_some() {
+        let statement: ResolvedStatement = ResolvedStatement::Texture {
+            params: copy_params(&params),
+            transform: api_state.cur_transform.t[0],
+        };
+        api_state.record(statement);
+    }
     api_state.param_set = params;
-    make_texture(api_state);
+    // pbrtTexture (api.cpp:1049)
+    let mut geom_params: ParamSet = ParamSet::default();
+    let mut material_params: ParamSet = ParamSet::default();
+    geom_params.copy_from(&api_state.param_set);
+    material_params.copy_from(&api_state.param_set);
+    material_params.name = api_state.param_set.name.clone();
+    let mut tp: TextureParams = TextureParams {
+        float_textures: api_state.graphics_state.float_textures.clone(),
+        spectrum_textures: api_state.graphics_state.spectrum_textures.clone(),
+        geom_params: geom_params,
+        material_params: material_params,
+    };
+    let reported: usize = api_state.reported;
+    make_texture(api_state, &mut tp)?;
+    if api_state.reported == reported {
+        api_state.warn_unused(unused_texture_params(&tp));
+    }
+    Ok(())
+}
+
+/// Defines a float texture the caller created itself, e.g. from
+/// image data embedded in a glTF file. There is no statement for it
+/// in the scene description, so it can't be written.
+pub fn pbrt_float_texture(
+    api_state: &mut ApiState,
+    name: &str,
+    texture: Arc<Texture<Float> + Send + Sync>,
+) -> Result<(), ApiError> {
+    if api_state.scene_writer.is_some() {
+        return api_state.report(ApiError::WriteFailed {
+            directive: String::from("Texture"),
+            message: format!("float texture \"{}\" only exists in memory", name),
+        });
+    }
+    api_state.record(ResolvedStatement::InMemoryTexture {
+        name: String::from(name),
+    });
+    api_state
+        .graphics_state
+        .float_textures
+        .insert(String::from(name), texture);
+    Ok(())
 }
 
-pub fn pbrt_material(api_state: &mut ApiState, params: ParamSet) {
+/// Like **pbrt_float_texture()**, but for spectrum textures.
+pub fn pbrt_spectrum_texture(
+    api_state: &mut ApiState,
+    name: &str,
+    texture: Arc<Texture<Spectrum> + Send + Sync>,
+) -> Result<(), ApiError> {
+    if api_state.scene_writer.is_some() {
+        return api_state.report(ApiError::WriteFailed {
+            directive: String::from("Texture"),
+            message: format!("spectrum texture \"{}\" only exists in memory", name),
+        });
+    }
+    api_state.record(ResolvedStatement::InMemoryTexture {
+        name: String::from(name),
+    });
+    api_state
+        .graphics_state
+        .spectrum_textures
+        .insert(String::from(name), texture);
+    Ok(())
+}
+
+pub fn pbrt_material(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
     // println!("MakeMaterial \"{}\"", params.name);
     // print_params(&params);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("Material", &params);
+    }
     api_state.param_set = params;
     api_state.graphics_state.material = api_state.param_set.name.clone();
     api_state
         .graphics_state
         .material_params
         .copy_from(&api_state.param_set);
+    api_state.graphics_state.material_params.name = api_state.param_set.name.clone();
     api_state.graphics_state.current_material = String::new();
+    Ok(())
 }
 
 pub fn pbrt_make_named_material(
     api_state: &mut ApiState,
     bsdf_state: &mut BsdfState,
     params: ParamSet,
-) {
+) -> Result<(), ApiError> {
     // println!("MakeNamedMaterial \"{}\"", params.name);
     // print_params(&params);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("MakeNamedMaterial", &params);
+    }
+    if api_state.scene_record.is_some() {
+        let statement: ResolvedStatement = ResolvedStatement::MakeNamedMaterial {
+            params: copy_params(&params),
+        };
+        api_state.record(statement);
+    }
     api_state.param_set = params;
     let mat_type: String = api_state.param_set.find_one_string("type", String::new());
     if mat_type == "" {
-        panic!("No parameter string \"type\" found in MakeNamedMaterial");
+        return api_state.report(ApiError::invalid_parameter(
+            "MakeNamedMaterial",
+            String::from("no parameter string \"type\" found"),
+        ));
     }
     api_state.graphics_state.material = mat_type.clone();
     api_state
         .graphics_state
         .material_params
         .copy_from(&api_state.param_set);
+    api_state.graphics_state.material_params.name = api_state.param_set.name.clone();
     api_state.graphics_state.current_material = String::new();
-    let mtl: Option<Arc<Material + Send + Sync>> = create_material(&api_state, bsdf_state);
+    let mtl: Option<Arc<Material + Send + Sync>> = create_material(api_state, bsdf_state)?;
     match api_state
         .graphics_state
         .named_materials
@@ -2740,113 +3661,266 @@ pub fn pbrt_make_named_material(
         .graphics_state
         .named_materials
         .insert(api_state.param_set.name.clone(), mtl);
+    if api_state.scene_record.is_some() {
+        let named_params: Arc<ParamSet> = Arc::new(copy_params(&api_state.param_set));
+        api_state
+            .graphics_state
+            .named_material_params
+            .insert(api_state.param_set.name.clone(), named_params);
+    }
+    Ok(())
 }
 
-pub fn pbrt_named_material(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_named_material(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
     // println!("NamedMaterial \"{}\"", params.name);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement(&format!("NamedMaterial \"{}\"", params.name));
+    }
     api_state.param_set = params;
     api_state.graphics_state.current_material = api_state.param_set.name.clone();
+    Ok(())
 }
 
-pub fn pbrt_light_source(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_light_source(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
     // println!("LightSource \"{}\"", params.name);
     // print_params(&params);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("LightSource", &params);
+    }
+    if api_state.scene_record.is_some() {
+        let statement: ResolvedStatement = ResolvedStatement::LightSource {
+            params: copy_params(&params),
+            transform: api_state.cur_transform.t[0],
+            inside_medium: api_state.graphics_state.current_inside_medium.clone(),
+            outside_medium: api_state.graphics_state.current_outside_medium.clone(),
+        };
+        api_state.record(statement);
+    }
     api_state.param_set = params;
-    let mi: MediumInterface = create_medium_interface(&api_state);
-    make_light(api_state, &mi);
+    let reported: usize = api_state.reported;
+    let mi: MediumInterface = create_medium_interface(api_state)?;
+    make_light(api_state, &mi)?;
+    if api_state.reported == reported {
+        let unused: Vec<ApiError> = unused_parameters(&api_state.param_set);
+        api_state.warn_unused(unused);
+    }
+    Ok(())
 }
 
-pub fn pbrt_area_light_source(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_area_light_source(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
     // println!("AreaLightSource \"{}\"", params.name);
     // print_params(&params);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.named_statement("AreaLightSource", &params);
+    }
     api_state.param_set = params;
     api_state.graphics_state.area_light = api_state.param_set.name.clone();
     api_state
         .graphics_state
         .area_light_params
         .copy_from(&api_state.param_set);
+    api_state.graphics_state.area_light_params.name = api_state.param_set.name.clone();
+    Ok(())
+}
+
+/// Numbers the materials used by shapes (starting at 1), for the
+/// "materialid" AOV.
+fn material_id(api_state: &mut ApiState, material: &Option<Arc<Material + Send + Sync>>) -> u32 {
+    if let Some(ref material) = *material {
+        let address: usize = &**material as *const (Material + Send + Sync) as *const u8 as usize;
+        let n_materials: u32 = api_state.render_options.material_ids.len() as u32;
+        *api_state
+            .render_options
+            .material_ids
+            .entry(address)
+            .or_insert(n_materials + 1)
+    } else {
+        0_u32
+    }
+}
+
+/// Each shape holds the shared object to world transform and its
+/// inverse instead of its own copies (see _TransformCache_).
+fn report_transform_savings(n_shapes: usize) {
+    let saved_per_shape: usize =
+        2 * (mem::size_of::<Transform>() - mem::size_of::<Arc<Transform>>());
+    TRANSFORM_CACHE_SAVED_BYTES.add(n_shapes * saved_per_shape);
+}
+
+/// Creates the shape(s) of a **Shape** statement without an area
+/// light and adds them to **prims**.
+fn make_geometric_prims(
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+    primitive_id: u32,
+    prims: &mut Vec<Arc<Primitive + Send + Sync>>,
+) -> Result<(), ApiError> {
+    let (shapes, materials) = get_shapes_and_materials(api_state, bsdf_state)?;
+    assert_eq!(shapes.len(), materials.len());
+    report_transform_savings(shapes.len());
+    // MediumInterface
+    let mi: MediumInterface = create_medium_interface(api_state)?;
+    for i in 0..shapes.len() {
+        let shape = &shapes[i];
+        let material = &materials[i];
+        let mut geo_prim: GeometricPrimitive = GeometricPrimitive::new(
+            shape.clone(),
+            material.clone(),
+            None,
+            Some(Arc::new(mi.clone())),
+        );
+        geo_prim.primitive_id = primitive_id;
+        geo_prim.material_id = material_id(api_state, material);
+        let geo_prim = Arc::new(geo_prim);
+        prims.push(geo_prim.clone());
+    }
+    Ok(())
+}
+
+/// Creates the shapes of the current **Shape** statement together
+/// with a diffuse area light for each of them (see pbrtShape() and
+/// MakeAreaLight() in api.cpp).
+fn make_area_light_prims(
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+    primitive_id: u32,
+    prims: &mut Vec<Arc<Primitive + Send + Sync>>,
+    area_lights: &mut Vec<Arc<Light + Send + Sync>>,
+) -> Result<(), ApiError> {
+    // first create the shape
+    let (shapes, materials) = get_shapes_and_materials(api_state, bsdf_state)?;
+    assert_eq!(shapes.len(), materials.len());
+    report_transform_savings(shapes.len());
+    // MediumInterface
+    let mi: MediumInterface = create_medium_interface(api_state)?;
+    for i in 0..shapes.len() {
+        let shape = &shapes[i];
+        let material = &materials[i];
+        // CreateDiffuseAreaLight
+        let light_to_world: Transform = api_state.cur_transform.t[0];
+        let l: Spectrum = api_state
+            .graphics_state
+            .area_light_params
+            .find_one_spectrum("L", Spectrum::new(1.0));
+        let sc: Spectrum = api_state
+            .graphics_state
+            .area_light_params
+            .find_one_spectrum("scale", Spectrum::new(1.0));
+        let n_samples: i32 = // try "nsamples" first
+            api_state.graphics_state.area_light_params.find_one_int("nsamples",
+                                                          1);
+        let mut n_samples: i32 = // try "samples"next
+            api_state.graphics_state.area_light_params.find_one_int("samples",
+                                                          n_samples);
+        let two_sided: bool = api_state
+            .graphics_state
+            .area_light_params
+            .find_one_bool("twosided", false);
+        if api_state.quick_render {
+            n_samples = std::cmp::max(1, n_samples / 4);
+        }
+        let l_emit: Spectrum = l * sc;
+        let area_light: Arc<DiffuseAreaLight> = Arc::new(DiffuseAreaLight::new(
+            &light_to_world,
+            &mi,
+            &l_emit,
+            n_samples,
+            shape.clone(),
+            two_sided,
+        ));
+        area_lights.push(area_light.clone());
+        let mut geo_prim: GeometricPrimitive = GeometricPrimitive::new(
+            shape.clone(),
+            material.clone(),
+            Some(area_light.clone()),
+            Some(Arc::new(mi.clone())),
+        );
+        geo_prim.primitive_id = primitive_id;
+        geo_prim.material_id = material_id(api_state, material);
+        let geo_prim = Arc::new(geo_prim);
+        prims.push(geo_prim.clone());
+    }
+    Ok(())
 }
 
-pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params: ParamSet) {
+pub fn pbrt_shape(
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+    params: ParamSet,
+) -> Result<(), ApiError> {
     // println!("Shape \"{}\"", params.name);
     // print_params(&params);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.shape(&params);
+    }
+    if api_state.scene_record.is_some() {
+        let statement: ResolvedStatement = shape_statement(api_state, &params);
+        api_state.record(statement);
+    }
     api_state.param_set = params;
+    let reported: usize = api_state.reported;
+    api_state.render_options.n_shapes += 1;
+    let primitive_id: u32 = api_state.render_options.n_shapes;
     // collect area lights
     let mut prims: Vec<Arc<Primitive + Send + Sync>> = Vec::new();
     let mut area_lights: Vec<Arc<Light + Send + Sync>> = Vec::new();
+    // emissive shapes of instance definitions get created later
+    let mut deferred: bool = false;
     // possibly create area light for shape (see pbrtShape())
-    if api_state.graphics_state.area_light != String::new() {
+    let animated: bool = api_state.cur_transform.is_animated();
+    if animated && api_state.graphics_state.area_light != String::new() {
+        // the shape gets created, but without its area light
+        let area_light: String = api_state.graphics_state.area_light.clone();
+        api_state.report(ApiError::unsupported(
+            "Animated AreaLightSource",
+            &area_light,
+        ))?;
+    }
+    if !animated && api_state.graphics_state.area_light != String::new() {
         // MakeAreaLight
         if api_state.graphics_state.area_light == "area"
             || api_state.graphics_state.area_light == "diffuse"
         {
-            // first create the shape
-            let (shapes, materials) = get_shapes_and_materials(&api_state, bsdf_state);
-            assert_eq!(shapes.len(), materials.len());
-            // MediumInterface
-            let mi: MediumInterface = create_medium_interface(&api_state);
-            for i in 0..shapes.len() {
-                let shape = &shapes[i];
-                let material = &materials[i];
-                // CreateDiffuseAreaLight
-                let light_to_world: Transform = api_state.cur_transform.t[0];
-                let l: Spectrum = api_state
-                    .graphics_state
-                    .area_light_params
-                    .find_one_spectrum("L", Spectrum::new(1.0));
-                let sc: Spectrum = api_state
-                    .graphics_state
-                    .area_light_params
-                    .find_one_spectrum("scale", Spectrum::new(1.0));
-                let n_samples: i32 = // try "nsamples" first
-                    api_state.graphics_state.area_light_params.find_one_int("nsamples",
-                                                                  1);
-                let n_samples: i32 = // try "samples"next
-                    api_state.graphics_state.area_light_params.find_one_int("samples",
-                                                                  n_samples);
-                let two_sided: bool = api_state
-                    .graphics_state
-                    .area_light_params
-                    .find_one_bool("twosided", false);
-                // TODO: if (PbrtOptions.quickRender) nSamples = std::max(1, nSamples / 4);
-                let l_emit: Spectrum = l * sc;
-                let area_light: Arc<DiffuseAreaLight> = Arc::new(DiffuseAreaLight::new(
-                    &light_to_world,
-                    &mi,
-                    &l_emit,
-                    n_samples,
-                    shape.clone(),
-                    two_sided,
-                ));
-                area_lights.push(area_light.clone());
-                let geo_prim = Arc::new(GeometricPrimitive::new(
-                    shape.clone(),
-                    material.clone(),
-                    Some(area_light.clone()),
-                    Some(Arc::new(mi.clone())),
-                ));
-                prims.push(geo_prim.clone());
+            if api_state.render_options.current_instance != String::from("") {
+                // the shape and its light get created for each use of
+                // the instance (see pbrt_object_instance())
+                let mut param_set: ParamSet = ParamSet::default();
+                param_set.copy_from(&api_state.param_set);
+                param_set.name = api_state.param_set.name.clone();
+                let instance_area_light: InstanceAreaLight = InstanceAreaLight {
+                    param_set: param_set,
+                    graphics_state: api_state.graphics_state.clone(),
+                    transform: api_state.cur_transform.t[0],
+                    primitive_id: primitive_id,
+                };
+                if let Some(instance_area_lights) = api_state
+                    .render_options
+                    .instance_area_lights
+                    .get_mut(&api_state.render_options.current_instance)
+                {
+                    instance_area_lights.push(instance_area_light);
+                }
+                deferred = true;
+            } else {
+                make_area_light_prims(
+                    api_state,
+                    bsdf_state,
+                    primitive_id,
+                    &mut prims,
+                    &mut area_lights,
+                )?;
             }
+        } else {
+            api_state.report(ApiError::unknown(
+                "AreaLightSource",
+                &api_state.graphics_state.area_light,
+            ))?;
         }
     } else {
         // continue with shape itself
-        let (shapes, materials) = get_shapes_and_materials(&api_state, bsdf_state);
-        assert_eq!(shapes.len(), materials.len());
-        // MediumInterface
-        let mi: MediumInterface = create_medium_interface(&api_state);
-        for i in 0..shapes.len() {
-            let shape = &shapes[i];
-            let material = &materials[i];
-            let geo_prim = Arc::new(GeometricPrimitive::new(
-                shape.clone(),
-                material.clone(),
-                None,
-                Some(Arc::new(mi.clone())),
-            ));
-            prims.push(geo_prim.clone());
-        }
+        make_geometric_prims(api_state, bsdf_state, primitive_id, &mut prims)?;
         // animated?
-        if api_state.cur_transform.is_animated() {
+        if animated {
             let animated_object_to_world: AnimatedTransform = AnimatedTransform::new(
                 &api_state.cur_transform.t[0],
                 api_state.render_options.transform_start_time,
@@ -2870,9 +3944,6 @@ pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params:
     }
     // add _prims_ and _areaLights_ to scene or current instance
     if api_state.render_options.current_instance != String::from("") {
-        if area_lights.len() > 0 {
-            println!("WARNING: Area lights not supported with object instancing");
-        }
         if let Some(instance_vec) = api_state
             .render_options
             .instances
@@ -2892,102 +3963,173 @@ pub fn pbrt_shape(api_state: &mut ApiState, bsdf_state: &mut BsdfState, params:
             }
         }
     }
+    if api_state.reported == reported && !deferred {
+        let mut unused: Vec<ApiError> = unused_parameters(&api_state.param_set);
+        if !animated && api_state.graphics_state.area_light != String::new() {
+            unused.append(&mut unused_parameters(
+                &api_state.graphics_state.area_light_params,
+            ));
+        }
+        api_state.warn_unused(unused);
+    }
+    Ok(())
 }
 
-pub fn pbrt_reverse_orientation(api_state: &mut ApiState) {
+pub fn pbrt_reverse_orientation(api_state: &mut ApiState) -> Result<(), ApiError> {
     // println!("ReverseOrientation");
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement("ReverseOrientation");
+    }
     api_state.graphics_state.reverse_orientation = !api_state.graphics_state.reverse_orientation;
+    Ok(())
 }
 
-pub fn pbrt_object_begin(api_state: &mut ApiState, params: ParamSet) {
+pub fn pbrt_object_begin(api_state: &mut ApiState, params: ParamSet) -> Result<(), ApiError> {
     // println!("ObjectBegin \"{}\"", params.name);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.begin_block(&format!("ObjectBegin \"{}\"", params.name));
+    }
+    api_state.record(ResolvedStatement::ObjectBegin {
+        name: params.name.clone(),
+    });
     api_state.param_set = params;
-    pbrt_attribute_begin(api_state);
+    pbrt_attribute_begin(api_state)?;
     if api_state.render_options.current_instance != String::from("") {
-        println!("ERROR: ObjectBegin called inside of instance definition");
+        api_state.report(ApiError::invalid_state(
+            "ObjectBegin",
+            String::from("called inside of instance definition"),
+        ))?;
     }
     api_state
         .render_options
         .instances
         .insert(api_state.param_set.name.clone(), Vec::new());
+    api_state
+        .render_options
+        .instance_area_lights
+        .insert(api_state.param_set.name.clone(), Vec::new());
+    api_state
+        .render_options
+        .nested_instances
+        .insert(api_state.param_set.name.clone(), 0_u64);
     api_state.render_options.current_instance = api_state.param_set.name.clone();
+    N_OBJECT_INSTANCES_CREATED.inc();
+    Ok(())
 }
 
-pub fn pbrt_object_end(api_state: &mut ApiState) {
+pub fn pbrt_object_end(api_state: &mut ApiState) -> Result<(), ApiError> {
     // println!("ObjectEnd");
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.end_block("ObjectEnd");
+    }
+    api_state.record(ResolvedStatement::ObjectEnd);
     if api_state.render_options.current_instance == "" {
-        println!("ERROR: ObjectEnd called outside of instance definition");
+        api_state.report(ApiError::invalid_state(
+            "ObjectEnd",
+            String::from("called outside of instance definition"),
+        ))?;
     }
     api_state.render_options.current_instance = String::from("");
-    pbrt_attribute_end(api_state);
+    pbrt_attribute_end(api_state)
 }
 
-pub fn pbrt_object_instance(api_state: &mut ApiState, params: ParamSet) {
+/// Places an instance, either in the scene or, if called within
+/// another instance definition, in that one. Emissive shapes of the
+/// instance get created (in world space) and registered as lights for
+/// each instance placed in the scene.
+pub fn pbrt_object_instance(
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+    params: ParamSet,
+) -> Result<(), ApiError> {
     // println!("ObjectInstance \"{}\"", params.name);
+    if let Some(ref mut writer) = api_state.scene_writer {
+        return writer.statement(&format!("ObjectInstance \"{}\"", params.name));
+    }
+    let statement: ResolvedStatement = ResolvedStatement::ObjectInstance {
+        name: params.name.clone(),
+        transform: api_state.cur_transform,
+    };
+    api_state.record(statement);
     api_state.param_set = params;
+    let name: String = api_state.param_set.name.clone();
+    let current_instance: String = api_state.render_options.current_instance.clone();
     // perform object instance error checking
-    if api_state.render_options.current_instance != String::from("") {
-        println!("ERROR: ObjectInstance can't be called inside instance definition");
-        return;
+    if current_instance == name {
+        return api_state.report(ApiError::invalid_state(
+            "ObjectInstance",
+            format!("instance \"{}\" can't be used in its own definition", name),
+        ));
     }
-    if let Some(instance_vec) = api_state
-        .render_options
-        .instances
-        .get_mut(&api_state.param_set.name.clone())
-    {
-        if instance_vec.is_empty() {
-            return;
-        }
-        // TODO: ++nObjectInstancesUsed;
+    let mut prims: Vec<Arc<Primitive + Send + Sync>> = Vec::new();
+    if let Some(instance_vec) = api_state.render_options.instances.get_mut(&name) {
         if instance_vec.len() > 1_usize {
             // create aggregate for instance _Primitive_s
-            if api_state.render_options.accelerator_name == "bvh" {
-                //  CreateBVHAccelerator
-                let split_method_name: String = api_state
-                    .render_options
-                    .accelerator_params
-                    .find_one_string("splitmethod", String::from("sah"));
-                let split_method;
-                if split_method_name == "sah" {
-                    split_method = SplitMethod::SAH;
-                } else if split_method_name == "hlbvh" {
-                    split_method = SplitMethod::HLBVH;
-                } else if split_method_name == "middle" {
-                    split_method = SplitMethod::Middle;
-                } else if split_method_name == "equal" {
-                    split_method = SplitMethod::EqualCounts;
-                } else {
-                    println!(
-                        "WARNING: BVH split method \"{}\" unknown.  Using \"sah\".",
-                        split_method_name
-                    );
-                    split_method = SplitMethod::SAH;
-                }
-                let max_prims_in_node: i32 = api_state
-                    .render_options
-                    .accelerator_params
-                    .find_one_int("maxnodeprims", 4);
-                let accelerator: Arc<Primitive + Sync + Send> = Arc::new(BVHAccel::new(
-                    instance_vec.clone(),
-                    max_prims_in_node as usize,
-                    split_method,
-                ));
-                instance_vec.clear();
-                instance_vec.push(accelerator);
-            } else if api_state.render_options.accelerator_name == "kdtree" {
-                // println!("TODO: CreateKdTreeAccelerator");
-                // WARNING: Use BVHAccel for now !!!
-                let accelerator: Arc<Primitive + Sync + Send> =
-                    Arc::new(BVHAccel::new(instance_vec.clone(), 4, SplitMethod::SAH));
-                instance_vec.clear();
-                instance_vec.push(accelerator);
-            } else {
-                panic!(
-                    "Accelerator \"{}\" unknown.",
-                    api_state.render_options.accelerator_name
-                );
-            }
+            let accelerator: Arc<Primitive + Sync + Send> = make_accelerator(
+                &api_state.render_options.accelerator_name,
+                instance_vec.clone(),
+                &api_state.render_options.accelerator_params,
+            )?;
+            instance_vec.clear();
+            instance_vec.push(accelerator);
+        }
+        prims.extend(instance_vec.first().cloned());
+    } else {
+        return api_state.report(ApiError::unknown("ObjectInstance", &name));
+    }
+    // emissive shapes of the instance
+    let area_lights: Vec<InstanceAreaLight> =
+        match api_state.render_options.instance_area_lights.get(&name) {
+            Some(area_lights) => area_lights.clone(),
+            None => Vec::new(),
+        };
+    if prims.is_empty() && area_lights.is_empty() {
+        return Ok(());
+    }
+    N_OBJECT_INSTANCES_USED.inc();
+    let n_nested: u64 = match api_state.render_options.nested_instances.get(&name) {
+        Some(n_nested) => *n_nested,
+        None => 0_u64,
+    };
+    if current_instance != "" {
+        if let Some(n) = api_state
+            .render_options
+            .nested_instances
+            .get_mut(&current_instance)
+        {
+            *n += 1 + n_nested;
+        }
+    } else {
+        N_OBJECT_INSTANCES_TOTAL.add(1 + n_nested as i64);
+    }
+    let animated: bool = api_state.cur_transform.is_animated();
+    if animated && !area_lights.is_empty() {
+        api_state.report(ApiError::unsupported(
+            "Area lights of animated ObjectInstance",
+            &name,
+        ))?;
+        // keep the emissive shapes as plain geometry of the instance
+        for area_light in &area_lights {
+            create_instance_shape(
+                api_state,
+                bsdf_state,
+                area_light.clone(),
+                false,
+                &mut prims,
+                &mut Vec::new(),
+            )?;
+        }
+        if prims.len() > 1_usize {
+            let accelerator: Arc<Primitive + Sync + Send> = make_accelerator(
+                &api_state.render_options.accelerator_name,
+                prims.clone(),
+                &api_state.render_options.accelerator_params,
+            )?;
+            prims.clear();
+            prims.push(accelerator);
         }
+    }
+    if let Some(instance) = prims.pop() {
         // create _animatedInstanceToWorld_ transform for instance
         let animated_instance_to_world: AnimatedTransform = AnimatedTransform::new(
             &api_state.cur_transform.t[0],
@@ -2996,15 +4138,89 @@ pub fn pbrt_object_instance(api_state: &mut ApiState, params: ParamSet) {
             api_state.render_options.transform_end_time,
         );
         let prim: Arc<Primitive + Send + Sync> = Arc::new(TransformedPrimitive::new(
-            instance_vec[0].clone(),
+            instance,
             animated_instance_to_world,
         ));
-        api_state.render_options.primitives.push(prim.clone());
+        if current_instance != "" {
+            // nested instance
+            if let Some(instance_vec) = api_state
+                .render_options
+                .instances
+                .get_mut(&current_instance)
+            {
+                instance_vec.push(prim);
+            }
+        } else {
+            api_state.render_options.primitives.push(prim);
+        }
+    }
+    if animated || area_lights.is_empty() {
+        return Ok(());
+    }
+    let instance_to_world: Transform = api_state.cur_transform.t[0];
+    if current_instance != "" {
+        if let Some(instance_area_lights) = api_state
+            .render_options
+            .instance_area_lights
+            .get_mut(&current_instance)
+        {
+            for area_light in &area_lights {
+                instance_area_lights.push(area_light.transformed(&instance_to_world));
+            }
+        }
     } else {
-        println!(
-            "ERROR: Unable to find instance named {:?}",
-            api_state.param_set.name.clone()
-        );
-        return;
+        let mut lights: Vec<Arc<Light + Send + Sync>> = Vec::new();
+        for area_light in &area_lights {
+            create_instance_shape(
+                api_state,
+                bsdf_state,
+                area_light.transformed(&instance_to_world),
+                true,
+                &mut prims,
+                &mut lights,
+            )?;
+        }
+        api_state.render_options.primitives.extend(prims);
+        api_state.render_options.lights.extend(lights);
     }
+    Ok(())
+}
+
+/// Creates an emissive shape of an instance (with the graphics state
+/// of its definition) and adds it to **prims**. Its area lights are
+/// added to **lights** if **emissive** is set, otherwise the shape
+/// becomes plain geometry.
+fn create_instance_shape(
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+    area_light: InstanceAreaLight,
+    emissive: bool,
+    prims: &mut Vec<Arc<Primitive + Send + Sync>>,
+    lights: &mut Vec<Arc<Light + Send + Sync>>,
+) -> Result<(), ApiError> {
+    // temporarily switch to the state the shape was defined with
+    let param_set: ParamSet = mem::replace(&mut api_state.param_set, area_light.param_set);
+    let graphics_state: GraphicsState =
+        mem::replace(&mut api_state.graphics_state, area_light.graphics_state);
+    let cur_transform: TransformSet = mem::replace(
+        &mut api_state.cur_transform,
+        TransformSet {
+            t: [area_light.transform; 2],
+        },
+    );
+    let result: Result<(), ApiError> = if emissive {
+        make_area_light_prims(
+            api_state,
+            bsdf_state,
+            area_light.primitive_id,
+            prims,
+            lights,
+        )
+    } else {
+        make_geometric_prims(api_state, bsdf_state, area_light.primitive_id, prims)
+    };
+    api_state.param_set = param_set;
+    api_state.graphics_state = graphics_state;
+    api_state.cur_transform = cur_transform;
+    result
 }
@@ -19,6 +19,7 @@ use core::pbrt::clamp_t;
 use core::pbrt::INV_4_PI;
 use core::pbrt::{Float, Spectrum};
 use core::primitive::Primitive;
+use core::profiler::{Prof, ProfilePhase};
 use core::reflection::{cos_theta, fr_dielectric};
 use core::reflection::{Bsdf, Bxdf, BxdfType};
 use core::scene::Scene;
@@ -118,7 +119,7 @@ impl TabulatedBssrdf {
 
 impl Bssrdf for TabulatedBssrdf {
     fn s(&self, pi: &SurfaceInteraction, wi: &Vector3f) -> Spectrum {
-        // ProfilePhase pp(Prof::BSSRDFEvaluation);
+        let _p = ProfilePhase::new(Prof::BSSRDFEvaluation);
         let ft: Float = fr_dielectric(cos_theta(&self.po_wo), 1.0 as Float, self.eta);
         self.sp(pi) * self.sw(wi) * (1.0 as Float - ft)
     }
@@ -134,7 +135,7 @@ impl Bssrdf for TabulatedBssrdf {
         u2: &Point2f,
         pdf: &mut Float,
     ) -> (Spectrum, Option<SurfaceInteraction>) {
-        // ProfilePhase pp(Prof::BSSRDFSampling);
+        let _p = ProfilePhase::new(Prof::BSSRDFSampling);
         let mut si: SurfaceInteraction = SurfaceInteraction::default();
         let sp: Spectrum = self.sample_sp(scene, u1, u2, &mut si, pdf);
         if !sp.is_black() {
@@ -201,7 +202,7 @@ impl SeparableBssrdf for TabulatedBssrdf {
         pi: &mut SurfaceInteraction,
         pdf: &mut Float,
     ) -> Spectrum {
-        // ProfilePhase pp(Prof::BSSRDFEvaluation);
+        let _p = ProfilePhase::new(Prof::BSSRDFEvaluation);
         let mut u1: Float = u1; // shadowing input parameter
 
         // choose projection axis for BSSRDF sampling
@@ -0,0 +1,202 @@
+//! Long renders can be interrupted and continued later. While
+//! rendering, the accumulated state of the film (pixel sums, filter
+//! weights, splats, and AOVs) gets written every now and then to a
+//! checkpoint file, together with the image tiles which are finished.
+//! Because the sampler of each tile gets seeded with the tile's
+//! index, a render resumed from a checkpoint only has to render the
+//! remaining tiles to end up with the same image as an uninterrupted
+//! render.
+//!
+//! Checkpoints are supported by the tile based integrators
+//! (**render()** and **render_bdpt()**), MLT and SPPM ignore them.
+
+// std
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+// others
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+// pbrt
+use core::film::Film;
+use core::geometry::Point2i;
+use core::pbrt::{bits_to_float, float_to_bits, Float};
+
+const MAGIC: &'static [u8; 8] = b"PBRTCKPT";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Default, Clone)]
+pub struct Checkpoint {
+    /// The checkpoint file, next to the image
+    pub filename: String,
+    /// Time between two checkpoints, none get written if not set
+    pub interval: Option<Duration>,
+    /// Continue from the checkpoint file (if there is one)
+    pub resume: bool,
+}
+
+impl Checkpoint {
+    pub fn new(image_filename: &str, interval: Option<Duration>, resume: bool) -> Self {
+        Checkpoint {
+            filename: format!("{}.checkpoint", image_filename),
+            interval: interval,
+            resume: resume,
+        }
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.interval.is_some() || self.resume
+    }
+    /// Is it time to write the next checkpoint (the last one was
+    /// written at *last*)?
+    pub fn is_due(&self, last: &Instant) -> bool {
+        match self.interval {
+            Some(interval) => last.elapsed() >= interval,
+            None => false,
+        }
+    }
+    /// Restores the *film* from the checkpoint file and returns the
+    /// tiles which are finished already. Starts from scratch (with no
+    /// finished tiles) if there is nothing to resume from.
+    pub fn resume(
+        &self,
+        film: &Film,
+        n_tiles: &Point2i,
+        samples_per_pixel: i64,
+    ) -> Vec<(u32, u32)> {
+        if !self.resume {
+            return Vec::new();
+        }
+        let file: File = match File::open(&self.filename) {
+            Ok(file) => file,
+            Err(_) => {
+                println!(
+                    "WARNING: No checkpoint \"{}\" found, starting a new render.",
+                    self.filename
+                );
+                return Vec::new();
+            }
+        };
+        let mut reader: BufReader<File> = BufReader::new(file);
+        match read_checkpoint(&mut reader, film, n_tiles, samples_per_pixel) {
+            Ok(finished) => {
+                println!(
+                    "Resuming from checkpoint \"{}\" ({} of {} tiles finished) ...",
+                    self.filename,
+                    finished.len(),
+                    n_tiles.x * n_tiles.y
+                );
+                finished
+            }
+            Err(e) => {
+                println!(
+                    "ERROR: Unable to resume from checkpoint \"{}\": {}",
+                    self.filename, e
+                );
+                Vec::new()
+            }
+        }
+    }
+    /// Writes the state of the *film* and the *finished* tiles. The
+    /// checkpoint gets written to a temporary file first, so an
+    /// interruption while writing keeps the previous checkpoint.
+    pub fn write(
+        &self,
+        film: &Film,
+        n_tiles: &Point2i,
+        samples_per_pixel: i64,
+        finished: &[(u32, u32)],
+    ) {
+        let tmp_filename: String = format!("{}.tmp", self.filename);
+        let result = File::create(&tmp_filename)
+            .and_then(|file| {
+                let mut writer: BufWriter<File> = BufWriter::new(file);
+                write_checkpoint(&mut writer, film, n_tiles, samples_per_pixel, finished)?;
+                writer.flush()
+            })
+            .and_then(|_| fs::rename(&tmp_filename, &self.filename));
+        if let Err(e) = result {
+            println!(
+                "ERROR: Unable to write checkpoint \"{}\": {}",
+                self.filename, e
+            );
+        }
+    }
+    /// Removes the checkpoint file once the image is finished.
+    pub fn remove(&self) {
+        if self.is_enabled() {
+            let _ = fs::remove_file(&self.filename);
+        }
+    }
+    /// Keeps the checkpoint file if the image couldn't be written, a
+    /// resumed render writes the image again.
+    pub fn keep(&self) {
+        if self.is_enabled() && Path::new(&self.filename).exists() {
+            println!(
+                "Keeping checkpoint \"{}\", resume the render (--resume) to write the image.",
+                self.filename
+            );
+        }
+    }
+}
+
+fn write_checkpoint(
+    w: &mut Write,
+    film: &Film,
+    n_tiles: &Point2i,
+    samples_per_pixel: i64,
+    finished: &[(u32, u32)],
+) -> Result<()> {
+    w.write_all(MAGIC)?;
+    w.write_u32::<LittleEndian>(VERSION)?;
+    w.write_i32::<LittleEndian>(n_tiles.x)?;
+    w.write_i32::<LittleEndian>(n_tiles.y)?;
+    w.write_i64::<LittleEndian>(samples_per_pixel)?;
+    w.write_u32::<LittleEndian>(finished.len() as u32)?;
+    for tile in finished {
+        w.write_u32::<LittleEndian>(tile.0)?;
+        w.write_u32::<LittleEndian>(tile.1)?;
+    }
+    film.write_checkpoint(w)
+}
+
+fn read_checkpoint(
+    r: &mut Read,
+    film: &Film,
+    n_tiles: &Point2i,
+    samples_per_pixel: i64,
+) -> Result<Vec<(u32, u32)>> {
+    let mut magic: [u8; 8] = [0; 8];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC || r.read_u32::<LittleEndian>()? != VERSION {
+        return Err(invalid_data("not a checkpoint file"));
+    }
+    let x: i32 = r.read_i32::<LittleEndian>()?;
+    let y: i32 = r.read_i32::<LittleEndian>()?;
+    let spp: i64 = r.read_i64::<LittleEndian>()?;
+    if x != n_tiles.x || y != n_tiles.y || spp != samples_per_pixel {
+        return Err(invalid_data(
+            "image tiles or samples per pixel don't match the scene",
+        ));
+    }
+    let n_finished: u32 = r.read_u32::<LittleEndian>()?;
+    let mut finished: Vec<(u32, u32)> = Vec::with_capacity(n_finished as usize);
+    for _ in 0..n_finished {
+        let tx: u32 = r.read_u32::<LittleEndian>()?;
+        let ty: u32 = r.read_u32::<LittleEndian>()?;
+        finished.push((tx, ty));
+    }
+    film.read_checkpoint(r)?;
+    Ok(finished)
+}
+
+pub fn write_float(w: &mut Write, v: Float) -> Result<()> {
+    w.write_u32::<LittleEndian>(float_to_bits(v))
+}
+
+pub fn read_float(r: &mut Read) -> Result<Float> {
+    Ok(bits_to_float(r.read_u32::<LittleEndian>()?))
+}
+
+pub fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
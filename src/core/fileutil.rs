@@ -0,0 +1,30 @@
+//! Helper functions to deal with file names and to open (possibly
+//! compressed) files referenced by a scene description.
+
+// std
+use std::fs::File;
+use std::io::{BufRead, BufReader, Result};
+use std::path::Path;
+// others
+use flate2::read::GzDecoder;
+
+// see fileutil.h
+
+/// Checks (case-insensitively) if a file name ends with the given
+/// extension, e.g. `has_extension("scene.pbrt.gz", ".gz")`.
+pub fn has_extension(filename: &str, ext: &str) -> bool {
+    filename.to_lowercase().ends_with(&ext.to_lowercase())
+}
+
+/// Opens a file for buffered reading. Files ending in **.gz** are
+/// decompressed on the fly while being read, so the decompressed
+/// content never has to be kept in memory as a whole.
+pub fn open_file(path: &Path) -> Result<Box<BufRead>> {
+    let f: File = File::open(path)?;
+    let filename: String = path.to_string_lossy().into_owned();
+    if has_extension(&filename, ".gz") {
+        Ok(Box::new(BufReader::new(GzDecoder::new(f))))
+    } else {
+        Ok(Box::new(BufReader::new(f)))
+    }
+}
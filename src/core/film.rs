@@ -10,24 +10,23 @@
 //!
 
 // std
-#[cfg(feature = "openexr")]
-use std;
+use std::io::{Read, Result, Write};
 use std::ops::{DerefMut, Index};
-use std::path::Path;
 use std::sync::{Arc, RwLock, RwLockWriteGuard};
 // others
-use image;
-#[cfg(feature = "openexr")]
-use openexr::{FrameBuffer, Header, PixelType, ScanlineOutputFile};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 // pbrt
+use core::aov::{AovPixel, AovSample, AovType};
+use core::checkpoint::{invalid_data, read_float, write_float};
 use core::filter::Filter;
 use core::geometry::{
     bnd2_intersect_bnd2, pnt2_ceil, pnt2_floor, pnt2_inside_exclusive, pnt2_max_pnt2, pnt2_min_pnt2,
 };
 use core::geometry::{Bounds2f, Bounds2i, Point2f, Point2i, Vector2f};
+use core::imageio::{write_image, write_image_layers, ImageLayer};
 use core::parallel::AtomicFloat;
-use core::pbrt::{clamp_t, gamma_correct};
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::spectrum::xyz_to_rgb;
 
 // see film.h
@@ -71,6 +70,8 @@ pub struct FilmTile<'a> {
     filter_table_size: usize,
     pixels: Vec<FilmTilePixel>,
     max_sample_luminance: Float,
+    aovs: &'a [AovType],
+    aov_pixels: Vec<AovPixel>,
 }
 
 impl<'a> FilmTile<'a> {
@@ -80,7 +81,13 @@ impl<'a> FilmTile<'a> {
         filter_table: &'a [Float; FILTER_TABLE_WIDTH * FILTER_TABLE_WIDTH],
         filter_table_size: usize,
         max_sample_luminance: Float,
+        aovs: &'a [AovType],
     ) -> Self {
+        let aov_pixels: Vec<AovPixel> = if aovs.is_empty() {
+            Vec::new()
+        } else {
+            vec![AovPixel::new(aovs); pixel_bounds.area() as usize]
+        };
         FilmTile {
             pixel_bounds: pixel_bounds,
             filter_radius: filter_radius,
@@ -93,10 +100,12 @@ impl<'a> FilmTile<'a> {
             // TODO: pixels = std::vector<FilmTilePixel>(std::max(0, pixelBounds.Area()));
             pixels: vec![FilmTilePixel::default(); pixel_bounds.area() as usize],
             max_sample_luminance: max_sample_luminance,
+            aovs: aovs,
+            aov_pixels: aov_pixels,
         }
     }
     pub fn add_sample(&mut self, p_film: &Point2f, l: &mut Spectrum, sample_weight: Float) {
-        // TODO: ProfilePhase _(Prof::AddFilmSample);
+        let _p = ProfilePhase::new(Prof::AddFilmSample);
         if l.y() > self.max_sample_luminance {
             *l *= Spectrum::new(self.max_sample_luminance / l.y());
         }
@@ -149,6 +158,22 @@ impl<'a> FilmTile<'a> {
             }
         }
     }
+    /// Adds the AOV values of a camera sample to the pixel containing
+    /// the sample (they don't get filtered).
+    pub fn add_aov_sample(&mut self, p_film: &Point2f, aov: &AovSample) {
+        if self.aovs.is_empty() {
+            return;
+        }
+        let p: Point2i = Point2i {
+            x: p_film.x.floor() as i32,
+            y: p_film.y.floor() as i32,
+        };
+        if !pnt2_inside_exclusive(&p, &self.pixel_bounds) {
+            return;
+        }
+        let idx = self.get_pixel_index(p.x, p.y);
+        self.aov_pixels[idx].add_sample(self.aovs, aov);
+    }
     fn get_pixel_index(&self, x: i32, y: i32) -> usize {
         let width: i32 = self.pixel_bounds.p_max.x - self.pixel_bounds.p_min.x;
         let pidx = (y - self.pixel_bounds.p_min.y) * width + (x - self.pixel_bounds.p_min.x);
@@ -164,13 +189,18 @@ pub struct Film {
     pub diagonal: Float,
     /// A filter function
     pub filter: Arc<Filter + Sync + Send>,
-    /// The filename of the output image
+    /// The filename of the output image, its extension selects the format
     pub filename: String,
+    /// Bits per channel (8 or 16) for PNG images
+    pub bit_depth: u8,
+    /// Arbitrary output variables recorded next to the image
+    pub aovs: Vec<AovType>,
     /// A crop window that may specify a subset of the image to render
     pub cropped_pixel_bounds: Bounds2i,
 
     // Film Private Data
     pub pixels: RwLock<Vec<Pixel>>,
+    aov_pixels: RwLock<Vec<AovPixel>>,
     filter_table: [Float; FILTER_TABLE_WIDTH * FILTER_TABLE_WIDTH],
     scale: Float,
     max_sample_luminance: Float,
@@ -183,8 +213,10 @@ impl Film {
         filter: Arc<Filter + Sync + Send>,
         diagonal: Float,
         filename: String,
+        bit_depth: u8,
         scale: Float,
         max_sample_luminance: Float,
+        aovs: Vec<AovType>,
     ) -> Self {
         let cropped_pixel_bounds: Bounds2i = Bounds2i {
             p_min: Point2i {
@@ -218,8 +250,15 @@ impl Film {
             diagonal: diagonal * 0.001,
             filter: filter,
             filename: filename,
+            bit_depth: bit_depth,
             cropped_pixel_bounds: cropped_pixel_bounds,
             pixels: RwLock::new(vec![Pixel::default(); cropped_pixel_bounds.area() as usize]),
+            aov_pixels: RwLock::new(if aovs.is_empty() {
+                Vec::new()
+            } else {
+                vec![AovPixel::new(&aovs); cropped_pixel_bounds.area() as usize]
+            }),
+            aovs: aovs,
             filter_table: filter_table,
             scale: scale,
             max_sample_luminance: max_sample_luminance,
@@ -306,10 +345,11 @@ impl Film {
             &self.filter_table,
             FILTER_TABLE_WIDTH,
             self.max_sample_luminance,
+            &self.aovs,
         )
     }
     pub fn merge_film_tile(&self, tile: &FilmTile) {
-        // TODO: ProfilePhase p(Prof::MergeFilmTile);
+        let _p = ProfilePhase::new(Prof::MergeFilmTile);
         // println!("Merging film tile {:?}", tile.pixel_bounds);
         // TODO: std::lock_guard<std::mutex> lock(mutex);
         for pixel in &tile.pixel_bounds {
@@ -332,6 +372,10 @@ impl Film {
             merge_pixel.filter_weight_sum += tile_pixel.filter_weight_sum;
             // write pixel back
             // pixels_write[offset as usize] = *merge_pixel;
+            if !self.aovs.is_empty() {
+                let mut aov_pixels_write = self.aov_pixels.write().unwrap();
+                aov_pixels_write[offset as usize].merge(&self.aovs, &tile.aov_pixels[idx]);
+            }
         }
     }
     pub fn set_image(&self, img: &[Spectrum]) {
@@ -350,9 +394,72 @@ impl Film {
             merge_pixel.splat_xyz[2] = AtomicFloat::new(0.0 as Float);
         }
     }
+    /// Writes the accumulated pixel sums, filter weights, splats, and
+    /// AOVs to a checkpoint (see **core::checkpoint**).
+    pub fn write_checkpoint(&self, w: &mut Write) -> Result<()> {
+        w.write_i32::<LittleEndian>(self.cropped_pixel_bounds.p_min.x)?;
+        w.write_i32::<LittleEndian>(self.cropped_pixel_bounds.p_min.y)?;
+        w.write_i32::<LittleEndian>(self.cropped_pixel_bounds.p_max.x)?;
+        w.write_i32::<LittleEndian>(self.cropped_pixel_bounds.p_max.y)?;
+        w.write_u32::<LittleEndian>(self.aovs.len() as u32)?;
+        for pixel in self.pixels.read().unwrap().iter() {
+            for i in 0..3 {
+                write_float(w, pixel.xyz[i])?;
+            }
+            write_float(w, pixel.filter_weight_sum)?;
+            for i in 0..3 {
+                write_float(w, Float::from(&pixel.splat_xyz[i]))?;
+            }
+        }
+        for aov_pixel in self.aov_pixels.read().unwrap().iter() {
+            aov_pixel.write(w)?;
+        }
+        Ok(())
+    }
+    /// Restores the pixels written by **write_checkpoint()**. The
+    /// film stays unchanged if that fails.
+    pub fn read_checkpoint(&self, r: &mut Read) -> Result<()> {
+        let p_min: Point2i = Point2i {
+            x: r.read_i32::<LittleEndian>()?,
+            y: r.read_i32::<LittleEndian>()?,
+        };
+        let p_max: Point2i = Point2i {
+            x: r.read_i32::<LittleEndian>()?,
+            y: r.read_i32::<LittleEndian>()?,
+        };
+        let n_aovs: u32 = r.read_u32::<LittleEndian>()?;
+        if p_min != self.cropped_pixel_bounds.p_min
+            || p_max != self.cropped_pixel_bounds.p_max
+            || n_aovs != self.aovs.len() as u32
+        {
+            return Err(invalid_data("film resolution or AOVs don't match the scene"));
+        }
+        let n_pixels: usize = self.cropped_pixel_bounds.area() as usize;
+        let mut pixels: Vec<Pixel> = vec![Pixel::default(); n_pixels];
+        for pixel in pixels.iter_mut() {
+            for i in 0..3 {
+                pixel.xyz[i] = read_float(r)?;
+            }
+            pixel.filter_weight_sum = read_float(r)?;
+            for i in 0..3 {
+                pixel.splat_xyz[i] = AtomicFloat::new(read_float(r)?);
+            }
+        }
+        let mut aov_pixels: Vec<AovPixel> = if self.aovs.is_empty() {
+            Vec::new()
+        } else {
+            vec![AovPixel::new(&self.aovs); n_pixels]
+        };
+        for aov_pixel in aov_pixels.iter_mut() {
+            aov_pixel.read(r)?;
+        }
+        *self.pixels.write().unwrap() = pixels;
+        *self.aov_pixels.write().unwrap() = aov_pixels;
+        Ok(())
+    }
     pub fn add_splat(&self, p: &Point2f, v: &Spectrum) {
         let mut v: Spectrum = *v;
-        // TODO: ProfilePhase pp(Prof::SplatFilm);
+        let _p = ProfilePhase::new(Prof::SplatFilm);
         if v.has_nans() {
             println!(
                 "ERROR: Ignoring splatted spectrum with NaN values at ({:?}, {:?})",
@@ -399,8 +506,9 @@ impl Film {
         splat_xyz[1].add(xyz[1]);
         splat_xyz[2].add(xyz[2]);
     }
-    #[cfg(not(feature = "openexr"))]
-    pub fn write_image(&self, splat_scale: Float) {
+    /// Writes the image (and its AOVs) to the file given by the
+    /// **Film** statement.
+    pub fn write_image(&self, splat_scale: Float) -> Result<()> {
         let mut rgb: Vec<Float> =
             vec![0.0 as Float; (3 * self.cropped_pixel_bounds.area()) as usize];
         let mut offset;
@@ -443,169 +551,46 @@ impl Film {
             rgb[start + 1] *= self.scale;
             rgb[start + 2] *= self.scale;
         }
-        let filename = "pbrt.png";
         println!(
             "Writing image {:?} with bounds {:?}",
-            filename, // TODO: self.filename,
-            self.cropped_pixel_bounds
+            self.filename, self.cropped_pixel_bounds
         );
-        // TODO: pbrt::WriteImage(filename, &rgb[0], croppedPixelBounds, fullResolution);
-        let mut buffer: Vec<u8> = vec![0.0 as u8; (3 * self.cropped_pixel_bounds.area()) as usize];
-        // 8-bit format; apply gamma (see WriteImage(...) in imageio.cpp)
-        let width: u32 =
-            (self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x) as u32;
-        let height: u32 =
-            (self.cropped_pixel_bounds.p_max.y - self.cropped_pixel_bounds.p_min.y) as u32;
-        for y in 0..height {
-            for x in 0..width {
-                // red
-                let index: usize = (3 * (y * width + x) + 0) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // green
-                let index: usize = (3 * (y * width + x) + 1) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // blue
-                let index: usize = (3 * (y * width + x) + 2) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-            }
+        if self.aovs.is_empty() {
+            write_image(
+                &self.filename,
+                &rgb,
+                &self.cropped_pixel_bounds,
+                self.bit_depth,
+            )
+        } else {
+            write_image_layers(
+                &self.filename,
+                &rgb,
+                &self.aov_layers(),
+                &self.cropped_pixel_bounds,
+                self.bit_depth,
+            )
         }
-        // write "pbrt.png" to disk
-        image::save_buffer(
-            &Path::new("pbrt.png"),
-            &buffer,
-            width,
-            height,
-            image::RGB(8),
-        ).unwrap();
     }
-    #[cfg(feature = "openexr")]
-    pub fn write_image(&self, splat_scale: Float) {
-        let mut rgb: Vec<Float> =
-            vec![0.0 as Float; (3 * self.cropped_pixel_bounds.area()) as usize];
-        let mut exr: Vec<(Float, Float, Float)> = // copy data for OpenEXR image
-            vec![(0.0_f32, 0.0_f32, 0.0_f32); self.cropped_pixel_bounds.area() as usize];
-        let mut offset;
-        for p in &self.cropped_pixel_bounds {
-            // convert pixel XYZ color to RGB
-            assert!(pnt2_inside_exclusive(&p, &self.cropped_pixel_bounds));
-            let width: i32 = self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x;
-            offset = ((p.x - self.cropped_pixel_bounds.p_min.x)
-                + (p.y - self.cropped_pixel_bounds.p_min.y) * width) as usize;
-            let pixel: &Pixel = &self.pixels.read().unwrap()[offset];
-            let start = 3 * offset;
-            let mut rgb_array: [Float; 3] = [0.0 as Float; 3];
-            xyz_to_rgb(&pixel.xyz, &mut rgb_array); // TODO: Use 'rgb' directly.
-            rgb[start + 0] = rgb_array[0];
-            rgb[start + 1] = rgb_array[1];
-            rgb[start + 2] = rgb_array[2];
-            // normalize pixel with weight sum
-            let filter_weight_sum: Float = pixel.filter_weight_sum;
-            if filter_weight_sum != 0.0 as Float {
-                let inv_wt: Float = 1.0 as Float / filter_weight_sum;
-                rgb[start + 0] = (rgb[start + 0] * inv_wt).max(0.0 as Float);
-                rgb[start + 1] = (rgb[start + 1] * inv_wt).max(0.0 as Float);
-                rgb[start + 2] = (rgb[start + 2] * inv_wt).max(0.0 as Float);
-            }
-            // add splat value at pixel
-            let mut splat_rgb: [Float; 3] = [0.0 as Float; 3];
-            let pixel_splat_xyz: &[AtomicFloat; 3] = &pixel.splat_xyz;
-            let splat_xyz: [Float; 3] = [
-                Float::from(pixel_splat_xyz.index(0)),
-                Float::from(pixel_splat_xyz.index(1)),
-                Float::from(pixel_splat_xyz.index(2)),
-            ];
-            xyz_to_rgb(&splat_xyz, &mut splat_rgb);
-            rgb[start + 0] += splat_scale * splat_rgb[0];
-            rgb[start + 1] += splat_scale * splat_rgb[1];
-            rgb[start + 2] += splat_scale * splat_rgb[2];
-            // scale pixel value by _scale_
-            rgb[start + 0] *= self.scale;
-            rgb[start + 1] *= self.scale;
-            rgb[start + 2] *= self.scale;
-            // copy data for OpenEXR image
-            exr[offset].0 = rgb[start + 0];
-            exr[offset].1 = rgb[start + 1];
-            exr[offset].2 = rgb[start + 2];
-        }
-        let filename = "pbrt.png";
-        println!(
-            "Writing image {:?} with bounds {:?}",
-            filename, // TODO: self.filename,
-            self.cropped_pixel_bounds
-        );
-        // TODO: pbrt::WriteImage(filename, &rgb[0], croppedPixelBounds, fullResolution);
-        let mut buffer: Vec<u8> = vec![0.0 as u8; (3 * self.cropped_pixel_bounds.area()) as usize];
-        // 8-bit format; apply gamma (see WriteImage(...) in imageio.cpp)
-        let width: u32 =
-            (self.cropped_pixel_bounds.p_max.x - self.cropped_pixel_bounds.p_min.x) as u32;
-        let height: u32 =
-            (self.cropped_pixel_bounds.p_max.y - self.cropped_pixel_bounds.p_min.y) as u32;
-        // OpenEXR
-        let filename = "pbrt_rust.exr";
-        println!(
-            "Writing image {:?} with bounds {:?}",
-            filename, // TODO: self.filename,
-            self.cropped_pixel_bounds
-        );
-        let mut file = std::fs::File::create("pbrt_rust.exr").unwrap();
-        let mut output_file = ScanlineOutputFile::new(
-            &mut file,
-            Header::new()
-                .set_resolution(width, height)
-                .add_channel("R", PixelType::FLOAT)
-                .add_channel("G", PixelType::FLOAT)
-                .add_channel("B", PixelType::FLOAT),
-        ).unwrap();
-        let mut fb = FrameBuffer::new(width as u32, height as u32);
-        fb.insert_channels(&["R", "G", "B"], &exr);
-        output_file.write_pixels(&fb).unwrap();
-
-        // OpenEXR
-        for y in 0..height {
-            for x in 0..width {
-                // red
-                let index: usize = (3 * (y * width + x) + 0) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // green
-                let index: usize = (3 * (y * width + x) + 1) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
-                // blue
-                let index: usize = (3 * (y * width + x) + 2) as usize;
-                buffer[index] = clamp_t(
-                    255.0 as Float * gamma_correct(rgb[index]) + 0.5,
-                    0.0 as Float,
-                    255.0 as Float,
-                ) as u8;
+    fn aov_layers(&self) -> Vec<ImageLayer> {
+        let aov_pixels = self.aov_pixels.read().unwrap();
+        let mut layers: Vec<ImageLayer> = Vec::with_capacity(self.aovs.len());
+        let mut offset: usize = 0;
+        for aov in &self.aovs {
+            let mut values: Vec<Float> =
+                Vec::with_capacity(aov.channels().len() * aov_pixels.len());
+            for aov_pixel in aov_pixels.iter() {
+                aov_pixel.resolve(aov, offset, self.scale, &mut values);
             }
+            layers.push(ImageLayer {
+                name: aov.name(),
+                channels: aov.channels(),
+                color: aov.is_color(),
+                values: values,
+            });
+            offset += aov.channels().len();
         }
-        // write "pbrt.png" to disk
-        image::save_buffer(
-            &Path::new("pbrt.png"),
-            &buffer,
-            width,
-            height,
-            image::RGB(8),
-        ).unwrap();
+        layers
     }
     // pub fn get_pixel<'a>(&self, p: &Point2i) -> &'a Pixel {
     //     assert!(pnt2_inside_exclusive(p, &self.cropped_pixel_bounds));
@@ -1744,6 +1744,32 @@ impl Bounds3<Float> {
         }
         (t_min < ray.t_max) && (t_max > 0.0)
     }
+    /// Like **intersect_p()**, but also returns the parametric range
+    /// of the ray inside the bounds (in **hitt0** and **hitt1**).
+    pub fn intersect_b(&self, ray: &Ray, hitt0: &mut Float, hitt1: &mut Float) -> bool {
+        let mut t0: Float = 0.0;
+        let mut t1: Float = ray.t_max;
+        for i in 0..3_u8 {
+            // update interval for _i_th bounding box slab
+            let inv_ray_dir: Float = 1.0 / ray.d[i];
+            let mut t_near: Float = (self.p_min[i] - ray.o[i]) * inv_ray_dir;
+            let mut t_far: Float = (self.p_max[i] - ray.o[i]) * inv_ray_dir;
+            // update parametric interval from slab intersection $t$ values
+            if t_near > t_far {
+                std::mem::swap(&mut t_near, &mut t_far);
+            }
+            // update _t_far_ to ensure robust ray--bounds intersection
+            t_far *= 1.0 + 2.0 * gamma(3_i32);
+            t0 = if t_near > t0 { t_near } else { t0 };
+            t1 = if t_far < t1 { t_far } else { t1 };
+            if t0 > t1 {
+                return false;
+            }
+        }
+        *hitt0 = t0;
+        *hitt1 = t1;
+        true
+    }
 }
 
 impl<T> Index<u8> for Bounds3<T> {
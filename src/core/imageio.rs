@@ -0,0 +1,310 @@
+//! Writing the final image to disk. The file format gets chosen by
+//! the extension of the filename: PNG (8 or 16 bits per channel,
+//! gamma corrected), OpenEXR (needs the **openexr** feature), PFM,
+//! Radiance HDR, and TGA (8 bits per channel, gamma corrected).
+//!
+//! Extra layers (like the film's AOVs) become additional channels of
+//! an OpenEXR file, for all other formats they get written as sidecar
+//! images next to the image. Color layers use the image's format,
+//! e.g. `pbrt_albedo.png` next to `pbrt.png`, all other layers (like
+//! depth, normals, or ids) are written as linear floats to PFM files,
+//! e.g. `pbrt_depth.pfm`, so they don't get gamma corrected or
+//! clamped.
+
+// std
+use std::fs::File;
+use std::io::{BufWriter, Error, ErrorKind, Result, Write};
+use std::path::Path;
+// others
+use image;
+use image::hdr::HDREncoder;
+use image::Rgb;
+#[cfg(feature = "openexr")]
+use openexr::{FrameBuffer, Header, PixelType, ScanlineOutputFile};
+// pbrt
+use core::fileutil::has_extension;
+use core::geometry::Bounds2i;
+use core::pbrt::Float;
+use core::pbrt::{clamp_t, gamma_correct};
+
+// see imageio.h
+
+/// An extra layer of an image, with *channels.len()* values per pixel.
+pub struct ImageLayer {
+    pub name: &'static str,
+    pub channels: &'static [&'static str],
+    /// Color values can be stored like the image (tone curve, 8 bits,
+    /// etc.), all other values need linear floats.
+    pub color: bool,
+    pub values: Vec<Float>,
+}
+
+/// Checks that **write_image()** knows the format of the file *name*,
+/// so a bad filename can be reported before rendering.
+pub fn check_image_file(name: &str) -> Result<()> {
+    if has_extension(name, ".exr") {
+        if cfg!(feature = "openexr") {
+            Ok(())
+        } else {
+            Err(exr_unsupported(name))
+        }
+    } else if [".pfm", ".hdr", ".tga", ".png"]
+        .iter()
+        .any(|ext| has_extension(name, ext))
+    {
+        Ok(())
+    } else {
+        Err(unknown_suffix(name))
+    }
+}
+
+/// Writes the RGB values (three floats per pixel, row by row) of the
+/// pixels within *output_bounds* to the file *name*. PNG files are
+/// written with *bit_depth* (8 or 16) bits per channel.
+pub fn write_image(
+    name: &str,
+    rgb: &[Float],
+    output_bounds: &Bounds2i,
+    bit_depth: u8,
+) -> Result<()> {
+    let width: u32 = (output_bounds.p_max.x - output_bounds.p_min.x) as u32;
+    let height: u32 = (output_bounds.p_max.y - output_bounds.p_min.y) as u32;
+    if has_extension(name, ".exr") {
+        write_image_exr(name, rgb, width, height)
+    } else if has_extension(name, ".pfm") {
+        write_image_pfm(name, rgb, width, height)
+    } else if has_extension(name, ".hdr") {
+        let pixels: Vec<Rgb<f32>> = rgb
+            .chunks(3)
+            .map(|c| Rgb {
+                data: [c[0] as f32, c[1] as f32, c[2] as f32],
+            })
+            .collect();
+        let file: BufWriter<File> = BufWriter::new(File::create(name)?);
+        HDREncoder::new(file)
+            .encode(&pixels, width as usize, height as usize)
+            .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))
+    } else if has_extension(name, ".tga") {
+        write_image_tga(name, rgb, width, height)
+    } else if has_extension(name, ".png") {
+        if bit_depth == 16 {
+            // 16-bit PNG stores big-endian samples
+            let mut buffer: Vec<u8> = Vec::with_capacity(2 * rgb.len());
+            for v in rgb {
+                let value: u16 = clamp_t(
+                    65535.0 as Float * gamma_correct(*v) + 0.5,
+                    0.0 as Float,
+                    65535.0 as Float,
+                ) as u16;
+                buffer.push((value >> 8) as u8);
+                buffer.push((value & 0xff) as u8);
+            }
+            image::save_buffer(&Path::new(name), &buffer, width, height, image::RGB(16))
+        } else {
+            let buffer: Vec<u8> = rgb.iter().map(|v| to_byte(*v)).collect();
+            image::save_buffer(&Path::new(name), &buffer, width, height, image::RGB(8))
+        }
+    } else {
+        Err(unknown_suffix(name))
+    }
+}
+
+fn unknown_suffix(name: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!(
+            "Can't determine image file type from suffix of filename \"{}\"",
+            name
+        ),
+    )
+}
+
+fn exr_unsupported(name: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!(
+            "Can't write \"{}\", OpenEXR support needs the \"openexr\" feature",
+            name
+        ),
+    )
+}
+
+/// Writes the image like **write_image()**, plus extra *layers*.
+pub fn write_image_layers(
+    name: &str,
+    rgb: &[Float],
+    layers: &[ImageLayer],
+    output_bounds: &Bounds2i,
+    bit_depth: u8,
+) -> Result<()> {
+    if has_extension(name, ".exr") {
+        let width: u32 = (output_bounds.p_max.x - output_bounds.p_min.x) as u32;
+        let height: u32 = (output_bounds.p_max.y - output_bounds.p_min.y) as u32;
+        return write_image_exr_layers(name, rgb, layers, width, height);
+    }
+    write_image(name, rgb, output_bounds, bit_depth)?;
+    let path: &Path = Path::new(name);
+    let stem: String = path
+        .file_stem()
+        .map_or(String::new(), |s| s.to_string_lossy().into_owned());
+    let extension: String = path
+        .extension()
+        .map_or(String::new(), |s| s.to_string_lossy().into_owned());
+    for layer in layers {
+        // sidecar images are RGB, too
+        let n_channels: usize = layer.channels.len();
+        let mut layer_rgb: Vec<Float> = Vec::with_capacity(rgb.len());
+        for values in layer.values.chunks(n_channels) {
+            for c in 0..3 {
+                layer_rgb.push(match n_channels {
+                    1 => values[0],
+                    2 => {
+                        if c < 2 {
+                            values[c]
+                        } else {
+                            0.0 as Float
+                        }
+                    }
+                    _ => values[c],
+                });
+            }
+        }
+        let layer_extension: &str = if layer.color {
+            extension.as_str()
+        } else {
+            "pfm"
+        };
+        let layer_name: String = path
+            .with_file_name(format!("{}_{}.{}", stem, layer.name, layer_extension))
+            .to_string_lossy()
+            .into_owned();
+        println!("Writing image {:?}", layer_name);
+        write_image(&layer_name, &layer_rgb, output_bounds, bit_depth)?;
+    }
+    Ok(())
+}
+
+/// 8-bit value; apply gamma (see WriteImage(...) in imageio.cpp)
+fn to_byte(v: Float) -> u8 {
+    clamp_t(
+        255.0 as Float * gamma_correct(v) + 0.5,
+        0.0 as Float,
+        255.0 as Float,
+    ) as u8
+}
+
+fn write_image_exr(name: &str, rgb: &[Float], width: u32, height: u32) -> Result<()> {
+    write_image_exr_layers(name, rgb, &[], width, height)
+}
+
+#[cfg(feature = "openexr")]
+fn write_image_exr_layers(
+    name: &str,
+    rgb: &[Float],
+    layers: &[ImageLayer],
+    width: u32,
+    height: u32,
+) -> Result<()> {
+    // one buffer per channel, starting with R, G, and B
+    let mut channel_names: Vec<String> =
+        vec![String::from("R"), String::from("G"), String::from("B")];
+    let mut channels: Vec<Vec<f32>> = Vec::new();
+    for c in 0..3 {
+        channels.push(rgb.iter().skip(c).step_by(3).map(|v| *v as f32).collect());
+    }
+    for layer in layers {
+        let n_channels: usize = layer.channels.len();
+        for c in 0..n_channels {
+            channel_names.push(format!("{}.{}", layer.name, layer.channels[c]));
+            channels.push(
+                layer
+                    .values
+                    .iter()
+                    .skip(c)
+                    .step_by(n_channels)
+                    .map(|v| *v as f32)
+                    .collect(),
+            );
+        }
+    }
+    let mut header: Header = Header::new();
+    header.set_resolution(width, height);
+    for channel_name in &channel_names {
+        header.add_channel(channel_name, PixelType::FLOAT);
+    }
+    let mut file: File = File::create(name)?;
+    let mut output_file = ScanlineOutputFile::new(&mut file, &header)
+        .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))?;
+    let mut fb = FrameBuffer::new(width, height);
+    for (channel_name, channel) in channel_names.iter().zip(channels.iter()) {
+        fb.insert_channels(&[channel_name.as_str()], channel);
+    }
+    output_file
+        .write_pixels(&fb)
+        .map_err(|e| Error::new(ErrorKind::Other, e.to_string()))
+}
+
+#[cfg(not(feature = "openexr"))]
+fn write_image_exr_layers(
+    name: &str,
+    _rgb: &[Float],
+    _layers: &[ImageLayer],
+    _width: u32,
+    _height: u32,
+) -> Result<()> {
+    Err(exr_unsupported(name))
+}
+
+/// Portable float map: little-endian floats, rows from bottom to top
+/// (see WriteImagePFM(...) in imageio.cpp).
+fn write_image_pfm(name: &str, rgb: &[Float], width: u32, height: u32) -> Result<()> {
+    let mut file: BufWriter<File> = BufWriter::new(File::create(name)?);
+    // a negative scale means little-endian
+    write!(file, "PF\n{} {}\n-1\n", width, height)?;
+    for y in (0..height).rev() {
+        let start: usize = (3 * y * width) as usize;
+        let end: usize = start + (3 * width) as usize;
+        for v in &rgb[start..end] {
+            let bits: u32 = (*v as f32).to_bits();
+            file.write_all(&[
+                (bits & 0xff) as u8,
+                ((bits >> 8) & 0xff) as u8,
+                ((bits >> 16) & 0xff) as u8,
+                (bits >> 24) as u8,
+            ])?;
+        }
+    }
+    file.flush()
+}
+
+/// Uncompressed true-color Targa image with the origin at the upper
+/// left (see WriteImageTGA(...) in imageio.cpp).
+fn write_image_tga(name: &str, rgb: &[Float], width: u32, height: u32) -> Result<()> {
+    let mut file: BufWriter<File> = BufWriter::new(File::create(name)?);
+    let header: [u8; 18] = [
+        0,
+        0,
+        2, // uncompressed true-color image
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        (width & 0xff) as u8,
+        (width >> 8) as u8,
+        (height & 0xff) as u8,
+        (height >> 8) as u8,
+        24,   // bits per pixel
+        0x20, // origin at the upper left
+    ];
+    file.write_all(&header)?;
+    // pixels are stored as BGR
+    for c in rgb.chunks(3) {
+        file.write_all(&[to_byte(c[2]), to_byte(c[1]), to_byte(c[0])])?;
+    }
+    file.flush()
+}
@@ -5,13 +5,16 @@
 use std;
 use std::sync::Arc;
 // pbrt
+use core::aov::AovSample;
 use core::geometry::vec3_abs_dot_nrm;
 use core::geometry::{Bounds2i, Point2f, Ray, Vector3f};
 use core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
 use core::light::is_delta_light;
 use core::light::{Light, VisibilityTester};
+use core::material::TransportMode;
 use core::pbrt::{Float, Spectrum};
 use core::primitive::Primitive;
+use core::profiler::{Prof, ProfilePhase};
 use core::reflection::BxdfType;
 use core::sampler::Sampler;
 use core::sampling::power_heuristic;
@@ -34,6 +37,27 @@ pub trait SamplerIntegrator {
         // arena: &mut Arena,
         depth: i32,
     ) -> Spectrum;
+    /// Like **li()**, but also records the arbitrary output variables
+    /// of a camera ray (see **core::aov**). By default the first
+    /// intersection costs another intersection test and all radiance
+    /// counts as direct, diffuse light.
+    fn li_aov(
+        &self,
+        ray: &mut Ray,
+        scene: &Scene,
+        sampler: &mut Box<Sampler + Send + Sync>,
+        depth: i32,
+        aov: &mut AovSample,
+    ) -> Spectrum {
+        let mut first_ray: Ray = ray.clone();
+        if let Some(mut isect) = scene.intersect(&mut first_ray) {
+            isect.compute_scattering_functions(&mut first_ray, false, TransportMode::Radiance);
+            aov.record_hit(&ray.o, &isect);
+        }
+        let l: Spectrum = self.li(ray, scene, sampler, depth);
+        aov.record_radiance(&l, &Spectrum::default(), &l, false);
+        l
+    }
     fn get_pixel_bounds(&self) -> Bounds2i;
 }
 
@@ -47,7 +71,7 @@ pub fn uniform_sample_all_lights(
     n_light_samples: &Vec<i32>,
     handle_media: bool,
 ) -> Spectrum {
-    // TODO: ProfilePhase p(Prof::DirectLighting);
+    let _p = ProfilePhase::new(Prof::DirectLighting);
     let mut l: Spectrum = Spectrum::new(0.0);
     for j in 0..scene.lights.len() {
         // accumulate contribution of _j_th light to _L_
@@ -97,7 +121,7 @@ pub fn uniform_sample_one_light<S: Sampler + Send + Sync + ?Sized>(
     handle_media: bool,
     light_distrib: Option<&Distribution1D>,
 ) -> Spectrum {
-    // TODO: ProfilePhase p(Prof::DirectLighting);
+    let _p = ProfilePhase::new(Prof::DirectLighting);
 
     // randomly choose a single light to sample, _light_
     let n_lights: usize = scene.lights.len();
@@ -21,6 +21,7 @@ use core::medium::{Medium, MediumInterface, PhaseFunction};
 use core::pbrt::SHADOW_EPSILON;
 use core::pbrt::{Float, Spectrum};
 use core::primitive::{GeometricPrimitive, Primitive};
+use core::profiler::{Prof, ProfilePhase};
 use core::reflection::Bsdf;
 use core::shape::Shape;
 use core::transform::solve_linear_system_2x2;
@@ -377,6 +378,7 @@ impl<'p, 's> SurfaceInteraction<'p, 's> {
         allow_multiple_lobes: bool,
         mode: TransportMode,
     ) {
+        let _p = ProfilePhase::new(Prof::ComputeScatteringFuncs);
         self.compute_differentials(ray);
         if let Some(primitive) = self.primitive {
             primitive.compute_scattering_functions(
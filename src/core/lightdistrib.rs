@@ -13,11 +13,19 @@ use core::lowdiscrepancy::radical_inverse;
 use core::medium::MediumInterface;
 use core::pbrt::clamp_t;
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::sampling::Distribution1D;
 use core::scene::Scene;
+use core::stats::{StatCounter, StatIntDistribution, StatRatio};
 
 // see lightdistrib.h
 
+static N_CREATED: StatCounter = StatCounter::new("SpatialLightDistribution/Distributions created");
+static LOOKUPS_PER_DISTRIBUTION: StatRatio =
+    StatRatio::new("SpatialLightDistribution/Lookups per distribution");
+static PROBES_PER_LOOKUP: StatIntDistribution =
+    StatIntDistribution::new("SpatialLightDistribution/Hash probes per lookup");
+
 /// LightDistribution defines a general interface for classes that
 /// provide probability distributions for sampling light sources at a
 /// given point in space.
@@ -147,6 +155,8 @@ impl SpatialLightDistribution {
     /// Compute the sampling distribution for the voxel with integer
     /// coordiantes given by "pi".
     pub fn compute_distribution(&self, pi: &Point3i) -> Distribution1D {
+        N_CREATED.inc();
+        LOOKUPS_PER_DISTRIBUTION.add(0, 1);
         // Compute the world-space bounding box of the voxel
         // corresponding to |pi|.
         let p0: Point3f = Point3f {
@@ -241,8 +251,8 @@ impl SpatialLightDistribution {
 
 impl LightDistribution for SpatialLightDistribution {
     fn lookup(&self, p: &Point3f) -> Arc<Distribution1D> {
-        // TODO: ProfilePhase _(Prof::LightDistribLookup);
-        // TODO: ++nLookups;
+        let _p = ProfilePhase::new(Prof::LightDistribLookup);
+        LOOKUPS_PER_DISTRIBUTION.add(1, 0);
 
         // first, compute integer voxel coordinates for the given
         // point |p| with respect to the overall voxel grid.
@@ -299,9 +309,9 @@ impl LightDistribution for SpatialLightDistribution {
         // entry is already used for another value; step stores the
         // square root of the probe step.
         let mut step: u64 = 1;
-        // TODO: int nProbes = 0;
+        let mut n_probes: i64 = 0;
         loop {
-            // TODO: ++nProbes;
+            n_probes += 1;
             let entry: &HashEntry = &self.hash_table[hash as usize];
             // does the hash table entry at offset |hash| match the current point?
             let entry_packed_pos: u64 = entry.packed_pos.load(Ordering::Acquire);
@@ -311,6 +321,7 @@ impl LightDistribution for SpatialLightDistribution {
                 let option: &Option<Arc<Distribution1D>> = &*entry.distribution.read().unwrap();
                 if let Some(ref dist) = *option {
                     // We have a valid sampling distribution.
+                    PROBES_PER_LOOKUP.report_value(n_probes);
                     return Arc::clone(dist);
                 }
             } else if entry_packed_pos != INVALID_PACKED_POS {
@@ -345,6 +356,7 @@ impl LightDistribution for SpatialLightDistribution {
                     let arc_dist: Arc<Distribution1D> = Arc::new(dist.clone());
                     let mut distribution = entry.distribution.write().unwrap();
                     *distribution = Some(arc_dist.clone());
+                    PROBES_PER_LOOKUP.report_value(n_probes);
                     return arc_dist;
                 }
             }
@@ -4,10 +4,10 @@
 //std
 use std::sync::Arc;
 // pbrt
-use core::geometry::vec3_cross_vec3;
+use core::geometry::{vec3_cross_vec3, vec3_dot_vec3};
 use core::geometry::{Normal3f, Vector2f, Vector3f};
 use core::interaction::SurfaceInteraction;
-use core::pbrt::Float;
+use core::pbrt::{Float, Spectrum};
 use core::texture::Texture;
 
 // see material.h
@@ -89,4 +89,37 @@ pub trait Material {
         let dndv = si.shading.dndv;
         si.set_shading_geometry(&dpdu, &dpdv, &dndu, &dndv, false);
     }
+    /// Tilts the shading normal by a tangent space normal map, which
+    /// stores the normal's components (mapped to [0, 1]) in its RGB
+    /// channels.
+    fn normal_map(d: &Arc<Texture<Spectrum> + Send + Sync>, si: &mut SurfaceInteraction)
+    where
+        Self: Sized,
+    {
+        let mut rgb: [Float; 3] = [0.0 as Float; 3];
+        d.evaluate(si).to_rgb(&mut rgb);
+        let ns: Vector3f = Vector3f {
+            x: 2.0 as Float * rgb[0] - 1.0 as Float,
+            y: 2.0 as Float * rgb[1] - 1.0 as Float,
+            z: 2.0 as Float * rgb[2] - 1.0 as Float,
+        };
+        if ns.length_squared() == 0.0 as Float || si.shading.dpdu.length_squared() == 0.0 as Float
+        {
+            return;
+        }
+        // transform the normal from the shading frame to world space
+        let z: Vector3f = Vector3f::from(si.shading.n);
+        let x: Vector3f = si.shading.dpdu.normalize();
+        let y: Vector3f = vec3_cross_vec3(&z, &x);
+        let ns: Vector3f = (x * ns.x + y * ns.y + z * ns.z).normalize();
+        // find the shading geometry matching the new normal
+        let ulen: Float = si.shading.dpdu.length();
+        let vlen: Float = si.shading.dpdv.length();
+        let dpdu: Vector3f =
+            (si.shading.dpdu - ns * vec3_dot_vec3(&si.shading.dpdu, &ns)).normalize() * ulen;
+        let dpdv: Vector3f = vec3_cross_vec3(&ns, &dpdu).normalize() * vlen;
+        let dndu = si.shading.dndu;
+        let dndv = si.shading.dndv;
+        si.set_shading_geometry(&dpdu, &dpdv, &dndu, &dndv, false);
+    }
 }
@@ -10,6 +10,7 @@ use core::geometry::{Point2f, Ray, Vector3f};
 use core::interaction::MediumInteraction;
 use core::pbrt::INV_4_PI;
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::sampler::Sampler;
 
 pub const SUBSURFACE_PARAMETER_TABLE: [MeasuredSS; 47] = [
@@ -279,11 +280,11 @@ pub struct HenyeyGreenstein {
 
 impl PhaseFunction for HenyeyGreenstein {
     fn p(&self, wo: &Vector3f, wi: &Vector3f) -> Float {
-        // TODO: ProfilePhase _(Prof::PhaseFuncEvaluation);
+        let _p = ProfilePhase::new(Prof::PhaseFuncEvaluation);
         phase_hg(vec3_dot_vec3(wo, wi), self.g)
     }
     fn sample_p(&self, wo: &Vector3f, wi: &mut Vector3f, u: &Point2f) -> Float {
-        // TODO: ProfilePhase _(Prof::PhaseFuncSampling);
+        let _p = ProfilePhase::new(Prof::PhaseFuncSampling);
         // compute $\cos \theta$ for Henyey--Greenstein sample
         let cos_theta: Float;
         if self.g.abs() < 1e-3 as Float {
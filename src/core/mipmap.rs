@@ -13,10 +13,16 @@ use core::geometry::{Point2f, Point2i, Vector2f};
 use core::memory::BlockedArray;
 use core::pbrt::{clamp_t, is_power_of_2, lerp, mod_t, round_up_pow2_32};
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
+use core::stats::{StatCounter, StatMemoryCounter};
 use core::texture::lanczos;
 
 // see mipmap.h
 
+static N_EWA_LOOKUPS: StatCounter = StatCounter::new("Texture/EWA lookups");
+static N_TRILERP_LOOKUPS: StatCounter = StatCounter::new("Texture/Trilinear lookups");
+static MIP_MAP_MEMORY: StatMemoryCounter = StatMemoryCounter::new("Memory/Texture MIP maps");
+
 const WEIGHT_LUT_SIZE: usize = 128;
 
 #[derive(Debug, Clone)]
@@ -63,6 +69,7 @@ where
         max_anisotropy: Float,
         wrap_mode: ImageWrap,
     ) -> Self {
+        let _p = ProfilePhase::new(Prof::MIPMapCreation);
         let mut resolution = *res;
         let mut resampled_image: Vec<T> = Vec::new();
         if !is_power_of_2(resolution.x) || !is_power_of_2(resolution.y) {
@@ -194,7 +201,8 @@ where
                 mipmap.weight_lut[i] = (-alpha * r2).exp() - (-alpha).exp();
             }
         }
-        // TODO: mipMapMemory += (4 * resolution[0] * resolution[1] * sizeof(T)) / 3;
+        MIP_MAP_MEMORY
+            .add((4 * resolution.x as usize * resolution.y as usize * std::mem::size_of::<T>()) / 3);
         mipmap
     }
     pub fn width(&self) -> i32 {
@@ -234,8 +242,8 @@ where
         &l[(ss, tt)]
     }
     pub fn lookup_pnt_flt(&self, st: &Point2f, width: Float) -> T {
-        // TODO: ++nTrilerpLookups;
-        // TODO: ProfilePhase p(Prof::TexFiltTrilerp);
+        N_TRILERP_LOOKUPS.inc();
+        let _p = ProfilePhase::new(Prof::TexFiltTrilerp);
         // compute MIPMap level for trilinear filtering
         let level: Float = self.levels() as Float - 1.0 as Float + width.max(1e-8 as Float).log2();
         // perform trilinear interpolation at appropriate MIPMap level
@@ -262,8 +270,8 @@ where
                 .max(dst1.x.abs().max(dst1.y.abs()));
             return self.lookup_pnt_flt(st, width);
         }
-        // TODO: ++nEWALookups;
-        // TODO: ProfilePhase p(Prof::TexFiltEWA);
+        N_EWA_LOOKUPS.inc();
+        let _p = ProfilePhase::new(Prof::TexFiltEWA);
         // compute ellipse minor and major axes
         if dst0.length_squared() < dst1.length_squared() {
             // std::swap(dst0, dst1);
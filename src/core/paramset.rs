@@ -1,6 +1,7 @@
 //! Bundle up parameters and their values in a generic way.
 
 // std
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::env;
 use std::fs::File;
@@ -21,7 +22,20 @@ pub struct ParamSetItem<T> {
     pub name: String,
     pub values: Vec<T>,
     pub n_values: usize,
-    pub looked_up: bool, // false
+    pub looked_up: Cell<bool>, // false
+}
+
+/// How a spectrum parameter was given, before it got converted (for
+/// **core::scenewriter**, which would otherwise have to write RGB
+/// values).
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpectrumSource {
+    /// Pairs of wavelength (nm) and value.
+    Sampled(Vec<Float>),
+    /// Names of .spd files.
+    Files(Vec<String>),
+    /// Pairs of temperature (K) and scale.
+    Blackbody(Vec<Float>),
 }
 
 #[derive(Default)]
@@ -30,6 +44,9 @@ pub struct ParamSet {
     pub name: String,
     pub tex_type: String,
     pub tex_name: String,
+    /// Where the statement was found (e.g. "scene.pbrt:12:1"), if
+    /// parsed from a file.
+    pub location: String,
     pub bools: Vec<ParamSetItem<bool>>,
     pub ints: Vec<ParamSetItem<i32>>,
     pub floats: Vec<ParamSetItem<Float>>,
@@ -41,6 +58,10 @@ pub struct ParamSet {
     pub spectra: Vec<ParamSetItem<Spectrum>>,
     pub strings: Vec<ParamSetItem<String>>,
     pub textures: Vec<ParamSetItem<String>>,
+    /// Spectra which were not given as RGB values.
+    pub spectrum_sources: HashMap<String, SpectrumSource>,
+    // parameters (type and name) the code asked for
+    lookups: RefCell<Vec<String>>,
 }
 
 impl ParamSet {
@@ -49,6 +70,8 @@ impl ParamSet {
         self.name = name;
         self.tex_type = tex_type;
         self.tex_name = tex_name;
+        self.location = String::new();
+        self.lookups.borrow_mut().clear();
         self.bools.clear();
         self.ints.clear();
         self.floats.clear();
@@ -60,13 +83,14 @@ impl ParamSet {
         self.spectra.clear();
         self.strings.clear();
         self.textures.clear();
+        self.spectrum_sources.clear();
     }
     pub fn add_float(&mut self, name: String, value: Float) {
         self.floats.push(ParamSetItem::<Float> {
             name: name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_floats(&mut self, name: String, values: Vec<Float>) {
@@ -75,7 +99,7 @@ impl ParamSet {
             name: name,
             values: values,
             n_values: n_values,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_int(&mut self, name: String, value: i32) {
@@ -83,7 +107,7 @@ impl ParamSet {
             name: name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_ints(&mut self, name: String, values: Vec<i32>) {
@@ -92,7 +116,7 @@ impl ParamSet {
             name: name,
             values: values,
             n_values: n_values,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_bool(&mut self, name: String, value: bool) {
@@ -100,7 +124,7 @@ impl ParamSet {
             name: name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_point2f(&mut self, name: String, value: Point2f) {
@@ -108,7 +132,7 @@ impl ParamSet {
             name: name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_point2fs(&mut self, name: String, values: Vec<Float>) {
@@ -130,7 +154,7 @@ impl ParamSet {
             name: name,
             values: p_values,
             n_values: n_points,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_point3f(&mut self, name: String, value: Point3f) {
@@ -138,7 +162,7 @@ impl ParamSet {
             name: name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_point3fs(&mut self, name: String, values: Vec<Float>) {
@@ -161,8 +185,37 @@ impl ParamSet {
             name: name,
             values: p_values,
             n_values: n_points,
-            looked_up: false,
+            looked_up: Cell::new(false),
+        });
+    }
+    pub fn add_sampled_spectrum(&mut self, name: String, values: Vec<Float>) -> Result<(), String> {
+        if values.len() % 2 != 0 {
+            return Err(format!(
+                "spectrum parameter {:?} needs pairs of wavelength and value, found {} values",
+                name,
+                values.len()
+            ));
+        }
+        self.erase_spectrum(name.clone());
+        // wavelength (nm), value, ...
+        let n_values: usize = values.len() / 2_usize;
+        let mut wls: Vec<Float> = Vec::with_capacity(n_values);
+        let mut v: Vec<Float> = Vec::with_capacity(n_values);
+        for i in 0..n_values {
+            wls.push(values[2 * i]);
+            v.push(values[2 * i + 1]);
+        }
+        // unsorted samples get sorted by from_sampled()
+        let s: Spectrum = Spectrum::from_sampled(&wls[..], &v[..], n_values as i32);
+        self.spectrum_sources
+            .insert(name.clone(), SpectrumSource::Sampled(values));
+        self.spectra.push(ParamSetItem::<Spectrum> {
+            name: name,
+            values: vec![s],
+            n_values: 1_usize,
+            looked_up: Cell::new(false),
         });
+        Ok(())
     }
     pub fn add_sampled_spectrum_files(&mut self, name: String, names: Vec<String>) {
         // TODO: cachedSpectra
@@ -206,11 +259,13 @@ impl ParamSet {
             }
         }
         let n_values: usize = s.len();
+        self.spectrum_sources
+            .insert(name.clone(), SpectrumSource::Files(names));
         self.spectra.push(ParamSetItem::<Spectrum> {
             name: name.clone(),
             values: s,
             n_values: n_values,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_string(&mut self, name: String, value: String) {
@@ -218,7 +273,16 @@ impl ParamSet {
             name: name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
+        });
+    }
+    pub fn add_strings(&mut self, name: String, values: Vec<String>) {
+        let n_values: usize = values.len();
+        self.strings.push(ParamSetItem::<String> {
+            name: name,
+            values: values,
+            n_values: n_values,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_texture(&mut self, name: String, value: String) {
@@ -226,7 +290,7 @@ impl ParamSet {
             name: name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_vector3f(&mut self, name: String, value: Vector3f) {
@@ -234,7 +298,7 @@ impl ParamSet {
             name: name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_vector3fs(&mut self, name: String, values: Vec<Float>) {
@@ -252,7 +316,7 @@ impl ParamSet {
             name: name,
             values: p_values,
             n_values: n_vectors,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_normal3f(&mut self, name: String, value: Normal3f) {
@@ -260,7 +324,7 @@ impl ParamSet {
             name: name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_normal3fs(&mut self, name: String, values: Vec<Float>) {
@@ -278,15 +342,16 @@ impl ParamSet {
             name: name,
             values: p_values,
             n_values: n_normals,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_rgb_spectrum(&mut self, name: String, value: Spectrum) {
+        self.spectrum_sources.remove(&name);
         self.spectra.push(ParamSetItem::<Spectrum> {
             name: name,
             values: vec![value],
             n_values: 1_usize,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn add_blackbody_spectrum(&mut self, name: String, values: Vec<Float>) {
@@ -301,16 +366,19 @@ impl ParamSet {
                 Spectrum::from_sampled(&CIE_LAMBDA, &v, N_CIE_SAMPLES as i32) * values[2 * i + 1],
             );
         }
+        self.spectrum_sources
+            .insert(name.clone(), SpectrumSource::Blackbody(values));
         self.spectra.push(ParamSetItem::<Spectrum> {
             name: name,
             values: s,
             n_values: n_values,
-            looked_up: false,
+            looked_up: Cell::new(false),
         });
     }
     pub fn copy_from(&mut self, param_set: &ParamSet) {
         self.key_word = param_set.key_word.clone();
         // self.name = param_set.name.clone();
+        self.location = param_set.location.clone();
         self.bools.clear();
         for b in &param_set.bools {
             let mut values: Vec<bool> = Vec::new();
@@ -321,7 +389,7 @@ impl ParamSet {
                 name: b.name.clone(),
                 values: values,
                 n_values: b.n_values,
-                looked_up: false,
+                looked_up: Cell::new(false),
             });
         }
         self.ints.clear();
@@ -334,7 +402,7 @@ impl ParamSet {
                 name: i.name.clone(),
                 values: values,
                 n_values: i.n_values,
-                looked_up: false,
+                looked_up: Cell::new(false),
             });
         }
         self.floats.clear();
@@ -347,11 +415,35 @@ impl ParamSet {
                 name: f.name.clone(),
                 values: values,
                 n_values: f.n_values,
-                looked_up: false,
+                looked_up: Cell::new(false),
             });
         }
         self.point2fs.clear();
+        for p in &param_set.point2fs {
+            let mut values: Vec<Point2f> = Vec::new();
+            for ix in 0..p.n_values {
+                values.push(p.values[ix].clone());
+            }
+            self.point2fs.push(ParamSetItem::<Point2f> {
+                name: p.name.clone(),
+                values: values,
+                n_values: p.n_values,
+                looked_up: Cell::new(false),
+            });
+        }
         self.vector2fs.clear();
+        for p in &param_set.vector2fs {
+            let mut values: Vec<Vector2f> = Vec::new();
+            for ix in 0..p.n_values {
+                values.push(p.values[ix].clone());
+            }
+            self.vector2fs.push(ParamSetItem::<Vector2f> {
+                name: p.name.clone(),
+                values: values,
+                n_values: p.n_values,
+                looked_up: Cell::new(false),
+            });
+        }
         self.point3fs.clear();
         for p in &param_set.point3fs {
             let mut values: Vec<Point3f> = Vec::new();
@@ -362,11 +454,35 @@ impl ParamSet {
                 name: p.name.clone(),
                 values: values,
                 n_values: p.n_values,
-                looked_up: false,
+                looked_up: Cell::new(false),
             });
         }
         self.vector3fs.clear();
+        for p in &param_set.vector3fs {
+            let mut values: Vec<Vector3f> = Vec::new();
+            for ix in 0..p.n_values {
+                values.push(p.values[ix].clone());
+            }
+            self.vector3fs.push(ParamSetItem::<Vector3f> {
+                name: p.name.clone(),
+                values: values,
+                n_values: p.n_values,
+                looked_up: Cell::new(false),
+            });
+        }
         self.normals.clear();
+        for p in &param_set.normals {
+            let mut values: Vec<Normal3f> = Vec::new();
+            for ix in 0..p.n_values {
+                values.push(p.values[ix].clone());
+            }
+            self.normals.push(ParamSetItem::<Normal3f> {
+                name: p.name.clone(),
+                values: values,
+                n_values: p.n_values,
+                looked_up: Cell::new(false),
+            });
+        }
         self.spectra.clear();
         for s in &param_set.spectra {
             let mut values: Vec<Spectrum> = Vec::new();
@@ -377,7 +493,7 @@ impl ParamSet {
                 name: s.name.clone(),
                 values: values,
                 n_values: s.n_values,
-                looked_up: false,
+                looked_up: Cell::new(false),
             });
         }
         self.strings.clear();
@@ -390,7 +506,7 @@ impl ParamSet {
                 name: s.name.clone(),
                 values: values,
                 n_values: s.n_values,
-                looked_up: false,
+                looked_up: Cell::new(false),
             });
         }
         self.textures.clear();
@@ -403,11 +519,13 @@ impl ParamSet {
                 name: s.name.clone(),
                 values: values,
                 n_values: s.n_values,
-                looked_up: false,
+                looked_up: Cell::new(false),
             });
         }
+        self.spectrum_sources = param_set.spectrum_sources.clone();
     }
     pub fn erase_spectrum(&mut self, name: String) -> bool {
+        self.spectrum_sources.remove(&name);
         for i in 0..self.spectra.len() {
             if self.spectra[i].name == name {
                 self.spectra.remove(i);
@@ -417,63 +535,70 @@ impl ParamSet {
         false
     }
     pub fn find_one_float(&self, name: &str, d: Float) -> Float {
+        self.record_lookup("float", name);
         for v in &self.floats {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
         d
     }
     pub fn find_one_int(&self, name: &str, d: i32) -> i32 {
+        self.record_lookup("integer", name);
         for v in &self.ints {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
         d
     }
     pub fn find_one_bool(&self, name: &str, d: bool) -> bool {
+        self.record_lookup("bool", name);
         for v in &self.bools {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
         d
     }
     pub fn find_one_point3f(&self, name: &str, d: Point3f) -> Point3f {
+        self.record_lookup("point", name);
         for v in &self.point3fs {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
         d
     }
     pub fn find_one_vector3f(&self, name: &str, d: Vector3f) -> Vector3f {
+        self.record_lookup("vector", name);
         for v in &self.vector3fs {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
         d
     }
     pub fn find_one_spectrum(&self, name: &str, d: Spectrum) -> Spectrum {
+        self.record_lookup("spectrum", name);
         for v in &self.spectra {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0];
             }
         }
         d
     }
     pub fn find_one_string(&self, name: &str, d: String) -> String {
+        self.record_lookup("string", name);
         for v in &self.strings {
             if v.name == name && v.n_values == 1 {
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 return v.values[0].clone();
             }
         }
@@ -488,15 +613,17 @@ impl ParamSet {
         filename
     }
     pub fn find_texture(&self, name: &str) -> String {
+        self.record_lookup("texture", name);
         let d: String = String::new();
         lookup_one(&self.textures, name, d)
     }
     pub fn find_int(&self, name: &str) -> Vec<i32> {
+        self.record_lookup("integer", name);
         let mut values: Vec<i32> = Vec::new();
         for v in &self.ints {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -504,12 +631,24 @@ impl ParamSet {
         }
         values
     }
+    pub fn find_string(&self, name: &str) -> Vec<String> {
+        self.record_lookup("string", name);
+        let mut values: Vec<String> = Vec::new();
+        for v in &self.strings {
+            if v.name == name {
+                v.looked_up.set(true);
+                values.extend(v.values.iter().cloned());
+            }
+        }
+        values
+    }
     pub fn find_float(&self, name: &str) -> Vec<Float> {
+        self.record_lookup("float", name);
         let mut values: Vec<Float> = Vec::new();
         for v in &self.floats {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -518,11 +657,12 @@ impl ParamSet {
         values
     }
     pub fn find_point2f(&self, name: &str) -> Vec<Point2f> {
+        self.record_lookup("point2", name);
         let mut values: Vec<Point2f> = Vec::new();
         for v in &self.point2fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -531,11 +671,12 @@ impl ParamSet {
         values
     }
     pub fn find_vector2f(&self, name: &str) -> Vec<Vector2f> {
+        self.record_lookup("vector2", name);
         let mut values: Vec<Vector2f> = Vec::new();
         for v in &self.vector2fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -544,11 +685,12 @@ impl ParamSet {
         values
     }
     pub fn find_point3f(&self, name: &str) -> Vec<Point3f> {
+        self.record_lookup("point", name);
         let mut values: Vec<Point3f> = Vec::new();
         for v in &self.point3fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -557,11 +699,12 @@ impl ParamSet {
         values
     }
     pub fn find_vector3f(&self, name: &str) -> Vec<Vector3f> {
+        self.record_lookup("vector", name);
         let mut values: Vec<Vector3f> = Vec::new();
         for v in &self.vector3fs {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -570,11 +713,12 @@ impl ParamSet {
         values
     }
     pub fn find_normal3f(&self, name: &str) -> Vec<Normal3f> {
+        self.record_lookup("normal", name);
         let mut values: Vec<Normal3f> = Vec::new();
         for v in &self.normals {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -583,11 +727,12 @@ impl ParamSet {
         values
     }
     pub fn find_spectrum(&self, name: &str) -> Vec<Spectrum> {
+        self.record_lookup("spectrum", name);
         let mut values: Vec<Spectrum> = Vec::new();
         for v in &self.spectra {
             if v.name == name {
                 let n_values = v.n_values;
-                // v.looked_up = true;
+                v.looked_up.set(true);
                 for i in 0..n_values {
                     values.push(v.values[i]);
                 }
@@ -595,6 +740,41 @@ impl ParamSet {
         }
         values
     }
+    fn record_lookup(&self, param_type: &str, name: &str) {
+        let lookup: String = format!("{} {}", param_type, name);
+        let mut lookups = self.lookups.borrow_mut();
+        if !lookups.contains(&lookup) {
+            lookups.push(lookup);
+        }
+    }
+    /// All parameters (type and name) the code asked for, regardless
+    /// if they were found or not.
+    pub fn lookups(&self) -> Vec<String> {
+        self.lookups.borrow().clone()
+    }
+    /// All parameters (type and name, e.g. "float fov") which were
+    /// never looked up, in the order they were added.
+    pub fn unused(&self) -> Vec<String> {
+        self.collect(true)
+    }
+    fn has(&self, parameter: &str) -> bool {
+        self.collect(false).iter().any(|p| p == parameter)
+    }
+    fn collect(&self, only_unused: bool) -> Vec<String> {
+        let mut params: Vec<String> = Vec::new();
+        collect_params(&self.bools, "bool", only_unused, &mut params);
+        collect_params(&self.ints, "integer", only_unused, &mut params);
+        collect_params(&self.floats, "float", only_unused, &mut params);
+        collect_params(&self.point2fs, "point2", only_unused, &mut params);
+        collect_params(&self.vector2fs, "vector2", only_unused, &mut params);
+        collect_params(&self.point3fs, "point", only_unused, &mut params);
+        collect_params(&self.vector3fs, "vector", only_unused, &mut params);
+        collect_params(&self.normals, "normal", only_unused, &mut params);
+        collect_params(&self.spectra, "spectrum", only_unused, &mut params);
+        collect_params(&self.strings, "string", only_unused, &mut params);
+        collect_params(&self.textures, "texture", only_unused, &mut params);
+        params
+    }
 }
 
 #[derive(Default)]
@@ -754,6 +934,103 @@ impl TextureParams {
         self.geom_params
             .find_one_spectrum(name, self.material_params.find_one_spectrum(name, d))
     }
+    /// Parameters which were looked up in neither the geometry nor the
+    /// material parameters.
+    pub fn unused(&self) -> Vec<String> {
+        let geom_unused: Vec<String> = self.geom_params.unused();
+        let material_unused: Vec<String> = self.material_params.unused();
+        let mut unused: Vec<String> = Vec::new();
+        for name in &geom_unused {
+            if !self.material_params.has(name) || material_unused.contains(name) {
+                unused.push(name.clone());
+            }
+        }
+        for name in &material_unused {
+            if !self.geom_params.has(name) && !unused.contains(name) {
+                unused.push(name.clone());
+            }
+        }
+        unused
+    }
+    pub fn lookups(&self) -> Vec<String> {
+        let mut lookups: Vec<String> = self.geom_params.lookups();
+        for lookup in self.material_params.lookups() {
+            if !lookups.contains(&lookup) {
+                lookups.push(lookup);
+            }
+        }
+        lookups
+    }
+}
+
+fn collect_params<T>(
+    vec: &Vec<ParamSetItem<T>>,
+    param_type: &str,
+    only_unused: bool,
+    params: &mut Vec<String>,
+) {
+    for v in vec {
+        if !only_unused || !v.looked_up.get() {
+            params.push(format!("{} {}", param_type, v.name));
+        }
+    }
+}
+
+/// Returns the parameter (type and name) from **known** which is the
+/// closest match for a (misspelled) parameter, if any is close enough.
+pub fn suggest_parameter(parameter: &str, known: &Vec<String>) -> Option<String> {
+    let (param_type, name) = split_parameter(parameter);
+    let mut best: Option<(usize, String)> = None;
+    for candidate in known {
+        if candidate == parameter {
+            continue;
+        }
+        let (candidate_type, candidate_name) = split_parameter(candidate);
+        let distance: usize = edit_distance(name, candidate_name);
+        // allow roughly one typo per three characters
+        if distance > std::cmp::max(1, candidate_name.len() / 3) {
+            continue;
+        }
+        // for the same distance prefer the same type
+        let mut score: usize = 2 * distance;
+        if candidate_type != param_type {
+            score += 1;
+        }
+        let better: bool = match best {
+            Some((best_score, _)) => score < best_score,
+            None => true,
+        };
+        if better {
+            best = Some((score, candidate.clone()));
+        }
+    }
+    best.map(|(_, candidate)| candidate)
+}
+
+fn split_parameter(parameter: &str) -> (&str, &str) {
+    let mut parts = parameter.splitn(2, ' ');
+    let param_type: &str = parts.next().unwrap_or("");
+    match parts.next() {
+        Some(name) => (param_type, name),
+        None => ("", param_type),
+    }
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..(b.len() + 1)).collect();
+    for i in 0..a.len() {
+        let mut previous: usize = row[0];
+        row[0] = i + 1;
+        for j in 0..b.len() {
+            let substitution: usize = if a[i] == b[j] { previous } else { previous + 1 };
+            previous = row[j + 1];
+            row[j + 1] = std::cmp::min(substitution, std::cmp::min(row[j], row[j + 1]) + 1);
+        }
+    }
+    row[b.len()]
 }
 
 /// Replaces a macro on the C++ side.
@@ -763,7 +1040,7 @@ where
 {
     for v in vec {
         if v.name == name && v.n_values == 1_usize {
-            // v.looked_up = true;
+            v.looked_up.set(true);
             return v.values[0].clone();
         }
     }
@@ -51,6 +51,10 @@ pub struct GeometricPrimitive {
     pub material: Option<Arc<Material + Send + Sync>>,
     pub area_light: Option<Arc<AreaLight + Send + Sync>>,
     pub medium_interface: Option<Arc<MediumInterface>>,
+    /// Identifies the **Shape** statement (for AOVs), 0 if unknown
+    pub primitive_id: u32,
+    /// Identifies the material (for AOVs), 0 if unknown
+    pub material_id: u32,
 }
 
 impl GeometricPrimitive {
@@ -67,6 +71,8 @@ impl GeometricPrimitive {
                     material: material,
                     area_light: Some(area_light),
                     medium_interface: Some(medium_interface),
+                    primitive_id: 0_u32,
+                    material_id: 0_u32,
                 }
             } else {
                 GeometricPrimitive {
@@ -74,6 +80,8 @@ impl GeometricPrimitive {
                     material: material,
                     area_light: Some(area_light),
                     medium_interface: None,
+                    primitive_id: 0_u32,
+                    material_id: 0_u32,
                 }
             }
         } else {
@@ -83,6 +91,8 @@ impl GeometricPrimitive {
                     material: material,
                     area_light: None,
                     medium_interface: Some(medium_interface),
+                    primitive_id: 0_u32,
+                    material_id: 0_u32,
                 }
             } else {
                 GeometricPrimitive {
@@ -90,6 +100,8 @@ impl GeometricPrimitive {
                     material: material,
                     area_light: None,
                     medium_interface: None,
+                    primitive_id: 0_u32,
+                    material_id: 0_u32,
                 }
             }
         }
@@ -186,27 +198,12 @@ impl Primitive for TransformedPrimitive {
             r.t_max = ray.t_max;
             // transform instance's intersection data to world space
             if !interpolated_prim_to_world.is_identity() {
-                let new_isect = interpolated_prim_to_world.transform_surface_interaction(&isect);
+                let new_isect: SurfaceInteraction =
+                    interpolated_prim_to_world.transform_surface_interaction(&isect);
                 assert!(nrm_dot_nrm(&new_isect.n, &new_isect.shading.n) >= 0.0 as Float);
-                let mut is: SurfaceInteraction = SurfaceInteraction::new(
-                    &new_isect.p,
-                    &new_isect.p_error,
-                    &new_isect.uv,
-                    &new_isect.wo,
-                    &new_isect.dpdu,
-                    &new_isect.dpdv,
-                    &new_isect.dndu,
-                    &new_isect.dndv,
-                    new_isect.time,
-                    None,
-                );
-                // we need to preserve the primitive pointer
-                if let Some(primitive) = isect.primitive {
-                    is.primitive = Some(primitive);
-                }
-                return Some(is);
+                return Some(new_isect);
             }
-            None
+            Some(isect)
         } else {
             None
         }
@@ -0,0 +1,385 @@
+//! A sampling profiler which attributes the time spent rendering to
+//! phases like camera ray generation, acceleration structure
+//! traversal, shape intersection, BSDF evaluation, light sampling,
+//! and film accumulation. It gets compiled in with the **profiler**
+//! feature:
+//!
+//! ```shell
+//! > cargo build --release --features profiler --example rs_pbrt
+//! ```
+//!
+//! Hot functions mark the phase they belong to with a guard, which
+//! ends the phase when it goes out of scope:
+//!
+//! ```rust,ignore
+//! let _p = ProfilePhase::new(Prof::ShapeIntersect);
+//! ```
+//!
+//! Each thread keeps a bit mask of its active phases, and a background
+//! thread looks at the masks of all threads every few milliseconds
+//! and counts how often each combination of phases was active. The
+//! breakdown gets printed after rendering. Without the feature the
+//! guards do nothing.
+
+// std
+#[cfg(feature = "profiler")]
+use std::collections::HashMap;
+#[cfg(feature = "profiler")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "profiler")]
+use std::sync::{Arc, Mutex, Weak};
+#[cfg(feature = "profiler")]
+use std::thread::{self, JoinHandle};
+#[cfg(feature = "profiler")]
+use std::time::Duration;
+
+// see stats.h
+
+/// The phases the profiler distinguishes. Phases which usually
+/// contain others come first, the report nests them in that order.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Prof {
+    AccelConstruction,
+    MIPMapCreation,
+    IntegratorRender,
+    SamplerIntegratorLi,
+    SPPMCameraPass,
+    SPPMGridConstruction,
+    SPPMPhotonPass,
+    SPPMStatsUpdate,
+    BDPTGenerateSubpath,
+    BDPTConnectSubpaths,
+    LightDistribLookup,
+    DirectLighting,
+    BSDFEvaluation,
+    BSDFSampling,
+    BSDFPdf,
+    BSSRDFEvaluation,
+    BSSRDFSampling,
+    PhaseFuncEvaluation,
+    PhaseFuncSampling,
+    AccelIntersect,
+    AccelIntersectP,
+    LightSample,
+    LightPdf,
+    MediumSample,
+    MediumTr,
+    TriIntersect,
+    TriIntersectP,
+    CurveIntersect,
+    CurveIntersectP,
+    ShapeIntersect,
+    ShapeIntersectP,
+    ComputeScatteringFuncs,
+    GenerateCameraRay,
+    MergeFilmTile,
+    SplatFilm,
+    AddFilmSample,
+    StartPixel,
+    GetSample,
+    TexFiltTrilerp,
+    TexFiltEWA,
+}
+
+#[cfg(feature = "profiler")]
+const ALL_PHASES: [Prof; 40] = [
+    Prof::AccelConstruction,
+    Prof::MIPMapCreation,
+    Prof::IntegratorRender,
+    Prof::SamplerIntegratorLi,
+    Prof::SPPMCameraPass,
+    Prof::SPPMGridConstruction,
+    Prof::SPPMPhotonPass,
+    Prof::SPPMStatsUpdate,
+    Prof::BDPTGenerateSubpath,
+    Prof::BDPTConnectSubpaths,
+    Prof::LightDistribLookup,
+    Prof::DirectLighting,
+    Prof::BSDFEvaluation,
+    Prof::BSDFSampling,
+    Prof::BSDFPdf,
+    Prof::BSSRDFEvaluation,
+    Prof::BSSRDFSampling,
+    Prof::PhaseFuncEvaluation,
+    Prof::PhaseFuncSampling,
+    Prof::AccelIntersect,
+    Prof::AccelIntersectP,
+    Prof::LightSample,
+    Prof::LightPdf,
+    Prof::MediumSample,
+    Prof::MediumTr,
+    Prof::TriIntersect,
+    Prof::TriIntersectP,
+    Prof::CurveIntersect,
+    Prof::CurveIntersectP,
+    Prof::ShapeIntersect,
+    Prof::ShapeIntersectP,
+    Prof::ComputeScatteringFuncs,
+    Prof::GenerateCameraRay,
+    Prof::MergeFilmTile,
+    Prof::SplatFilm,
+    Prof::AddFilmSample,
+    Prof::StartPixel,
+    Prof::GetSample,
+    Prof::TexFiltTrilerp,
+    Prof::TexFiltEWA,
+];
+
+impl Prof {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Prof::AccelConstruction => "Acceleration structure creation",
+            Prof::MIPMapCreation => "MIP map generation",
+            Prof::IntegratorRender => "Integrator::render()",
+            Prof::SamplerIntegratorLi => "SamplerIntegrator::li()",
+            Prof::SPPMCameraPass => "SPPM camera pass",
+            Prof::SPPMGridConstruction => "SPPM grid construction",
+            Prof::SPPMPhotonPass => "SPPM photon pass",
+            Prof::SPPMStatsUpdate => "SPPM photon statistics update",
+            Prof::BDPTGenerateSubpath => "BDPT subpath generation",
+            Prof::BDPTConnectSubpaths => "BDPT subpath connections",
+            Prof::LightDistribLookup => "Light distribution lookup",
+            Prof::DirectLighting => "Direct lighting",
+            Prof::BSDFEvaluation => "Bsdf::f()",
+            Prof::BSDFSampling => "Bsdf::sample_f()",
+            Prof::BSDFPdf => "Bsdf::pdf()",
+            Prof::BSSRDFEvaluation => "Bssrdf::f()",
+            Prof::BSSRDFSampling => "Bssrdf::sample_s()",
+            Prof::PhaseFuncEvaluation => "PhaseFunction::p()",
+            Prof::PhaseFuncSampling => "PhaseFunction::sample_p()",
+            Prof::AccelIntersect => "Accelerator::intersect()",
+            Prof::AccelIntersectP => "Accelerator::intersect_p()",
+            Prof::LightSample => "Light::sample_*()",
+            Prof::LightPdf => "Light::pdf_*()",
+            Prof::MediumSample => "Medium::sample()",
+            Prof::MediumTr => "Medium::tr()",
+            Prof::TriIntersect => "Triangle::intersect()",
+            Prof::TriIntersectP => "Triangle::intersect_p()",
+            Prof::CurveIntersect => "Curve::intersect()",
+            Prof::CurveIntersectP => "Curve::intersect_p()",
+            Prof::ShapeIntersect => "Other Shape::intersect()",
+            Prof::ShapeIntersectP => "Other Shape::intersect_p()",
+            Prof::ComputeScatteringFuncs => "Material::compute_scattering_functions()",
+            Prof::GenerateCameraRay => "Camera::generate_ray[_differential]()",
+            Prof::MergeFilmTile => "Film::merge_film_tile()",
+            Prof::SplatFilm => "Film::add_splat()",
+            Prof::AddFilmSample => "FilmTile::add_sample()",
+            Prof::StartPixel => "Sampler::start_pixel()",
+            Prof::GetSample => "Sampler::get_[12]d()",
+            Prof::TexFiltTrilerp => "MipMap::lookup() (trilinear)",
+            Prof::TexFiltEWA => "MipMap::lookup() (EWA)",
+        }
+    }
+    #[cfg(feature = "profiler")]
+    fn bit(&self) -> u64 {
+        1_u64 << (*self as u64)
+    }
+}
+
+/// Marks the current thread as being in a phase until the guard gets
+/// dropped (see ProfilePhase in stats.h).
+#[cfg(feature = "profiler")]
+pub struct ProfilePhase {
+    // the bits which weren't set before
+    bits: u64,
+}
+
+#[cfg(feature = "profiler")]
+impl ProfilePhase {
+    pub fn new(phase: Prof) -> Self {
+        ProfilePhase::resume(phase.bit())
+    }
+    /// Continues the phases of another thread (see
+    /// **current_profiler_state()**), e.g. within worker threads.
+    pub fn resume(state: u64) -> Self {
+        let bits: u64 = PROFILER_STATE.with(|s| {
+            let old: u64 = s.load(Ordering::Relaxed);
+            s.store(old | state, Ordering::Relaxed);
+            state & !old
+        });
+        ProfilePhase { bits: bits }
+    }
+}
+
+#[cfg(feature = "profiler")]
+impl Drop for ProfilePhase {
+    fn drop(&mut self) {
+        if self.bits != 0 {
+            let bits: u64 = self.bits;
+            let _ = PROFILER_STATE.try_with(|s| {
+                let old: u64 = s.load(Ordering::Relaxed);
+                s.store(old & !bits, Ordering::Relaxed);
+            });
+        }
+    }
+}
+
+#[cfg(not(feature = "profiler"))]
+pub struct ProfilePhase {}
+
+#[cfg(not(feature = "profiler"))]
+impl ProfilePhase {
+    #[inline(always)]
+    pub fn new(_phase: Prof) -> Self {
+        ProfilePhase {}
+    }
+    #[inline(always)]
+    pub fn resume(_state: u64) -> Self {
+        ProfilePhase {}
+    }
+}
+
+#[cfg(feature = "profiler")]
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(10);
+
+#[cfg(feature = "profiler")]
+struct Profiler {
+    // the phases of all threads which entered one
+    threads: Vec<Weak<AtomicU64>>,
+    // how often each combination of phases was seen
+    samples: HashMap<u64, u64>,
+    sample_thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(feature = "profiler")]
+lazy_static! {
+    static ref PROFILER: Mutex<Profiler> = Mutex::new(Profiler {
+        threads: Vec::new(),
+        samples: HashMap::new(),
+        sample_thread: None,
+    });
+}
+
+#[cfg(feature = "profiler")]
+thread_local! {
+    static PROFILER_STATE: Arc<AtomicU64> = {
+        let state: Arc<AtomicU64> = Arc::new(AtomicU64::new(0));
+        PROFILER.lock().unwrap().threads.push(Arc::downgrade(&state));
+        state
+    };
+}
+
+/// The phases active on the current thread, to be continued on
+/// worker threads via **ProfilePhase::resume()**.
+#[cfg(feature = "profiler")]
+pub fn current_profiler_state() -> u64 {
+    PROFILER_STATE.with(|s| s.load(Ordering::Relaxed))
+}
+
+#[cfg(not(feature = "profiler"))]
+#[inline(always)]
+pub fn current_profiler_state() -> u64 {
+    0_u64
+}
+
+/// Starts the thread which samples the phases of all threads (see
+/// InitProfiler() in stats.cpp).
+#[cfg(feature = "profiler")]
+pub fn init_profiler() {
+    let mut profiler = PROFILER.lock().unwrap();
+    if profiler.sample_thread.is_some() {
+        return;
+    }
+    profiler.sample_thread = Some(thread::spawn(move || loop {
+        thread::sleep(SAMPLE_INTERVAL);
+        let mut profiler = PROFILER.lock().unwrap();
+        let mut states: Vec<u64> = Vec::with_capacity(profiler.threads.len());
+        profiler.threads.retain(|thread| match thread.upgrade() {
+            Some(state) => {
+                states.push(state.load(Ordering::Relaxed));
+                true
+            }
+            None => false,
+        });
+        for state in states {
+            if state != 0 {
+                *profiler.samples.entry(state).or_insert(0) += 1;
+            }
+        }
+    }));
+}
+
+#[cfg(not(feature = "profiler"))]
+pub fn init_profiler() {}
+
+/// Forgets all samples, e.g. before rendering the next scene (see
+/// ClearProfiler() in stats.cpp).
+#[cfg(feature = "profiler")]
+pub fn clear_profiler() {
+    let mut profiler = PROFILER.lock().unwrap();
+    profiler.samples.clear();
+}
+
+#[cfg(not(feature = "profiler"))]
+pub fn clear_profiler() {}
+
+/// Prints which share of the sampled time was spent in each
+/// combination of phases, nested by phase, followed by the totals
+/// per phase (see ReportProfilerResults() in stats.cpp). The times
+/// are summed over all threads.
+#[cfg(feature = "profiler")]
+pub fn report_profiler_results() {
+    let profiler = PROFILER.lock().unwrap();
+    let overall_count: u64 = profiler.samples.values().sum();
+    if overall_count == 0 {
+        return;
+    }
+    let interval: f64 =
+        SAMPLE_INTERVAL.as_secs() as f64 + SAMPLE_INTERVAL.subsec_nanos() as f64 * 1e-9;
+    // the phases of each combination, outermost first
+    let mut entries: Vec<(Vec<&'static str>, u64)> = profiler
+        .samples
+        .iter()
+        .map(|(state, count)| {
+            let names: Vec<&'static str> = ALL_PHASES
+                .iter()
+                .filter(|phase| state & phase.bit() != 0)
+                .map(|phase| phase.name())
+                .collect();
+            (names, *count)
+        })
+        .collect();
+    entries.sort();
+    println!("Profile:");
+    for (names, count) in entries.iter() {
+        let indent: usize = 2 * names.len();
+        let pct: f64 = 100.0 * *count as f64 / overall_count as f64;
+        println!(
+            "{:indent$}{:<width$} {:6.2}% ({:.2}s)",
+            "",
+            names[names.len() - 1],
+            pct,
+            *count as f64 * interval,
+            indent = indent,
+            width = 60_usize.saturating_sub(indent)
+        );
+    }
+    // totals per phase
+    let mut totals: Vec<(u64, &'static str)> = ALL_PHASES
+        .iter()
+        .map(|phase| {
+            let count: u64 = profiler
+                .samples
+                .iter()
+                .filter(|&(state, _)| state & phase.bit() != 0)
+                .map(|(_, count)| count)
+                .sum();
+            (count, phase.name())
+        })
+        .filter(|&(count, _)| count > 0)
+        .collect();
+    totals.sort_by(|a, b| b.cmp(a));
+    println!("Profile (flattened):");
+    for (count, name) in totals.iter() {
+        let pct: f64 = 100.0 * *count as f64 / overall_count as f64;
+        println!(
+            "  {:<58} {:6.2}% ({:.2}s)",
+            name,
+            pct,
+            *count as f64 * interval
+        );
+    }
+}
+
+#[cfg(not(feature = "profiler"))]
+pub fn report_profiler_results() {}
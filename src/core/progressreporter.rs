@@ -0,0 +1,131 @@
+//! Reports the progress of long running computations (like
+//! rendering) on the console. A background thread redraws a progress
+//! bar with the elapsed time and an estimate of the remaining time,
+//! while the workers just count how much work they finished.
+
+// std
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+// see progressreporter.h
+
+const LINE_WIDTH: usize = 80;
+
+pub struct ProgressReporter {
+    total_work: i64,
+    title: String,
+    start_time: Instant,
+    work_done: Arc<AtomicI64>,
+    exit_thread: Arc<AtomicBool>,
+    update_thread: Option<JoinHandle<()>>,
+}
+
+impl ProgressReporter {
+    pub fn new(total_work: i64, title: &str) -> Self {
+        let start_time: Instant = Instant::now();
+        let work_done: Arc<AtomicI64> = Arc::new(AtomicI64::new(0));
+        let exit_thread: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+        let update_thread = {
+            let title: String = String::from(title);
+            let work_done = work_done.clone();
+            let exit_thread = exit_thread.clone();
+            thread::spawn(move || {
+                // redraw often at first, then less frequently
+                let mut sleep: Duration = Duration::from_millis(250);
+                let mut iterations: u32 = 0;
+                loop {
+                    thread::park_timeout(sleep);
+                    if exit_thread.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    iterations += 1;
+                    if iterations == 10 {
+                        sleep = Duration::from_millis(1000);
+                    } else if iterations == 70 {
+                        sleep = Duration::from_millis(5000);
+                    }
+                    print_bar(
+                        &title,
+                        work_done.load(Ordering::Relaxed),
+                        total_work,
+                        &start_time,
+                    );
+                }
+            })
+        };
+        ProgressReporter {
+            total_work: total_work.max(1),
+            title: String::from(title),
+            start_time: start_time,
+            work_done: work_done,
+            exit_thread: exit_thread,
+            update_thread: Some(update_thread),
+        }
+    }
+    /// Adds *num* units of finished work.
+    pub fn update(&self, num: i64) {
+        if num == 0 {
+            return;
+        }
+        self.work_done.fetch_add(num, Ordering::Relaxed);
+    }
+    pub fn elapsed_ms(&self) -> u64 {
+        let elapsed: Duration = self.start_time.elapsed();
+        elapsed.as_secs() * 1000 + elapsed.subsec_millis() as u64
+    }
+    /// Draws the full bar with the total time and ends the line.
+    pub fn done(&mut self) {
+        self.work_done.store(self.total_work, Ordering::Relaxed);
+        self.stop();
+        print_bar(&self.title, self.total_work, self.total_work, &self.start_time);
+        println!();
+    }
+    fn stop(&mut self) {
+        if let Some(update_thread) = self.update_thread.take() {
+            self.exit_thread.store(true, Ordering::Relaxed);
+            update_thread.thread().unpark();
+            let _ = update_thread.join();
+        }
+    }
+}
+
+impl Drop for ProgressReporter {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn print_bar(title: &str, work_done: i64, total_work: i64, start_time: &Instant) {
+    let fraction: f64 = (work_done as f64 / total_work.max(1) as f64).min(1.0);
+    let elapsed: f64 = duration_to_seconds(&start_time.elapsed());
+    let times: String = if work_done >= total_work {
+        format!(" ({:.1}s)", elapsed)
+    } else if work_done > 0 {
+        let remaining: f64 = (elapsed / fraction - elapsed).max(0.0);
+        format!(" ({:.1}s|{:.1}s)", elapsed, remaining)
+    } else {
+        format!(" ({:.1}s|?s)", elapsed)
+    };
+    // "title: [+++   ] (elapsed|remaining)"
+    let bar_length: usize = LINE_WIDTH.saturating_sub(title.len() + 4 + 16).max(10);
+    let plusses: usize = (bar_length as f64 * fraction).round() as usize;
+    let mut line: String = String::with_capacity(LINE_WIDTH);
+    line.push_str(title);
+    line.push_str(": [");
+    for i in 0..bar_length {
+        line.push(if i < plusses { '+' } else { ' ' });
+    }
+    line.push(']');
+    line.push_str(&times);
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    let _ = write!(handle, "\r{:<width$}", line, width = LINE_WIDTH);
+    let _ = handle.flush();
+}
+
+fn duration_to_seconds(d: &Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9
+}
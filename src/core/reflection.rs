@@ -26,6 +26,7 @@ use core::microfacet::{MicrofacetDistribution, TrowbridgeReitzDistribution};
 use core::pbrt::INV_PI;
 use core::pbrt::{clamp_t, radians};
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::rng::FLOAT_ONE_MINUS_EPSILON;
 use core::sampling::cosine_sample_hemisphere;
 
@@ -250,7 +251,7 @@ impl Bsdf {
         }
     }
     pub fn f(&self, wo_w: &Vector3f, wi_w: &Vector3f, flags: u8) -> Spectrum {
-        // TODO: ProfilePhase pp(Prof::BSDFEvaluation);
+        let _p = ProfilePhase::new(Prof::BSDFEvaluation);
         let wi: Vector3f = self.world_to_local(wi_w);
         let wo: Vector3f = self.world_to_local(wo_w);
         if wo.z == 0.0 as Float {
@@ -282,7 +283,7 @@ impl Bsdf {
         bsdf_flags: u8,
         sampled_type: &mut u8,
     ) -> Spectrum {
-        // TODO: ProfilePhase pp(Prof::BSDFSampling);
+        let _p = ProfilePhase::new(Prof::BSDFSampling);
         // choose which _BxDF_ to sample
         let matching_comps: u8 = self.num_components(bsdf_flags);
         if matching_comps == 0 {
@@ -393,7 +394,7 @@ impl Bsdf {
         }
     }
     pub fn pdf(&self, wo_world: &Vector3f, wi_world: &Vector3f, bsdf_flags: u8) -> Float {
-        // TODO: ProfilePhase pp(Prof::BSDFPdf);
+        let _p = ProfilePhase::new(Prof::BSDFPdf);
         let n_bxdfs: usize = self.bxdfs.len();
         if n_bxdfs == 0 {
             return 0.0 as Float;
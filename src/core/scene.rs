@@ -6,24 +6,31 @@
 // std
 use std::sync::Arc;
 // pbrt
-use accelerators::bvh::BVHAccel;
 use core::geometry::{Bounds3f, Ray, Vector3f};
 use core::interaction::SurfaceInteraction;
 use core::light::{Light, LightFlags};
 use core::primitive::Primitive;
+use core::stats::StatCounter;
 
 // see scene.h
 
+static N_INTERSECTION_TESTS: StatCounter =
+    StatCounter::new("Intersections/Regular ray intersection tests");
+static N_SHADOW_TESTS: StatCounter = StatCounter::new("Intersections/Shadow ray intersection tests");
+
 #[derive(Clone)]
 pub struct Scene {
     pub lights: Vec<Arc<Light + Sync + Send>>,
     pub infinite_lights: Vec<Arc<Light + Sync + Send>>,
-    pub aggregate: Arc<BVHAccel>, // TODO: Primitive,
+    pub aggregate: Arc<Primitive + Sync + Send>,
     pub world_bound: Bounds3f,
 }
 
 impl Scene {
-    pub fn new(aggregate: Arc<BVHAccel>, lights: Vec<Arc<Light + Sync + Send>>) -> Self {
+    pub fn new(
+        aggregate: Arc<Primitive + Sync + Send>,
+        lights: Vec<Arc<Light + Sync + Send>>,
+    ) -> Self {
         let world_bound: Bounds3f = aggregate.world_bound();
         let scene: Scene = Scene {
             lights: Vec::new(),
@@ -52,7 +59,7 @@ impl Scene {
         self.world_bound
     }
     pub fn intersect(&self, ray: &mut Ray) -> Option<SurfaceInteraction> {
-        // TODO: ++nIntersectionTests;
+        N_INTERSECTION_TESTS.inc();
         assert_ne!(
             ray.d,
             Vector3f {
@@ -64,7 +71,7 @@ impl Scene {
         self.aggregate.intersect(ray)
     }
     pub fn intersect_p(&self, ray: &mut Ray) -> bool {
-        // TODO: ++nShadowTests;
+        N_SHADOW_TESTS.inc();
         assert_ne!(
             ray.d,
             Vector3f {
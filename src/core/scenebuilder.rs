@@ -0,0 +1,659 @@
+//! A typed alternative to calling the *pbrt_* API functions (or
+//! writing a scene description) by hand.
+//!
+//! The **SceneBuilder** takes care of the order the API functions
+//! have to be called in (render options first, then the world) and
+//! turns the typed descriptions into parameters internally, so the
+//! result is exactly what **pbrt_world_end()** creates for the
+//! equivalent scene file:
+//!
+//! ```rust,no_run
+//! use pbrt::core::geometry::{Point3f, Vector3f};
+//! use pbrt::core::pbrt::Spectrum;
+//! use pbrt::core::scenebuilder::{CameraKind, FilmSettings, LightKind};
+//! use pbrt::core::scenebuilder::{MaterialKind, SceneBuilder, ShapeKind};
+//! use pbrt::core::transform::Transform;
+//!
+//! let mut builder: SceneBuilder = SceneBuilder::new(0_u8);
+//! let world_to_camera: Transform = Transform::look_at(
+//!     &Point3f { x: 0.0, y: 0.0, z: 5.0 },
+//!     &Point3f { x: 0.0, y: 0.0, z: 0.0 },
+//!     &Vector3f { x: 0.0, y: 1.0, z: 0.0 },
+//! );
+//! builder.camera(CameraKind::Perspective { fov: 45.0 }, &world_to_camera).unwrap();
+//! builder.film(FilmSettings { x_resolution: 320, y_resolution: 240, ..Default::default() }).unwrap();
+//! builder.light(&Transform::default(), LightKind::Point {
+//!     intensity: Spectrum::new(10.0),
+//!     from: Point3f { x: 0.0, y: 4.0, z: 4.0 },
+//! }).unwrap();
+//! builder.shape(
+//!     &Transform::default(),
+//!     ShapeKind::Sphere { radius: 1.0 },
+//!     MaterialKind::Matte { kd: Spectrum::new(0.5), sigma: 0.0 },
+//! ).unwrap();
+//! let mut render_setup = builder.build().unwrap();
+//! render_setup.render();
+//! ```
+
+// std
+use std::env;
+use std::path::{Path, PathBuf};
+// pbrt
+use core::api::{pbrt_area_light_source, pbrt_attribute_begin, pbrt_attribute_end, pbrt_camera};
+use core::api::{pbrt_film, pbrt_init, pbrt_integrator, pbrt_light_source};
+use core::api::{pbrt_make_named_material, pbrt_material, pbrt_named_material};
+use core::api::{pbrt_object_begin, pbrt_object_end, pbrt_object_instance};
+use core::api::{pbrt_pixel_filter, pbrt_sampler, pbrt_shape, pbrt_transform};
+use core::api::{pbrt_world_begin, pbrt_world_end};
+use core::api::{ApiError, ApiState, BsdfState, ErrorMode, RenderSetup};
+use core::geometry::{Normal3f, Point2f, Point3f};
+use core::paramset::ParamSet;
+use core::pbrt::{Float, Spectrum};
+use core::scenewriter::SceneWriter;
+use core::transform::Transform;
+
+pub enum CameraKind {
+    Perspective { fov: Float },
+    Orthographic,
+    Environment,
+}
+
+pub struct FilmSettings {
+    pub x_resolution: i32,
+    pub y_resolution: i32,
+    /// An empty name means the default file name.
+    pub filename: String,
+}
+
+impl Default for FilmSettings {
+    fn default() -> Self {
+        FilmSettings {
+            x_resolution: 1280,
+            y_resolution: 720,
+            filename: String::new(),
+        }
+    }
+}
+
+/// The width is used for both, x and y direction.
+pub enum FilterKind {
+    Box { width: Float },
+    Gaussian { width: Float, alpha: Float },
+    Mitchell { width: Float, b: Float, c: Float },
+    Triangle { width: Float },
+}
+
+pub enum SamplerKind {
+    Halton { pixel_samples: i32 },
+    Random { pixel_samples: i32 },
+    Sobol { pixel_samples: i32 },
+    ZeroTwoSequence { pixel_samples: i32 },
+}
+
+pub enum IntegratorKind {
+    AmbientOcclusion {
+        n_samples: i32,
+        cos_sample: bool,
+    },
+    BDPT {
+        max_depth: i32,
+    },
+    DirectLighting {
+        max_depth: i32,
+    },
+    MLT {
+        max_depth: i32,
+        mutations_per_pixel: i32,
+    },
+    Path {
+        max_depth: i32,
+    },
+    SPPM {
+        iterations: i32,
+        max_depth: i32,
+        radius: Float,
+    },
+}
+
+pub enum LightKind {
+    Distant {
+        radiance: Spectrum,
+        from: Point3f,
+        to: Point3f,
+    },
+    /// An optional environment map is given by its file name.
+    Infinite {
+        radiance: Spectrum,
+        n_samples: i32,
+        map_name: Option<String>,
+    },
+    Point {
+        intensity: Spectrum,
+        from: Point3f,
+    },
+    Spot {
+        intensity: Spectrum,
+        from: Point3f,
+        to: Point3f,
+        cone_angle: Float,
+        cone_delta_angle: Float,
+    },
+}
+
+pub enum MaterialKind {
+    Glass {
+        kr: Spectrum,
+        kt: Spectrum,
+        eta: Float,
+    },
+    Matte {
+        kd: Spectrum,
+        sigma: Float,
+    },
+    Metal {
+        eta: Spectrum,
+        k: Spectrum,
+        roughness: Float,
+    },
+    Mirror {
+        kr: Spectrum,
+    },
+    Plastic {
+        kd: Spectrum,
+        ks: Spectrum,
+        roughness: Float,
+    },
+    /// Refers to a material defined via **named_material()**.
+    Named(String),
+}
+
+pub enum ShapeKind {
+    Cylinder {
+        radius: Float,
+        z_min: Float,
+        z_max: Float,
+    },
+    Disk {
+        radius: Float,
+        height: Float,
+    },
+    /// The mesh file is searched relative to the search directory
+    /// (see **SceneBuilder::search_directory()**).
+    PlyMesh {
+        filename: String,
+    },
+    Sphere {
+        radius: Float,
+    },
+    /// Normals and uv coordinates are optional (leave them empty).
+    TriangleMesh {
+        indices: Vec<i32>,
+        p: Vec<Point3f>,
+        n: Vec<Normal3f>,
+        uv: Vec<Point2f>,
+    },
+}
+
+pub struct SceneBuilder {
+    api_state: ApiState,
+    bsdf_state: BsdfState,
+    world_to_camera: Transform,
+    camera_params: Option<ParamSet>,
+    film_params: Option<ParamSet>,
+    filter_params: Option<ParamSet>,
+    sampler_params: Option<ParamSet>,
+    integrator_params: Option<ParamSet>,
+    world_begun: bool,
+}
+
+impl SceneBuilder {
+    /// Problems are reported as errors (see **ErrorMode::Strict**),
+    /// all render options not set explicitly use the same defaults as
+    /// a scene file would. Mesh files are searched relative to the
+    /// current directory (see **search_directory()**).
+    pub fn new(number_of_threads: u8) -> Self {
+        let (mut api_state, bsdf_state) = pbrt_init(number_of_threads);
+        api_state.error_mode = ErrorMode::Strict;
+        if let Ok(current_dir) = env::current_dir() {
+            api_state.search_directory = Some(Box::new(current_dir));
+        }
+        SceneBuilder {
+            api_state: api_state,
+            bsdf_state: bsdf_state,
+            world_to_camera: Transform::default(),
+            camera_params: None,
+            film_params: None,
+            filter_params: None,
+            sampler_params: None,
+            integrator_params: None,
+            world_begun: false,
+        }
+    }
+    /// Relative file names of meshes (e.g. **ShapeKind::PlyMesh**) get
+    /// resolved against **directory**.
+    pub fn search_directory(&mut self, directory: &Path) {
+        self.api_state.search_directory = Some(Box::new(PathBuf::from(directory)));
+    }
+    /// Keeps everything added from now on for **write_scene()**.
+    pub fn record_scene(&mut self) {
+        self.api_state.scene_record = Some(Vec::new());
+    }
+    // render options
+
+    /// The camera looks down the positive z axis of its own
+    /// coordinate system, e.g. use **Transform::look_at()** for
+    /// **world_to_camera**.
+    pub fn camera(
+        &mut self,
+        camera: CameraKind,
+        world_to_camera: &Transform,
+    ) -> Result<(), ApiError> {
+        self.check_options("Camera")?;
+        let mut params: ParamSet = new_params("Camera", "perspective");
+        match camera {
+            CameraKind::Perspective { fov } => {
+                params.add_float(String::from("fov"), fov);
+            }
+            CameraKind::Orthographic => params.name = String::from("orthographic"),
+            CameraKind::Environment => params.name = String::from("environment"),
+        }
+        self.world_to_camera = *world_to_camera;
+        self.camera_params = Some(params);
+        Ok(())
+    }
+    pub fn film(&mut self, film: FilmSettings) -> Result<(), ApiError> {
+        self.check_options("Film")?;
+        let mut params: ParamSet = new_params("Film", "image");
+        params.add_int(String::from("xresolution"), film.x_resolution);
+        params.add_int(String::from("yresolution"), film.y_resolution);
+        if film.filename != "" {
+            params.add_string(String::from("filename"), film.filename);
+        }
+        self.film_params = Some(params);
+        Ok(())
+    }
+    pub fn pixel_filter(&mut self, filter: FilterKind) -> Result<(), ApiError> {
+        self.check_options("PixelFilter")?;
+        let mut params: ParamSet;
+        let width: Float;
+        match filter {
+            FilterKind::Box { width: w } => {
+                params = new_params("PixelFilter", "box");
+                width = w;
+            }
+            FilterKind::Gaussian { width: w, alpha } => {
+                params = new_params("PixelFilter", "gaussian");
+                params.add_float(String::from("alpha"), alpha);
+                width = w;
+            }
+            FilterKind::Mitchell { width: w, b, c } => {
+                params = new_params("PixelFilter", "mitchell");
+                params.add_float(String::from("B"), b);
+                params.add_float(String::from("C"), c);
+                width = w;
+            }
+            FilterKind::Triangle { width: w } => {
+                params = new_params("PixelFilter", "triangle");
+                width = w;
+            }
+        }
+        params.add_float(String::from("xwidth"), width);
+        params.add_float(String::from("ywidth"), width);
+        self.filter_params = Some(params);
+        Ok(())
+    }
+    pub fn sampler(&mut self, sampler: SamplerKind) -> Result<(), ApiError> {
+        self.check_options("Sampler")?;
+        let (name, pixel_samples) = match sampler {
+            SamplerKind::Halton { pixel_samples } => ("halton", pixel_samples),
+            SamplerKind::Random { pixel_samples } => ("random", pixel_samples),
+            SamplerKind::Sobol { pixel_samples } => ("sobol", pixel_samples),
+            SamplerKind::ZeroTwoSequence { pixel_samples } => ("02sequence", pixel_samples),
+        };
+        let mut params: ParamSet = new_params("Sampler", name);
+        params.add_int(String::from("pixelsamples"), pixel_samples);
+        self.sampler_params = Some(params);
+        Ok(())
+    }
+    pub fn integrator(&mut self, integrator: IntegratorKind) -> Result<(), ApiError> {
+        self.check_options("Integrator")?;
+        let mut params: ParamSet;
+        match integrator {
+            IntegratorKind::AmbientOcclusion {
+                n_samples,
+                cos_sample,
+            } => {
+                params = new_params("Integrator", "ambientocclusion");
+                params.add_int(String::from("nsamples"), n_samples);
+                params.add_bool(String::from("cossample"), cos_sample);
+            }
+            IntegratorKind::BDPT { max_depth } => {
+                params = new_params("Integrator", "bdpt");
+                params.add_int(String::from("maxdepth"), max_depth);
+            }
+            IntegratorKind::DirectLighting { max_depth } => {
+                params = new_params("Integrator", "directlighting");
+                params.add_int(String::from("maxdepth"), max_depth);
+            }
+            IntegratorKind::MLT {
+                max_depth,
+                mutations_per_pixel,
+            } => {
+                params = new_params("Integrator", "mlt");
+                params.add_int(String::from("maxdepth"), max_depth);
+                params.add_int(String::from("mutationsperpixel"), mutations_per_pixel);
+            }
+            IntegratorKind::Path { max_depth } => {
+                params = new_params("Integrator", "path");
+                params.add_int(String::from("maxdepth"), max_depth);
+            }
+            IntegratorKind::SPPM {
+                iterations,
+                max_depth,
+                radius,
+            } => {
+                params = new_params("Integrator", "sppm");
+                params.add_int(String::from("iterations"), iterations);
+                params.add_int(String::from("maxdepth"), max_depth);
+                params.add_float(String::from("radius"), radius);
+            }
+        }
+        self.integrator_params = Some(params);
+        Ok(())
+    }
+    // world
+
+    pub fn light(&mut self, light_to_world: &Transform, light: LightKind) -> Result<(), ApiError> {
+        self.begin_world()?;
+        let mut params: ParamSet;
+        match light {
+            LightKind::Distant { radiance, from, to } => {
+                params = new_params("LightSource", "distant");
+                params.add_rgb_spectrum(String::from("L"), radiance);
+                params.add_point3f(String::from("from"), from);
+                params.add_point3f(String::from("to"), to);
+            }
+            LightKind::Infinite {
+                radiance,
+                n_samples,
+                map_name,
+            } => {
+                params = new_params("LightSource", "infinite");
+                params.add_rgb_spectrum(String::from("L"), radiance);
+                params.add_int(String::from("nsamples"), n_samples);
+                if let Some(map_name) = map_name {
+                    params.add_string(String::from("mapname"), map_name);
+                }
+            }
+            LightKind::Point { intensity, from } => {
+                params = new_params("LightSource", "point");
+                params.add_rgb_spectrum(String::from("I"), intensity);
+                params.add_point3f(String::from("from"), from);
+            }
+            LightKind::Spot {
+                intensity,
+                from,
+                to,
+                cone_angle,
+                cone_delta_angle,
+            } => {
+                params = new_params("LightSource", "spot");
+                params.add_rgb_spectrum(String::from("I"), intensity);
+                params.add_point3f(String::from("from"), from);
+                params.add_point3f(String::from("to"), to);
+                params.add_float(String::from("coneangle"), cone_angle);
+                params.add_float(String::from("conedeltaangle"), cone_delta_angle);
+            }
+        }
+        pbrt_attribute_begin(&mut self.api_state)?;
+        pbrt_transform(&mut self.api_state, light_to_world)?;
+        pbrt_light_source(&mut self.api_state, params)?;
+        pbrt_attribute_end(&mut self.api_state)
+    }
+    /// Defines a material which can be used by several shapes via
+    /// **MaterialKind::Named**.
+    pub fn named_material(&mut self, name: &str, material: MaterialKind) -> Result<(), ApiError> {
+        self.begin_world()?;
+        if let MaterialKind::Named(_) = material {
+            return Err(ApiError::InvalidParameter {
+                directive: String::from("MakeNamedMaterial"),
+                message: format!("\"{}\" can't refer to another named material", name),
+            });
+        }
+        let mut params: ParamSet = material_params(material);
+        let material_type: String = params.name.clone();
+        params.reset(
+            String::from("MakeNamedMaterial"),
+            String::from(name),
+            String::new(),
+            String::new(),
+        );
+        params.add_string(String::from("type"), material_type);
+        pbrt_make_named_material(&mut self.api_state, &mut self.bsdf_state, params)
+    }
+    pub fn shape(
+        &mut self,
+        object_to_world: &Transform,
+        shape: ShapeKind,
+        material: MaterialKind,
+    ) -> Result<(), ApiError> {
+        self.begin_world()?;
+        pbrt_attribute_begin(&mut self.api_state)?;
+        pbrt_transform(&mut self.api_state, object_to_world)?;
+        self.add_shape(shape, material)?;
+        pbrt_attribute_end(&mut self.api_state)
+    }
+    /// A shape emitting light (with the given radiance) from its
+    /// surface, see the *diffuse* area light.
+    pub fn area_light(
+        &mut self,
+        object_to_world: &Transform,
+        shape: ShapeKind,
+        material: MaterialKind,
+        radiance: Spectrum,
+        two_sided: bool,
+    ) -> Result<(), ApiError> {
+        self.begin_world()?;
+        let mut params: ParamSet = new_params("AreaLightSource", "diffuse");
+        params.add_rgb_spectrum(String::from("L"), radiance);
+        params.add_bool(String::from("twosided"), two_sided);
+        pbrt_attribute_begin(&mut self.api_state)?;
+        pbrt_transform(&mut self.api_state, object_to_world)?;
+        pbrt_area_light_source(&mut self.api_state, params)?;
+        self.add_shape(shape, material)?;
+        pbrt_attribute_end(&mut self.api_state)
+    }
+    /// Defines an object (made of several shapes, each with its own
+    /// transformation and material), which can be placed several
+    /// times via **object_instance()**.
+    pub fn object(
+        &mut self,
+        name: &str,
+        shapes: Vec<(Transform, ShapeKind, MaterialKind)>,
+    ) -> Result<(), ApiError> {
+        self.begin_world()?;
+        pbrt_object_begin(&mut self.api_state, new_params("ObjectBegin", name))?;
+        for (object_to_instance, shape, material) in shapes {
+            pbrt_attribute_begin(&mut self.api_state)?;
+            pbrt_transform(&mut self.api_state, &object_to_instance)?;
+            self.add_shape(shape, material)?;
+            pbrt_attribute_end(&mut self.api_state)?;
+        }
+        pbrt_object_end(&mut self.api_state)
+    }
+    pub fn object_instance(
+        &mut self,
+        instance_to_world: &Transform,
+        name: &str,
+    ) -> Result<(), ApiError> {
+        self.begin_world()?;
+        pbrt_attribute_begin(&mut self.api_state)?;
+        pbrt_transform(&mut self.api_state, instance_to_world)?;
+        pbrt_object_instance(
+            &mut self.api_state,
+            &mut self.bsdf_state,
+            new_params("ObjectInstance", name),
+        )?;
+        pbrt_attribute_end(&mut self.api_state)
+    }
+    /// Writes the scene added so far as a **pbrt** scene file (see
+    /// **SceneWriter::write_state()**), if **record_scene()** was
+    /// called before adding anything.
+    pub fn write_scene(&mut self, writer: &mut SceneWriter) -> Result<(), ApiError> {
+        self.begin_world()?;
+        writer.write_state(&self.api_state)
+    }
+    /// Creates the scene, camera, sampler, and integrator, ready to
+    /// be rendered.
+    pub fn build(mut self) -> Result<RenderSetup, ApiError> {
+        self.begin_world()?;
+        pbrt_world_end(&mut self.api_state)
+    }
+    fn check_options(&self, directive: &str) -> Result<(), ApiError> {
+        if self.world_begun {
+            return Err(ApiError::InvalidState {
+                directive: String::from(directive),
+                message: String::from("render options have to be set before the world"),
+            });
+        }
+        Ok(())
+    }
+    /// Hands the render options over (in the order a scene file would
+    /// use) before the first light or shape gets added.
+    fn begin_world(&mut self) -> Result<(), ApiError> {
+        if self.world_begun {
+            return Ok(());
+        }
+        self.world_begun = true;
+        pbrt_transform(&mut self.api_state, &self.world_to_camera)?;
+        let camera_params: ParamSet = match self.camera_params.take() {
+            Some(params) => params,
+            None => new_params("Camera", "perspective"),
+        };
+        pbrt_camera(&mut self.api_state, camera_params)?;
+        if let Some(params) = self.film_params.take() {
+            pbrt_film(&mut self.api_state, params)?;
+        }
+        if let Some(params) = self.filter_params.take() {
+            pbrt_pixel_filter(&mut self.api_state, params)?;
+        }
+        if let Some(params) = self.sampler_params.take() {
+            pbrt_sampler(&mut self.api_state, params)?;
+        }
+        if let Some(params) = self.integrator_params.take() {
+            pbrt_integrator(&mut self.api_state, params)?;
+        }
+        pbrt_world_begin(&mut self.api_state)
+    }
+    fn add_shape(&mut self, shape: ShapeKind, material: MaterialKind) -> Result<(), ApiError> {
+        match material {
+            MaterialKind::Named(name) => {
+                pbrt_named_material(&mut self.api_state, new_params("NamedMaterial", &name))?
+            }
+            _ => pbrt_material(&mut self.api_state, material_params(material))?,
+        }
+        let mut params: ParamSet;
+        match shape {
+            ShapeKind::Cylinder {
+                radius,
+                z_min,
+                z_max,
+            } => {
+                params = new_params("Shape", "cylinder");
+                params.add_float(String::from("radius"), radius);
+                params.add_float(String::from("zmin"), z_min);
+                params.add_float(String::from("zmax"), z_max);
+            }
+            ShapeKind::Disk { radius, height } => {
+                params = new_params("Shape", "disk");
+                params.add_float(String::from("radius"), radius);
+                params.add_float(String::from("height"), height);
+            }
+            ShapeKind::PlyMesh { filename } => {
+                params = new_params("Shape", "plymesh");
+                params.add_string(String::from("filename"), filename);
+            }
+            ShapeKind::Sphere { radius } => {
+                params = new_params("Shape", "sphere");
+                params.add_float(String::from("radius"), radius);
+            }
+            ShapeKind::TriangleMesh { indices, p, n, uv } => {
+                params = new_params("Shape", "trianglemesh");
+                params.add_ints(String::from("indices"), indices);
+                let mut values: Vec<Float> = Vec::with_capacity(p.len() * 3);
+                for point in &p {
+                    values.push(point.x);
+                    values.push(point.y);
+                    values.push(point.z);
+                }
+                params.add_point3fs(String::from("P"), values);
+                if !n.is_empty() {
+                    let mut values: Vec<Float> = Vec::with_capacity(n.len() * 3);
+                    for normal in &n {
+                        values.push(normal.x);
+                        values.push(normal.y);
+                        values.push(normal.z);
+                    }
+                    params.add_normal3fs(String::from("N"), values);
+                }
+                if !uv.is_empty() {
+                    let mut values: Vec<Float> = Vec::with_capacity(uv.len() * 2);
+                    for point in &uv {
+                        values.push(point.x);
+                        values.push(point.y);
+                    }
+                    params.add_point2fs(String::from("uv"), values);
+                }
+            }
+        }
+        pbrt_shape(&mut self.api_state, &mut self.bsdf_state, params)
+    }
+}
+
+fn new_params(key_word: &str, name: &str) -> ParamSet {
+    let mut params: ParamSet = ParamSet::default();
+    params.reset(
+        String::from(key_word),
+        String::from(name),
+        String::new(),
+        String::new(),
+    );
+    params
+}
+
+fn material_params(material: MaterialKind) -> ParamSet {
+    let mut params: ParamSet;
+    match material {
+        MaterialKind::Glass { kr, kt, eta } => {
+            params = new_params("Material", "glass");
+            params.add_rgb_spectrum(String::from("Kr"), kr);
+            params.add_rgb_spectrum(String::from("Kt"), kt);
+            params.add_float(String::from("eta"), eta);
+        }
+        MaterialKind::Matte { kd, sigma } => {
+            params = new_params("Material", "matte");
+            params.add_rgb_spectrum(String::from("Kd"), kd);
+            params.add_float(String::from("sigma"), sigma);
+        }
+        MaterialKind::Metal { eta, k, roughness } => {
+            params = new_params("Material", "metal");
+            params.add_rgb_spectrum(String::from("eta"), eta);
+            params.add_rgb_spectrum(String::from("k"), k);
+            params.add_float(String::from("roughness"), roughness);
+        }
+        MaterialKind::Mirror { kr } => {
+            params = new_params("Material", "mirror");
+            params.add_rgb_spectrum(String::from("Kr"), kr);
+        }
+        MaterialKind::Plastic { kd, ks, roughness } => {
+            params = new_params("Material", "plastic");
+            params.add_rgb_spectrum(String::from("Kd"), kd);
+            params.add_rgb_spectrum(String::from("Ks"), ks);
+            params.add_float(String::from("roughness"), roughness);
+        }
+        MaterialKind::Named(name) => {
+            params = new_params("NamedMaterial", &name);
+        }
+    }
+    params
+}
@@ -0,0 +1,514 @@
+//! Writes a scene description back in **pbrt**'s file format, either
+//! statement by statement, like the `--cat` and `--toply` options of
+//! the C++ version do, or as a dump of the resolved API state.
+//!
+//! Once a **SceneWriter** is set for an **ApiState**, the *pbrt_* API
+//! functions echo each statement instead of executing it. Included
+//! files end up inlined, because the parser feeds their statements
+//! through the same API calls, and parameters are written in a
+//! canonical form, one parameter per line. Spectra are written the
+//! way they were given (`"rgb"`, `"spectrum"`, or `"blackbody"`).
+//! Inline `trianglemesh` shapes can optionally be converted to
+//! external PLY files:
+//!
+//! ```rust,no_run
+//! use pbrt::core::api::pbrt_init;
+//! use pbrt::core::scenewriter::SceneWriter;
+//! use pbrt::parser::parse_file;
+//! use std::fs::File;
+//! use std::path::{Path, PathBuf};
+//!
+//! let (mut api_state, mut bsdf_state) = pbrt_init(0_u8);
+//! let out = File::create("canonical.pbrt").unwrap();
+//! api_state.scene_writer = Some(SceneWriter::new(
+//!     Box::new(out),
+//!     Some(PathBuf::from("meshes")),
+//! ));
+//! parse_file(Path::new("scene.pbrt"), &mut api_state, &mut bsdf_state).unwrap();
+//! ```
+//!
+//! A scene built through the API (e.g. by a **SceneBuilder**) has no
+//! statements to echo. If **ApiState::scene_record** is set, the
+//! world block gets recorded while the scene is built, and
+//! **SceneWriter::write_state()** writes the resolved scene: the
+//! render options, every transformation as an absolute matrix, and
+//! each shape in its own attribute block with its material (named
+//! materials resolved), area light, and media. Textures, named
+//! materials, and media are written in the order they were defined:
+//!
+//! ```rust,no_run
+//! use pbrt::core::api::pbrt_init;
+//! use pbrt::core::scenewriter::SceneWriter;
+//! use pbrt::parser::parse_file;
+//! use std::fs::File;
+//! use std::path::Path;
+//!
+//! let (mut api_state, mut bsdf_state) = pbrt_init(0_u8);
+//! api_state.scene_record = Some(Vec::new());
+//! parse_file(Path::new("scene.pbrt"), &mut api_state, &mut bsdf_state).unwrap();
+//! let out = File::create("resolved.pbrt").unwrap();
+//! let mut writer: SceneWriter = SceneWriter::new(Box::new(out), None);
+//! writer.write_state(&api_state).unwrap();
+//! ```
+
+// std
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+// pbrt
+use core::api::{ApiError, ApiState, RenderOptions, TransformSet};
+use core::geometry::{Normal3f, Point2f, Point3f};
+use core::paramset::{ParamSet, ParamSetItem, SpectrumSource};
+use core::pbrt::Float;
+use core::transform::Transform;
+use shapes::plymesh::write_ply_file;
+
+// see api.cpp (PbrtOptions.cat and PbrtOptions.toPly)
+
+/// Parameters of a `trianglemesh` which end up in the PLY file(s).
+const MESH_PARAMETERS: [&str; 8] = ["indices", "P", "N", "S", "uv", "st", "P1", "N1"];
+
+/// A statement of the world block, recorded together with the
+/// transformation and graphics state it depends on (see
+/// **ApiState::scene_record**).
+pub enum ResolvedStatement {
+    MakeNamedMedium {
+        params: ParamSet,
+        transform: Transform,
+    },
+    Texture {
+        params: ParamSet,
+        transform: Transform,
+    },
+    /// A texture created by the caller (e.g. an importer), which has
+    /// no parameters to write.
+    InMemoryTexture {
+        name: String,
+    },
+    MakeNamedMaterial {
+        params: ParamSet,
+    },
+    LightSource {
+        params: ParamSet,
+        transform: Transform,
+        inside_medium: String,
+        outside_medium: String,
+    },
+    Shape {
+        params: ParamSet,
+        transform: TransformSet,
+        /// Named as the material type (a "type" parameter is ignored).
+        material: ParamSet,
+        area_light: Option<ParamSet>,
+        inside_medium: String,
+        outside_medium: String,
+        reverse_orientation: bool,
+    },
+    ObjectBegin {
+        name: String,
+    },
+    ObjectEnd,
+    ObjectInstance {
+        name: String,
+        transform: TransformSet,
+    },
+}
+
+pub struct SceneWriter {
+    out: BufWriter<Box<Write>>,
+    indent: usize,
+    ply_directory: Option<PathBuf>,
+    n_ply_files: usize,
+}
+
+impl SceneWriter {
+    /// All statements get written to **out**. If a **ply_directory**
+    /// is given, inline triangle meshes are written as PLY files into
+    /// that directory (named `mesh_00001.ply` etc.) and get referenced
+    /// via `Shape "plymesh"`.
+    pub fn new(out: Box<Write>, ply_directory: Option<PathBuf>) -> Self {
+        SceneWriter {
+            out: BufWriter::new(out),
+            indent: 0_usize,
+            ply_directory: ply_directory,
+            n_ply_files: 0_usize,
+        }
+    }
+    /// Writes a statement without parameters, e.g. `Translate 1 2 3`.
+    pub fn statement(&mut self, statement: &str) -> Result<(), ApiError> {
+        let indent: usize = self.indent;
+        let directive: &str = statement.split(' ').next().unwrap_or(statement);
+        self.write_line(directive, indent, statement)
+    }
+    /// Writes a statement like `AttributeBegin` and indents everything
+    /// up to the matching end statement.
+    pub fn begin_block(&mut self, statement: &str) -> Result<(), ApiError> {
+        self.statement(statement)?;
+        self.indent += 4;
+        Ok(())
+    }
+    pub fn end_block(&mut self, statement: &str) -> Result<(), ApiError> {
+        if self.indent >= 4 {
+            self.indent -= 4;
+        }
+        self.statement(statement)
+    }
+    /// Writes the 16 values of a `Transform` or `ConcatTransform`
+    /// statement (in the column-major order used by the file format).
+    pub fn transform(&mut self, directive: &str, tr: &Transform) -> Result<(), ApiError> {
+        let mut line: String = format!("{} [", directive);
+        for j in 0..4 {
+            for i in 0..4 {
+                line.push_str(&format!(" {}", tr.m.m[i][j]));
+            }
+        }
+        line.push_str(" ]");
+        self.statement(&line)
+    }
+    /// Writes a statement with a name and parameters, e.g. `Shape
+    /// "sphere" "float radius" [ 1 ]`. For `Texture` statements the
+    /// texture type and class are written as well.
+    pub fn named_statement(&mut self, directive: &str, params: &ParamSet) -> Result<(), ApiError> {
+        let mut line: String = format!("{} \"{}\"", directive, params.name);
+        if params.tex_type != "" {
+            line.push_str(&format!(" \"{}\" \"{}\"", params.tex_type, params.tex_name));
+        }
+        self.statement(&line)?;
+        self.parameters(directive, params, &[])
+    }
+    /// Writes a `Shape` statement. Triangle meshes get converted to
+    /// PLY files, if a directory for them was given.
+    pub fn shape(&mut self, params: &ParamSet) -> Result<(), ApiError> {
+        if params.name != "trianglemesh" || self.ply_directory.is_none() {
+            return self.named_statement("Shape", params);
+        }
+        let vertex_indices: Vec<i32> = params.find_int("indices");
+        let p: Vec<Point3f> = params.find_point3f("P");
+        let n: Vec<Normal3f> = params.find_normal3f("N");
+        let mut uvs: Vec<Point2f> = params.find_point2f("uv");
+        if uvs.is_empty() {
+            uvs = params.find_point2f("st");
+        }
+        if uvs.is_empty() {
+            let mut fuv: Vec<Float> = params.find_float("uv");
+            if fuv.is_empty() {
+                fuv = params.find_float("st");
+            }
+            for pair in fuv.chunks(2) {
+                if pair.len() == 2 {
+                    uvs.push(Point2f {
+                        x: pair[0],
+                        y: pair[1],
+                    });
+                }
+            }
+        }
+        if !params.find_vector3f("S").is_empty() {
+            println!("WARNING: PLY mesh will be missing tangent vectors \"S\".");
+        }
+        self.n_ply_files += 1;
+        let mut path: PathBuf = self.ply_directory.clone().unwrap_or_default();
+        path.push(format!("mesh_{:05}.ply", self.n_ply_files));
+        if let Err(e) = write_ply_file(&path, &vertex_indices, &p, &n, &uvs) {
+            return Err(ApiError::WriteFailed {
+                directive: String::from("Shape"),
+                message: format!("unable to write PLY file {:?}: {}", path, e),
+            });
+        }
+        // vertices (and normals) at shutter close go to a second file
+        let p1: Vec<Point3f> = params.find_point3f("P1");
+        let mut path1: Option<PathBuf> = None;
+        if !p1.is_empty() {
+            let n1: Vec<Normal3f> = params.find_normal3f("N1");
+            let mut path: PathBuf = self.ply_directory.clone().unwrap_or_default();
+            path.push(format!("mesh_{:05}_1.ply", self.n_ply_files));
+            if let Err(e) = write_ply_file(&path, &vertex_indices, &p1, &n1, &Vec::new()) {
+                return Err(ApiError::WriteFailed {
+                    directive: String::from("Shape"),
+                    message: format!("unable to write PLY file {:?}: {}", path, e),
+                });
+            }
+            path1 = Some(path);
+        }
+        self.statement("Shape \"plymesh\"")?;
+        let indent: usize = self.indent + 2;
+        let filename: String = format!("\"string filename\" [ \"{}\" ]", path.to_string_lossy());
+        self.write_line("Shape", indent, &filename)?;
+        if let Some(path1) = path1 {
+            let filename1: String =
+                format!("\"string filename1\" [ \"{}\" ]", path1.to_string_lossy());
+            self.write_line("Shape", indent, &filename1)?;
+        }
+        self.parameters("Shape", params, &MESH_PARAMETERS)
+    }
+    /// Writes the scene recorded in **api_state** (see the module
+    /// documentation), from the render options to `WorldEnd`.
+    pub fn write_state(&mut self, api_state: &ApiState) -> Result<(), ApiError> {
+        let statements: &Vec<ResolvedStatement> = match api_state.scene_record {
+            Some(ref statements) => statements,
+            None => {
+                return Err(ApiError::WriteFailed {
+                    directive: String::from("WorldBegin"),
+                    message: String::from("the world block was not recorded"),
+                })
+            }
+        };
+        let options: &RenderOptions = api_state.render_options();
+        self.statement(&format!(
+            "TransformTimes {} {}",
+            options.transform_start_time, options.transform_end_time
+        ))?;
+        let world_to_camera: TransformSet = TransformSet {
+            t: [
+                Transform::inverse(&options.camera_to_world.t[0]),
+                Transform::inverse(&options.camera_to_world.t[1]),
+            ],
+        };
+        self.transform_set(&world_to_camera)?;
+        self.option_statement("Camera", &options.camera_name, &options.camera_params)?;
+        self.option_statement("Sampler", &options.sampler_name, &options.sampler_params)?;
+        self.option_statement("Film", &options.film_name, &options.film_params)?;
+        self.option_statement("PixelFilter", &options.filter_name, &options.filter_params)?;
+        self.option_statement(
+            "Integrator",
+            &options.integrator_name,
+            &options.integrator_params,
+        )?;
+        self.option_statement(
+            "Accelerator",
+            &options.accelerator_name,
+            &options.accelerator_params,
+        )?;
+        self.statement("")?;
+        self.statement("WorldBegin")?;
+        for statement in statements {
+            match *statement {
+                ResolvedStatement::MakeNamedMedium {
+                    ref params,
+                    ref transform,
+                } => {
+                    self.transform("Transform", transform)?;
+                    self.named_statement("MakeNamedMedium", params)?;
+                }
+                ResolvedStatement::Texture {
+                    ref params,
+                    ref transform,
+                } => {
+                    self.transform("Transform", transform)?;
+                    self.named_statement("Texture", params)?;
+                }
+                ResolvedStatement::InMemoryTexture { ref name } => {
+                    self.statement(&format!("# Texture \"{}\" only exists in memory", name))?;
+                }
+                ResolvedStatement::MakeNamedMaterial { ref params } => {
+                    self.named_statement("MakeNamedMaterial", params)?;
+                }
+                ResolvedStatement::LightSource {
+                    ref params,
+                    ref transform,
+                    ref inside_medium,
+                    ref outside_medium,
+                } => {
+                    self.begin_block("AttributeBegin")?;
+                    self.transform("Transform", transform)?;
+                    self.medium_interface(inside_medium, outside_medium)?;
+                    self.named_statement("LightSource", params)?;
+                    self.end_block("AttributeEnd")?;
+                }
+                ResolvedStatement::Shape {
+                    ref params,
+                    ref transform,
+                    ref material,
+                    ref area_light,
+                    ref inside_medium,
+                    ref outside_medium,
+                    reverse_orientation,
+                } => {
+                    self.begin_block("AttributeBegin")?;
+                    self.transform_set(transform)?;
+                    self.medium_interface(inside_medium, outside_medium)?;
+                    let material_name: &str = if material.name == "" {
+                        "none"
+                    } else {
+                        &material.name
+                    };
+                    self.option_statement("Material", material_name, material)?;
+                    if let Some(ref area_light) = *area_light {
+                        self.named_statement("AreaLightSource", area_light)?;
+                    }
+                    if reverse_orientation {
+                        self.statement("ReverseOrientation")?;
+                    }
+                    self.shape(params)?;
+                    self.end_block("AttributeEnd")?;
+                }
+                ResolvedStatement::ObjectBegin { ref name } => {
+                    self.begin_block(&format!("ObjectBegin \"{}\"", name))?;
+                }
+                ResolvedStatement::ObjectEnd => self.end_block("ObjectEnd")?,
+                ResolvedStatement::ObjectInstance {
+                    ref name,
+                    ref transform,
+                } => {
+                    self.begin_block("AttributeBegin")?;
+                    self.transform_set(transform)?;
+                    self.statement(&format!("ObjectInstance \"{}\"", name))?;
+                    self.end_block("AttributeEnd")?;
+                }
+            }
+        }
+        self.statement("WorldEnd")?;
+        self.flush()
+    }
+    pub fn flush(&mut self) -> Result<(), ApiError> {
+        match self.out.flush() {
+            Ok(()) => Ok(()),
+            Err(e) => Err(ApiError::WriteFailed {
+                directive: String::from("WorldEnd"),
+                message: format!("{}", e),
+            }),
+        }
+    }
+    /// Writes the transformations at shutter open and close, or a
+    /// single one if they are the same.
+    fn transform_set(&mut self, transform: &TransformSet) -> Result<(), ApiError> {
+        if !transform.is_animated() {
+            return self.transform("Transform", &transform.t[0]);
+        }
+        self.statement("ActiveTransform StartTime")?;
+        self.transform("Transform", &transform.t[0])?;
+        self.statement("ActiveTransform EndTime")?;
+        self.transform("Transform", &transform.t[1])?;
+        self.statement("ActiveTransform All")
+    }
+    fn medium_interface(&mut self, inside: &str, outside: &str) -> Result<(), ApiError> {
+        if inside == "" && outside == "" {
+            return Ok(());
+        }
+        self.statement(&format!("MediumInterface \"{}\" \"{}\"", inside, outside))
+    }
+    /// Like **named_statement()**, but with the name given separately
+    /// (a "type" parameter, as used by named materials, is skipped).
+    fn option_statement(
+        &mut self,
+        directive: &str,
+        name: &str,
+        params: &ParamSet,
+    ) -> Result<(), ApiError> {
+        self.statement(&format!("{} \"{}\"", directive, name))?;
+        self.parameters(directive, params, &["type"])
+    }
+    /// Writes one parameter per line (see ParamSet::Print() in
+    /// paramset.cpp), except the ones listed in **skip**.
+    fn parameters(
+        &mut self,
+        directive: &str,
+        params: &ParamSet,
+        skip: &[&str],
+    ) -> Result<(), ApiError> {
+        let mut lines: Vec<String> = Vec::new();
+        parameter_lines(&params.ints, "integer", skip, &mut lines, |v| v.to_string());
+        parameter_lines(&params.bools, "bool", skip, &mut lines, |v| {
+            format!("\"{}\"", v)
+        });
+        parameter_lines(&params.floats, "float", skip, &mut lines, |v| v.to_string());
+        parameter_lines(&params.point2fs, "point2", skip, &mut lines, |v| {
+            format!("{} {}", v.x, v.y)
+        });
+        parameter_lines(&params.vector2fs, "vector2", skip, &mut lines, |v| {
+            format!("{} {}", v.x, v.y)
+        });
+        parameter_lines(&params.point3fs, "point", skip, &mut lines, |v| {
+            format!("{} {} {}", v.x, v.y, v.z)
+        });
+        parameter_lines(&params.vector3fs, "vector", skip, &mut lines, |v| {
+            format!("{} {} {}", v.x, v.y, v.z)
+        });
+        parameter_lines(&params.normals, "normal", skip, &mut lines, |v| {
+            format!("{} {} {}", v.x, v.y, v.z)
+        });
+        spectrum_lines(params, skip, &mut lines);
+        parameter_lines(&params.strings, "string", skip, &mut lines, |v| {
+            format!("\"{}\"", v)
+        });
+        parameter_lines(&params.textures, "texture", skip, &mut lines, |v| {
+            format!("\"{}\"", v)
+        });
+        let indent: usize = self.indent + 2;
+        for line in lines {
+            self.write_line(directive, indent, &line)?;
+        }
+        Ok(())
+    }
+    fn write_line(&mut self, directive: &str, indent: usize, line: &str) -> Result<(), ApiError> {
+        match write!(self.out, "{:indent$}{}\n", "", line, indent = indent) {
+            Ok(()) => Ok(()),
+            Err(e) => Err(ApiError::WriteFailed {
+                directive: String::from(directive),
+                message: format!("{}", e),
+            }),
+        }
+    }
+}
+
+/// Spectra are written the way they were given, so sampled and
+/// blackbody spectra don't lose their shape.
+fn spectrum_lines(params: &ParamSet, skip: &[&str], lines: &mut Vec<String>) {
+    for item in &params.spectra {
+        if skip.contains(&item.name.as_str()) {
+            continue;
+        }
+        let (param_type, values): (&str, Vec<String>) =
+            match params.spectrum_sources.get(&item.name) {
+                Some(&SpectrumSource::Sampled(ref values)) => {
+                    ("spectrum", values.iter().map(|v| v.to_string()).collect())
+                }
+                Some(&SpectrumSource::Files(ref names)) => (
+                    "spectrum",
+                    names.iter().map(|name| format!("\"{}\"", name)).collect(),
+                ),
+                Some(&SpectrumSource::Blackbody(ref values)) => {
+                    ("blackbody", values.iter().map(|v| v.to_string()).collect())
+                }
+                None => (
+                    "rgb",
+                    item.values
+                        .iter()
+                        .map(|v| {
+                            let mut rgb: [Float; 3] = [0.0 as Float; 3];
+                            v.to_rgb(&mut rgb);
+                            format!("{} {} {}", rgb[0], rgb[1], rgb[2])
+                        })
+                        .collect(),
+                ),
+            };
+        lines.push(format!(
+            "\"{} {}\" [ {} ]",
+            param_type,
+            item.name,
+            values.join(" ")
+        ));
+    }
+}
+
+fn parameter_lines<T, F>(
+    vec: &Vec<ParamSetItem<T>>,
+    param_type: &str,
+    skip: &[&str],
+    lines: &mut Vec<String>,
+    format_value: F,
+) where
+    F: Fn(&T) -> String,
+{
+    for item in vec {
+        if skip.contains(&item.name.as_str()) {
+            continue;
+        }
+        let values: Vec<String> = item.values.iter().map(|v| format_value(v)).collect();
+        lines.push(format!(
+            "\"{} {}\" [ {} ]",
+            param_type,
+            item.name,
+            values.join(" ")
+        ));
+    }
+}
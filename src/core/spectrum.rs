@@ -1576,11 +1576,10 @@ impl RGBSpectrum {
     pub fn from_sampled(lambda: &[Float], v: &[Float], n: i32) -> RGBSpectrum {
         // sort samples if unordered, use sorted for returned spectrum
         if !spectrum_samples_sorted(lambda, v, n) {
-            panic!("TODO: if !spectrum_samples_sorted(...) {...}");
-            // std::vector<Float> slambda(&lambda[0], &lambda[n]);
-            // std::vector<Float> sv(&v[0], &v[n]);
-            // SortSpectrumSamples(&slambda[0], &sv[0], n);
-            // return FromSampled(&slambda[0], &sv[0], n);
+            let mut slambda: Vec<Float> = lambda[..n as usize].to_vec();
+            let mut sv: Vec<Float> = v[..n as usize].to_vec();
+            sort_spectrum_samples(&mut slambda, &mut sv);
+            return RGBSpectrum::from_sampled(&slambda[..], &sv[..], n);
         }
         let mut xyz: [Float; 3] = [0.0 as Float; 3];
         for i in 0..N_CIE_SAMPLES {
@@ -1837,6 +1836,20 @@ pub fn spectrum_samples_sorted(lambda: &[Float], _vals: &[Float], n: i32) -> boo
     true
 }
 
+/// Sort the samples by wavelength (keeping each value with its
+/// wavelength).
+pub fn sort_spectrum_samples(lambda: &mut Vec<Float>, vals: &mut Vec<Float>) {
+    let mut sort_vec: Vec<(Float, Float)> = Vec::with_capacity(lambda.len());
+    for i in 0..lambda.len() {
+        sort_vec.push((lambda[i], vals[i]));
+    }
+    sort_vec.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    for i in 0..sort_vec.len() {
+        lambda[i] = sort_vec[i].0;
+        vals[i] = sort_vec[i].1;
+    }
+}
+
 /// Find responsible interval and linearly interpolate between the two
 /// sample values.
 pub fn interpolate_spectrum_samples(lambda: &[Float], vals: &[Float], n: i32, l: Float) -> Float {
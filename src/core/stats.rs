@@ -0,0 +1,367 @@
+//! Statistics about the scene and the render, printed after
+//! rendering. Each module declares the statistics it collects as
+//! statics, named "Category/Title":
+//!
+//! ```rust,ignore
+//! static N_CAMERA_RAYS: StatCounter = StatCounter::new("Integrator/Camera rays traced");
+//!
+//! N_CAMERA_RAYS.inc();
+//! ```
+//!
+//! The values get accumulated per thread (without any locking) and
+//! merged into the statistics report when a thread finishes, or when
+//! **report_thread_stats()** gets called, e.g. by threads of a thread
+//! pool.
+
+// std
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+// see stats.h
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum StatKind {
+    Counter,
+    MemoryCounter,
+    IntDistribution,
+    FloatDistribution,
+    Percent,
+    Ratio,
+}
+
+/// Accumulated value of one statistic. Counters only use *value*,
+/// distributions count the reported values in *value*, and percents
+/// and ratios use *value* as numerator.
+#[derive(Debug, Copy, Clone)]
+struct StatValue {
+    value: i64,
+    denominator: i64,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for StatValue {
+    fn default() -> Self {
+        StatValue {
+            value: 0_i64,
+            denominator: 0_i64,
+            sum: 0.0_f64,
+            min: std::f64::INFINITY,
+            max: std::f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl StatValue {
+    fn merge(&mut self, other: &StatValue) {
+        self.value += other.value;
+        self.denominator += other.denominator;
+        self.sum += other.sum;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// All statistics registered so far, with the values of all threads
+/// which reported theirs.
+struct StatsAccumulator {
+    stats: Vec<(&'static str, StatKind)>,
+    values: Vec<StatValue>,
+}
+
+lazy_static! {
+    static ref STATS: Mutex<StatsAccumulator> = Mutex::new(StatsAccumulator {
+        stats: Vec::new(),
+        values: Vec::new(),
+    });
+}
+
+/// Values collected by the current thread, indexed by statistic.
+struct ThreadStats {
+    values: Vec<StatValue>,
+}
+
+impl ThreadStats {
+    fn report(&mut self) {
+        if self.values.is_empty() {
+            return;
+        }
+        let mut stats = STATS.lock().unwrap();
+        for (i, value) in self.values.iter().enumerate() {
+            stats.values[i].merge(value);
+        }
+        self.values.clear();
+    }
+}
+
+impl Drop for ThreadStats {
+    fn drop(&mut self) {
+        self.report();
+    }
+}
+
+thread_local! {
+    static THREAD_STATS: RefCell<ThreadStats> = RefCell::new(ThreadStats { values: Vec::new() });
+}
+
+/// Registration of a statistic, which happens on first use.
+struct Stat {
+    title: &'static str,
+    kind: StatKind,
+    // index + 1 within the accumulator, 0 if not registered yet
+    id: AtomicUsize,
+}
+
+impl Stat {
+    const fn new(title: &'static str, kind: StatKind) -> Self {
+        Stat {
+            title: title,
+            kind: kind,
+            id: AtomicUsize::new(0),
+        }
+    }
+    fn index(&self) -> usize {
+        let id: usize = self.id.load(Ordering::Relaxed);
+        if id != 0 {
+            return id - 1;
+        }
+        let mut stats = STATS.lock().unwrap();
+        // another thread might have been faster
+        let id: usize = self.id.load(Ordering::Relaxed);
+        if id != 0 {
+            return id - 1;
+        }
+        stats.stats.push((self.title, self.kind));
+        stats.values.push(StatValue::default());
+        let index: usize = stats.stats.len() - 1;
+        self.id.store(index + 1, Ordering::Relaxed);
+        index
+    }
+    fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut StatValue),
+    {
+        let index: usize = self.index();
+        // ignore values reported while the thread shuts down
+        let _ = THREAD_STATS.try_with(|thread_stats| {
+            let mut thread_stats = thread_stats.borrow_mut();
+            if thread_stats.values.len() <= index {
+                thread_stats.values.resize(index + 1, StatValue::default());
+            }
+            f(&mut thread_stats.values[index]);
+        });
+    }
+}
+
+/// Counts events (see STAT_COUNTER in stats.h).
+pub struct StatCounter(Stat);
+
+impl StatCounter {
+    pub const fn new(title: &'static str) -> Self {
+        StatCounter(Stat::new(title, StatKind::Counter))
+    }
+    pub fn inc(&self) {
+        self.add(1);
+    }
+    pub fn add(&self, n: i64) {
+        self.0.update(|v| v.value += n);
+    }
+}
+
+/// Counts bytes of memory (see STAT_MEMORY_COUNTER in stats.h).
+pub struct StatMemoryCounter(Stat);
+
+impl StatMemoryCounter {
+    pub const fn new(title: &'static str) -> Self {
+        StatMemoryCounter(Stat::new(title, StatKind::MemoryCounter))
+    }
+    pub fn add(&self, bytes: usize) {
+        self.0.update(|v| v.value += bytes as i64);
+    }
+}
+
+/// Average and range of integer values (see STAT_INT_DISTRIBUTION in
+/// stats.h).
+pub struct StatIntDistribution(Stat);
+
+impl StatIntDistribution {
+    pub const fn new(title: &'static str) -> Self {
+        StatIntDistribution(Stat::new(title, StatKind::IntDistribution))
+    }
+    pub fn report_value(&self, value: i64) {
+        self.0.update(|v| {
+            v.value += 1;
+            v.sum += value as f64;
+            v.min = v.min.min(value as f64);
+            v.max = v.max.max(value as f64);
+        });
+    }
+}
+
+/// Average and range of floating-point values (see
+/// STAT_FLOAT_DISTRIBUTION in stats.h).
+pub struct StatFloatDistribution(Stat);
+
+impl StatFloatDistribution {
+    pub const fn new(title: &'static str) -> Self {
+        StatFloatDistribution(Stat::new(title, StatKind::FloatDistribution))
+    }
+    pub fn report_value(&self, value: f64) {
+        self.0.update(|v| {
+            v.value += 1;
+            v.sum += value;
+            v.min = v.min.min(value);
+            v.max = v.max.max(value);
+        });
+    }
+}
+
+/// How often something happened, e.g. hits per intersection test
+/// (see STAT_PERCENT in stats.h).
+pub struct StatPercent(Stat);
+
+impl StatPercent {
+    pub const fn new(title: &'static str) -> Self {
+        StatPercent(Stat::new(title, StatKind::Percent))
+    }
+    pub fn add(&self, numerator: i64, denominator: i64) {
+        self.0.update(|v| {
+            v.value += numerator;
+            v.denominator += denominator;
+        });
+    }
+}
+
+/// Ratio of two counts, e.g. lookups per distribution (see STAT_RATIO
+/// in stats.h).
+pub struct StatRatio(Stat);
+
+impl StatRatio {
+    pub const fn new(title: &'static str) -> Self {
+        StatRatio(Stat::new(title, StatKind::Ratio))
+    }
+    pub fn add(&self, numerator: i64, denominator: i64) {
+        self.0.update(|v| {
+            v.value += numerator;
+            v.denominator += denominator;
+        });
+    }
+}
+
+/// Merges the values collected by the current thread into the
+/// statistics report. Threads do that when they finish, but threads
+/// of a pool (like rayon's) have to call it explicitly.
+pub fn report_thread_stats() {
+    let _ = THREAD_STATS.try_with(|thread_stats| thread_stats.borrow_mut().report());
+}
+
+/// Prints all statistics with a value, sorted by category and title
+/// (see PrintStats() in stats.cpp).
+pub fn print_stats() {
+    report_thread_stats();
+    let stats = STATS.lock().unwrap();
+    let mut categories: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for (&(title, kind), value) in stats.stats.iter().zip(stats.values.iter()) {
+        let (category, title) = match title.find('/') {
+            Some(i) => (&title[..i], &title[i + 1..]),
+            None => ("", title),
+        };
+        let line: String = match kind {
+            StatKind::Counter => {
+                if value.value == 0 {
+                    continue;
+                }
+                format!("{:<42}               {:>12}", title, value.value)
+            }
+            StatKind::MemoryCounter => {
+                if value.value == 0 {
+                    continue;
+                }
+                let kib: f64 = value.value as f64 / 1024.0;
+                if kib < 1024.0 {
+                    format!("{:<42}                  {:9.2} kB", title, kib)
+                } else if kib < 1024.0 * 1024.0 {
+                    format!("{:<42}                  {:9.2} MiB", title, kib / 1024.0)
+                } else {
+                    format!(
+                        "{:<42}                  {:9.2} GiB",
+                        title,
+                        kib / (1024.0 * 1024.0)
+                    )
+                }
+            }
+            StatKind::IntDistribution => {
+                if value.value == 0 {
+                    continue;
+                }
+                format!(
+                    "{:<42}                      {:.3} avg [range {} - {}]",
+                    title,
+                    value.sum / value.value as f64,
+                    value.min as i64,
+                    value.max as i64
+                )
+            }
+            StatKind::FloatDistribution => {
+                if value.value == 0 {
+                    continue;
+                }
+                format!(
+                    "{:<42}                      {:.3} avg [range {} - {}]",
+                    title,
+                    value.sum / value.value as f64,
+                    value.min,
+                    value.max
+                )
+            }
+            StatKind::Percent => {
+                if value.denominator == 0 {
+                    continue;
+                }
+                format!(
+                    "{:<42}{:>12} / {:>12} ({:.2}%)",
+                    title,
+                    value.value,
+                    value.denominator,
+                    100.0 * value.value as f64 / value.denominator as f64
+                )
+            }
+            StatKind::Ratio => {
+                if value.denominator == 0 {
+                    continue;
+                }
+                format!(
+                    "{:<42}{:>12} / {:>12} ({:.2}x)",
+                    title,
+                    value.value,
+                    value.denominator,
+                    value.value as f64 / value.denominator as f64
+                )
+            }
+        };
+        categories
+            .entry(category)
+            .or_insert_with(Vec::new)
+            .push(line);
+    }
+    println!("Statistics:");
+    for (category, lines) in categories.iter_mut() {
+        lines.sort();
+        println!("  {}", category);
+        for line in lines.iter() {
+            println!("    {}", line);
+        }
+    }
+}
+
+/// Resets all statistics, e.g. before rendering the next scene.
+pub fn clear_stats() {
+    report_thread_stats();
+    let mut stats = STATS.lock().unwrap();
+    for value in stats.values.iter_mut() {
+        *value = StatValue::default();
+    }
+}
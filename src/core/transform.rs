@@ -838,7 +838,10 @@ impl Transform {
             medium: r.medium.clone(),
         }
     }
-    pub fn transform_surface_interaction(&self, si: &SurfaceInteraction) -> SurfaceInteraction {
+    pub fn transform_surface_interaction<'p, 's>(
+        &self,
+        si: &SurfaceInteraction<'p, 's>,
+    ) -> SurfaceInteraction<'p, 's> {
         let mut ret: SurfaceInteraction = SurfaceInteraction::default();
         // transform _p_ and _pError_ in _SurfaceInteraction_
         ret.p = self.transform_point_with_abs_error(&si.p, &si.p_error, &mut ret.p_error);
@@ -846,8 +849,9 @@ impl Transform {
         ret.n = self.transform_normal(&si.n).normalize();
         ret.wo = self.transform_vector(&si.wo).normalize();
         ret.time = si.time;
+        ret.medium_interface = si.medium_interface.clone();
         ret.uv = si.uv;
-        ret.shape = None; // TODO? si.shape;
+        ret.shape = si.shape;
         ret.dpdu = self.transform_vector(&si.dpdu);
         ret.dpdv = self.transform_vector(&si.dpdv);
         ret.dndu = self.transform_normal(&si.dndu);
@@ -865,7 +869,7 @@ impl Transform {
         ret.dpdy = self.transform_vector(&si.dpdy);
         ret.bsdf = si.bsdf.clone();
         ret.bssrdf = si.bssrdf.clone();
-        ret.primitive = None; // TODO? si.primitive;
+        ret.primitive = si.primitive;
         ret.shading.n = nrm_faceforward_nrm(&ret.shading.n, &ret.n);
         // TODO: ret.faceIndex = si.faceIndex;
         ret
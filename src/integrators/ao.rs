@@ -5,6 +5,7 @@ use core::integrator::SamplerIntegrator;
 use core::interaction::Interaction;
 use core::material::TransportMode;
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::sampler::Sampler;
 use core::sampling::{
     cosine_hemisphere_pdf, cosine_sample_hemisphere, uniform_hemisphere_pdf,
@@ -51,7 +52,7 @@ impl SamplerIntegrator for AOIntegrator {
         // arena: &mut Arena,
         _depth: i32,
     ) -> Spectrum {
-        // TODO: ProfilePhase p(Prof::SamplerIntegratorLi);
+        let _p = ProfilePhase::new(Prof::SamplerIntegratorLi);
         let mut l: Spectrum = Spectrum::default();
         let mut ray: Ray = Ray {
             o: r.o,
@@ -1,15 +1,16 @@
 extern crate crossbeam;
 extern crate num_cpus;
-extern crate pbr;
 
 // std
 use std;
 use std::f32::consts::PI;
 use std::sync::mpsc;
 use std::sync::Arc;
+use std::time::Instant;
 // pbrt
 use blockqueue::BlockQueue;
 use core::camera::{Camera, CameraSample};
+use core::checkpoint::Checkpoint;
 use core::geometry::{
     nrm_abs_dot_vec3, pnt2_inside_exclusive, pnt3_offset_ray_origin, vec3_abs_dot_nrm,
     vec3_dot_nrm,
@@ -25,13 +26,19 @@ use core::material::TransportMode;
 use core::medium::{Medium, MediumInterface, PhaseFunction};
 use core::pbrt::{Float, Spectrum};
 use core::primitive::Primitive;
+use core::profiler::{Prof, ProfilePhase};
+use core::progressreporter::ProgressReporter;
 use core::reflection::BxdfType;
 use core::sampler::Sampler;
 use core::sampling::Distribution1D;
 use core::scene::Scene;
+use core::stats::{StatIntDistribution, StatPercent};
 
 // see bdpt.h
 
+static ZERO_RADIANCE_PATHS: StatPercent = StatPercent::new("Integrator/Zero-radiance paths");
+static PATH_LENGTH: StatIntDistribution = StatIntDistribution::new("Integrator/Path length");
+
 #[derive(Default)]
 pub struct EndpointInteraction<'a> {
     // Interaction Public Data
@@ -860,7 +867,7 @@ pub fn generate_camera_subpath<'a>(
     if max_depth == 0 {
         return (0_usize, Point3f::default(), Float::default());
     }
-    // TODO: ProfilePhase _(Prof::BDPTGenerateSubpath);
+    let _p = ProfilePhase::new(Prof::BDPTGenerateSubpath);
     // sample initial ray for camera subpath
     let mut camera_sample: CameraSample = CameraSample::default();
     camera_sample.p_film = *p_film;
@@ -907,7 +914,7 @@ pub fn generate_light_subpath<'a>(
     if max_depth == 0_u32 {
         return 0_usize;
     }
-    // TODO: ProfilePhase _(Prof::BDPTGenerateSubpath);
+    let _p = ProfilePhase::new(Prof::BDPTGenerateSubpath);
     // sample initial ray for light subpath
     let mut light_pdf: Option<Float> = Some(0.0 as Float);
     let light_num: usize = light_distr.sample_discrete(sampler.get_1d(), light_pdf.as_mut());
@@ -1894,7 +1901,7 @@ pub fn connect_bdpt<'a>(
     p_raster: &mut Point2f,
     mis_weight_opt: Option<&mut Float>,
 ) -> Spectrum {
-    // TODO: ProfilePhase _(Prof::BDPTConnectSubpaths);
+    let _p = ProfilePhase::new(Prof::BDPTConnectSubpaths);
     let mut l: Spectrum = Spectrum::default();
     // ignore invalid connections related to infinite area lights
     if t > 1 && s != 0 && camera_vertices[t - 1].vertex_type == VertexType::Light {
@@ -2117,10 +2124,8 @@ pub fn connect_bdpt<'a>(
             }
         }
     }
-    // TODO:
-    // ++totalPaths;
-    // if (L.IsBlack()) ++zeroRadiancePaths;
-    // ReportValue(pathLength, s + t - 2);
+    ZERO_RADIANCE_PATHS.add(if l.is_black() { 1 } else { 0 }, 1);
+    PATH_LENGTH.report_value((s + t) as i64 - 2);
 
     // compute MIS weight for connection strategy
     let mut mis_weight_flt: Float = 0.0 as Float;
@@ -2172,7 +2177,9 @@ pub fn infinite_light_density<'a>(
 }
 
 /// **Main function** to **render** a scene multi-threaded (using all
-/// available cores) with **bidirectional** path tracing.
+/// available cores) with **bidirectional** path tracing. The
+/// *checkpoint* allows to continue an interrupted render (see
+/// **core::checkpoint**).
 ///
 /// ![bdpt](/doc/img/uml_pbrt_rust_render_bdpt.png)
 pub fn render_bdpt(
@@ -2181,6 +2188,7 @@ pub fn render_bdpt(
     sampler: &mut Box<Sampler + Send + Sync>,
     integrator: &mut Box<BDPTIntegrator>,
     num_threads: u8,
+    checkpoint: &Checkpoint,
 ) {
     // TODO
     // Compute a reverse mapping from light pointers to offsets into
@@ -2199,7 +2207,10 @@ pub fn render_bdpt(
     let tile_size: i32 = 16;
     let n_x_tiles: i32 = (sample_extent.x + tile_size - 1) / tile_size;
     let n_y_tiles: i32 = (sample_extent.y + tile_size - 1) / tile_size;
-    // TODO: ProgressReporter reporter(nXTiles * nYTiles, "Rendering");
+    let n_tiles: Point2i = Point2i {
+        x: n_x_tiles,
+        y: n_y_tiles,
+    };
     // TODO: Allocate buffers for debug visualization
     // ...
     // render and write the output image to disk
@@ -2213,7 +2224,7 @@ pub fn render_bdpt(
         }
         println!("Rendering with {:?} thread(s) ...", num_cores);
         {
-            let block_queue = BlockQueue::new(
+            let mut block_queue = BlockQueue::new(
                 (
                     (n_x_tiles * tile_size) as u32,
                     (n_y_tiles * tile_size) as u32,
@@ -2221,6 +2232,11 @@ pub fn render_bdpt(
                 (tile_size as u32, tile_size as u32),
                 (0, 0),
             );
+            let mut finished: Vec<(u32, u32)> =
+                checkpoint.resume(&film, &n_tiles, samples_per_pixel);
+            block_queue.remove_blocks(&finished);
+            let mut reporter: ProgressReporter =
+                ProgressReporter::new(block_queue.len() as i64, "Rendering");
             let integrator = &integrator;
             let bq = &block_queue;
             let sampler = sampler;
@@ -2234,6 +2250,7 @@ pub fn render_bdpt(
                     let pixel_tx = pixel_tx.clone();
                     let mut tile_sampler: Box<Sampler + Send + Sync> = sampler.box_clone();
                     scope.spawn(move |_| {
+                        let _p = ProfilePhase::new(Prof::IntegratorRender);
                         while let Some((x, y)) = bq.next() {
                             let tile: Point2i = Point2i {
                                 x: x as i32,
@@ -2249,6 +2266,10 @@ pub fn render_bdpt(
                                 Bounds2i::new(Point2i { x: x0, y: y0 }, Point2i { x: x1, y: y1 });
                             // println!("Starting image tile {:?}", tile_bounds);
                             let mut film_tile = film.get_film_tile(&tile_bounds);
+                            // splats get added when the tile gets
+                            // merged, so a checkpoint never contains
+                            // parts of unfinished tiles
+                            let mut splats: Vec<(Point2f, Spectrum)> = Vec::new();
                             for p_pixel in &tile_bounds {
                                 tile_sampler.start_pixel(&p_pixel);
                                 if !pnt2_inside_exclusive(&p_pixel, &integrator.pixel_bounds) {
@@ -2366,7 +2387,7 @@ pub fn render_bdpt(
                                                     l += lpath;
                                                 } else {
                                                     if !lpath.is_black() {
-                                                        film.add_splat(&p_film_new, &lpath);
+                                                        splats.push((p_film_new, lpath));
                                                     }
                                                 }
                                             }
@@ -2384,22 +2405,41 @@ pub fn render_bdpt(
                             }
                             // send the tile through the channel to main thread
                             pixel_tx
-                                .send(film_tile)
+                                .send(((x, y), film_tile, splats))
                                 .expect(&format!("Failed to send tile"));
                         }
                     });
                 }
                 // spawn thread to collect pixels and render image to file
+                let finished = &mut finished;
+                let reporter = &reporter;
                 scope.spawn(move |_| {
-                    for _ in pbr::PbIter::new(0..bq.len()) {
-                        let film_tile = pixel_rx.recv().unwrap();
+                    let mut last_checkpoint: Instant = Instant::now();
+                    for _ in 0..bq.len() {
+                        let (tile, film_tile, splats) = pixel_rx.recv().unwrap();
                         // merge image tile into _Film_
                         film.merge_film_tile(&film_tile);
+                        for (p_film, l) in splats {
+                            film.add_splat(&p_film, &l);
+                        }
+                        finished.push(tile);
+                        if checkpoint.is_due(&last_checkpoint) {
+                            checkpoint.write(film, &n_tiles, samples_per_pixel, finished);
+                            last_checkpoint = Instant::now();
+                        }
+                        reporter.update(1);
                     }
                 });
             }).unwrap();
+            reporter.done();
+        }
+        match film.write_image(1.0 as Float / samples_per_pixel as Float) {
+            Ok(()) => checkpoint.remove(),
+            Err(e) => {
+                println!("ERROR: Unable to write image \"{}\": {}", film.filename, e);
+                checkpoint.keep();
+            }
         }
-        film.write_image(1.0 as Float / samples_per_pixel as Float);
         // TODO: Write buffers for debug visualization
     }
 }
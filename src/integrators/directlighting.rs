@@ -1,4 +1,5 @@
 // pbrt
+use core::aov::AovSample;
 use core::geometry::{vec3_abs_dot_nrm, vec3_dot_nrm};
 use core::geometry::{Bounds2i, Normal3f, Ray, RayDifferential, Vector3f};
 use core::integrator::SamplerIntegrator;
@@ -6,6 +7,7 @@ use core::integrator::{uniform_sample_all_lights, uniform_sample_one_light};
 use core::interaction::{Interaction, SurfaceInteraction};
 use core::material::TransportMode;
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::reflection::BxdfType;
 use core::sampler::Sampler;
 use core::scene::Scene;
@@ -37,6 +39,73 @@ impl DirectLightingIntegrator {
             n_light_samples: Vec::new(),
         }
     }
+    /// Direct lighting, which keeps track of the AOVs if asked for.
+    fn li_with_aov(
+        &self,
+        ray: &mut Ray,
+        scene: &Scene,
+        sampler: &mut Box<Sampler + Send + Sync>,
+        // arena: &mut Arena,
+        depth: i32,
+        mut aov: Option<&mut AovSample>,
+    ) -> Spectrum {
+        let _p = ProfilePhase::new(Prof::SamplerIntegratorLi);
+        let mut l: Spectrum = Spectrum::new(0.0 as Float);
+        // for the AOVs: light emitted by and arriving directly at the
+        // intersection, everything else got reflected specularly
+        let l_emitted: Spectrum;
+        let l_direct: Spectrum;
+        // find closest ray intersection or return background radiance
+        if let Some(mut isect) = scene.intersect(ray) {
+            // compute scattering functions for surface interaction
+            let mode: TransportMode = TransportMode::Radiance;
+            isect.compute_scattering_functions(ray /* arena, */, false, mode);
+            // if (!isect.bsdf)
+            //     return Li(isect.SpawnRay(ray.d), scene, sampler, arena, depth);
+            if let Some(ref mut aov) = aov {
+                aov.record_hit(&ray.o, &isect);
+            }
+            let wo: Vector3f = isect.wo;
+            l += isect.le(&wo);
+            l_emitted = l;
+            if scene.lights.len() > 0 {
+                // compute direct lighting for _DirectLightingIntegrator_ integrator
+                if self.strategy == LightStrategy::UniformSampleAll {
+                    l += uniform_sample_all_lights(
+                        &isect,
+                        scene,
+                        sampler,
+                        &self.n_light_samples,
+                        false,
+                    );
+                } else {
+                    l += uniform_sample_one_light(&isect, scene, sampler, false, None);
+                }
+            }
+            l_direct = l;
+            if ((depth + 1_i32) as i64) < self.max_depth {
+                // trace rays for specular reflection and refraction
+                l += self.specular_reflect(
+                    ray, &isect, scene, sampler, // arena,
+                    depth,
+                );
+                l += self.specular_transmit(
+                    ray, &isect, scene, sampler, // arena,
+                    depth,
+                );
+            }
+        } else {
+            for light in &scene.lights {
+                l += light.le(ray);
+            }
+            l_emitted = l;
+            l_direct = l;
+        }
+        if let Some(aov) = aov {
+            aov.record_radiance(&l, &l_emitted, &l_direct, true);
+        }
+        l
+    }
     pub fn specular_reflect(
         &self,
         ray: &Ray,
@@ -194,48 +263,17 @@ impl SamplerIntegrator for DirectLightingIntegrator {
         // arena: &mut Arena,
         depth: i32,
     ) -> Spectrum {
-        // TODO: ProfilePhase p(Prof::SamplerIntegratorLi);
-        let mut l: Spectrum = Spectrum::new(0.0 as Float);
-        // find closest ray intersection or return background radiance
-        if let Some(mut isect) = scene.intersect(ray) {
-            // compute scattering functions for surface interaction
-            let mode: TransportMode = TransportMode::Radiance;
-            isect.compute_scattering_functions(ray /* arena, */, false, mode);
-            // if (!isect.bsdf)
-            //     return Li(isect.SpawnRay(ray.d), scene, sampler, arena, depth);
-            let wo: Vector3f = isect.wo;
-            l += isect.le(&wo);
-            if scene.lights.len() > 0 {
-                // compute direct lighting for _DirectLightingIntegrator_ integrator
-                if self.strategy == LightStrategy::UniformSampleAll {
-                    l += uniform_sample_all_lights(
-                        &isect,
-                        scene,
-                        sampler,
-                        &self.n_light_samples,
-                        false,
-                    );
-                } else {
-                    l += uniform_sample_one_light(&isect, scene, sampler, false, None);
-                }
-            }
-            if ((depth + 1_i32) as i64) < self.max_depth {
-                // trace rays for specular reflection and refraction
-                l += self.specular_reflect(
-                    ray, &isect, scene, sampler, // arena,
-                    depth,
-                );
-                l += self.specular_transmit(
-                    ray, &isect, scene, sampler, // arena,
-                    depth,
-                );
-            }
-        } else {
-            for light in &scene.lights {
-                l += light.le(ray);
-            }
-        }
-        l
+        self.li_with_aov(ray, scene, sampler, depth, None)
+    }
+    fn li_aov(
+        &self,
+        ray: &mut Ray,
+        scene: &Scene,
+        sampler: &mut Box<Sampler + Send + Sync>,
+        depth: i32,
+        aov: &mut AovSample,
+    ) -> Spectrum {
+        self.li_with_aov(ray, scene, sampler, depth, Some(aov))
     }
     fn get_pixel_bounds(&self) -> Bounds2i {
         self.pixel_bounds
@@ -1,11 +1,8 @@
 extern crate crossbeam;
 extern crate num_cpus;
-extern crate pbr;
 
 // std
-use std::sync::mpsc;
 use std::sync::Arc;
-use std::thread;
 // pbrt
 use core::camera::Camera;
 use core::film::Film;
@@ -14,10 +11,13 @@ use core::integrator::compute_light_power_distribution;
 use core::pbrt::erf_inv;
 use core::pbrt::SQRT_2;
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
+use core::progressreporter::ProgressReporter;
 use core::rng::Rng;
 use core::sampler::Sampler;
 use core::sampling::Distribution1D;
 use core::scene::Scene;
+use core::stats::{report_thread_stats, StatPercent};
 use integrators::bdpt::Vertex;
 use integrators::bdpt::{connect_bdpt, generate_camera_subpath, generate_light_subpath};
 // others
@@ -28,6 +28,8 @@ pub const LIGHT_STREAM_INDEX: u8 = 1;
 pub const CONNECTION_STREAM_INDEX: u8 = 2;
 pub const N_SAMPLE_STREAMS: u8 = 3;
 
+static ACCEPTANCE_RATE: StatPercent = StatPercent::new("Integrator/Acceptance rate");
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct PrimarySample {
     pub value: Float,
@@ -179,7 +181,7 @@ impl Sampler for MLTSampler {
         self.array_2d_offset = 0_usize;
     }
     fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _p = ProfilePhase::new(Prof::GetSample);
         let index: i32 = self.get_next_index();
         self.ensure_ready(index);
         self.x[index as usize].value
@@ -403,24 +405,24 @@ pub fn render_mlt(
         num_cores = num_threads as usize;
     }
     if let Some(light_distr) = compute_light_power_distribution(scene) {
-        println!("Generating bootstrap paths ...");
         // generate bootstrap samples and compute normalization constant $b$
         let n_bootstrap_samples: u32 = integrator.n_bootstrap * (integrator.max_depth + 1);
         let mut bootstrap_weights: Vec<Float> = vec![0.0 as Float; n_bootstrap_samples as usize];
         if scene.lights.len() > 0 {
-            // TODO: ProgressReporter progress(nBootstrap / 256, "Generating bootstrap paths");
+            let mut progress: ProgressReporter =
+                ProgressReporter::new(n_bootstrap_samples as i64, "Generating bootstrap paths");
             // let chunk_size: u32 = clamp_t(integrator.n_bootstrap / 128, 1, 8192);
             let chunk_size: usize = (n_bootstrap_samples / num_cores as u32) as usize;
             {
                 let bands: Vec<&mut [Float]> = bootstrap_weights.chunks_mut(chunk_size).collect();
                 let integrator = &integrator;
                 let light_distr = &light_distr;
+                let progress = &progress;
                 crossbeam::scope(|scope| {
-                    let (band_tx, band_rx) = mpsc::channel();
                     // spawn worker threads
                     for (b, band) in bands.into_iter().enumerate() {
-                        let band_tx = band_tx.clone();
                         scope.spawn(move |_| {
+                            let _p = ProfilePhase::new(Prof::IntegratorRender);
                             for (w, weight) in band.into_iter().enumerate() {
                                 let rng_index: u64 = ((b * chunk_size) + w) as u64;
                                 let depth: u32 =
@@ -436,21 +438,14 @@ pub fn render_mlt(
                                 *weight = integrator
                                     .l(scene, &light_distr, &mut sampler, depth, &mut p_raster)
                                     .y();
+                                progress.update(1);
                             }
                         });
-                        // send progress through the channel to main thread
-                        band_tx.send(b).expect(&format!("Failed to send progress"));
                     }
-                    // spawn thread to report progress
-                    scope.spawn(move |_| {
-                        for _ in pbr::PbIter::new(0..num_cores) {
-                            band_rx.recv().unwrap();
-                        }
-                    });
                 }).unwrap();
             }
+            progress.done();
         }
-        println!("Rendering ...");
         let bootstrap: Distribution1D = Distribution1D::new(bootstrap_weights);
         let b: Float = bootstrap.func_int * (integrator.max_depth + 1) as Float;
         // run _n_chains_ Markov chains in parallel
@@ -458,22 +453,18 @@ pub fn render_mlt(
         let n_total_mutations: u64 =
             integrator.mutations_per_pixel as u64 * film.get_sample_bounds().area() as u64;
         if scene.lights.len() > 0 {
-            // TODO: let progress_frequency = 32768;
-            // TODO: ProgressReporter progress(nTotalMutations / progressFrequency,
-            //                           "Rendering");
-            // use parallel iterator (par_iter_with) from rayon crate
-            let (sender, receiver) = mpsc::channel();
+            let progress_frequency: u64 = 32768;
+            let mut progress: ProgressReporter = ProgressReporter::new(
+                (n_total_mutations / progress_frequency) as i64,
+                "Rendering",
+            );
+            // use parallel iterator (par_iter) from rayon crate
             let n_chains = integrator.n_chains;
-            // spawn thread to report progress
-            let finish = thread::spawn(move || {
-                for _ in pbr::PbIter::new(0..n_chains) {
-                    receiver.recv().unwrap();
-                }
-            });
             // for i in 0..n_chains {
             let ivec: Vec<u32> = (0..n_chains).collect();
-            ivec.par_iter().for_each_with(sender, |s, &i| {
-                s.send(i).expect(&format!("Failed to send chain"));
+            let progress_ref = &progress;
+            ivec.par_iter().for_each(|&i| {
+                let _p = ProfilePhase::new(Prof::IntegratorRender);
                 let n_chain_mutations: u64 = ((i as u64 + 1) * n_total_mutations / n_chains as u64)
                     .min(n_total_mutations)
                     - i as u64 * n_total_mutations / n_chains as u64;
@@ -494,7 +485,7 @@ pub fn render_mlt(
                 let mut l_current: Spectrum =
                     integrator.l(scene, &light_distr, &mut sampler, depth, &mut p_current);
                 // run the Markov chain for _n_chain_mutations_ steps
-                for _j in 0..n_chain_mutations {
+                for j in 0..n_chain_mutations {
                     sampler.start_iteration();
                     let mut p_proposed: Point2f = Point2f::default();
                     let l_proposed: Spectrum =
@@ -514,20 +505,26 @@ pub fn render_mlt(
                         p_current = p_proposed;
                         l_current = l_proposed;
                         sampler.accept();
-                    // TODO: ++acceptedMutations;
+                        ACCEPTANCE_RATE.add(1, 0);
                     } else {
                         sampler.reject();
                     }
-                    // TODO: ++totalMutations;
-                    // if (i * n_total_mutations / n_chains + j) % progress_frequency == 0 {
-                    //     progress.update();
-                    // }
+                    ACCEPTANCE_RATE.add(0, 1);
+                    if (i as u64 * n_total_mutations / n_chains as u64 + j) % progress_frequency
+                        == 0
+                    {
+                        progress_ref.update(1);
+                    }
                     // TODO: arena.Reset();
                 }
+                // rayon's threads keep running
+                report_thread_stats();
             });
-            finish.join().unwrap();
+            progress.done();
         }
         // Store final image computed with MLT
-        film.write_image(b / integrator.mutations_per_pixel as Float);
+        if let Err(e) = film.write_image(b / integrator.mutations_per_pixel as Float) {
+            println!("ERROR: Unable to write image \"{}\": {}", film.filename, e);
+        }
     }
 }
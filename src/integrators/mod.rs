@@ -60,20 +60,25 @@
 
 extern crate crossbeam;
 extern crate num_cpus;
-extern crate pbr;
 // std
 use std;
 use std::sync::mpsc;
 use std::sync::Arc;
+use std::time::Instant;
 // pbrt
 use blockqueue::BlockQueue;
+use core::aov::AovSample;
 use core::camera::{Camera, CameraSample};
+use core::checkpoint::Checkpoint;
 use core::geometry::pnt2_inside_exclusive;
 use core::geometry::{Bounds2i, Point2i, Ray, Vector2i};
 use core::integrator::SamplerIntegrator;
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
+use core::progressreporter::ProgressReporter;
 use core::sampler::Sampler;
 use core::scene::Scene;
+use core::stats::StatCounter;
 
 pub mod ao;
 pub mod bdpt;
@@ -82,14 +87,18 @@ pub mod mlt;
 pub mod path;
 pub mod sppm;
 
+static N_CAMERA_RAYS: StatCounter = StatCounter::new("Integrator/Camera rays traced");
+
 /// **Main function** to **render** a scene mutli-threaded (using all
-/// available cores).
+/// available cores). The *checkpoint* allows to continue an
+/// interrupted render (see **core::checkpoint**).
 pub fn render(
     scene: &Scene,
     camera: &Arc<Camera + Send + Sync>,
     sampler: &mut Box<Sampler + Send + Sync>,
     integrator: &mut Box<SamplerIntegrator + Send + Sync>,
     num_threads: u8,
+    checkpoint: &Checkpoint,
 ) {
     // SamplerIntegrator::Render (integrator.cpp)
     let film = camera.get_film();
@@ -101,7 +110,6 @@ pub fn render(
     let x: i32 = (sample_extent.x + tile_size - 1) / tile_size;
     let y: i32 = (sample_extent.y + tile_size - 1) / tile_size;
     let n_tiles: Point2i = Point2i { x: x, y: y };
-    // TODO: ProgressReporter reporter(nTiles.x * nTiles.y, "Rendering");
     let num_cores: usize;
     if num_threads == 0_u8 {
         num_cores = num_cpus::get();
@@ -110,7 +118,7 @@ pub fn render(
     }
     println!("Rendering with {:?} thread(s) ...", num_cores);
     {
-        let block_queue = BlockQueue::new(
+        let mut block_queue = BlockQueue::new(
             (
                 (n_tiles.x * tile_size) as u32,
                 (n_tiles.y * tile_size) as u32,
@@ -118,12 +126,18 @@ pub fn render(
             (tile_size as u32, tile_size as u32),
             (0, 0),
         );
+        let samples_per_pixel: i64 = sampler.get_samples_per_pixel();
+        let mut finished: Vec<(u32, u32)> = checkpoint.resume(&film, &n_tiles, samples_per_pixel);
+        block_queue.remove_blocks(&finished);
+        let mut reporter: ProgressReporter =
+            ProgressReporter::new(block_queue.len() as i64, "Rendering");
         let integrator = &integrator;
         let bq = &block_queue;
         let sampler = sampler;
         let camera = &camera;
         let film = &film;
         let pixel_bounds = integrator.get_pixel_bounds().clone();
+        let record_aovs: bool = !film.aovs.is_empty();
         crossbeam::scope(|scope| {
             let (pixel_tx, pixel_rx) = mpsc::channel();
             // spawn worker threads
@@ -131,6 +145,7 @@ pub fn render(
                 let pixel_tx = pixel_tx.clone();
                 let mut tile_sampler: Box<Sampler + Send + Sync> = sampler.box_clone();
                 scope.spawn(move |_| {
+                    let _p = ProfilePhase::new(Prof::IntegratorRender);
                     while let Some((x, y)) = bq.next() {
                         let tile: Point2i = Point2i {
                             x: x as i32,
@@ -167,17 +182,28 @@ pub fn render(
                                     1.0 as Float
                                         / (tile_sampler.get_samples_per_pixel() as Float).sqrt(),
                                 );
-                                // TODO: ++nCameraRays;
+                                N_CAMERA_RAYS.inc();
                                 // evaluate radiance along camera ray
                                 let mut l: Spectrum = Spectrum::new(0.0 as Float);
                                 let y: Float = l.y();
+                                let mut aov: AovSample = AovSample::default();
                                 if ray_weight > 0.0 {
-                                    l = integrator.li(
-                                        &mut ray,
-                                        scene,
-                                        &mut tile_sampler, // &mut arena,
-                                        0_i32,
-                                    );
+                                    if record_aovs {
+                                        l = integrator.li_aov(
+                                            &mut ray,
+                                            scene,
+                                            &mut tile_sampler,
+                                            0_i32,
+                                            &mut aov,
+                                        );
+                                    } else {
+                                        l = integrator.li(
+                                            &mut ray,
+                                            scene,
+                                            &mut tile_sampler, // &mut arena,
+                                            0_i32,
+                                        );
+                                    }
                                 }
                                 if l.has_nans() {
                                     println!(
@@ -212,26 +238,45 @@ pub fn render(
                                 //          camera_sample, ray, l);
                                 // add camera ray's contribution to image
                                 film_tile.add_sample(&camera_sample.p_film, &mut l, ray_weight);
+                                if record_aovs {
+                                    film_tile.add_aov_sample(&camera_sample.p_film, &aov);
+                                }
                                 done = !tile_sampler.start_next_sample();
                             } // arena is dropped here !
                         }
                         // send the tile through the channel to main thread
                         pixel_tx
-                            .send(film_tile)
+                            .send(((x, y), film_tile))
                             .expect(&format!("Failed to send tile"));
                     }
                 });
             }
             // spawn thread to collect pixels and render image to file
+            let finished = &mut finished;
+            let reporter = &reporter;
             scope.spawn(move |_| {
-                for _ in pbr::PbIter::new(0..bq.len()) {
-                    let film_tile = pixel_rx.recv().unwrap();
+                let mut last_checkpoint: Instant = Instant::now();
+                for _ in 0..bq.len() {
+                    let (tile, film_tile) = pixel_rx.recv().unwrap();
                     // merge image tile into _Film_
                     film.merge_film_tile(&film_tile);
+                    finished.push(tile);
+                    if checkpoint.is_due(&last_checkpoint) {
+                        checkpoint.write(film, &n_tiles, samples_per_pixel, finished);
+                        last_checkpoint = Instant::now();
+                    }
+                    reporter.update(1);
                 }
             });
         })
         .unwrap();
+        reporter.done();
+    }
+    match film.write_image(1.0 as Float) {
+        Ok(()) => checkpoint.remove(),
+        Err(e) => {
+            println!("ERROR: Unable to write image \"{}\": {}", film.filename, e);
+            checkpoint.keep();
+        }
     }
-    film.write_image(1.0 as Float);
 }
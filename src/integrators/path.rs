@@ -2,6 +2,7 @@
 use std::borrow::Borrow;
 use std::sync::Arc;
 // pbrt
+use core::aov::AovSample;
 use core::bssrdf::Bssrdf;
 use core::geometry::{vec3_abs_dot_nrm, vec3_dot_nrm};
 use core::geometry::{Bounds2i, Point2f, Ray, Vector3f};
@@ -12,13 +13,18 @@ use core::lightdistrib::create_light_sample_distribution;
 use core::lightdistrib::LightDistribution;
 use core::material::TransportMode;
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::reflection::BxdfType;
 use core::sampler::Sampler;
 use core::sampling::Distribution1D;
 use core::scene::Scene;
+use core::stats::{StatIntDistribution, StatPercent};
 
 // see path.h
 
+static ZERO_RADIANCE_PATHS: StatPercent = StatPercent::new("Integrator/Zero-radiance paths");
+static PATH_LENGTH: StatIntDistribution = StatIntDistribution::new("Integrator/Path length");
+
 /// Path Tracing (Global Illumination)
 pub struct PathIntegrator {
     // inherited from SamplerIntegrator (see integrator.h)
@@ -48,23 +54,21 @@ impl PathIntegrator {
             light_distribution: None,
         }
     }
-}
-
-impl SamplerIntegrator for PathIntegrator {
-    fn preprocess(&mut self, scene: &Scene, _sampler: &mut Box<Sampler + Send + Sync>) {
-        self.light_distribution =
-            create_light_sample_distribution(self.light_sample_strategy.clone(), scene);
-    }
-    fn li(
+    /// Path tracing, which keeps track of the AOVs if asked for.
+    fn li_with_aov(
         &self,
         r: &mut Ray,
         scene: &Scene,
         sampler: &mut Box<Sampler + Send + Sync>,
-        // arena: &mut Arena,
-        _depth: i32,
+        mut aov: Option<&mut AovSample>,
     ) -> Spectrum {
-        // TODO: ProfilePhase p(Prof::SamplerIntegratorLi);
+        let _p = ProfilePhase::new(Prof::SamplerIntegratorLi);
         let mut l: Spectrum = Spectrum::default();
+        // for the AOVs: light emitted by and arriving directly at the
+        // first intersection, and the first sampled BSDF lobe
+        let mut l_emitted: Spectrum = Spectrum::default();
+        let mut l_direct: Option<Spectrum> = None;
+        let mut first_specular: bool = false;
         let mut beta: Spectrum = Spectrum::new(1.0 as Float);
         let mut ray: Ray = Ray {
             o: r.o,
@@ -97,6 +101,9 @@ impl SamplerIntegrator for PathIntegrator {
                     l += beta * isect.le(&-ray.d);
                     // println!("Added Le -> L = {:?}", l);
                 }
+                if bounces == 0 {
+                    l_emitted = l;
+                }
                 // terminate path if _maxDepth_ was reached
                 if bounces >= self.max_depth {
                     break;
@@ -112,6 +119,11 @@ impl SamplerIntegrator for PathIntegrator {
                     // bounces--;
                     continue;
                 }
+                if bounces == 0 {
+                    if let Some(ref mut aov) = aov {
+                        aov.record_hit(&r.o, &isect);
+                    }
+                }
                 if let Some(ref light_distribution) = self.light_distribution {
                     let distrib: Arc<Distribution1D> = light_distribution.lookup(&isect.p);
                     // Sample illumination from lights to find path contribution.
@@ -119,7 +131,6 @@ impl SamplerIntegrator for PathIntegrator {
                     let bsdf_flags: u8 = BxdfType::BsdfAll as u8 & !(BxdfType::BsdfSpecular as u8);
                     if let Some(ref bsdf) = isect.bsdf {
                         if bsdf.num_components(bsdf_flags) > 0 {
-                            // TODO: ++total_paths;
                             let ld: Spectrum = beta
                                 * uniform_sample_one_light(
                                     &isect,
@@ -129,12 +140,13 @@ impl SamplerIntegrator for PathIntegrator {
                                     Some(Arc::borrow(&distrib)),
                                 );
                             // TODO: println!("Sampled direct lighting Ld = {:?}", ld);
-                            // TODO: if ld.is_black() {
-                            //     ++zero_radiance_paths;
-                            // }
+                            ZERO_RADIANCE_PATHS.add(if ld.is_black() { 1 } else { 0 }, 1);
                             assert!(ld.y() >= 0.0 as Float, "ld = {:?}", ld);
                             l += ld;
                         }
+                        if bounces == 0 {
+                            l_direct = Some(l);
+                        }
                         // Sample BSDF to get new path direction
                         let wo: Vector3f = -ray.d;
                         let mut wi: Vector3f = Vector3f::default();
@@ -168,6 +180,9 @@ impl SamplerIntegrator for PathIntegrator {
                             pdf
                         );
                         specular_bounce = (sampled_type & BxdfType::BsdfSpecular as u8) != 0_u8;
+                        if bounces == 0 {
+                            first_specular = specular_bounce;
+                        }
                         if ((sampled_type & BxdfType::BsdfSpecular as u8) != 0_u8)
                             && ((sampled_type & BxdfType::BsdfTransmission as u8) != 0_u8)
                         {
@@ -274,13 +289,47 @@ impl SamplerIntegrator for PathIntegrator {
                     }
                     // println!("Added infinite area lights -> L = {:?}", l);
                 }
+                if bounces == 0 {
+                    l_emitted = l;
+                }
                 // terminate path if ray escaped
                 break;
             }
             bounces += 1_u32;
         }
+        PATH_LENGTH.report_value(bounces as i64);
+        if let Some(aov) = aov {
+            aov.record_radiance(&l, &l_emitted, &l_direct.unwrap_or(l), first_specular);
+        }
         l
     }
+}
+
+impl SamplerIntegrator for PathIntegrator {
+    fn preprocess(&mut self, scene: &Scene, _sampler: &mut Box<Sampler + Send + Sync>) {
+        self.light_distribution =
+            create_light_sample_distribution(self.light_sample_strategy.clone(), scene);
+    }
+    fn li(
+        &self,
+        r: &mut Ray,
+        scene: &Scene,
+        sampler: &mut Box<Sampler + Send + Sync>,
+        // arena: &mut Arena,
+        _depth: i32,
+    ) -> Spectrum {
+        self.li_with_aov(r, scene, sampler, None)
+    }
+    fn li_aov(
+        &self,
+        r: &mut Ray,
+        scene: &Scene,
+        sampler: &mut Box<Sampler + Send + Sync>,
+        _depth: i32,
+        aov: &mut AovSample,
+    ) -> Spectrum {
+        self.li_with_aov(r, scene, sampler, Some(aov))
+    }
     fn get_pixel_bounds(&self) -> Bounds2i {
         self.pixel_bounds
     }
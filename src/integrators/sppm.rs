@@ -1,7 +1,6 @@
 extern crate atom;
 extern crate crossbeam;
 extern crate num_cpus;
-extern crate pbr;
 
 // std
 use std;
@@ -29,11 +28,23 @@ use core::material::TransportMode;
 use core::parallel::AtomicFloat;
 use core::pbrt::{clamp_t, lerp};
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{current_profiler_state, Prof, ProfilePhase};
+use core::progressreporter::ProgressReporter;
 use core::reflection::{Bsdf, BxdfType};
 use core::sampler::{GlobalSampler, Sampler};
 use core::scene::Scene;
+use core::stats::{StatCounter, StatIntDistribution, StatMemoryCounter, StatRatio};
 use samplers::halton::HaltonSampler;
 
+static VISIBLE_POINTS_CHECKED: StatRatio = StatRatio::new(
+    "Stochastic Progressive Photon Mapping/Visible points checked per photon intersection",
+);
+static PHOTON_PATHS: StatCounter =
+    StatCounter::new("Stochastic Progressive Photon Mapping/Photon paths followed");
+static GRID_CELLS_PER_VISIBLE_POINT: StatIntDistribution =
+    StatIntDistribution::new("Stochastic Progressive Photon Mapping/Grid cells per visible point");
+static SPPM_PIXEL_MEMORY: StatMemoryCounter = StatMemoryCounter::new("Memory/SPPM Pixels");
+
 /// Stochastic Progressive Photon Mapping
 pub struct SPPMIntegrator {
     pub initial_search_radius: Float,
@@ -137,7 +148,7 @@ pub fn render_sppm(
         num_cores = num_threads as usize;
     }
     println!("Rendering with {:?} thread(s) ...", num_cores);
-    // TODO: ProfilePhase p(Prof::IntegratorRender);
+    let _p = ProfilePhase::new(Prof::IntegratorRender);
 
     // initialize _pixel_bounds_ and _pixels_ array for SPPM
     let film: Arc<Film> = camera.get_film();
@@ -150,7 +161,7 @@ pub fn render_sppm(
         pixels.push(pixel);
     }
     let inv_sqrt_spp: Float = 1.0 as Float / (integrator.n_iterations as Float).sqrt();
-    // TODO: let pixel_memory_bytes: usize = n_pixels as usize * std::mem::size_of::<SPPMPixel>();
+    SPPM_PIXEL_MEMORY.add(n_pixels as usize * std::mem::size_of::<SPPMPixel>());
 
     // compute _light_distr_ for sampling lights proportional to power
     if let Some(light_distr) = compute_light_power_distribution(scene) {
@@ -167,11 +178,12 @@ pub fn render_sppm(
             x: (pixel_extent.x + tile_size - 1) / tile_size,
             y: (pixel_extent.y + tile_size - 1) / tile_size,
         };
-        // TODO: ProgressReporter progress(2 * nIterations, "Rendering");
-        for iteration in pbr::PbIter::new(0..integrator.n_iterations) {
+        let mut progress: ProgressReporter =
+            ProgressReporter::new(2 * integrator.n_iterations as i64, "Rendering");
+        for iteration in 0..integrator.n_iterations {
             // generate SPPM visible points
             {
-                // TODO: ProfilePhase _(Prof::SPPMCameraPass);
+                let _p = ProfilePhase::new(Prof::SPPMCameraPass);
                 // println!("Generate SPPM visible points ...");
                 {
                     let block_queue = BlockQueue::new(
@@ -186,12 +198,14 @@ pub fn render_sppm(
                     let bq = &block_queue;
                     let sampler = &sampler;
                     let pixels = &mut pixels;
+                    let profiler_state: u64 = current_profiler_state();
                     crossbeam::scope(|scope| {
                         let (pixel_tx, pixel_rx) = mpsc::channel();
                         // spawn worker threads
                         for _ in 0..num_cores {
                             let pixel_tx = pixel_tx.clone();
                             scope.spawn(move |_| {
+                                let _p = ProfilePhase::resume(profiler_state);
                                 while let Some((x, y)) = bq.next() {
                                     let tile: Point2i = Point2i {
                                         x: x as i32,
@@ -249,7 +263,6 @@ pub fn render_sppm(
                                         );
                                         let mut specular_bounce: bool = false;
                                         for depth in 0..integrator.max_depth {
-                                            // TODO: ++totalPhotonSurfaceInteractions;
                                             if let Some(mut isect) = scene.intersect(&mut ray) {
                                                 // process SPPM camera ray intersection
 
@@ -386,7 +399,7 @@ pub fn render_sppm(
                 grid_once.push(AtomSetOnce::empty());
             }
             {
-                // TODO: ProfilePhase _(Prof::SPPMGridConstruction);
+                let _p = ProfilePhase::new(Prof::SPPMGridConstruction);
 
                 // compute grid bounds for SPPM visible points
                 let mut max_radius: Float = 0.0 as Float;
@@ -421,12 +434,14 @@ pub fn render_sppm(
                 {
                     let bands: Vec<&mut [SPPMPixel]> = pixels.chunks_mut(chunk_size).collect();
                     let grid = &grid;
+                    let profiler_state: u64 = current_profiler_state();
                     crossbeam::scope(|scope| {
                         let (band_tx, band_rx) = mpsc::channel();
                         // spawn worker threads
                         for (b, band) in bands.into_iter().enumerate() {
                             let band_tx = band_tx.clone();
                             scope.spawn(move |_| {
+                                let _p = ProfilePhase::resume(profiler_state);
                                 for pixel in band.into_iter() {
                                     // for pixel_index in 0..n_pixels as usize {
                                     // let pixel = &pixels[pixel_index];
@@ -474,9 +489,12 @@ pub fn render_sppm(
                                                 }
                                             }
                                         }
-                                        // ReportValue(grid_cells_per_visible_point,
-                                        //             (1 + pMax.x - pMin.x) * (1 + pMax.y - pMin.y) *
-                                        //                 (1 + pMax.z - pMin.z));
+                                        GRID_CELLS_PER_VISIBLE_POINT.report_value(
+                                            ((1 + p_max.x - p_min.x)
+                                                * (1 + p_max.y - p_min.y)
+                                                * (1 + p_max.z - p_min.z))
+                                                as i64,
+                                        );
                                     }
                                 }
                             });
@@ -493,6 +511,7 @@ pub fn render_sppm(
                     .unwrap();
                 }
             }
+            progress.update(1);
             // trace photons and accumulate contributions
             for h in 0..hash_size {
                 // take
@@ -503,7 +522,7 @@ pub fn render_sppm(
             }
             std::mem::drop(grid);
             {
-                // TODO: ProfilePhase _(Prof::SPPMPhotonPass);
+                let _p = ProfilePhase::new(Prof::SPPMPhotonPass);
                 // println!("Trace photons and accumulate contributions ...");
                 let chunk_size: usize =
                     (integrator.photons_per_iteration / num_cores as i32) as usize;
@@ -513,12 +532,14 @@ pub fn render_sppm(
                     let grid_once = &grid_once;
                     let integrator = &integrator;
                     let light_distr = &light_distr;
+                    let profiler_state: u64 = current_profiler_state();
                     crossbeam::scope(|scope| {
                         let (band_tx, band_rx) = mpsc::channel();
                         // spawn worker threads
                         for (b, band) in bands.into_iter().enumerate() {
                             let band_tx = band_tx.clone();
                             scope.spawn(move |_| {
+                                let _p = ProfilePhase::resume(profiler_state);
                                 for photon_index in band.into_iter() {
                                     // for photon_index in 0..integrator.photons_per_iteration as usize {
                                     // MemoryArena &arena = photonShootArenas[ThreadIndex];
@@ -596,11 +617,12 @@ pub fn render_sppm(
                                             break;
                                         }
                                         // follow photon path through scene and record intersections
+                                        PHOTON_PATHS.inc();
                                         for depth in 0..integrator.max_depth {
                                             if let Some(mut isect) =
                                                 scene.intersect(&mut photon_ray)
                                             {
-                                                // TODO: ++totalPhotonSurfaceInteractions;
+                                                VISIBLE_POINTS_CHECKED.add(0, 1);
                                                 if depth > 0 {
                                                     // add photon contribution to nearby visible points
                                                     let mut photon_grid_index: Point3i =
@@ -627,6 +649,8 @@ pub fn render_sppm(
                                                             loop {
                                                                 // deal with linked list
                                                                 if let Some(node) = opt {
+                                                                    VISIBLE_POINTS_CHECKED
+                                                                        .add(1, 0);
                                                                     let pixel = node.pixel;
                                                                     let radius: Float =
                                                                         pixel.radius;
@@ -764,17 +788,19 @@ pub fn render_sppm(
             }
             // update pixel values from this pass's photons
             {
-                // TODO: ProfilePhase _(Prof::SPPMStatsUpdate);
+                let _p = ProfilePhase::new(Prof::SPPMStatsUpdate);
                 // println!("Update pixel values from this pass's photons ...");
                 let chunk_size: usize = (n_pixels / num_cores as i32) as usize;
                 {
                     let bands: Vec<&mut [SPPMPixel]> = pixels.chunks_mut(chunk_size).collect();
+                    let profiler_state: u64 = current_profiler_state();
                     crossbeam::scope(|scope| {
                         let (band_tx, band_rx) = mpsc::channel();
                         // spawn worker threads
                         for (b, band) in bands.into_iter().enumerate() {
                             let band_tx = band_tx.clone();
                             scope.spawn(move |_| {
+                                let _p = ProfilePhase::resume(profiler_state);
                                 for p in band.into_iter() {
                                     // let mut p = &mut pixels[i];
                                     let p_m = p.m.load(atomic::Ordering::Relaxed);
@@ -815,6 +841,7 @@ pub fn render_sppm(
                     .unwrap();
                 }
             }
+            progress.update(1);
             // periodically store SPPM image in film and write image
             if iteration + 1 == integrator.n_iterations
                 || ((iteration + 1) % integrator.write_frequency) == 0
@@ -835,7 +862,9 @@ pub fn render_sppm(
                     }
                 }
                 film.set_image(&image[..]);
-                film.write_image(1.0 as Float);
+                if let Err(e) = film.write_image(1.0 as Float) {
+                    println!("ERROR: Unable to write image \"{}\": {}", film.filename, e);
+                }
                 // TODO: write SPPM radius image, if requested
                 // if (getenv("SPPM_RADIUS")) {
                 //     std::unique_ptr<Float[]> rimg(
@@ -871,6 +900,6 @@ pub fn render_sppm(
                 // }
             }
         }
-        // TODO: progress.Done();
+        progress.done();
     }
 }
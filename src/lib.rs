@@ -25,7 +25,10 @@
 extern crate hexf;
 extern crate atom;
 extern crate atomic;
+extern crate base64;
 extern crate byteorder;
+extern crate flate2;
+extern crate gltf;
 #[cfg(feature = "openexr")]
 extern crate half;
 extern crate image;
@@ -34,6 +37,9 @@ extern crate lazy_static;
 extern crate num;
 #[cfg(feature = "openexr")]
 extern crate openexr;
+extern crate pest;
+#[macro_use]
+extern crate pest_derive;
 extern crate ply_rs;
 extern crate rayon;
 extern crate time;
@@ -48,6 +54,7 @@ pub mod integrators;
 pub mod lights;
 pub mod materials;
 pub mod media;
+pub mod parser;
 pub mod samplers;
 pub mod shapes;
 pub mod textures;
@@ -9,6 +9,7 @@ use core::interaction::{Interaction, InteractionCommon};
 use core::light::{AreaLight, Light, LightFlags, VisibilityTester};
 use core::medium::{Medium, MediumInterface};
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::rng::FLOAT_ONE_MINUS_EPSILON;
 use core::sampling::{cosine_hemisphere_pdf, cosine_sample_hemisphere};
 use core::scene::Scene;
@@ -75,7 +76,7 @@ impl Light for DiffuseAreaLight {
         pdf: &mut Float,
         vis: &mut VisibilityTester,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _p = ProfilePhase::new(Prof::LightSample);
         let p_shape: InteractionCommon = self.shape.sample_with_ref_point(&iref, &*u, pdf);
         // TODO: iref.mediumInterface = mediumInterface;
         if *pdf == 0.0 as Float || (p_shape.p - iref.p).length_squared() == 0.0 as Float {
@@ -119,7 +120,7 @@ impl Light for DiffuseAreaLight {
         Spectrum::default()
     }
     fn pdf_li(&self, iref: &Interaction, wi: Vector3f) -> Float {
-        // TODO: ProfilePhase _(Prof::LightPdf);
+        let _p = ProfilePhase::new(Prof::LightPdf);
         self.shape.pdf_with_ref_point(iref, &wi)
     }
     fn sample_le(
@@ -132,7 +133,7 @@ impl Light for DiffuseAreaLight {
         pdf_pos: &mut Float,
         pdf_dir: &mut Float,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _p = ProfilePhase::new(Prof::LightSample);
 
         // sample a point on the area light's _Shape_, _p_shape_
         let ic: InteractionCommon = self.shape.sample(u1, pdf_pos);
@@ -9,6 +9,7 @@ use core::interaction::{Interaction, InteractionCommon};
 use core::light::{Light, LightFlags, VisibilityTester};
 use core::medium::MediumInterface;
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::sampling::concentric_sample_disk;
 use core::scene::Scene;
 use core::transform::Transform;
@@ -54,7 +55,7 @@ impl Light for DistantLight {
         pdf: &mut Float,
         vis: &mut VisibilityTester,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _p = ProfilePhase::new(Prof::LightSample);
         *wi = self.w_light;
         *pdf = 1.0 as Float;
         let p_outside: Point3f =
@@ -117,7 +118,7 @@ impl Light for DistantLight {
         pdf_pos: &mut Float,
         pdf_dir: &mut Float,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _p = ProfilePhase::new(Prof::LightSample);
 
         // choose point on disk oriented toward infinite light direction
         let mut v1: Vector3f = Vector3f::default();
@@ -19,6 +19,7 @@ use core::medium::MediumInterface;
 use core::mipmap::{ImageWrap, MipMap};
 use core::pbrt::{Float, Spectrum};
 use core::pbrt::{INV_2_PI, INV_PI};
+use core::profiler::{Prof, ProfilePhase};
 use core::sampling::concentric_sample_disk;
 use core::sampling::Distribution2D;
 use core::scene::Scene;
@@ -304,7 +305,7 @@ impl Light for InfiniteAreaLight {
         pdf: &mut Float,
         vis: &mut VisibilityTester,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _p = ProfilePhase::new(Prof::LightSample);
         // find $(u,v)$ sample coordinates in infinite light texture
         let mut map_pdf: Float = 0.0 as Float;
         let uv: Point2f = self.distribution.sample_continuous(&u, &mut map_pdf);
@@ -394,7 +395,7 @@ impl Light for InfiniteAreaLight {
         self.lmap.lookup_pnt_flt(&st, 0.0 as Float)
     }
     fn pdf_li(&self, _iref: &Interaction, w: Vector3f) -> Float {
-        // TODO: ProfilePhase _(Prof::LightPdf);
+        let _p = ProfilePhase::new(Prof::LightPdf);
         let wi: Vector3f = self.world_to_light.transform_vector(&w);
         let theta: Float = spherical_theta(&wi);
         let phi: Float = spherical_phi(&wi);
@@ -418,7 +419,7 @@ impl Light for InfiniteAreaLight {
         pdf_pos: &mut Float,
         pdf_dir: &mut Float,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _p = ProfilePhase::new(Prof::LightSample);
 
         // find $(u,v)$ sample coordinates in infinite light texture
         let mut map_pdf: Float = 0.0 as Float;
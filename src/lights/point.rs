@@ -9,6 +9,7 @@ use core::interaction::{Interaction, InteractionCommon};
 use core::light::{Light, LightFlags, VisibilityTester};
 use core::medium::{Medium, MediumInterface};
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::sampling::{uniform_sample_sphere, uniform_sphere_pdf};
 use core::scene::Scene;
 use core::transform::Transform;
@@ -62,7 +63,7 @@ impl Light for PointLight {
         pdf: &mut Float,
         vis: &mut VisibilityTester,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _p = ProfilePhase::new(Prof::LightSample);
         *wi = (self.p_light - iref.p).normalize();
         *pdf = 1.0 as Float;
         *vis = VisibilityTester {
@@ -107,7 +108,7 @@ impl Light for PointLight {
         pdf_pos: &mut Float,
         pdf_dir: &mut Float,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _p = ProfilePhase::new(Prof::LightSample);
         *ray = Ray {
             o: self.p_light,
             d: uniform_sample_sphere(u1),
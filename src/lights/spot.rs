@@ -10,6 +10,7 @@ use core::light::{Light, LightFlags, VisibilityTester};
 use core::medium::{Medium, MediumInterface};
 use core::pbrt::radians;
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::reflection::cos_theta;
 use core::sampling::{uniform_cone_pdf, uniform_sample_cone};
 use core::scene::Scene;
@@ -87,7 +88,7 @@ impl Light for SpotLight {
         pdf: &mut Float,
         vis: &mut VisibilityTester,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _p = ProfilePhase::new(Prof::LightSample);
         *wi = (self.p_light - iref.p).normalize();
         *pdf = 1.0 as Float;
         // medium_interface1
@@ -159,7 +160,7 @@ impl Light for SpotLight {
         pdf_pos: &mut Float,
         pdf_dir: &mut Float,
     ) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::LightSample);
+        let _p = ProfilePhase::new(Prof::LightSample);
         let w: Vector3f = uniform_sample_cone(u1, self.cos_total_width);
         let mut inside: Option<Arc<Medium + Send + Sync>> = None;
         if let Some(ref mi_inside) = self.medium_interface.inside {
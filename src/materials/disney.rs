@@ -33,6 +33,7 @@ pub struct DisneyMaterial {
     flatness: Arc<Texture<Float> + Send + Sync>,
     diff_trans: Arc<Texture<Float> + Send + Sync>,
     bump_map: Option<Arc<Texture<Float> + Send + Sync>>,
+    normal_map: Option<Arc<Texture<Spectrum> + Send + Sync>>,
     thin: bool,
 }
 
@@ -54,6 +55,7 @@ impl DisneyMaterial {
         let flatness = mp.get_float_texture("flatness", 0.0);
         let diff_trans = mp.get_float_texture("difftrans", 1.0);
         let bump_map = mp.get_float_texture_or_null("bumpmap");
+        let normal_map = mp.get_spectrum_texture_or_null("normalmap");
 
         Arc::new(DisneyMaterial {
             color,
@@ -71,6 +73,7 @@ impl DisneyMaterial {
             flatness,
             diff_trans,
             bump_map,
+            normal_map,
             thin,
         })
     }
@@ -84,7 +87,9 @@ impl Material for DisneyMaterial {
         _allow_multiple_lobes: bool,
         _material: Option<Arc<Material + Send + Sync>>,
     ) {
-        if let Some(ref bump) = self.bump_map {
+        if let Some(ref normal_map) = self.normal_map {
+            Self::normal_map(normal_map, si);
+        } else if let Some(ref bump) = self.bump_map {
             Self::bump(bump, si);
         }
 
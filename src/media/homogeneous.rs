@@ -6,6 +6,7 @@ use core::geometry::Ray;
 use core::interaction::MediumInteraction;
 use core::medium::{HenyeyGreenstein, Medium};
 use core::pbrt::{Float, Spectrum};
+use core::profiler::{Prof, ProfilePhase};
 use core::sampler::Sampler;
 
 // see homogeneous.h
@@ -30,11 +31,11 @@ impl HomogeneousMedium {
 
 impl Medium for HomogeneousMedium {
     fn tr(&self, ray: &Ray, _sampler: &mut Sampler) -> Spectrum {
-        // TODO: ProfilePhase _(Prof::MediumTr);
+        let _p = ProfilePhase::new(Prof::MediumTr);
         (-self.sigma_t * (ray.t_max * ray.d.length()).min(f32::MAX)).exp()
     }
     fn sample(&self, ray: &Ray, sampler: &mut Sampler) -> (Spectrum, Option<MediumInteraction>) {
-        // TODO: ProfilePhase _(Prof::MediumSample);
+        let _p = ProfilePhase::new(Prof::MediumSample);
         // sample a channel and distance along the ray
         let channel: usize = ((sampler.get_1d() * 3.0 as Float) as usize).min(2_usize);
         let dist: Float = -((1.0 as Float - sampler.get_1d()).ln()) / self.sigma_t[channel];
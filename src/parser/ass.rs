@@ -0,0 +1,1288 @@
+//! Imports scenes in Arnold's scene source format (**.ass**), as
+//! defined by the [pest][pest] grammar in `ass.pest`, by translating
+//! the Arnold nodes into calls of the *pbrt_* functions of the
+//! **core::api** module.
+//!
+//! All nodes are read first, because they can refer to each other
+//! by name regardless of their order. The render options come from
+//! the `options` node, the camera it refers to, and the filter used
+//! by the first output. The world gets filled with materials (and
+//! the textures they need), lights, and shapes (in that order).
+//! Supported nodes are:
+//!
+//! * cameras: `persp_camera`, `ortho_camera`, `spherical_camera`
+//! * filters: `box_filter`, `gaussian_filter`, `triangle_filter`
+//! * shaders: `standard_surface` (as `disney`), `lambert` (as
+//!   `matte`), and `image` (as `imagemap` texture)
+//! * lights: `point_light`, `spot_light`, `distant_light`,
+//!   `quad_light`, `skydome_light`, and `mesh_light`
+//! * shapes: `polymesh` (with per face shaders, normals, and uv
+//!   coordinates), `sphere`, `disk`, `cylinder`, and `ginstance`
+//!
+//! Other nodes are ignored, unless a supported node refers to them,
+//! which gets reported like any unsupported type of the scene
+//! description (see **ErrorMode**). Like for **parse_file()** in the
+//! **parser** module, rendering is left to the caller:
+//!
+//! ```rust,no_run
+//! use pbrt::core::api::{pbrt_cleanup, pbrt_init};
+//! use pbrt::parser::ass::parse_file;
+//! use std::path::Path;
+//!
+//! let (mut api_state, mut bsdf_state) = pbrt_init(0_u8);
+//! match parse_file(Path::new("scene.ass"), &mut api_state, &mut bsdf_state) {
+//!     Ok(()) => pbrt_cleanup(&mut api_state).unwrap(),
+//!     Err(e) => println!("{}", e),
+//! }
+//! ```
+//!
+//! [pest]: https://pest.rs
+
+// std
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+// others
+use pest::iterators::Pair;
+use pest::Parser;
+// pbrt
+use core::api::{pbrt_area_light_source, pbrt_attribute_begin, pbrt_attribute_end, pbrt_camera};
+use core::api::{pbrt_film, pbrt_integrator, pbrt_light_source, pbrt_make_named_material};
+use core::api::{pbrt_material, pbrt_named_material, pbrt_object_begin, pbrt_object_end};
+use core::api::{pbrt_object_instance, pbrt_pixel_filter, pbrt_sampler, pbrt_shape};
+use core::api::{pbrt_texture, pbrt_transform, pbrt_world_begin};
+use core::api::{ApiError, ApiState, BsdfState};
+use core::fileutil::open_file;
+use core::geometry::Vector3f;
+use core::paramset::ParamSet;
+use core::pbrt::{Float, Spectrum};
+use core::transform::Transform;
+use parser::{frame, Chunk, ParseError, STRING_INPUT};
+
+#[derive(Parser)]
+#[grammar = "parser/ass.pest"]
+struct AssParser;
+
+/// Arrays are written as `<name> <num_elements> <num_motionblur_keys>
+/// <type>` followed by the values (e.g. `vlist 8 1 VECTOR ...`), each
+/// element of the type taking that many values.
+const ARRAY_TYPES: [(&str, usize); 14] = [
+    ("BOOL", 1),
+    ("BYTE", 1),
+    ("FLOAT", 1),
+    ("INT", 1),
+    ("MATRIX", 16),
+    ("NODE", 1),
+    ("POINT", 3),
+    ("POINT2", 2),
+    ("RGB", 3),
+    ("RGBA", 4),
+    ("STRING", 1),
+    ("UINT", 1),
+    ("VECTOR", 3),
+    ("VECTOR2", 2),
+];
+
+/// A parameter with its values as found in the file (strings without
+/// quotes). Arrays keep the values of all motion keys.
+struct Parameter {
+    name: String,
+    values: Vec<String>,
+    n_keys: usize,
+    line: usize,
+    column: usize,
+}
+
+struct Node {
+    node_type: String,
+    name: String,
+    parameters: Vec<Parameter>,
+    line: usize,
+    column: usize,
+}
+
+impl Node {
+    /// If a parameter is given several times, the last one counts.
+    fn find(&self, name: &str) -> Option<&Parameter> {
+        self.parameters.iter().rev().find(|p| p.name == name)
+    }
+}
+
+/// Keeps track of what was handed over to the API already.
+struct ApiCalls<'a> {
+    api_state: &'a mut ApiState,
+    bsdf_state: &'a mut BsdfState,
+    float_textures: HashSet<String>,
+    spectrum_textures: HashSet<String>,
+    materials: HashSet<String>,
+}
+
+/// All nodes of a file, which can be looked up by name.
+struct AssScene<'a> {
+    file: &'a str,
+    nodes: Vec<Node>,
+    names: HashMap<String, usize>,
+    /// **mesh_light** nodes by the name of the mesh emitting light
+    mesh_lights: HashMap<String, usize>,
+}
+
+/// Reads the Arnold scene from the file found at `path` and feeds it
+/// into `api_state`. The directory containing the file becomes the
+/// search directory for other files (e.g. textures) referenced by the
+/// scene.
+pub fn parse_file(
+    path: &Path,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let file: String = path.display().to_string();
+    let absolute_path: PathBuf = if path.is_relative() {
+        match env::current_dir() {
+            Ok(cp) => cp.join(path),
+            Err(e) => return Err(ParseError::new(&file, 0, 0, e.to_string())),
+        }
+    } else {
+        PathBuf::from(path)
+    };
+    if let Some(search_directory) = absolute_path.parent() {
+        api_state.search_directory = Some(Box::new(PathBuf::from(search_directory)));
+    }
+    let mut reader = match open_file(path) {
+        Ok(reader) => reader,
+        Err(e) => return Err(ParseError::new(&file, 0, 0, e.to_string())),
+    };
+    let mut source: String = String::new();
+    if let Err(e) = reader.read_to_string(&mut source) {
+        return Err(ParseError::new(&file, 0, 0, e.to_string()));
+    }
+    import(&source, &file, api_state, bsdf_state)
+}
+
+/// Reads the Arnold scene from a string and feeds it into
+/// `api_state`.
+pub fn parse_str(
+    source: &str,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    import(source, STRING_INPUT, api_state, bsdf_state)
+}
+
+fn import(
+    source: &str,
+    file: &str,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let chunk: Chunk = Chunk {
+        file: file,
+        first_line: 1,
+    };
+    let pairs = match AssParser::parse(Rule::ass, source) {
+        Ok(pairs) => pairs,
+        Err(e) => return Err(ParseError::from_pest(&chunk, e)),
+    };
+    let mut nodes: Vec<Node> = Vec::new();
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::node => nodes.push(parse_node(pair, &chunk)?),
+            Rule::EOI => {}
+            _ => {
+                let message: String = format!("unexpected {:?}", pair.as_rule());
+                return Err(ParseError::at(&chunk, &pair, message));
+            }
+        }
+    }
+    let scene: AssScene = AssScene::new(file, nodes);
+    let mut api: ApiCalls = ApiCalls {
+        api_state: api_state,
+        bsdf_state: bsdf_state,
+        float_textures: HashSet::new(),
+        spectrum_textures: HashSet::new(),
+        materials: HashSet::new(),
+    };
+    scene.render_options(&mut api)?;
+    scene.world(&mut api)
+}
+
+/// Groups the tokens of a node into parameters. A parameter name is
+/// followed by an array, a sequence of numbers, or a single value
+/// (string, name, or enum).
+fn parse_node(pair: Pair<Rule>, chunk: &Chunk) -> Result<Node, ParseError> {
+    let (line, column) = chunk.line_col(&pair);
+    let mut inner = pair.into_inner();
+    let node_type: String = match inner.next() {
+        Some(ident) => String::from(ident.as_str()),
+        None => String::new(),
+    };
+    let tokens: Vec<Pair<Rule>> = inner.collect();
+    let mut parameters: Vec<Parameter> = Vec::new();
+    let mut i: usize = 0;
+    while i < tokens.len() {
+        let name_pair: &Pair<Rule> = &tokens[i];
+        if name_pair.as_rule() != Rule::word || is_number(name_pair.as_str()) {
+            return Err(ParseError::at(
+                chunk,
+                name_pair,
+                format!("parameter name expected, found {:?}", name_pair.as_str()),
+            ));
+        }
+        i += 1;
+        let mut n_keys: usize = 1;
+        let n_values: usize;
+        if name_pair.as_str() == "declare" {
+            // declare <name> <class> [ARRAY] <type> (for user data)
+            if i + 2 < tokens.len() && tokens[i + 2].as_str() == "ARRAY" {
+                n_values = 4;
+            } else {
+                n_values = 3;
+            }
+        } else if let Some((n_elements, n_motionblur_keys, n_components)) =
+            array_header(&tokens[i..])
+        {
+            i += 3;
+            n_keys = n_motionblur_keys;
+            n_values = n_elements * n_motionblur_keys * n_components;
+        } else if i < tokens.len() && is_number(tokens[i].as_str()) {
+            n_values = tokens[i..]
+                .iter()
+                .take_while(|token| token.as_rule() == Rule::word && is_number(token.as_str()))
+                .count();
+        } else {
+            n_values = 1;
+        }
+        if i + n_values > tokens.len() {
+            return Err(ParseError::at(
+                chunk,
+                name_pair,
+                format!(
+                    "{} value(s) expected for parameter {:?}",
+                    n_values,
+                    name_pair.as_str()
+                ),
+            ));
+        }
+        if name_pair.as_str() != "declare" {
+            let (line, column) = chunk.line_col(name_pair);
+            parameters.push(Parameter {
+                name: String::from(name_pair.as_str()),
+                values: tokens[i..i + n_values].iter().map(token_value).collect(),
+                n_keys: n_keys,
+                line: line,
+                column: column,
+            });
+        }
+        i += n_values;
+    }
+    let name: String = match parameters.iter().rev().find(|p| p.name == "name") {
+        Some(parameter) => parameter.values[0].clone(),
+        None => String::new(),
+    };
+    Ok(Node {
+        node_type: node_type,
+        name: name,
+        parameters: parameters,
+        line: line,
+        column: column,
+    })
+}
+
+/// Returns the number of elements, motion keys, and values per
+/// element, if the tokens start with an array header.
+fn array_header(tokens: &[Pair<Rule>]) -> Option<(usize, usize, usize)> {
+    if tokens.len() < 3 {
+        return None;
+    }
+    let n_elements: usize = match usize::from_str(tokens[0].as_str()) {
+        Ok(n) => n,
+        Err(_) => return None,
+    };
+    let n_keys: usize = match usize::from_str(tokens[1].as_str()) {
+        Ok(n) => n,
+        Err(_) => return None,
+    };
+    for &(array_type, n_components) in ARRAY_TYPES.iter() {
+        if tokens[2].as_str() == array_type {
+            return Some((n_elements, n_keys, n_components));
+        }
+    }
+    None
+}
+
+fn is_number(token: &str) -> bool {
+    match token.chars().next() {
+        Some(c) => {
+            (c.is_ascii_digit() || c == '-' || c == '+' || c == '.') && f64::from_str(token).is_ok()
+        }
+        None => false,
+    }
+}
+
+fn token_value(pair: &Pair<Rule>) -> String {
+    match pair.as_rule() {
+        Rule::string => match pair.clone().into_inner().next() {
+            Some(inner) => String::from(inner.as_str()),
+            None => String::new(),
+        },
+        _ => String::from(pair.as_str()),
+    }
+}
+
+fn new_params(key_word: &str, name: &str, node: &Node, file: &str) -> ParamSet {
+    let mut params: ParamSet = ParamSet::default();
+    params.reset(
+        String::from(key_word),
+        String::from(name),
+        String::new(),
+        String::new(),
+    );
+    params.location = format!("{}:{}:{}", file, node.line, node.column);
+    params
+}
+
+/// Collects the triangles of a **polymesh** using the same shader.
+/// Vertices get duplicated, where positions, normals, and uv
+/// coordinates are indexed differently.
+#[derive(Default)]
+struct TriangleMeshData {
+    indices: Vec<i32>,
+    p: Vec<Float>,
+    n: Vec<Float>,
+    uv: Vec<Float>,
+    vertices: HashMap<(usize, usize, usize), i32>,
+}
+
+impl TriangleMeshData {
+    fn add_vertex(
+        &mut self,
+        key: (usize, usize, usize),
+        vlist: &[Float],
+        nlist: &[Float],
+        uvlist: &[Float],
+    ) {
+        if let Some(index) = self.vertices.get(&key) {
+            self.indices.push(*index);
+            return;
+        }
+        let index: i32 = (self.p.len() / 3) as i32;
+        let (vi, ni, uvi) = key;
+        self.p.extend_from_slice(&vlist[vi * 3..vi * 3 + 3]);
+        if !nlist.is_empty() {
+            self.n.extend_from_slice(&nlist[ni * 3..ni * 3 + 3]);
+        }
+        if !uvlist.is_empty() {
+            self.uv.extend_from_slice(&uvlist[uvi * 2..uvi * 2 + 2]);
+        }
+        self.vertices.insert(key, index);
+        self.indices.push(index);
+    }
+}
+
+impl<'a> AssScene<'a> {
+    fn new(file: &'a str, nodes: Vec<Node>) -> Self {
+        let mut names: HashMap<String, usize> = HashMap::new();
+        let mut mesh_lights: HashMap<String, usize> = HashMap::new();
+        for (i, node) in nodes.iter().enumerate() {
+            if node.name != "" {
+                names.insert(node.name.clone(), i);
+            }
+            if node.node_type == "mesh_light" {
+                if let Some(mesh) = node.find("mesh") {
+                    mesh_lights.insert(mesh.values[0].clone(), i);
+                }
+            }
+        }
+        AssScene {
+            file: file,
+            nodes: nodes,
+            names: names,
+            mesh_lights: mesh_lights,
+        }
+    }
+    // render options
+
+    fn render_options(&self, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let options: Option<&Node> = self.nodes.iter().find(|n| n.node_type == "options");
+        let mut x_resolution: i32 = 320;
+        let mut y_resolution: i32 = 240;
+        let mut aa_samples: i32 = 1;
+        let mut max_depth: i32 = 10;
+        let mut camera: Option<&Node> = None;
+        let mut filter: Option<&Node> = None;
+        if let Some(options) = options {
+            x_resolution = self.integer(options, "xres", x_resolution)?;
+            y_resolution = self.integer(options, "yres", y_resolution)?;
+            aa_samples = self.integer(options, "AA_samples", aa_samples)?;
+            max_depth = self.integer(options, "GI_total_depth", max_depth)?;
+            camera = self.link(options, "camera");
+            // e.g. "RGBA RGBA filter driver"
+            if let Some(outputs) = options.find("outputs") {
+                if let Some(output) = outputs.values.first() {
+                    if let Some(filter_name) = output.split_whitespace().nth(2) {
+                        filter = self.named(filter_name);
+                    }
+                }
+            }
+        }
+        if camera.is_none() {
+            camera = self.nodes.iter().find(|n| n.node_type.ends_with("_camera"));
+        }
+        if filter.is_none() {
+            filter = self.nodes.iter().find(|n| n.node_type.ends_with("_filter"));
+        }
+        if let Some(camera) = camera {
+            self.camera(camera, x_resolution, y_resolution, api)?;
+        }
+        if let Some(filter) = filter {
+            self.filter(filter, api)?;
+        }
+        let mut params: ParamSet = ParamSet::default();
+        params.reset(
+            String::from("Film"),
+            String::from("image"),
+            String::new(),
+            String::new(),
+        );
+        params.add_int(String::from("xresolution"), x_resolution);
+        params.add_int(String::from("yresolution"), y_resolution);
+        self.api_result(options, pbrt_film(api.api_state, params))?;
+        // AA_samples are camera rays per pixel in each direction
+        params = ParamSet::default();
+        params.reset(
+            String::from("Sampler"),
+            String::from("halton"),
+            String::new(),
+            String::new(),
+        );
+        params.add_int(String::from("pixelsamples"), aa_samples.max(1).pow(2));
+        self.api_result(options, pbrt_sampler(api.api_state, params))?;
+        params = ParamSet::default();
+        params.reset(
+            String::from("Integrator"),
+            String::from("path"),
+            String::new(),
+            String::new(),
+        );
+        params.add_int(String::from("maxdepth"), max_depth);
+        self.api_result(options, pbrt_integrator(api.api_state, params))?;
+        self.api_result(options, pbrt_world_begin(api.api_state))
+    }
+    fn camera(
+        &self,
+        node: &Node,
+        x_resolution: i32,
+        y_resolution: i32,
+        api: &mut ApiCalls,
+    ) -> Result<(), ParseError> {
+        // Arnold's cameras look down the negative z axis
+        let camera_to_world: Transform = self.matrix(node)? * Transform::scale(1.0, 1.0, -1.0);
+        self.api_result(
+            Some(node),
+            pbrt_transform(api.api_state, &Transform::inverse(&camera_to_world)),
+        )?;
+        let mut params: ParamSet;
+        match node.node_type.as_str() {
+            "persp_camera" => {
+                params = new_params("Camera", "perspective", node, self.file);
+                // Arnold's fov is horizontal, pbrt's belongs to the
+                // shorter image axis
+                let mut fov: Float = self.float(node, "fov", 54.43)?;
+                if x_resolution > y_resolution {
+                    fov = 2.0
+                        * ((fov.to_radians() / 2.0).tan() * y_resolution as Float
+                            / x_resolution as Float)
+                            .atan()
+                            .to_degrees();
+                }
+                params.add_float(String::from("fov"), fov);
+            }
+            "ortho_camera" => params = new_params("Camera", "orthographic", node, self.file),
+            "spherical_camera" => params = new_params("Camera", "environment", node, self.file),
+            _ => {
+                self.unsupported(node, api)?;
+                params = new_params("Camera", "perspective", node, self.file);
+            }
+        }
+        self.api_result(Some(node), pbrt_camera(api.api_state, params))
+    }
+    fn filter(&self, node: &Node, api: &mut ApiCalls) -> Result<(), ParseError> {
+        // Arnold's filter width is pbrt's filter diameter
+        let (name, width): (&str, Float) = match node.node_type.as_str() {
+            "box_filter" => ("box", self.float(node, "width", 1.0)?),
+            "gaussian_filter" => ("gaussian", self.float(node, "width", 2.0)?),
+            "triangle_filter" => ("triangle", self.float(node, "width", 2.0)?),
+            _ => return self.unsupported(node, api),
+        };
+        let mut params: ParamSet = new_params("PixelFilter", name, node, self.file);
+        params.add_float(String::from("xwidth"), width / 2.0);
+        params.add_float(String::from("ywidth"), width / 2.0);
+        self.api_result(Some(node), pbrt_pixel_filter(api.api_state, params))
+    }
+    // world
+
+    fn world(&self, api: &mut ApiCalls) -> Result<(), ParseError> {
+        for node in &self.nodes {
+            match node.node_type.as_str() {
+                "standard_surface" | "lambert" => self.material(node, api)?,
+                _ => {}
+            }
+        }
+        for node in &self.nodes {
+            match node.node_type.as_str() {
+                "point_light" | "spot_light" | "distant_light" => self.light(node, api)?,
+                "quad_light" => self.quad_light(node, api)?,
+                "skydome_light" => self.skydome_light(node, api)?,
+                "mesh_light" => {}
+                node_type if node_type.ends_with("_light") => self.unsupported(node, api)?,
+                _ => {}
+            }
+        }
+        // shapes used by a ginstance become objects
+        let mut instanced: HashSet<&str> = HashSet::new();
+        for node in self.nodes.iter().filter(|n| n.node_type == "ginstance") {
+            if let Some(shape) = self.link(node, "node") {
+                if is_shape(shape) {
+                    instanced.insert(&shape.name);
+                }
+            }
+        }
+        for node in self
+            .nodes
+            .iter()
+            .filter(|n| instanced.contains(n.name.as_str()))
+        {
+            let params: ParamSet = new_params("ObjectBegin", &node.name, node, self.file);
+            self.api_result(Some(node), pbrt_object_begin(api.api_state, params))?;
+            self.shape(node, &Transform::default(), api)?;
+            self.api_result(Some(node), pbrt_object_end(api.api_state))?;
+        }
+        for node in &self.nodes {
+            if node.node_type == "ginstance" {
+                if self.integer(node, "visibility", 255)? != 0 {
+                    self.instance(node, api)?;
+                }
+            } else if is_shape(node) && self.integer(node, "visibility", 255)? != 0 {
+                let object_to_world: Transform = self.matrix(node)?;
+                self.shape(node, &object_to_world, api)?;
+            }
+        }
+        Ok(())
+    }
+    fn material(&self, node: &Node, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let mut params: ParamSet = new_params("MakeNamedMaterial", &node.name, node, self.file);
+        if node.node_type == "standard_surface" {
+            params.add_string(String::from("type"), String::from("disney"));
+            let base: Float = self.float(node, "base", 0.8)?;
+            self.spectrum_parameter(node, "base_color", base, "color", &mut params, api)?;
+            self.float_parameter(node, "metalness", 0.0, "metallic", &mut params, api)?;
+            self.float_parameter(
+                node,
+                "specular_roughness",
+                0.2,
+                "roughness",
+                &mut params,
+                api,
+            )?;
+            self.float_parameter(node, "specular_IOR", 1.5, "eta", &mut params, api)?;
+            self.float_parameter(
+                node,
+                "specular_anisotropy",
+                0.0,
+                "anisotropic",
+                &mut params,
+                api,
+            )?;
+            self.float_parameter(node, "sheen", 0.0, "sheen", &mut params, api)?;
+            self.float_parameter(node, "coat", 0.0, "clearcoat", &mut params, api)?;
+            let coat_roughness: Float = self.float(node, "coat_roughness", 0.1)?;
+            params.add_float(String::from("clearcoatgloss"), 1.0 - coat_roughness);
+            self.float_parameter(node, "transmission", 0.0, "spectrans", &mut params, api)?;
+            let thin_walled: bool = self.boolean(node, "thin_walled", false)?;
+            params.add_bool(String::from("thin"), thin_walled);
+        } else {
+            // lambert
+            params.add_string(String::from("type"), String::from("matte"));
+            let kd: Float = self.float(node, "Kd", 0.7)?;
+            self.spectrum_parameter(node, "Kd_color", kd, "Kd", &mut params, api)?;
+        }
+        self.api_result(
+            Some(node),
+            pbrt_make_named_material(api.api_state, api.bsdf_state, params),
+        )?;
+        api.materials.insert(node.name.clone());
+        Ok(())
+    }
+    /// Adds a color (multiplied by **scale**) or a texture, if the
+    /// parameter is linked to an **image** node.
+    fn spectrum_parameter(
+        &self,
+        node: &Node,
+        name: &str,
+        scale: Float,
+        pbrt_name: &str,
+        params: &mut ParamSet,
+        api: &mut ApiCalls,
+    ) -> Result<(), ParseError> {
+        if let Some(image) = self.link(node, name) {
+            if image.node_type != "image" {
+                self.unsupported(image, api)?;
+                params.add_rgb_spectrum(String::from(pbrt_name), Spectrum::new(scale));
+                return Ok(());
+            }
+            self.texture(image, "spectrum", api)?;
+            if scale == 1.0 as Float {
+                params.add_texture(String::from(pbrt_name), image.name.clone());
+                return Ok(());
+            }
+            let texture_name: String = format!("{}.{}", node.name, name);
+            let mut tex_params: ParamSet = ParamSet::default();
+            tex_params.reset(
+                String::from("Texture"),
+                texture_name.clone(),
+                String::from("spectrum"),
+                String::from("scale"),
+            );
+            tex_params.add_texture(String::from("tex1"), image.name.clone());
+            tex_params.add_rgb_spectrum(String::from("tex2"), Spectrum::new(scale));
+            self.api_result(Some(node), pbrt_texture(api.api_state, tex_params))?;
+            params.add_texture(String::from(pbrt_name), texture_name);
+        } else {
+            let color: Spectrum = self.rgb(node, name, Spectrum::new(1.0))?;
+            params.add_rgb_spectrum(String::from(pbrt_name), color * scale);
+        }
+        Ok(())
+    }
+    fn float_parameter(
+        &self,
+        node: &Node,
+        name: &str,
+        default: Float,
+        pbrt_name: &str,
+        params: &mut ParamSet,
+        api: &mut ApiCalls,
+    ) -> Result<(), ParseError> {
+        if let Some(image) = self.link(node, name) {
+            if image.node_type != "image" {
+                self.unsupported(image, api)?;
+                params.add_float(String::from(pbrt_name), default);
+                return Ok(());
+            }
+            self.texture(image, "float", api)?;
+            params.add_texture(String::from(pbrt_name), image.name.clone());
+        } else {
+            let value: Float = self.float(node, name, default)?;
+            params.add_float(String::from(pbrt_name), value);
+        }
+        Ok(())
+    }
+    /// Creates an **imagemap** texture (named like the **image**
+    /// node) the first time it's needed as float or spectrum texture.
+    fn texture(&self, node: &Node, tex_type: &str, api: &mut ApiCalls) -> Result<(), ParseError> {
+        {
+            let created: &mut HashSet<String> = if tex_type == "float" {
+                &mut api.float_textures
+            } else {
+                &mut api.spectrum_textures
+            };
+            if !created.insert(node.name.clone()) {
+                return Ok(());
+            }
+        }
+        let mut params: ParamSet = new_params("Texture", &node.name, node, self.file);
+        params.tex_type = String::from(tex_type);
+        params.tex_name = String::from("imagemap");
+        params.add_string(String::from("filename"), self.filename(node)?);
+        params.add_float(String::from("uscale"), self.float(node, "uscale", 1.0)?);
+        params.add_float(String::from("vscale"), self.float(node, "vscale", 1.0)?);
+        params.add_float(String::from("udelta"), self.float(node, "uoffset", 0.0)?);
+        params.add_float(String::from("vdelta"), self.float(node, "voffset", 0.0)?);
+        let wrap: String = self.string(node, "swrap", "periodic");
+        match wrap.as_str() {
+            "periodic" => params.add_string(String::from("wrap"), String::from("repeat")),
+            "black" | "clamp" => params.add_string(String::from("wrap"), wrap),
+            _ => {
+                // the imagemap texture repeats by default ("periodic")
+                let error: ApiError = ApiError::Unsupported {
+                    directive: format!("image \"{}\": swrap", node.name),
+                    name: wrap.clone(),
+                };
+                self.api_result(Some(node), api.api_state.report(error))?;
+            }
+        }
+        self.api_result(Some(node), pbrt_texture(api.api_state, params))
+    }
+    fn light(&self, node: &Node, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let color: Spectrum = self.light_color(node)?;
+        let mut params: ParamSet;
+        match node.node_type.as_str() {
+            "point_light" => {
+                params = new_params("LightSource", "point", node, self.file);
+                params.add_rgb_spectrum(String::from("I"), color);
+                if let Some(position) = node.find("position") {
+                    let from: Vec<Float> = self.numbers(position, 3)?;
+                    params.add_point3fs(String::from("from"), from);
+                }
+            }
+            "spot_light" => {
+                // Arnold uses the full angles
+                params = new_params("LightSource", "spot", node, self.file);
+                params.add_rgb_spectrum(String::from("I"), color);
+                params.add_point3fs(String::from("from"), vec![0.0, 0.0, 0.0]);
+                params.add_point3fs(String::from("to"), vec![0.0, 0.0, -1.0]);
+                let cone_angle: Float = self.float(node, "cone_angle", 65.0)?;
+                let penumbra_angle: Float = self.float(node, "penumbra_angle", 0.0)?;
+                params.add_float(String::from("coneangle"), cone_angle / 2.0);
+                params.add_float(String::from("conedeltaangle"), penumbra_angle / 2.0);
+            }
+            _ => {
+                // distant_light
+                params = new_params("LightSource", "distant", node, self.file);
+                params.add_rgb_spectrum(String::from("L"), color);
+                params.add_point3fs(String::from("from"), vec![0.0, 0.0, 0.0]);
+                params.add_point3fs(String::from("to"), vec![0.0, 0.0, -1.0]);
+            }
+        }
+        let light_to_world: Transform = self.matrix(node)?;
+        self.api_result(Some(node), pbrt_attribute_begin(api.api_state))?;
+        self.api_result(Some(node), pbrt_transform(api.api_state, &light_to_world))?;
+        self.api_result(Some(node), pbrt_light_source(api.api_state, params))?;
+        self.api_result(Some(node), pbrt_attribute_end(api.api_state))
+    }
+    /// A **quad_light** emits (towards its negative z axis) from a
+    /// mesh of two triangles.
+    fn quad_light(&self, node: &Node, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let mut p: Vec<Float> = vec![
+            -1.0, 1.0, 0.0, -1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 1.0, 1.0, 0.0,
+        ];
+        if let Some(vertices) = node.find("vertices") {
+            p = self.numbers(vertices, 12)?;
+        }
+        // pbrt's triangles face the side the vertices are ordered
+        // counterclockwise on
+        let e1: Vector3f = Vector3f {
+            x: p[3] - p[0],
+            y: p[4] - p[1],
+            z: p[5] - p[2],
+        };
+        let e2: Vector3f = Vector3f {
+            x: p[6] - p[0],
+            y: p[7] - p[1],
+            z: p[8] - p[2],
+        };
+        let indices: Vec<i32> = if e1.x * e2.y - e1.y * e2.x > 0.0 as Float {
+            vec![0, 2, 1, 0, 3, 2]
+        } else {
+            vec![0, 1, 2, 0, 2, 3]
+        };
+        let area_light: ParamSet = self.area_light_params(node)?;
+        let mut material: ParamSet = new_params("Material", "matte", node, self.file);
+        material.add_rgb_spectrum(String::from("Kd"), Spectrum::new(0.0));
+        let mut shape: ParamSet = new_params("Shape", "trianglemesh", node, self.file);
+        shape.add_ints(String::from("indices"), indices);
+        shape.add_point3fs(String::from("P"), p);
+        let light_to_world: Transform = self.matrix(node)?;
+        self.api_result(Some(node), pbrt_attribute_begin(api.api_state))?;
+        self.api_result(Some(node), pbrt_transform(api.api_state, &light_to_world))?;
+        self.api_result(Some(node), pbrt_material(api.api_state, material))?;
+        self.api_result(
+            Some(node),
+            pbrt_area_light_source(api.api_state, area_light),
+        )?;
+        self.api_result(Some(node), pbrt_shape(api.api_state, api.bsdf_state, shape))?;
+        self.api_result(Some(node), pbrt_attribute_end(api.api_state))
+    }
+    /// A **skydome_light** becomes an **infinite** light, using the
+    /// file of an **image** node linked to its color as environment
+    /// map.
+    fn skydome_light(&self, node: &Node, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let mut params: ParamSet = new_params("LightSource", "infinite", node, self.file);
+        let scale: Spectrum = self.light_color(node)?;
+        if let Some(linked) = self.link(node, "color") {
+            if linked.node_type == "image" {
+                params.add_string(String::from("mapname"), self.filename(linked)?);
+            } else {
+                self.unsupported(linked, api)?;
+            }
+        }
+        params.add_rgb_spectrum(String::from("L"), scale);
+        let n_samples: i32 = self.integer(node, "samples", 1)?;
+        params.add_int(String::from("nsamples"), n_samples);
+        // Arnold's up direction is the y axis, pbrt's environment
+        // maps use the z axis
+        let light_to_world: Transform = self.matrix(node)? * Transform::rotate_x(-90.0);
+        self.api_result(Some(node), pbrt_attribute_begin(api.api_state))?;
+        self.api_result(Some(node), pbrt_transform(api.api_state, &light_to_world))?;
+        self.api_result(Some(node), pbrt_light_source(api.api_state, params))?;
+        self.api_result(Some(node), pbrt_attribute_end(api.api_state))
+    }
+    /// Parameters of the **diffuse** area light for a **mesh_light**
+    /// or **quad_light** node.
+    fn area_light_params(&self, node: &Node) -> Result<ParamSet, ParseError> {
+        let mut params: ParamSet = new_params("AreaLightSource", "diffuse", node, self.file);
+        params.add_rgb_spectrum(String::from("L"), self.light_color(node)?);
+        params.add_int(String::from("nsamples"), self.integer(node, "samples", 1)?);
+        Ok(params)
+    }
+    fn shape(
+        &self,
+        node: &Node,
+        object_to_world: &Transform,
+        api: &mut ApiCalls,
+    ) -> Result<(), ParseError> {
+        let shaders: Vec<String> = match node.find("shader") {
+            Some(shader) => shader.values.clone(),
+            None => Vec::new(),
+        };
+        if node.node_type == "polymesh" {
+            return self.polymesh(node, object_to_world, &shaders, api);
+        }
+        let mut params: ParamSet = new_params("Shape", &node.node_type, node, self.file);
+        let origin: Vector3f = Vector3f::default();
+        let transform: Transform;
+        match node.node_type.as_str() {
+            "sphere" => {
+                let center: Vector3f = self.vector(node, "center", origin)?;
+                params.add_float(String::from("radius"), self.float(node, "radius", 0.5)?);
+                transform = *object_to_world * Transform::translate(&center);
+            }
+            "disk" => {
+                let center: Vector3f = self.vector(node, "center", origin)?;
+                let dir: Vector3f = self.vector(
+                    node,
+                    "dir",
+                    Vector3f {
+                        x: 0.0,
+                        y: 0.0,
+                        z: 1.0,
+                    },
+                )?;
+                params.add_float(String::from("radius"), self.float(node, "radius", 0.5)?);
+                params.add_float(String::from("innerradius"), self.float(node, "hole", 0.0)?);
+                transform = *object_to_world * Transform::translate(&center) * frame(&dir);
+            }
+            _ => {
+                // cylinder
+                let bottom: Vector3f = self.vector(node, "bottom", origin)?;
+                let top: Vector3f = self.vector(
+                    node,
+                    "top",
+                    Vector3f {
+                        x: 0.0,
+                        y: 1.0,
+                        z: 0.0,
+                    },
+                )?;
+                let axis: Vector3f = top - bottom;
+                params.add_float(String::from("radius"), self.float(node, "radius", 0.5)?);
+                params.add_float(String::from("zmin"), 0.0);
+                params.add_float(String::from("zmax"), axis.length());
+                transform = *object_to_world * Transform::translate(&bottom) * frame(&axis);
+            }
+        }
+        self.emit_shape(node, &transform, shaders.first(), params, api)
+    }
+    /// Splits a **polymesh** into triangle meshes, one per shader.
+    /// Polygons with more than three sides get triangulated as fans.
+    fn polymesh(
+        &self,
+        node: &Node,
+        object_to_world: &Transform,
+        shaders: &Vec<String>,
+        api: &mut ApiCalls,
+    ) -> Result<(), ParseError> {
+        let vlist: Vec<Float> = self.find_numbers(node, "vlist")?;
+        let vidxs: Vec<usize> = self.find_indices(node, "vidxs", vlist.len() / 3)?;
+        let nsides: Vec<usize> = match node.find("nsides") {
+            Some(_) => self.find_indices(node, "nsides", usize::max_value())?,
+            None => vec![3; vidxs.len() / 3],
+        };
+        let shidxs: Vec<usize> = self.find_indices(node, "shidxs", usize::max_value())?;
+        // Arnold ignores the normals of meshes which aren't smooth
+        let mut nlist: Vec<Float> = Vec::new();
+        let mut nidxs: Vec<usize> = Vec::new();
+        if self.boolean(node, "smoothing", false)? && node.find("nlist").is_some() {
+            nlist = self.find_numbers(node, "nlist")?;
+            nidxs = match node.find("nidxs") {
+                Some(_) => self.find_indices(node, "nidxs", nlist.len() / 3)?,
+                None => vidxs.clone(),
+            };
+        }
+        let mut uvlist: Vec<Float> = Vec::new();
+        let mut uvidxs: Vec<usize> = Vec::new();
+        if node.find("uvlist").is_some() {
+            uvlist = self.find_numbers(node, "uvlist")?;
+            uvidxs = match node.find("uvidxs") {
+                Some(_) => self.find_indices(node, "uvidxs", uvlist.len() / 2)?,
+                None => vidxs.clone(),
+            };
+        }
+        let n_corners: usize = nsides.iter().sum();
+        if n_corners != vidxs.len()
+            || (!nlist.is_empty() && nidxs.len() != n_corners)
+            || (!uvlist.is_empty() && uvidxs.len() != n_corners)
+        {
+            return Err(self.error(
+                node.line,
+                node.column,
+                format!(
+                    "polymesh \"{}\": nsides adds up to {} indices, but {} vidxs were given",
+                    node.name,
+                    n_corners,
+                    vidxs.len()
+                ),
+            ));
+        }
+        // collect triangles by shader index
+        let mut meshes: Vec<(usize, TriangleMeshData)> = Vec::new();
+        let mut corner: usize = 0;
+        for (polygon, n_sides) in nsides.iter().enumerate() {
+            let shidx: usize = *shidxs.get(polygon).unwrap_or(&0);
+            let mesh_index: usize = match meshes.iter().position(|&(i, _)| i == shidx) {
+                Some(mesh_index) => mesh_index,
+                None => {
+                    meshes.push((shidx, TriangleMeshData::default()));
+                    meshes.len() - 1
+                }
+            };
+            let mesh: &mut TriangleMeshData = &mut meshes[mesh_index].1;
+            for j in 1..n_sides.saturating_sub(1) {
+                for c in [corner, corner + j, corner + j + 1].iter() {
+                    let key: (usize, usize, usize) = (
+                        vidxs[*c],
+                        *nidxs.get(*c).unwrap_or(&0),
+                        *uvidxs.get(*c).unwrap_or(&0),
+                    );
+                    mesh.add_vertex(key, &vlist, &nlist, &uvlist);
+                }
+            }
+            corner += n_sides;
+        }
+        for (shidx, mesh) in meshes {
+            if mesh.indices.is_empty() {
+                continue;
+            }
+            let mut params: ParamSet = new_params("Shape", "trianglemesh", node, self.file);
+            params.add_ints(String::from("indices"), mesh.indices);
+            params.add_point3fs(String::from("P"), mesh.p);
+            if !mesh.n.is_empty() {
+                params.add_normal3fs(String::from("N"), mesh.n);
+            }
+            if !mesh.uv.is_empty() {
+                params.add_point2fs(String::from("uv"), mesh.uv);
+            }
+            self.emit_shape(node, object_to_world, shaders.get(shidx), params, api)?;
+        }
+        Ok(())
+    }
+    /// Hands a shape over to the API, using the named material of the
+    /// shader (if it was created) and emitting light, if a
+    /// **mesh_light** refers to the node.
+    fn emit_shape(
+        &self,
+        node: &Node,
+        object_to_world: &Transform,
+        shader: Option<&String>,
+        params: ParamSet,
+        api: &mut ApiCalls,
+    ) -> Result<(), ParseError> {
+        self.api_result(Some(node), pbrt_attribute_begin(api.api_state))?;
+        self.api_result(Some(node), pbrt_transform(api.api_state, object_to_world))?;
+        if let Some(shader) = shader {
+            if api.materials.contains(shader) {
+                let material: ParamSet = new_params("NamedMaterial", shader, node, self.file);
+                self.api_result(Some(node), pbrt_named_material(api.api_state, material))?;
+            }
+        }
+        if let Some(index) = self.mesh_lights.get(&node.name) {
+            let area_light: ParamSet = self.area_light_params(&self.nodes[*index])?;
+            self.api_result(
+                Some(node),
+                pbrt_area_light_source(api.api_state, area_light),
+            )?;
+        }
+        self.api_result(
+            Some(node),
+            pbrt_shape(api.api_state, api.bsdf_state, params),
+        )?;
+        self.api_result(Some(node), pbrt_attribute_end(api.api_state))
+    }
+    /// Places the object created for the shape a **ginstance** refers
+    /// to. With **inherit_xform** (the default) the transformation of
+    /// the shape gets applied as well.
+    fn instance(&self, node: &Node, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let shape: &Node = match self.link(node, "node") {
+            Some(shape) => shape,
+            None => {
+                let name: String = self.string(node, "node", "");
+                return self.api_result(
+                    Some(node),
+                    api.api_state.report(ApiError::Unknown {
+                        directive: String::from("ginstance"),
+                        name: name,
+                    }),
+                );
+            }
+        };
+        if !is_shape(shape) {
+            return self.unsupported(shape, api);
+        }
+        let mut instance_to_world: Transform = self.matrix(node)?;
+        if self.boolean(node, "inherit_xform", true)? {
+            instance_to_world = instance_to_world * self.matrix(shape)?;
+        }
+        let params: ParamSet = new_params("ObjectInstance", &shape.name, node, self.file);
+        self.api_result(Some(node), pbrt_attribute_begin(api.api_state))?;
+        self.api_result(
+            Some(node),
+            pbrt_transform(api.api_state, &instance_to_world),
+        )?;
+        self.api_result(
+            Some(node),
+            pbrt_object_instance(api.api_state, api.bsdf_state, params),
+        )?;
+        self.api_result(Some(node), pbrt_attribute_end(api.api_state))
+    }
+    // lookups
+
+    fn named(&self, name: &str) -> Option<&Node> {
+        match self.names.get(name) {
+            Some(index) => Some(&self.nodes[*index]),
+            None => None,
+        }
+    }
+    /// Returns the node a parameter refers to (instead of having a
+    /// value), ignoring output components like `.r`.
+    fn link(&self, node: &Node, name: &str) -> Option<&Node> {
+        match node.find(name) {
+            Some(parameter) => {
+                if parameter.values.len() != 1 || is_number(&parameter.values[0]) {
+                    return None;
+                }
+                let value: &str = &parameter.values[0];
+                match self.named(value) {
+                    Some(linked) => Some(linked),
+                    None => match value.rfind('.') {
+                        Some(dot) => self.named(&value[..dot]),
+                        None => None,
+                    },
+                }
+            }
+            None => None,
+        }
+    }
+    // values
+
+    fn error(&self, line: usize, column: usize, message: String) -> ParseError {
+        ParseError::new(self.file, line, column, message)
+    }
+    /// Attaches the location of the node to an error reported by one
+    /// of the *pbrt_* API functions.
+    fn api_result(
+        &self,
+        node: Option<&Node>,
+        result: Result<(), ApiError>,
+    ) -> Result<(), ParseError> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) => match node {
+                Some(node) => Err(self.error(node.line, node.column, e.to_string())),
+                None => Err(self.error(0, 0, e.to_string())),
+            },
+        }
+    }
+    fn unsupported(&self, node: &Node, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let error: ApiError = ApiError::Unsupported {
+            directive: node.node_type.clone(),
+            name: node.name.clone(),
+        };
+        self.api_result(Some(node), api.api_state.report(error))
+    }
+    /// The values of the first motion key, which need to be at least
+    /// **n_min** numbers.
+    fn numbers(&self, parameter: &Parameter, n_min: usize) -> Result<Vec<Float>, ParseError> {
+        let n_values: usize = parameter.values.len() / parameter.n_keys.max(1);
+        let mut numbers: Vec<Float> = Vec::with_capacity(n_values);
+        for value in &parameter.values[..n_values] {
+            match Float::from_str(value) {
+                Ok(number) => numbers.push(number),
+                Err(_) => {
+                    return Err(self.error(
+                        parameter.line,
+                        parameter.column,
+                        format!(
+                            "invalid number {:?} for parameter {:?}",
+                            value, parameter.name
+                        ),
+                    ))
+                }
+            }
+        }
+        if numbers.len() < n_min {
+            return Err(self.error(
+                parameter.line,
+                parameter.column,
+                format!(
+                    "{} number(s) expected for parameter {:?}",
+                    n_min, parameter.name
+                ),
+            ));
+        }
+        Ok(numbers)
+    }
+    fn find_numbers(&self, node: &Node, name: &str) -> Result<Vec<Float>, ParseError> {
+        match node.find(name) {
+            Some(parameter) => self.numbers(parameter, 0),
+            None => Ok(Vec::new()),
+        }
+    }
+    /// Unsigned integers (of the first motion key) below **bound**.
+    fn find_indices(
+        &self,
+        node: &Node,
+        name: &str,
+        bound: usize,
+    ) -> Result<Vec<usize>, ParseError> {
+        let parameter: &Parameter = match node.find(name) {
+            Some(parameter) => parameter,
+            None => return Ok(Vec::new()),
+        };
+        let n_values: usize = parameter.values.len() / parameter.n_keys.max(1);
+        let mut indices: Vec<usize> = Vec::with_capacity(n_values);
+        for value in &parameter.values[..n_values] {
+            match usize::from_str(value) {
+                Ok(index) if index < bound => indices.push(index),
+                Ok(index) => {
+                    return Err(self.error(
+                        parameter.line,
+                        parameter.column,
+                        format!(
+                            "out of bounds index {} for parameter {:?} ({} values given)",
+                            index, parameter.name, bound
+                        ),
+                    ))
+                }
+                Err(_) => {
+                    return Err(self.error(
+                        parameter.line,
+                        parameter.column,
+                        format!(
+                            "invalid index {:?} for parameter {:?}",
+                            value, parameter.name
+                        ),
+                    ))
+                }
+            }
+        }
+        Ok(indices)
+    }
+    fn float(&self, node: &Node, name: &str, default: Float) -> Result<Float, ParseError> {
+        match node.find(name) {
+            Some(parameter) => Ok(self.numbers(parameter, 1)?[0]),
+            None => Ok(default),
+        }
+    }
+    fn integer(&self, node: &Node, name: &str, default: i32) -> Result<i32, ParseError> {
+        match node.find(name) {
+            Some(parameter) => match i32::from_str(&parameter.values[0]) {
+                Ok(integer) => Ok(integer),
+                Err(_) => Err(self.error(
+                    parameter.line,
+                    parameter.column,
+                    format!(
+                        "invalid integer {:?} for parameter {:?}",
+                        parameter.values[0], parameter.name
+                    ),
+                )),
+            },
+            None => Ok(default),
+        }
+    }
+    fn boolean(&self, node: &Node, name: &str, default: bool) -> Result<bool, ParseError> {
+        match node.find(name) {
+            Some(parameter) => match parameter.values[0].as_str() {
+                "on" | "true" | "1" => Ok(true),
+                "off" | "false" | "0" => Ok(false),
+                value => Err(self.error(
+                    parameter.line,
+                    parameter.column,
+                    format!(
+                        "invalid bool {:?} for parameter {:?}",
+                        value, parameter.name
+                    ),
+                )),
+            },
+            None => Ok(default),
+        }
+    }
+    fn string(&self, node: &Node, name: &str, default: &str) -> String {
+        match node.find(name) {
+            Some(parameter) => parameter.values[0].clone(),
+            None => String::from(default),
+        }
+    }
+    /// RGB (or RGBA, ignoring alpha) colors.
+    fn rgb(&self, node: &Node, name: &str, default: Spectrum) -> Result<Spectrum, ParseError> {
+        match node.find(name) {
+            Some(parameter) => {
+                let v: Vec<Float> = self.numbers(parameter, 3)?;
+                Ok(Spectrum::rgb(v[0], v[1], v[2]))
+            }
+            None => Ok(default),
+        }
+    }
+    fn vector(&self, node: &Node, name: &str, default: Vector3f) -> Result<Vector3f, ParseError> {
+        match node.find(name) {
+            Some(parameter) => {
+                let v: Vec<Float> = self.numbers(parameter, 3)?;
+                Ok(Vector3f {
+                    x: v[0],
+                    y: v[1],
+                    z: v[2],
+                })
+            }
+            None => Ok(default),
+        }
+    }
+    /// Arnold matrices are written row by row, with the translation
+    /// in the last row.
+    fn matrix(&self, node: &Node) -> Result<Transform, ParseError> {
+        match node.find("matrix") {
+            Some(parameter) => {
+                let m: Vec<Float> = self.numbers(parameter, 16)?;
+                Ok(Transform::new(
+                    m[0], m[4], m[8], m[12], m[1], m[5], m[9], m[13], m[2], m[6], m[10], m[14],
+                    m[3], m[7], m[11], m[15],
+                ))
+            }
+            None => Ok(Transform::default()),
+        }
+    }
+    /// Color times intensity, scaled by the exposure (in stops).
+    fn light_color(&self, node: &Node) -> Result<Spectrum, ParseError> {
+        let mut color: Spectrum = Spectrum::new(1.0);
+        if self.link(node, "color").is_none() {
+            color = self.rgb(node, "color", color)?;
+        }
+        let intensity: Float = self.float(node, "intensity", 1.0)?;
+        let exposure: Float = self.float(node, "exposure", 0.0)?;
+        Ok(color * (intensity * (2.0 as Float).powf(exposure)))
+    }
+    fn filename(&self, node: &Node) -> Result<String, ParseError> {
+        match node.find("filename") {
+            Some(parameter) => Ok(parameter.values[0].clone()),
+            None => Err(self.error(
+                node.line,
+                node.column,
+                format!("{} \"{}\": no filename given", node.node_type, node.name),
+            )),
+        }
+    }
+}
+
+fn is_shape(node: &Node) -> bool {
+    match node.node_type.as_str() {
+        "polymesh" | "sphere" | "disk" | "cylinder" => true,
+        _ => false,
+    }
+}
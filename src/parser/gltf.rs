@@ -0,0 +1,563 @@
+//! Imports glTF 2.0 scenes (**.gltf** or binary **.glb** files) by
+//! translating the default scene (or the first one) into calls of
+//! the *pbrt_* functions of the **core::api** module:
+//!
+//! * The first perspective camera found in the node hierarchy
+//!   becomes the camera. Its aspect ratio (if given) determines the
+//!   height of the image, which is 1280 pixels wide.
+//! * Materials (metallic-roughness) become named **disney**
+//!   materials. Base color, metallic (blue channel), and roughness
+//!   (green channel) textures are supported, as well as normal maps.
+//!   Emissive materials turn meshes into **diffuse** area lights.
+//! * Lights of the `KHR_lights_punctual` extension become **point**,
+//!   **spot**, or **distant** lights.
+//! * Triangle primitives of meshes become **trianglemesh** shapes
+//!   with normals, tangents, and the first set of uv coordinates.
+//!
+//! Images can be embedded in the file (or a buffer), therefore
+//! textures are handed to the API as objects (see
+//! **pbrt_spectrum_texture()**), which can't be written by the scene
+//! writer. Like for **parse_file()** in the **parser** module,
+//! rendering is left to the caller.
+
+// std
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+// others
+use base64;
+use gltf::camera::Projection;
+use gltf::khr_lights_punctual::Kind;
+use gltf::material::Material as GltfMaterial;
+use gltf::mesh::Mode;
+use gltf::scene::Node;
+use gltf::texture::{Info, WrappingMode};
+use gltf::{buffer, image as gltf_image, Gltf};
+use image::{self, DynamicImage};
+// pbrt
+use core::api::{pbrt_area_light_source, pbrt_attribute_begin, pbrt_attribute_end, pbrt_camera};
+use core::api::{pbrt_film, pbrt_float_texture, pbrt_light_source, pbrt_make_named_material};
+use core::api::{pbrt_named_material, pbrt_shape, pbrt_spectrum_texture, pbrt_transform};
+use core::api::{pbrt_world_begin, ApiError, ApiState, BsdfState};
+use core::mipmap::ImageWrap;
+use core::paramset::ParamSet;
+use core::pbrt::{Float, Spectrum};
+use core::texture::{Texture, UVMapping2D};
+use core::transform::Transform;
+use parser::ParseError;
+use textures::constant::ConstantTexture;
+use textures::imagemap::{convert_to_spectrum, ImageTexture};
+use textures::scale::ScaleTexture;
+
+/// Name of the material used for primitives without one.
+const DEFAULT_MATERIAL: &str = "gltf:default";
+
+struct Importer<'a> {
+    file: String,
+    directory: PathBuf,
+    gltf: &'a Gltf,
+    buffers: Vec<Vec<u8>>,
+    /// decoded images by index
+    images: HashMap<usize, DynamicImage>,
+    default_material: bool,
+}
+
+/// Reads the glTF file found at `path` and feeds its default scene
+/// into `api_state`.
+pub fn parse_file(
+    path: &Path,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let file: String = path.display().to_string();
+    let gltf: Gltf = match Gltf::open(path) {
+        Ok(gltf) => gltf,
+        Err(e) => return Err(ParseError::new(&file, 0, 0, e.to_string())),
+    };
+    let directory: PathBuf = match path.parent() {
+        Some(parent) => PathBuf::from(parent),
+        None => PathBuf::new(),
+    };
+    if let Ok(absolute_directory) = fs::canonicalize(&directory) {
+        api_state.search_directory = Some(Box::new(absolute_directory));
+    }
+    let mut importer: Importer = Importer {
+        file: file,
+        directory: directory,
+        gltf: &gltf,
+        buffers: Vec::new(),
+        images: HashMap::new(),
+        default_material: false,
+    };
+    for buffer in gltf.buffers() {
+        let data: Vec<u8> = importer.load_buffer(&buffer)?;
+        importer.buffers.push(data);
+    }
+    let scene = match gltf.default_scene().or_else(|| gltf.scenes().next()) {
+        Some(scene) => scene,
+        None => return Err(importer.error(String::from("no scene found"))),
+    };
+    // nodes with their object to world transformations
+    let mut nodes: Vec<(Node, Transform)> = Vec::new();
+    for node in scene.nodes() {
+        collect_nodes(node, &Transform::default(), &mut nodes);
+    }
+    importer.render_options(&nodes, api_state)?;
+    importer.api(pbrt_world_begin(api_state))?;
+    for material in gltf.materials() {
+        importer.material(&material, api_state, bsdf_state)?;
+    }
+    for &(ref node, ref object_to_world) in &nodes {
+        if let Some(light) = node.light() {
+            let mut params: ParamSet = ParamSet::default();
+            let color: [f32; 3] = light.color();
+            let intensity: Spectrum =
+                Spectrum::rgb(color[0], color[1], color[2]) * light.intensity();
+            // all lights point down the negative z axis
+            match light.kind() {
+                Kind::Point => {
+                    reset(&mut params, "LightSource", "point");
+                    params.add_rgb_spectrum(String::from("I"), intensity);
+                }
+                Kind::Spot {
+                    inner_cone_angle,
+                    outer_cone_angle,
+                } => {
+                    reset(&mut params, "LightSource", "spot");
+                    params.add_rgb_spectrum(String::from("I"), intensity);
+                    params.add_point3fs(String::from("from"), vec![0.0, 0.0, 0.0]);
+                    params.add_point3fs(String::from("to"), vec![0.0, 0.0, -1.0]);
+                    params.add_float(String::from("coneangle"), outer_cone_angle.to_degrees());
+                    params.add_float(
+                        String::from("conedeltaangle"),
+                        (outer_cone_angle - inner_cone_angle).to_degrees(),
+                    );
+                }
+                Kind::Directional => {
+                    reset(&mut params, "LightSource", "distant");
+                    params.add_rgb_spectrum(String::from("L"), intensity);
+                    params.add_point3fs(String::from("from"), vec![0.0, 0.0, 0.0]);
+                    params.add_point3fs(String::from("to"), vec![0.0, 0.0, -1.0]);
+                }
+            }
+            importer.api(pbrt_attribute_begin(api_state))?;
+            importer.api(pbrt_transform(api_state, object_to_world))?;
+            importer.api(pbrt_light_source(api_state, params))?;
+            importer.api(pbrt_attribute_end(api_state))?;
+        }
+        if node.mesh().is_some() {
+            importer.mesh(node, object_to_world, api_state, bsdf_state)?;
+        }
+    }
+    Ok(())
+}
+
+/// glTF matrices are stored column by column.
+fn node_transform(node: &Node) -> Transform {
+    let m: [[f32; 4]; 4] = node.transform().matrix();
+    Transform::new(
+        m[0][0], m[1][0], m[2][0], m[3][0], m[0][1], m[1][1], m[2][1], m[3][1], m[0][2], m[1][2],
+        m[2][2], m[3][2], m[0][3], m[1][3], m[2][3], m[3][3],
+    )
+}
+
+fn collect_nodes<'a>(node: Node<'a>, parent: &Transform, nodes: &mut Vec<(Node<'a>, Transform)>) {
+    let object_to_world: Transform = *parent * node_transform(&node);
+    for child in node.children() {
+        collect_nodes(child, &object_to_world, nodes);
+    }
+    nodes.push((node, object_to_world));
+}
+
+fn reset(params: &mut ParamSet, key_word: &str, name: &str) {
+    params.reset(
+        String::from(key_word),
+        String::from(name),
+        String::new(),
+        String::new(),
+    );
+}
+
+fn material_name(material: &GltfMaterial) -> String {
+    match material.index() {
+        Some(index) => format!("{}#{}", material.name().unwrap_or("material"), index),
+        None => String::from(DEFAULT_MATERIAL),
+    }
+}
+
+/// Decodes `%XX` escapes of relative URIs.
+fn uri_to_path(uri: &str) -> PathBuf {
+    let bytes: &[u8] = uri.as_bytes();
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i: usize = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Ok(byte) = u8::from_str_radix(&uri[i + 1..i + 3], 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+impl<'a> Importer<'a> {
+    fn error(&self, message: String) -> ParseError {
+        ParseError::new(&self.file, 0, 0, message)
+    }
+    fn api(&self, result: Result<(), ApiError>) -> Result<(), ParseError> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) => Err(self.error(e.to_string())),
+        }
+    }
+    /// Reads the data of a URI, which is either embedded (base64) or
+    /// the path of a file relative to the glTF file.
+    fn read_uri(&self, uri: &str) -> Result<Vec<u8>, ParseError> {
+        if uri.starts_with("data:") {
+            let encoded: &str = match uri.find(";base64,") {
+                Some(start) => &uri[start + 8..],
+                None => return Err(self.error(String::from("data URI without base64 data"))),
+            };
+            match base64::decode(encoded) {
+                Ok(data) => Ok(data),
+                Err(e) => Err(self.error(format!("invalid base64 data: {}", e))),
+            }
+        } else {
+            let path: PathBuf = self.directory.join(uri_to_path(uri));
+            match fs::read(&path) {
+                Ok(data) => Ok(data),
+                Err(e) => Err(self.error(format!("can't read {:?}: {}", path.display(), e))),
+            }
+        }
+    }
+    fn load_buffer(&self, buffer: &buffer::Buffer) -> Result<Vec<u8>, ParseError> {
+        let data: Vec<u8> = match buffer.source() {
+            buffer::Source::Bin => match self.gltf.blob {
+                Some(ref blob) => blob.clone(),
+                None => return Err(self.error(String::from("binary buffer missing"))),
+            },
+            buffer::Source::Uri(uri) => self.read_uri(uri)?,
+        };
+        if data.len() < buffer.length() {
+            return Err(self.error(format!(
+                "buffer {} has {} bytes ({} expected)",
+                buffer.index(),
+                data.len(),
+                buffer.length()
+            )));
+        }
+        Ok(data)
+    }
+    fn image(&mut self, image: &gltf_image::Image) -> Result<&DynamicImage, ParseError> {
+        let index: usize = image.index();
+        if !self.images.contains_key(&index) {
+            let data: Vec<u8> = match image.source() {
+                gltf_image::Source::View { view, .. } => {
+                    let buffer: &Vec<u8> = &self.buffers[view.buffer().index()];
+                    let end: usize = view.offset() + view.length();
+                    if end > buffer.len() {
+                        return Err(self.error(format!("image {}: view out of bounds", index)));
+                    }
+                    buffer[view.offset()..end].to_vec()
+                }
+                gltf_image::Source::Uri { uri, .. } => self.read_uri(uri)?,
+            };
+            let decoded: DynamicImage = match image::load_from_memory(&data) {
+                Ok(decoded) => decoded,
+                Err(e) => return Err(self.error(format!("image {}: {}", index, e))),
+            };
+            self.images.insert(index, decoded);
+        }
+        Ok(&self.images[&index])
+    }
+    /// Only the first set of uv coordinates is imported.
+    fn check_tex_coord(
+        &self,
+        info: &Info,
+        material: &str,
+        api_state: &mut ApiState,
+    ) -> Result<(), ParseError> {
+        if info.tex_coord() != 0 {
+            self.api(api_state.report(ApiError::Unsupported {
+                directive: format!("glTF material \"{}\": uv set", material),
+                name: info.tex_coord().to_string(),
+            }))?;
+        }
+        Ok(())
+    }
+    fn spectrum_image_texture(
+        &mut self,
+        texture: &gltf::texture::Texture,
+        gamma: bool,
+    ) -> Result<Arc<Texture<Spectrum> + Send + Sync>, ParseError> {
+        let wrap_mode: ImageWrap = wrap_mode(texture);
+        let image: &DynamicImage = self.image(&texture.source())?;
+        Ok(Arc::new(ImageTexture::from_image(
+            Box::new(UVMapping2D {
+                su: 1.0,
+                sv: 1.0,
+                du: 0.0,
+                dv: 0.0,
+            }),
+            image,
+            false, // do_trilinear
+            8.0,   // max_aniso
+            wrap_mode,
+            1.0, // scale
+            gamma,
+            convert_to_spectrum,
+        )))
+    }
+    /// Uses a single (linear) color channel as float texture.
+    fn channel_texture(
+        &mut self,
+        texture: &gltf::texture::Texture,
+        channel: usize,
+        scale: Float,
+    ) -> Result<Arc<Texture<Float> + Send + Sync>, ParseError> {
+        let wrap_mode: ImageWrap = wrap_mode(texture);
+        let image: &DynamicImage = self.image(&texture.source())?;
+        Ok(Arc::new(ImageTexture::from_image(
+            Box::new(UVMapping2D {
+                su: 1.0,
+                sv: 1.0,
+                du: 0.0,
+                dv: 0.0,
+            }),
+            image,
+            false, // do_trilinear
+            8.0,   // max_aniso
+            wrap_mode,
+            scale,
+            false, // gamma
+            |s: &Spectrum| s.c[channel],
+        )))
+    }
+    fn render_options(
+        &self,
+        nodes: &Vec<(Node, Transform)>,
+        api_state: &mut ApiState,
+    ) -> Result<(), ParseError> {
+        let x_resolution: i32 = 1280;
+        let mut y_resolution: i32 = 720;
+        for &(ref node, ref camera_to_world) in nodes {
+            let camera = match node.camera() {
+                Some(camera) => camera,
+                None => continue,
+            };
+            let perspective = match camera.projection() {
+                Projection::Perspective(perspective) => perspective,
+                Projection::Orthographic(_) => {
+                    let name: String = camera.name().unwrap_or("").to_string();
+                    self.api(api_state.report(ApiError::Unsupported {
+                        directive: String::from("glTF orthographic camera"),
+                        name: name,
+                    }))?;
+                    continue;
+                }
+            };
+            // glTF cameras look down the negative z axis, the field
+            // of view is vertical
+            let mut fov: Float = perspective.yfov();
+            if let Some(aspect_ratio) = perspective.aspect_ratio() {
+                y_resolution = (x_resolution as Float / aspect_ratio).round().max(1.0) as i32;
+                if aspect_ratio < 1.0 as Float {
+                    fov = 2.0 as Float * ((fov / 2.0 as Float).tan() * aspect_ratio).atan();
+                }
+            }
+            let camera_to_world: Transform = *camera_to_world * Transform::scale(1.0, 1.0, -1.0);
+            self.api(pbrt_transform(
+                api_state,
+                &Transform::inverse(&camera_to_world),
+            ))?;
+            let mut params: ParamSet = ParamSet::default();
+            reset(&mut params, "Camera", "perspective");
+            params.add_float(String::from("fov"), fov.to_degrees());
+            self.api(pbrt_camera(api_state, params))?;
+            break;
+        }
+        let mut params: ParamSet = ParamSet::default();
+        reset(&mut params, "Film", "image");
+        params.add_int(String::from("xresolution"), x_resolution);
+        params.add_int(String::from("yresolution"), y_resolution);
+        self.api(pbrt_film(api_state, params))
+    }
+    fn material(
+        &mut self,
+        material: &GltfMaterial,
+        api_state: &mut ApiState,
+        bsdf_state: &mut BsdfState,
+    ) -> Result<(), ParseError> {
+        let name: String = material_name(material);
+        let mut params: ParamSet = ParamSet::default();
+        reset(&mut params, "MakeNamedMaterial", &name);
+        params.add_string(String::from("type"), String::from("disney"));
+        let pbr = material.pbr_metallic_roughness();
+        let factor: [f32; 4] = pbr.base_color_factor();
+        let base_color: Spectrum = Spectrum::rgb(factor[0], factor[1], factor[2]);
+        if let Some(info) = pbr.base_color_texture() {
+            self.check_tex_coord(&info, &name, api_state)?;
+            let mut texture: Arc<Texture<Spectrum> + Send + Sync> =
+                self.spectrum_image_texture(&info.texture(), true)?;
+            if base_color != Spectrum::new(1.0 as Float) {
+                texture = Arc::new(ScaleTexture::new(
+                    texture,
+                    Arc::new(ConstantTexture::new(base_color)),
+                ));
+            }
+            let texture_name: String = format!("{}.color", name);
+            self.api(pbrt_spectrum_texture(api_state, &texture_name, texture))?;
+            params.add_texture(String::from("color"), texture_name);
+        } else {
+            params.add_rgb_spectrum(String::from("color"), base_color);
+        }
+        // metallic (blue) and roughness (green) share a texture
+        if let Some(info) = pbr.metallic_roughness_texture() {
+            self.check_tex_coord(&info, &name, api_state)?;
+            let metallic: Arc<Texture<Float> + Send + Sync> =
+                self.channel_texture(&info.texture(), 2, pbr.metallic_factor())?;
+            let roughness: Arc<Texture<Float> + Send + Sync> =
+                self.channel_texture(&info.texture(), 1, pbr.roughness_factor())?;
+            let metallic_name: String = format!("{}.metallic", name);
+            let roughness_name: String = format!("{}.roughness", name);
+            self.api(pbrt_float_texture(api_state, &metallic_name, metallic))?;
+            self.api(pbrt_float_texture(api_state, &roughness_name, roughness))?;
+            params.add_texture(String::from("metallic"), metallic_name);
+            params.add_texture(String::from("roughness"), roughness_name);
+        } else {
+            params.add_float(String::from("metallic"), pbr.metallic_factor());
+            params.add_float(String::from("roughness"), pbr.roughness_factor());
+        }
+        if let Some(normal_texture) = material.normal_texture() {
+            let texture: Arc<Texture<Spectrum> + Send + Sync> =
+                self.spectrum_image_texture(&normal_texture.texture(), false)?;
+            let texture_name: String = format!("{}.normal", name);
+            self.api(pbrt_spectrum_texture(api_state, &texture_name, texture))?;
+            params.add_texture(String::from("normalmap"), texture_name);
+        }
+        self.api(pbrt_make_named_material(api_state, bsdf_state, params))
+    }
+    /// Defines glTF's default material (white, fully metallic and
+    /// rough) the first time a primitive without material needs it.
+    fn default_material(
+        &mut self,
+        api_state: &mut ApiState,
+        bsdf_state: &mut BsdfState,
+    ) -> Result<(), ParseError> {
+        if self.default_material {
+            return Ok(());
+        }
+        self.default_material = true;
+        let mut params: ParamSet = ParamSet::default();
+        reset(&mut params, "MakeNamedMaterial", DEFAULT_MATERIAL);
+        params.add_string(String::from("type"), String::from("disney"));
+        params.add_rgb_spectrum(String::from("color"), Spectrum::new(1.0 as Float));
+        params.add_float(String::from("metallic"), 1.0 as Float);
+        params.add_float(String::from("roughness"), 1.0 as Float);
+        self.api(pbrt_make_named_material(api_state, bsdf_state, params))
+    }
+    fn mesh(
+        &mut self,
+        node: &Node,
+        object_to_world: &Transform,
+        api_state: &mut ApiState,
+        bsdf_state: &mut BsdfState,
+    ) -> Result<(), ParseError> {
+        let mesh = match node.mesh() {
+            Some(mesh) => mesh,
+            None => return Ok(()),
+        };
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                self.api(api_state.report(ApiError::Unsupported {
+                    directive: String::from("glTF primitive mode"),
+                    name: format!("{:?}", primitive.mode()),
+                }))?;
+                continue;
+            }
+            let mut params: ParamSet = ParamSet::default();
+            reset(&mut params, "Shape", "trianglemesh");
+            {
+                let buffers: &Vec<Vec<u8>> = &self.buffers;
+                let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()][..]));
+                let p: Vec<Float> = match reader.read_positions() {
+                    Some(positions) => positions.flat_map(|v| v.to_vec()).collect(),
+                    None => {
+                        return Err(self.error(format!(
+                            "mesh {}: primitive without positions",
+                            mesh.index()
+                        )))
+                    }
+                };
+                let n_vertices: usize = p.len() / 3;
+                let indices: Vec<i32> = match reader.read_indices() {
+                    Some(indices) => indices.into_u32().map(|i| i as i32).collect(),
+                    None => (0..n_vertices as i32).collect(),
+                };
+                if indices.iter().any(|i| *i as usize >= n_vertices) {
+                    return Err(
+                        self.error(format!("mesh {}: out of bounds vertex index", mesh.index()))
+                    );
+                }
+                params.add_ints(String::from("indices"), indices);
+                params.add_point3fs(String::from("P"), p);
+                if let Some(normals) = reader.read_normals() {
+                    let n: Vec<Float> = normals.flat_map(|v| v.to_vec()).collect();
+                    params.add_normal3fs(String::from("N"), n);
+                }
+                if let Some(tangents) = reader.read_tangents() {
+                    let s: Vec<Float> = tangents.flat_map(|v| v[..3].to_vec()).collect();
+                    params.add_vector3fs(String::from("S"), s);
+                }
+                // glTF's v coordinate runs from the top to the
+                // bottom of an image
+                if let Some(tex_coords) = reader.read_tex_coords(0) {
+                    let uv: Vec<Float> = tex_coords
+                        .into_f32()
+                        .flat_map(|v| vec![v[0], 1.0 as Float - v[1]])
+                        .collect();
+                    params.add_point2fs(String::from("uv"), uv);
+                }
+            }
+            let material: GltfMaterial = primitive.material();
+            if material.index().is_none() {
+                self.default_material(api_state, bsdf_state)?;
+            }
+            let mut named_material: ParamSet = ParamSet::default();
+            reset(
+                &mut named_material,
+                "NamedMaterial",
+                &material_name(&material),
+            );
+            let emissive: [f32; 3] = material.emissive_factor();
+            self.api(pbrt_attribute_begin(api_state))?;
+            self.api(pbrt_transform(api_state, object_to_world))?;
+            self.api(pbrt_named_material(api_state, named_material))?;
+            if emissive.iter().any(|e| *e > 0.0) {
+                let mut area_light: ParamSet = ParamSet::default();
+                reset(&mut area_light, "AreaLightSource", "diffuse");
+                area_light.add_rgb_spectrum(
+                    String::from("L"),
+                    Spectrum::rgb(emissive[0], emissive[1], emissive[2]),
+                );
+                self.api(pbrt_area_light_source(api_state, area_light))?;
+            }
+            self.api(pbrt_shape(api_state, bsdf_state, params))?;
+            self.api(pbrt_attribute_end(api_state))?;
+        }
+        Ok(())
+    }
+}
+
+/// Mirrored repeat is not supported by **MipMap**.
+fn wrap_mode(texture: &gltf::texture::Texture) -> ImageWrap {
+    match texture.sampler().wrap_s() {
+        WrappingMode::ClampToEdge => ImageWrap::Clamp,
+        _ => ImageWrap::Repeat,
+    }
+}
@@ -0,0 +1,1690 @@
+//! Imports Mitsuba scenes (**.xml**, for Mitsuba 0.6 as well as
+//! Mitsuba 2 and 3), as defined by the XML subset in `mitsuba.pest`,
+//! by translating the plugins into calls of the *pbrt_* functions of
+//! the **core::api** module.
+//!
+//! Included files (`include`) get merged into the scene and defaults
+//! (`default`) replace their `$name` references. Property names of
+//! Mitsuba 0.6 (e.g. `toWorld`) are matched in their snake case
+//! spelling (`to_world`). Supported plugins are:
+//!
+//! * sensors: `perspective` and `thinlens`, with `hdrfilm` or
+//!   `ldrfilm` films and `box`, `gaussian`, `tent`, `mitchell`, or
+//!   `lanczos` reconstruction filters
+//! * samplers: `independent` (as `random`), `stratified`,
+//!   `ldsampler` (as `lowdiscrepancy`), `halton`, and `sobol`
+//! * integrators: `path` and `direct` (as `directlighting`)
+//! * BSDFs: `diffuse` (as `matte`), `conductor` and `roughconductor`
+//!   (as `metal`, or `mirror` for the material `none`), `dielectric`
+//!   and `roughdielectric` (as `glass`), `plastic` and `roughplastic`,
+//!   wrapped by `twosided` or not
+//! * textures: `bitmap` (as `imagemap`) and `checkerboard`
+//! * emitters: `area` (as `diffuse` area light), `point`, `spot`,
+//!   `directional` (as `distant`), `envmap`, and `constant` (as
+//!   `infinite`)
+//! * shapes: `obj` (as `objmesh`), `ply` (as `plymesh`),
+//!   `serialized`, `rectangle`, and `cube` (as `trianglemesh`),
+//!   `sphere`, `disk`, `cylinder`, as well as `shapegroup` and
+//!   `instance` (as objects)
+//!
+//! Everything else gets reported like any unsupported type of the
+//! scene description (see **ErrorMode**), properties which were never
+//! looked at like unused parameters. Like for **parse_file()** in the
+//! **parser** module, rendering is left to the caller.
+
+// std
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+// others
+use byteorder::{LittleEndian, ReadBytesExt};
+use flate2::read::ZlibDecoder;
+use pest::iterators::Pair;
+use pest::Parser;
+// pbrt
+use core::api::{pbrt_area_light_source, pbrt_attribute_begin, pbrt_attribute_end, pbrt_camera};
+use core::api::{pbrt_film, pbrt_integrator, pbrt_light_source, pbrt_make_named_material};
+use core::api::{pbrt_material, pbrt_named_material, pbrt_object_begin, pbrt_object_end};
+use core::api::{pbrt_object_instance, pbrt_pixel_filter, pbrt_reverse_orientation, pbrt_sampler};
+use core::api::{pbrt_shape, pbrt_texture, pbrt_transform, pbrt_world_begin};
+use core::api::{ApiError, ApiState, BsdfState};
+use core::geometry::{Point3f, Vector3f};
+use core::paramset::ParamSet;
+use core::pbrt::{Float, Spectrum};
+use core::transform::Transform;
+use parser::{frame, Chunk, ParseError, STRING_INPUT};
+
+#[derive(Parser)]
+#[grammar = "parser/mitsuba.pest"]
+struct MitsubaParser;
+
+/// Included files can include other files, but not endlessly.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Indices of refraction Mitsuba knows by name.
+const IOR_DATA: [(&str, Float); 23] = [
+    ("vacuum", 1.0),
+    ("helium", 1.00004),
+    ("hydrogen", 1.00013),
+    ("air", 1.000277),
+    ("carbon dioxide", 1.00045),
+    ("water", 1.333),
+    ("acetone", 1.36),
+    ("ethanol", 1.361),
+    ("carbon tetrachloride", 1.461),
+    ("glycerol", 1.4729),
+    ("benzene", 1.501),
+    ("silicone oil", 1.52045),
+    ("bromine", 1.661),
+    ("water ice", 1.31),
+    ("fused quartz", 1.458),
+    ("pyrex", 1.47),
+    ("acrylic glass", 1.49),
+    ("polypropylene", 1.49),
+    ("bk7", 1.5046),
+    ("sodium chloride", 1.544),
+    ("amber", 1.55),
+    ("pet", 1.575),
+    ("diamond", 2.419),
+];
+
+/// RGB fits of the complex indices of refraction (eta and k) of some
+/// of the conductors Mitsuba knows by name.
+const CONDUCTOR_DATA: [(&str, [Float; 3], [Float; 3]); 5] = [
+    (
+        "Ag",
+        [0.155_264_65, 0.116_723_3, 0.138_380_7],
+        [4.828_343_3, 3.122_246, 2.146_950_4],
+    ),
+    (
+        "Al",
+        [1.657_46, 0.880_369, 0.521_228_7],
+        [9.223_869, 6.269_523, 4.837_001],
+    ),
+    (
+        "Au",
+        [0.143_118_96, 0.374_957_04, 1.442_478_6],
+        [3.983_160_4, 2.385_720_7, 1.603_215_3],
+    ),
+    (
+        "Cr",
+        [4.369_683, 2.916_702_5, 1.654_700_5],
+        [5.206_434, 4.231_364_4, 3.754_946_7],
+    ),
+    (
+        "Cu",
+        [0.200_437_7, 0.924_033_4, 1.102_212],
+        [3.912_948_5, 2.452_847_7, 2.142_188],
+    ),
+];
+
+/// An XML element. Elements with a `name` attribute are properties
+/// of their parent (plugin), which remember being looked at.
+struct Element {
+    tag: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    /// index into the list of files read
+    file: usize,
+    line: usize,
+    column: usize,
+    used: Cell<bool>,
+}
+
+impl Element {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        match self.attributes.iter().find(|&&(ref key, _)| key == name) {
+            Some(&(_, ref value)) => Some(value),
+            None => None,
+        }
+    }
+    fn plugin_type(&self) -> &str {
+        self.attribute("type").unwrap_or("")
+    }
+    /// If a property is given several times, the last one counts.
+    fn property(&self, name: &str) -> Option<&Element> {
+        let found: Option<&Element> =
+            self.children
+                .iter()
+                .rev()
+                .find(|child| match child.attribute("name") {
+                    Some(key) => snake_case(key) == name,
+                    None => false,
+                });
+        if let Some(property) = found {
+            property.used.set(true);
+        }
+        found
+    }
+    fn child(&self, tag: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.tag == tag)
+    }
+}
+
+/// Keeps track of what was handed over to the API already.
+struct ApiCalls<'a> {
+    api_state: &'a mut ApiState,
+    bsdf_state: &'a mut BsdfState,
+    textures: HashSet<String>,
+    materials: HashSet<String>,
+}
+
+/// The scene with all plugins which can be referred to by id.
+struct MitsubaScene<'a> {
+    files: &'a Vec<String>,
+    root: &'a Element,
+    ids: HashMap<String, &'a Element>,
+}
+
+/// A triangle mesh read from a **serialized** file.
+#[derive(Default)]
+struct SerializedMesh {
+    indices: Vec<i32>,
+    p: Vec<Float>,
+    n: Vec<Float>,
+    uv: Vec<Float>,
+}
+
+/// Reads the Mitsuba scene from the file found at `path` and feeds
+/// it into `api_state`. The directory containing the file becomes the
+/// search directory for other files (e.g. meshes) referenced by the
+/// scene.
+pub fn parse_file(
+    path: &Path,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let file: String = path.display().to_string();
+    let absolute_path: PathBuf = if path.is_relative() {
+        match env::current_dir() {
+            Ok(cp) => cp.join(path),
+            Err(e) => return Err(ParseError::new(&file, 0, 0, e.to_string())),
+        }
+    } else {
+        PathBuf::from(path)
+    };
+    if let Some(search_directory) = absolute_path.parent() {
+        api_state.search_directory = Some(Box::new(PathBuf::from(search_directory)));
+    }
+    let mut files: Vec<String> = Vec::new();
+    let root: Element = load_file(path, 0, &mut files)?;
+    import(root, &files, api_state, bsdf_state)
+}
+
+/// Reads the Mitsuba scene from a string and feeds it into
+/// `api_state`. Included files are searched for in the current
+/// directory.
+pub fn parse_str(
+    source: &str,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let mut files: Vec<String> = Vec::new();
+    let root: Element = load_str(source, STRING_INPUT, Path::new(""), 0, &mut files)?;
+    import(root, &files, api_state, bsdf_state)
+}
+
+fn load_file(path: &Path, depth: usize, files: &mut Vec<String>) -> Result<Element, ParseError> {
+    let file: String = path.display().to_string();
+    let source: String = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => return Err(ParseError::new(&file, 0, 0, e.to_string())),
+    };
+    let directory: &Path = match path.parent() {
+        Some(parent) => parent,
+        None => Path::new(""),
+    };
+    load_str(&source, &file, directory, depth, files)
+}
+
+/// Parses the XML and merges the scenes of included files (relative
+/// to **directory**) into the root element.
+fn load_str(
+    source: &str,
+    file: &str,
+    directory: &Path,
+    depth: usize,
+    files: &mut Vec<String>,
+) -> Result<Element, ParseError> {
+    let chunk: Chunk = Chunk {
+        file: file,
+        first_line: 1,
+    };
+    files.push(String::from(file));
+    let file_index: usize = files.len() - 1;
+    let pairs = match MitsubaParser::parse(Rule::xml, source) {
+        Ok(pairs) => pairs,
+        Err(e) => return Err(ParseError::from_pest(&chunk, e)),
+    };
+    let mut root: Option<Element> = None;
+    for pair in pairs {
+        if pair.as_rule() == Rule::element {
+            root = Some(parse_element(pair, &chunk, file_index)?);
+        }
+    }
+    let root: Element = match root {
+        Some(root) => root,
+        None => {
+            return Err(ParseError::new(
+                file,
+                0,
+                0,
+                String::from("no XML element found"),
+            ))
+        }
+    };
+    if root.tag != "scene" {
+        return Err(ParseError::new(
+            file,
+            root.line,
+            root.column,
+            format!("<scene> expected, found <{}>", root.tag),
+        ));
+    }
+    let mut children: Vec<Element> = Vec::with_capacity(root.children.len());
+    for child in root.children {
+        if child.tag != "include" {
+            children.push(child);
+            continue;
+        }
+        let filename: &str = match child.attribute("filename") {
+            Some(filename) => filename,
+            None => {
+                return Err(ParseError::new(
+                    file,
+                    child.line,
+                    child.column,
+                    String::from("<include> without filename"),
+                ))
+            }
+        };
+        if depth >= MAX_INCLUDE_DEPTH {
+            return Err(ParseError::new(
+                file,
+                child.line,
+                child.column,
+                format!("files included more than {} levels deep", MAX_INCLUDE_DEPTH),
+            ));
+        }
+        let included: Element = load_file(&directory.join(filename), depth + 1, files)?;
+        children.extend(included.children);
+    }
+    Ok(Element {
+        children: children,
+        ..root
+    })
+}
+
+fn parse_element(pair: Pair<Rule>, chunk: &Chunk, file: usize) -> Result<Element, ParseError> {
+    let (line, column) = chunk.line_col(&pair);
+    let mut tag: String = String::new();
+    let mut attributes: Vec<(String, String)> = Vec::new();
+    let mut children: Vec<Element> = Vec::new();
+    for inner in pair.into_inner() {
+        match inner.as_rule() {
+            Rule::name => tag = String::from(inner.as_str()),
+            Rule::attribute => {
+                let mut key_value = inner.into_inner();
+                let key: String = match key_value.next() {
+                    Some(key) => String::from(key.as_str()),
+                    None => String::new(),
+                };
+                let value: String = match key_value.next().and_then(|v| v.into_inner().next()) {
+                    Some(value) => unescape(value.as_str()),
+                    None => String::new(),
+                };
+                attributes.push((key, value));
+            }
+            Rule::element => children.push(parse_element(inner, chunk, file)?),
+            Rule::end_tag => {
+                let end_tag: &str = match inner.clone().into_inner().next() {
+                    Some(name) => name.as_str(),
+                    None => "",
+                };
+                if end_tag != tag {
+                    return Err(ParseError::at(
+                        chunk,
+                        &inner,
+                        format!("</{}> expected, found </{}>", tag, end_tag),
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(Element {
+        tag: tag,
+        attributes: attributes,
+        children: children,
+        file: file,
+        line: line,
+        column: column,
+        used: Cell::new(false),
+    })
+}
+
+/// Replaces the predefined entities and character references.
+fn unescape(value: &str) -> String {
+    if !value.contains('&') {
+        return String::from(value);
+    }
+    let mut result: String = String::with_capacity(value.len());
+    let mut rest: &str = value;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end: usize = match rest.find(';') {
+            Some(end) => end,
+            None => break,
+        };
+        let replacement: Option<char> = match &rest[1..end] {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            reference if reference.starts_with("#x") => u32::from_str_radix(&reference[2..], 16)
+                .ok()
+                .and_then(::std::char::from_u32),
+            reference if reference.starts_with('#') => u32::from_str(&reference[1..])
+                .ok()
+                .and_then(::std::char::from_u32),
+            _ => None,
+        };
+        match replacement {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Mitsuba 0.6 uses camel case (e.g. `intIOR`), later versions snake
+/// case (`int_ior`).
+fn snake_case(name: &str) -> String {
+    let mut result: String = String::with_capacity(name.len() + 4);
+    let mut previous_lower: bool = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && previous_lower {
+            result.push('_');
+        }
+        previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        result.push(c.to_ascii_lowercase());
+    }
+    result
+}
+
+/// Replaces `$name` in all attribute values by the values of the
+/// `default` elements (longer names first).
+fn substitute_defaults(element: &mut Element, defaults: &Vec<(String, String)>) {
+    for &mut (_, ref mut value) in element.attributes.iter_mut() {
+        if value.contains('$') {
+            for &(ref name, ref default) in defaults {
+                *value = value.replace(&format!("${}", name), default);
+            }
+        }
+    }
+    for child in element.children.iter_mut() {
+        substitute_defaults(child, defaults);
+    }
+}
+
+fn import(
+    mut root: Element,
+    files: &Vec<String>,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let mut defaults: Vec<(String, String)> = Vec::new();
+    for child in root.children.iter().filter(|c| c.tag == "default") {
+        if let (Some(name), Some(value)) = (child.attribute("name"), child.attribute("value")) {
+            defaults.push((String::from(name), String::from(value)));
+        }
+    }
+    defaults.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+    substitute_defaults(&mut root, &defaults);
+    let scene: MitsubaScene = MitsubaScene::new(files, &root);
+    let mut api: ApiCalls = ApiCalls {
+        api_state: api_state,
+        bsdf_state: bsdf_state,
+        textures: HashSet::new(),
+        materials: HashSet::new(),
+    };
+    scene.render_options(&mut api)?;
+    scene.world(&mut api)
+}
+
+/// Splits a list of numbers separated by commas and/or white space.
+fn split_numbers(value: &str) -> Vec<&str> {
+    value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Mitsuba's environment maps use the y axis as up direction, with
+/// the center of the image looking down the negative z axis, pbrt's
+/// the z axis, with the center looking down the negative x axis.
+fn environment_frame() -> Transform {
+    Transform::new(
+        0.0, -1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+    )
+}
+
+/// Reads mesh number **shape_index** of a file in Mitsuba's
+/// serialized format, a zlib compressed stream per mesh, followed by
+/// a table of the offsets to all meshes.
+fn read_serialized(path: &Path, shape_index: usize) -> Result<SerializedMesh, String> {
+    // flags
+    const VERTEX_NORMALS: u32 = 0x0001;
+    const TEXTURE_COORDINATES: u32 = 0x0002;
+    const VERTEX_COLORS: u32 = 0x0008;
+    const FACE_NORMALS: u32 = 0x0010;
+    const DOUBLE_PRECISION: u32 = 0x2000;
+    let location = |message: String| format!("{}: {}", path.display(), message);
+    let data: Vec<u8> = fs::read(path).map_err(|e| location(e.to_string()))?;
+    let header = |offset: usize| -> Result<u16, String> {
+        if offset + 4 > data.len()
+            || (&data[offset..]).read_u16::<LittleEndian>().ok() != Some(0x041c)
+        {
+            return Err(location(String::from("not a serialized mesh file")));
+        }
+        Ok((&data[offset + 2..])
+            .read_u16::<LittleEndian>()
+            .unwrap_or(0))
+    };
+    let version: u16 = header(0)?;
+    let offset_size: usize = if version >= 4 { 8 } else { 4 };
+    let n_meshes: usize = if data.len() >= 4 {
+        (&data[data.len() - 4..])
+            .read_u32::<LittleEndian>()
+            .unwrap_or(0) as usize
+    } else {
+        0
+    };
+    if shape_index >= n_meshes || n_meshes * offset_size + 4 > data.len() {
+        return Err(location(format!(
+            "shape index {} out of range ({} meshes)",
+            shape_index, n_meshes
+        )));
+    }
+    let table: usize = data.len() - 4 - n_meshes * offset_size + shape_index * offset_size;
+    let mut entry: &[u8] = &data[table..];
+    let offset: usize = if offset_size == 8 {
+        entry.read_u64::<LittleEndian>().unwrap_or(0) as usize
+    } else {
+        entry.read_u32::<LittleEndian>().unwrap_or(0) as usize
+    };
+    let version: u16 = header(offset)?;
+    let mut stream = ZlibDecoder::new(&data[offset + 4..]);
+    let truncated = |e: ::std::io::Error| location(format!("mesh {}: {}", shape_index, e));
+    let flags: u32 = stream.read_u32::<LittleEndian>().map_err(&truncated)?;
+    if version >= 4 {
+        // null terminated name
+        while stream.read_u8().map_err(&truncated)? != 0 {}
+    }
+    let n_vertices: usize = stream.read_u64::<LittleEndian>().map_err(&truncated)? as usize;
+    let n_triangles: usize = stream.read_u64::<LittleEndian>().map_err(&truncated)? as usize;
+    let mut read_floats = |n: usize| -> Result<Vec<Float>, String> {
+        let mut values: Vec<Float> = Vec::with_capacity(n);
+        for _ in 0..n {
+            let value: Float = if flags & DOUBLE_PRECISION != 0 {
+                stream.read_f64::<LittleEndian>().map_err(&truncated)? as Float
+            } else {
+                stream.read_f32::<LittleEndian>().map_err(&truncated)?
+            };
+            values.push(value);
+        }
+        Ok(values)
+    };
+    let mut mesh: SerializedMesh = SerializedMesh::default();
+    mesh.p = read_floats(n_vertices * 3)?;
+    if flags & VERTEX_NORMALS != 0 {
+        mesh.n = read_floats(n_vertices * 3)?;
+    }
+    if flags & TEXTURE_COORDINATES != 0 {
+        mesh.uv = read_floats(n_vertices * 2)?;
+    }
+    if flags & VERTEX_COLORS != 0 {
+        read_floats(n_vertices * 3)?;
+    }
+    if flags & FACE_NORMALS != 0 {
+        mesh.n.clear();
+    }
+    mesh.indices.reserve(n_triangles * 3);
+    for _ in 0..n_triangles * 3 {
+        let index: usize = if n_vertices > u32::max_value() as usize {
+            stream.read_u64::<LittleEndian>().map_err(&truncated)? as usize
+        } else {
+            stream.read_u32::<LittleEndian>().map_err(&truncated)? as usize
+        };
+        if index >= n_vertices {
+            return Err(location(format!(
+                "mesh {}: out of bounds vertex index {}",
+                shape_index, index
+            )));
+        }
+        mesh.indices.push(index as i32);
+    }
+    Ok(mesh)
+}
+
+impl<'a> MitsubaScene<'a> {
+    fn new(files: &'a Vec<String>, root: &'a Element) -> Self {
+        let mut ids: HashMap<String, &'a Element> = HashMap::new();
+        for child in &root.children {
+            if let Some(id) = child.attribute("id") {
+                ids.insert(String::from(id), child);
+            }
+        }
+        MitsubaScene {
+            files: files,
+            root: root,
+            ids: ids,
+        }
+    }
+    // render options
+
+    fn render_options(&self, api: &mut ApiCalls) -> Result<(), ParseError> {
+        if let Some(sensor) = self.root.child("sensor") {
+            self.sensor(sensor, api)?;
+        }
+        let mut params: ParamSet;
+        match self.root.child("integrator") {
+            Some(integrator) => {
+                match integrator.plugin_type() {
+                    "path" => {
+                        // Mitsuba counts the path segments (-1 means
+                        // infinite), pbrt the bounces
+                        params = self.new_params("Integrator", "path", integrator);
+                        let max_depth: i32 = self.integer(integrator, "max_depth", -1)?;
+                        let max_depth: i32 = if max_depth < 0 { 64 } else { max_depth - 1 };
+                        params.add_int(String::from("maxdepth"), max_depth.max(0));
+                    }
+                    "direct" => {
+                        params = self.new_params("Integrator", "directlighting", integrator);
+                    }
+                    _ => {
+                        self.unsupported(integrator, api)?;
+                        params = self.new_params("Integrator", "path", integrator);
+                    }
+                }
+                self.api_result(integrator, pbrt_integrator(api.api_state, params))?;
+                self.check_unused(integrator, api);
+            }
+            None => {
+                // Mitsuba's default integrator
+                params = self.new_params("Integrator", "path", self.root);
+                self.api_result(self.root, pbrt_integrator(api.api_state, params))?;
+            }
+        }
+        self.api_result(self.root, pbrt_world_begin(api.api_state))
+    }
+    fn sensor(&self, sensor: &Element, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let mut x_resolution: i32 = 768;
+        let mut y_resolution: i32 = 576;
+        if let Some(film) = sensor.child("film") {
+            match film.plugin_type() {
+                "hdrfilm" | "ldrfilm" => {}
+                _ => self.unsupported(film, api)?,
+            }
+            x_resolution = self.integer(film, "width", x_resolution)?;
+            y_resolution = self.integer(film, "height", y_resolution)?;
+            if let Some(filter) = film.child("rfilter") {
+                self.filter(filter, api)?;
+            }
+            let mut params: ParamSet = self.new_params("Film", "image", film);
+            params.add_int(String::from("xresolution"), x_resolution);
+            params.add_int(String::from("yresolution"), y_resolution);
+            self.api_result(film, pbrt_film(api.api_state, params))?;
+            self.check_unused(film, api);
+        }
+        if let Some(sampler) = sensor.child("sampler") {
+            self.sampler(sampler, api)?;
+        }
+        // Mitsuba's cameras look down the z axis, with the x axis
+        // pointing to the left
+        let camera_to_world: Transform =
+            self.transform(sensor, "to_world")? * Transform::scale(-1.0, 1.0, 1.0);
+        self.api_result(
+            sensor,
+            pbrt_transform(api.api_state, &Transform::inverse(&camera_to_world)),
+        )?;
+        let mut params: ParamSet = self.new_params("Camera", "perspective", sensor);
+        match sensor.plugin_type() {
+            "perspective" | "thinlens" => {
+                // pbrt's field of view belongs to the shorter image
+                // axis
+                let (fov, fov_axis): (Float, String) = match sensor.property("focal_length") {
+                    Some(focal_length) => {
+                        let value: String = self.value(focal_length)?;
+                        let length: Float = match Float::from_str(value.trim_end_matches("mm")) {
+                            Ok(length) => length,
+                            Err(_) => {
+                                return Err(self.error(
+                                    focal_length,
+                                    format!("invalid focal length {:?}", value),
+                                ))
+                            }
+                        };
+                        // 35mm equivalent (36 x 24 mm)
+                        let diagonal: Float = (36.0 as Float).hypot(24.0);
+                        (
+                            2.0 * (diagonal / (2.0 * length)).atan().to_degrees(),
+                            String::from("diagonal"),
+                        )
+                    }
+                    None => (
+                        self.float(sensor, "fov", 90.0)?,
+                        self.string(sensor, "fov_axis", "x")?,
+                    ),
+                };
+                let (w, h): (Float, Float) = (x_resolution as Float, y_resolution as Float);
+                let t: Float = (fov.to_radians() / 2.0).tan();
+                let (tan_x, tan_y): (Float, Float) = match fov_axis.as_str() {
+                    "y" => (t * w / h, t),
+                    "diagonal" => (t * w / w.hypot(h), t * h / w.hypot(h)),
+                    "smaller" if w < h => (t, t * h / w),
+                    "smaller" => (t * w / h, t),
+                    "larger" if w < h => (t * w / h, t),
+                    _ => (t, t * h / w),
+                };
+                let fov: Float = 2.0 * tan_x.min(tan_y).atan().to_degrees();
+                params.add_float(String::from("fov"), fov);
+                if sensor.plugin_type() == "thinlens" {
+                    let lens_radius: Float = self.float(sensor, "aperture_radius", 0.0)?;
+                    let focal_distance: Float = self.float(sensor, "focus_distance", 0.0)?;
+                    params.add_float(String::from("lensradius"), lens_radius);
+                    params.add_float(String::from("focaldistance"), focal_distance);
+                }
+            }
+            _ => self.unsupported(sensor, api)?,
+        }
+        self.api_result(sensor, pbrt_camera(api.api_state, params))?;
+        self.check_unused(sensor, api);
+        Ok(())
+    }
+    fn filter(&self, filter: &Element, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let mut params: ParamSet;
+        match filter.plugin_type() {
+            "box" => {
+                params = self.new_params("PixelFilter", "box", filter);
+                let radius: Float = self.float(filter, "radius", 0.5)?;
+                params.add_float(String::from("xwidth"), radius);
+                params.add_float(String::from("ywidth"), radius);
+            }
+            "gaussian" => {
+                // Mitsuba cuts the filter off after four standard
+                // deviations
+                params = self.new_params("PixelFilter", "gaussian", filter);
+                let stddev: Float = self.float(filter, "stddev", 0.5)?;
+                params.add_float(String::from("alpha"), 1.0 / (2.0 * stddev * stddev));
+                params.add_float(String::from("xwidth"), 4.0 * stddev);
+                params.add_float(String::from("ywidth"), 4.0 * stddev);
+            }
+            "tent" => {
+                params = self.new_params("PixelFilter", "triangle", filter);
+                let radius: Float = self.float(filter, "radius", 1.0)?;
+                params.add_float(String::from("xwidth"), radius);
+                params.add_float(String::from("ywidth"), radius);
+            }
+            "mitchell" => {
+                params = self.new_params("PixelFilter", "mitchell", filter);
+                params.add_float(String::from("B"), self.float(filter, "b", 1.0 / 3.0)?);
+                params.add_float(String::from("C"), self.float(filter, "c", 1.0 / 3.0)?);
+            }
+            "lanczos" => {
+                params = self.new_params("PixelFilter", "sinc", filter);
+                let lobes: Float = self.integer(filter, "lobes", 3)? as Float;
+                params.add_float(String::from("xwidth"), lobes);
+                params.add_float(String::from("ywidth"), lobes);
+                params.add_float(String::from("tau"), lobes);
+            }
+            _ => return self.unsupported(filter, api),
+        }
+        self.api_result(filter, pbrt_pixel_filter(api.api_state, params))?;
+        self.check_unused(filter, api);
+        Ok(())
+    }
+    fn sampler(&self, sampler: &Element, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let sample_count: i32 = self.integer(sampler, "sample_count", 4)?;
+        let mut params: ParamSet;
+        match sampler.plugin_type() {
+            "independent" => {
+                params = self.new_params("Sampler", "random", sampler);
+                params.add_int(String::from("pixelsamples"), sample_count);
+            }
+            "stratified" => {
+                // Mitsuba rounds up to a square number
+                params = self.new_params("Sampler", "stratified", sampler);
+                let n: i32 = (sample_count as Float).sqrt().ceil() as i32;
+                params.add_int(String::from("xsamples"), n);
+                params.add_int(String::from("ysamples"), n);
+                params.add_bool(
+                    String::from("jitter"),
+                    self.boolean(sampler, "jitter", true)?,
+                );
+            }
+            "ldsampler" | "halton" | "sobol" => {
+                let name: &str = match sampler.plugin_type() {
+                    "ldsampler" => "lowdiscrepancy",
+                    name => name,
+                };
+                params = self.new_params("Sampler", name, sampler);
+                params.add_int(String::from("pixelsamples"), sample_count);
+            }
+            _ => return self.unsupported(sampler, api),
+        }
+        self.api_result(sampler, pbrt_sampler(api.api_state, params))?;
+        self.check_unused(sampler, api);
+        Ok(())
+    }
+    // world
+
+    fn world(&self, api: &mut ApiCalls) -> Result<(), ParseError> {
+        for element in &self.root.children {
+            match element.tag.as_str() {
+                "bsdf" => {
+                    // only the ones with an id can be referred to
+                    if let Some(id) = element.attribute("id") {
+                        if let Some(mut params) = self.bsdf(element, api)? {
+                            params.key_word = String::from("MakeNamedMaterial");
+                            params.add_string(String::from("type"), params.name.clone());
+                            params.name = String::from(id);
+                            self.api_result(
+                                element,
+                                pbrt_make_named_material(api.api_state, api.bsdf_state, params),
+                            )?;
+                            api.materials.insert(String::from(id));
+                        }
+                    }
+                }
+                "emitter" => self.emitter(element, api)?,
+                "shape" => self.shape(element, api)?,
+                // textures get created when needed, the rest is part
+                // of the render options
+                "texture" | "default" | "sensor" | "integrator" => {}
+                _ => self.unsupported(element, api)?,
+            }
+        }
+        Ok(())
+    }
+    /// Follows a `ref` element to the plugin with that id.
+    fn resolve<'b>(&'b self, element: &'b Element) -> Result<&'b Element, ParseError> {
+        if element.tag != "ref" {
+            return Ok(element);
+        }
+        let id: &str = element.attribute("id").unwrap_or("");
+        match self.ids.get(id) {
+            Some(referenced) => Ok(*referenced),
+            None => Err(self.error(element, format!("no plugin with id \"{}\"", id))),
+        }
+    }
+    /// Returns the parameters of a **Material** statement (named like
+    /// the pbrt material type), unless the BSDF isn't supported.
+    fn bsdf(&self, element: &Element, api: &mut ApiCalls) -> Result<Option<ParamSet>, ParseError> {
+        // pbrt's materials are two-sided anyway
+        if element.plugin_type() == "twosided" {
+            return match element
+                .children
+                .iter()
+                .find(|c| c.tag == "bsdf" || c.tag == "ref")
+            {
+                Some(inner) => {
+                    let inner: &Element = self.resolve(inner)?;
+                    self.bsdf(inner, api)
+                }
+                None => Err(self.error(element, String::from("twosided BSDF without BSDF"))),
+            };
+        }
+        let mut params: ParamSet;
+        match element.plugin_type() {
+            "diffuse" => {
+                params = self.new_params("Material", "matte", element);
+                self.spectrum_parameter(element, "reflectance", 0.5, "Kd", &mut params, api)?;
+            }
+            "conductor" | "roughconductor" => {
+                let material: String = self.string(element, "material", "Cu")?;
+                if material == "none" {
+                    params = self.new_params("Material", "mirror", element);
+                    self.spectrum_parameter(
+                        element,
+                        "specular_reflectance",
+                        1.0,
+                        "Kr",
+                        &mut params,
+                        api,
+                    )?;
+                    return Ok(Some(params));
+                }
+                params = self.new_params("Material", "metal", element);
+                if element.property("eta").is_some() || element.property("k").is_some() {
+                    let eta: Spectrum = self.spectrum(element, "eta", Spectrum::new(0.0), api)?;
+                    let k: Spectrum = self.spectrum(element, "k", Spectrum::new(1.0), api)?;
+                    params.add_rgb_spectrum(String::from("eta"), eta);
+                    params.add_rgb_spectrum(String::from("k"), k);
+                } else {
+                    match CONDUCTOR_DATA
+                        .iter()
+                        .find(|&&(name, _, _)| name == material)
+                    {
+                        Some(&(_, eta, k)) => {
+                            params.add_rgb_spectrum(String::from("eta"), Spectrum::from_rgb(&eta));
+                            params.add_rgb_spectrum(String::from("k"), Spectrum::from_rgb(&k));
+                        }
+                        None => {
+                            // pbrt's metal defaults to copper
+                            self.api_result(
+                                element,
+                                api.api_state.report(ApiError::Unsupported {
+                                    directive: String::from("conductor material"),
+                                    name: material,
+                                }),
+                            )?;
+                        }
+                    }
+                }
+                self.roughness(element, &mut params)?;
+            }
+            "dielectric" | "roughdielectric" => {
+                params = self.new_params("Material", "glass", element);
+                let int_ior: Float = self.ior(element, "int_ior", "bk7")?;
+                let ext_ior: Float = self.ior(element, "ext_ior", "air")?;
+                params.add_float(String::from("index"), int_ior / ext_ior);
+                if element.plugin_type() == "roughdielectric" {
+                    self.roughness(element, &mut params)?;
+                }
+            }
+            "plastic" | "roughplastic" => {
+                params = self.new_params("Material", "plastic", element);
+                self.spectrum_parameter(
+                    element,
+                    "diffuse_reflectance",
+                    0.5,
+                    "Kd",
+                    &mut params,
+                    api,
+                )?;
+                self.spectrum_parameter(
+                    element,
+                    "specular_reflectance",
+                    1.0,
+                    "Ks",
+                    &mut params,
+                    api,
+                )?;
+                if element.plugin_type() == "roughplastic" {
+                    let alpha: Float = self.float(element, "alpha", 0.1)?;
+                    params.add_float(String::from("roughness"), alpha);
+                    params.add_bool(String::from("remaproughness"), false);
+                } else {
+                    params.add_float(String::from("roughness"), 0.0);
+                }
+            }
+            _ => {
+                self.unsupported(element, api)?;
+                return Ok(None);
+            }
+        }
+        self.check_unused(element, api);
+        Ok(Some(params))
+    }
+    /// Smooth conductors and dielectrics use pbrt's smallest
+    /// roughness, rough ones Mitsuba's alpha (the default distribution
+    /// is GGX, which is pbrt's Trowbridge-Reitz).
+    fn roughness(&self, element: &Element, params: &mut ParamSet) -> Result<(), ParseError> {
+        if !element.plugin_type().starts_with("rough") {
+            if element.plugin_type() != "dielectric" {
+                params.add_float(String::from("roughness"), 0.0);
+            }
+            return Ok(());
+        }
+        let alpha: Float = self.float(element, "alpha", 0.1)?;
+        let alpha_u: Float = self.float(element, "alpha_u", alpha)?;
+        let alpha_v: Float = self.float(element, "alpha_v", alpha)?;
+        params.add_float(String::from("uroughness"), alpha_u);
+        params.add_float(String::from("vroughness"), alpha_v);
+        params.add_bool(String::from("remaproughness"), false);
+        Ok(())
+    }
+    /// An index of refraction, given as number or name.
+    fn ior(&self, element: &Element, name: &str, default: &str) -> Result<Float, ParseError> {
+        let value: String = match element.property(name) {
+            Some(property) => {
+                if property.tag == "float" {
+                    return self.float(element, name, 1.0);
+                }
+                self.value(property)?
+            }
+            None => String::from(default),
+        };
+        match IOR_DATA
+            .iter()
+            .find(|&&(ior_name, _)| ior_name == value.to_lowercase())
+        {
+            Some(&(_, ior)) => Ok(ior),
+            None => {
+                match Float::from_str(&value) {
+                    Ok(ior) => Ok(ior),
+                    Err(_) => match element.property(name) {
+                        Some(property) => Err(self
+                            .error(property, format!("unknown index of refraction {:?}", value))),
+                        None => Ok(1.0),
+                    },
+                }
+            }
+        }
+    }
+    /// Adds a color (multiplied by **scale**) or a texture, if the
+    /// property is a (reference to a) texture.
+    fn spectrum_parameter(
+        &self,
+        element: &Element,
+        name: &str,
+        default: Float,
+        pbrt_name: &str,
+        params: &mut ParamSet,
+        api: &mut ApiCalls,
+    ) -> Result<(), ParseError> {
+        let property: &Element = match element.property(name) {
+            Some(property) => self.resolve(property)?,
+            None => {
+                params.add_rgb_spectrum(String::from(pbrt_name), Spectrum::new(default));
+                return Ok(());
+            }
+        };
+        if property.tag == "texture" {
+            match self.texture(property, api)? {
+                Some(texture_name) => params.add_texture(String::from(pbrt_name), texture_name),
+                None => params.add_rgb_spectrum(String::from(pbrt_name), Spectrum::new(default)),
+            }
+            return Ok(());
+        }
+        let color: Spectrum = self.spectrum(element, name, Spectrum::new(default), api)?;
+        params.add_rgb_spectrum(String::from(pbrt_name), color);
+        Ok(())
+    }
+    /// Creates a spectrum texture the first time it's needed, named
+    /// like its id (or location), unless it isn't supported.
+    fn texture(&self, element: &Element, api: &mut ApiCalls) -> Result<Option<String>, ParseError> {
+        let name: String = match element.attribute("id") {
+            Some(id) => String::from(id),
+            None => self.location(element),
+        };
+        if api.textures.contains(&name) {
+            return Ok(Some(name));
+        }
+        // the uv coordinates of meshes created here are flipped in v,
+        // because Mitsuba's images start at the top, pbrt's at the
+        // bottom
+        let to_uv: Transform = self.transform(element, "to_uv")?;
+        let su: Float = to_uv.m.m[0][0] * self.float(element, "uscale", 1.0)?;
+        let sv: Float = to_uv.m.m[1][1] * self.float(element, "vscale", 1.0)?;
+        let du: Float = to_uv.m.m[0][3] + self.float(element, "uoffset", 0.0)?;
+        let dv: Float = to_uv.m.m[1][3] + self.float(element, "voffset", 0.0)?;
+        let mut params: ParamSet = self.new_params("Texture", &name, element);
+        params.tex_type = String::from("spectrum");
+        match element.plugin_type() {
+            "bitmap" => {
+                params.tex_name = String::from("imagemap");
+                params.add_string(
+                    String::from("filename"),
+                    self.string(element, "filename", "")?,
+                );
+                params.add_float(String::from("uscale"), su);
+                params.add_float(String::from("vscale"), sv);
+                params.add_float(String::from("udelta"), du);
+                params.add_float(String::from("vdelta"), 1.0 - sv - dv);
+                let wrap_mode: String = self.string(element, "wrap_mode", "repeat")?;
+                if wrap_mode == "clamp" {
+                    params.add_string(String::from("wrap"), wrap_mode);
+                }
+                if self.boolean(element, "raw", false)? {
+                    params.add_bool(String::from("gamma"), false);
+                }
+                // (re)sampling is done by pbrt's MIP map
+                element.property("filter_type");
+            }
+            "checkerboard" => {
+                // Mitsuba's checkerboard has two checks per unit
+                params.tex_name = String::from("checkerboard");
+                let color0: Spectrum = self.spectrum(element, "color0", Spectrum::new(0.4), api)?;
+                let color1: Spectrum = self.spectrum(element, "color1", Spectrum::new(0.2), api)?;
+                params.add_rgb_spectrum(String::from("tex1"), color0);
+                params.add_rgb_spectrum(String::from("tex2"), color1);
+                params.add_float(String::from("uscale"), 2.0 * su);
+                params.add_float(String::from("vscale"), -2.0 * sv);
+                params.add_float(String::from("udelta"), 2.0 * du);
+                params.add_float(String::from("vdelta"), 2.0 * (sv + dv));
+            }
+            _ => {
+                self.unsupported(element, api)?;
+                return Ok(None);
+            }
+        }
+        self.api_result(element, pbrt_texture(api.api_state, params))?;
+        self.check_unused(element, api);
+        api.textures.insert(name.clone());
+        Ok(Some(name))
+    }
+    fn emitter(&self, element: &Element, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let mut light_to_world: Transform = self.transform(element, "to_world")?;
+        let mut params: ParamSet;
+        match element.plugin_type() {
+            "point" => {
+                params = self.new_params("LightSource", "point", element);
+                let intensity: Spectrum =
+                    self.spectrum(element, "intensity", Spectrum::new(1.0), api)?;
+                let position: Vector3f = self.vector(element, "position", Vector3f::default())?;
+                params.add_rgb_spectrum(String::from("I"), intensity);
+                params.add_point3fs(
+                    String::from("from"),
+                    vec![position.x, position.y, position.z],
+                );
+            }
+            "spot" => {
+                params = self.new_params("LightSource", "spot", element);
+                let intensity: Spectrum =
+                    self.spectrum(element, "intensity", Spectrum::new(1.0), api)?;
+                let cutoff_angle: Float = self.float(element, "cutoff_angle", 20.0)?;
+                let beam_width: Float = self.float(element, "beam_width", cutoff_angle * 0.75)?;
+                params.add_rgb_spectrum(String::from("I"), intensity);
+                params.add_point3fs(String::from("from"), vec![0.0, 0.0, 0.0]);
+                params.add_point3fs(String::from("to"), vec![0.0, 0.0, 1.0]);
+                params.add_float(String::from("coneangle"), cutoff_angle);
+                params.add_float(String::from("conedeltaangle"), cutoff_angle - beam_width);
+            }
+            "directional" => {
+                params = self.new_params("LightSource", "distant", element);
+                let irradiance: Spectrum =
+                    self.spectrum(element, "irradiance", Spectrum::new(1.0), api)?;
+                let direction: Vector3f = self.vector(
+                    element,
+                    "direction",
+                    Vector3f {
+                        x: 0.0,
+                        y: 0.0,
+                        z: 1.0,
+                    },
+                )?;
+                params.add_rgb_spectrum(String::from("L"), irradiance);
+                params.add_point3fs(String::from("from"), vec![0.0, 0.0, 0.0]);
+                params.add_point3fs(
+                    String::from("to"),
+                    vec![direction.x, direction.y, direction.z],
+                );
+            }
+            "envmap" => {
+                params = self.new_params("LightSource", "infinite", element);
+                let scale: Float = self.float(element, "scale", 1.0)?;
+                params.add_string(
+                    String::from("mapname"),
+                    self.string(element, "filename", "")?,
+                );
+                params.add_rgb_spectrum(String::from("L"), Spectrum::new(scale));
+                light_to_world = light_to_world * environment_frame();
+            }
+            "constant" => {
+                params = self.new_params("LightSource", "infinite", element);
+                let radiance: Spectrum =
+                    self.spectrum(element, "radiance", Spectrum::new(1.0), api)?;
+                params.add_rgb_spectrum(String::from("L"), radiance);
+            }
+            _ => return self.unsupported(element, api),
+        }
+        self.api_result(element, pbrt_attribute_begin(api.api_state))?;
+        self.api_result(element, pbrt_transform(api.api_state, &light_to_world))?;
+        self.api_result(element, pbrt_light_source(api.api_state, params))?;
+        self.api_result(element, pbrt_attribute_end(api.api_state))?;
+        self.check_unused(element, api);
+        Ok(())
+    }
+    fn shape(&self, element: &Element, api: &mut ApiCalls) -> Result<(), ParseError> {
+        match element.plugin_type() {
+            "shapegroup" => return self.shape_group(element, api),
+            "instance" => return self.instance(element, api),
+            _ => {}
+        }
+        let mut object_to_world: Transform = self.transform(element, "to_world")?;
+        let flip_normals: bool = self.boolean(element, "flip_normals", false)?;
+        let mut params: ParamSet;
+        match element.plugin_type() {
+            "obj" => {
+                // materials come from the scene, not from MTL files
+                params = self.new_params("Shape", "objmesh", element);
+                params.add_string(
+                    String::from("filename"),
+                    self.string(element, "filename", "")?,
+                );
+                params.add_bool(String::from("usemtl"), false);
+            }
+            "ply" => {
+                params = self.new_params("Shape", "plymesh", element);
+                params.add_string(
+                    String::from("filename"),
+                    self.string(element, "filename", "")?,
+                );
+            }
+            "serialized" => {
+                params = self.new_params("Shape", "trianglemesh", element);
+                let filename: String = self.string(element, "filename", "")?;
+                let shape_index: i32 = self.integer(element, "shape_index", 0)?;
+                let mut path: PathBuf = PathBuf::from(filename);
+                if let Some(ref search_directory) = api.api_state.search_directory {
+                    path = search_directory.join(path);
+                }
+                let mut mesh: SerializedMesh =
+                    match read_serialized(&path, shape_index.max(0) as usize) {
+                        Ok(mesh) => mesh,
+                        Err(message) => return Err(self.error(element, message)),
+                    };
+                if self.boolean(element, "face_normals", false)? {
+                    mesh.n.clear();
+                }
+                params.add_ints(String::from("indices"), mesh.indices);
+                params.add_point3fs(String::from("P"), mesh.p);
+                if !mesh.n.is_empty() {
+                    // shading normals decide which side is the front
+                    if flip_normals {
+                        for n in mesh.n.iter_mut() {
+                            *n = -*n;
+                        }
+                    }
+                    params.add_normal3fs(String::from("N"), mesh.n);
+                }
+                if !mesh.uv.is_empty() {
+                    for v in mesh.uv.iter_mut().skip(1).step_by(2) {
+                        *v = 1.0 - *v;
+                    }
+                    params.add_point2fs(String::from("uv"), mesh.uv);
+                }
+            }
+            "rectangle" => {
+                // [-1, 1] x [-1, 1] facing the z axis
+                params = self.new_params("Shape", "trianglemesh", element);
+                params.add_ints(String::from("indices"), vec![0, 1, 2, 0, 2, 3]);
+                params.add_point3fs(
+                    String::from("P"),
+                    vec![
+                        -1.0, -1.0, 0.0, 1.0, -1.0, 0.0, 1.0, 1.0, 0.0, -1.0, 1.0, 0.0,
+                    ],
+                );
+                params.add_point2fs(
+                    String::from("uv"),
+                    vec![0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0],
+                );
+            }
+            "cube" => {
+                // [-1, 1]^3, four vertices per face
+                params = self.new_params("Shape", "trianglemesh", element);
+                let mut indices: Vec<i32> = Vec::with_capacity(36);
+                let mut p: Vec<Float> = Vec::with_capacity(72);
+                let mut uv: Vec<Float> = Vec::with_capacity(48);
+                for axis in 0..3 {
+                    for &sign in [-1.0 as Float, 1.0].iter() {
+                        let first: i32 = (p.len() / 3) as i32;
+                        for &(s, t) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].iter() {
+                            // counterclockwise seen from outside
+                            let mut vertex: [Float; 3] = [0.0; 3];
+                            vertex[axis] = sign;
+                            vertex[(axis + 1) % 3] = s * sign;
+                            vertex[(axis + 2) % 3] = t;
+                            p.extend_from_slice(&vertex);
+                            uv.push((s + 1.0) / 2.0);
+                            uv.push((1.0 - t) / 2.0);
+                        }
+                        indices.extend_from_slice(&[
+                            first,
+                            first + 1,
+                            first + 2,
+                            first,
+                            first + 2,
+                            first + 3,
+                        ]);
+                    }
+                }
+                params.add_ints(String::from("indices"), indices);
+                params.add_point3fs(String::from("P"), p);
+                params.add_point2fs(String::from("uv"), uv);
+            }
+            "sphere" => {
+                params = self.new_params("Shape", "sphere", element);
+                let center: Vector3f = self.vector(element, "center", Vector3f::default())?;
+                params.add_float(String::from("radius"), self.float(element, "radius", 1.0)?);
+                object_to_world = Transform::translate(&center) * object_to_world;
+            }
+            "disk" => {
+                params = self.new_params("Shape", "disk", element);
+                params.add_float(String::from("radius"), 1.0);
+            }
+            "cylinder" => {
+                params = self.new_params("Shape", "cylinder", element);
+                let p0: Vector3f = self.vector(element, "p0", Vector3f::default())?;
+                let p1: Vector3f = self.vector(
+                    element,
+                    "p1",
+                    Vector3f {
+                        x: 0.0,
+                        y: 0.0,
+                        z: 1.0,
+                    },
+                )?;
+                let axis: Vector3f = p1 - p0;
+                params.add_float(String::from("radius"), self.float(element, "radius", 1.0)?);
+                params.add_float(String::from("zmin"), 0.0);
+                params.add_float(String::from("zmax"), axis.length());
+                object_to_world = object_to_world * Transform::translate(&p0) * frame(&axis);
+            }
+            _ => return self.unsupported(element, api),
+        }
+        self.api_result(element, pbrt_attribute_begin(api.api_state))?;
+        self.api_result(element, pbrt_transform(api.api_state, &object_to_world))?;
+        if flip_normals {
+            self.api_result(element, pbrt_reverse_orientation(api.api_state))?;
+        }
+        for child in &element.children {
+            match child.tag.as_str() {
+                "bsdf" => {
+                    if let Some(material) = self.bsdf(child, api)? {
+                        self.api_result(child, pbrt_material(api.api_state, material))?;
+                    }
+                }
+                "ref" => {
+                    let id: &str = child.attribute("id").unwrap_or("");
+                    if api.materials.contains(id) {
+                        let material: ParamSet = self.new_params("NamedMaterial", id, child);
+                        self.api_result(child, pbrt_named_material(api.api_state, material))?;
+                    } else if self.resolve(child)?.tag != "bsdf" {
+                        self.unsupported(self.resolve(child)?, api)?;
+                    }
+                }
+                "emitter" => {
+                    if child.plugin_type() == "area" {
+                        let mut area_light: ParamSet =
+                            self.new_params("AreaLightSource", "diffuse", child);
+                        let radiance: Spectrum =
+                            self.spectrum(child, "radiance", Spectrum::new(1.0), api)?;
+                        area_light.add_rgb_spectrum(String::from("L"), radiance);
+                        self.api_result(child, pbrt_area_light_source(api.api_state, area_light))?;
+                        self.check_unused(child, api);
+                    } else {
+                        self.unsupported(child, api)?;
+                    }
+                }
+                "transform" => {}
+                _ => {
+                    if child.attribute("name").is_none() {
+                        self.unsupported(child, api)?;
+                    }
+                }
+            }
+        }
+        self.api_result(element, pbrt_shape(api.api_state, api.bsdf_state, params))?;
+        self.api_result(element, pbrt_attribute_end(api.api_state))?;
+        self.check_unused(element, api);
+        Ok(())
+    }
+    /// Shapes of a **shapegroup** become an object, which is only
+    /// visible through instances.
+    fn shape_group(&self, element: &Element, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let id: &str = element.attribute("id").unwrap_or("");
+        let params: ParamSet = self.new_params("ObjectBegin", id, element);
+        self.api_result(element, pbrt_object_begin(api.api_state, params))?;
+        for child in &element.children {
+            if child.tag == "shape" {
+                self.shape(child, api)?;
+            } else {
+                self.unsupported(child, api)?;
+            }
+        }
+        self.api_result(element, pbrt_object_end(api.api_state))
+    }
+    fn instance(&self, element: &Element, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let id: &str = match element.child("ref") {
+            Some(reference) => reference.attribute("id").unwrap_or(""),
+            None => return Err(self.error(element, String::from("instance without shapegroup"))),
+        };
+        let instance_to_world: Transform = self.transform(element, "to_world")?;
+        let params: ParamSet = self.new_params("ObjectInstance", id, element);
+        self.api_result(element, pbrt_attribute_begin(api.api_state))?;
+        self.api_result(element, pbrt_transform(api.api_state, &instance_to_world))?;
+        self.api_result(
+            element,
+            pbrt_object_instance(api.api_state, api.bsdf_state, params),
+        )?;
+        self.api_result(element, pbrt_attribute_end(api.api_state))?;
+        self.check_unused(element, api);
+        Ok(())
+    }
+    // values
+
+    fn location(&self, element: &Element) -> String {
+        format!(
+            "{}:{}:{}",
+            self.files[element.file], element.line, element.column
+        )
+    }
+    fn new_params(&self, key_word: &str, name: &str, element: &Element) -> ParamSet {
+        let mut params: ParamSet = ParamSet::default();
+        params.reset(
+            String::from(key_word),
+            String::from(name),
+            String::new(),
+            String::new(),
+        );
+        params.location = self.location(element);
+        params
+    }
+    fn error(&self, element: &Element, message: String) -> ParseError {
+        ParseError::new(
+            &self.files[element.file],
+            element.line,
+            element.column,
+            message,
+        )
+    }
+    /// Attaches the location of the element to an error reported by
+    /// one of the *pbrt_* API functions.
+    fn api_result(
+        &self,
+        element: &Element,
+        result: Result<(), ApiError>,
+    ) -> Result<(), ParseError> {
+        match result {
+            Ok(()) => Ok(()),
+            Err(e) => Err(self.error(element, e.to_string())),
+        }
+    }
+    fn unsupported(&self, element: &Element, api: &mut ApiCalls) -> Result<(), ParseError> {
+        let error: ApiError = ApiError::Unsupported {
+            directive: element.tag.clone(),
+            name: String::from(element.plugin_type()),
+        };
+        self.api_result(element, api.api_state.report(error))
+    }
+    /// Warns about properties of a plugin which were never looked at.
+    fn check_unused(&self, element: &Element, api: &mut ApiCalls) {
+        let mut unused: Vec<ApiError> = Vec::new();
+        for child in &element.children {
+            if let Some(name) = child.attribute("name") {
+                if !child.used.get() {
+                    unused.push(ApiError::UnusedParameter {
+                        location: self.location(child),
+                        directive: element.tag.clone(),
+                        name: String::from(element.plugin_type()),
+                        parameter: String::from(name),
+                        suggestion: None,
+                    });
+                }
+            }
+        }
+        api.api_state.warn_unused(unused);
+    }
+    fn value(&self, property: &Element) -> Result<String, ParseError> {
+        match property.attribute("value") {
+            Some(value) => Ok(String::from(value)),
+            None => Err(self.error(property, format!("<{}> without value", property.tag))),
+        }
+    }
+    fn numbers(&self, property: &Element, value: &str) -> Result<Vec<Float>, ParseError> {
+        let mut numbers: Vec<Float> = Vec::new();
+        for number in split_numbers(value) {
+            match Float::from_str(number) {
+                Ok(number) => numbers.push(number),
+                Err(_) => return Err(self.error(property, format!("invalid number {:?}", number))),
+            }
+        }
+        Ok(numbers)
+    }
+    fn float(&self, element: &Element, name: &str, default: Float) -> Result<Float, ParseError> {
+        match element.property(name) {
+            Some(property) => {
+                let value: String = self.value(property)?;
+                match Float::from_str(value.trim()) {
+                    Ok(number) if property.tag == "float" || property.tag == "integer" => {
+                        Ok(number)
+                    }
+                    _ => Err(self.error(
+                        property,
+                        format!("float expected for {:?}, found {:?}", name, value),
+                    )),
+                }
+            }
+            None => Ok(default),
+        }
+    }
+    fn integer(&self, element: &Element, name: &str, default: i32) -> Result<i32, ParseError> {
+        match element.property(name) {
+            Some(property) => {
+                let value: String = self.value(property)?;
+                match i32::from_str(value.trim()) {
+                    Ok(integer) => Ok(integer),
+                    Err(_) => Err(self.error(
+                        property,
+                        format!("integer expected for {:?}, found {:?}", name, value),
+                    )),
+                }
+            }
+            None => Ok(default),
+        }
+    }
+    fn boolean(&self, element: &Element, name: &str, default: bool) -> Result<bool, ParseError> {
+        match element.property(name) {
+            Some(property) => {
+                let value: String = self.value(property)?;
+                match value.trim() {
+                    "true" => Ok(true),
+                    "false" => Ok(false),
+                    _ => Err(self.error(
+                        property,
+                        format!("boolean expected for {:?}, found {:?}", name, value),
+                    )),
+                }
+            }
+            None => Ok(default),
+        }
+    }
+    fn string(&self, element: &Element, name: &str, default: &str) -> Result<String, ParseError> {
+        match element.property(name) {
+            Some(property) => self.value(property),
+            None => Ok(String::from(default)),
+        }
+    }
+    /// Points and vectors, given by a value (`"x, y, z"`) or by the
+    /// coordinates as attributes.
+    fn vector(
+        &self,
+        element: &Element,
+        name: &str,
+        default: Vector3f,
+    ) -> Result<Vector3f, ParseError> {
+        match element.property(name) {
+            Some(property) => self.xyz(property, 0.0),
+            None => Ok(default),
+        }
+    }
+    fn xyz(&self, element: &Element, default: Float) -> Result<Vector3f, ParseError> {
+        if let Some(value) = element.attribute("value") {
+            let v: Vec<Float> = self.numbers(element, value)?;
+            return match v.len() {
+                1 => Ok(Vector3f {
+                    x: v[0],
+                    y: v[0],
+                    z: v[0],
+                }),
+                3 => Ok(Vector3f {
+                    x: v[0],
+                    y: v[1],
+                    z: v[2],
+                }),
+                _ => Err(self.error(element, format!("3 numbers expected, found {:?}", value))),
+            };
+        }
+        let mut v: [Float; 3] = [default; 3];
+        for (i, axis) in ["x", "y", "z"].iter().enumerate() {
+            if let Some(value) = element.attribute(axis) {
+                v[i] = self
+                    .numbers(element, value)?
+                    .first()
+                    .cloned()
+                    .unwrap_or(default);
+            }
+        }
+        Ok(Vector3f {
+            x: v[0],
+            y: v[1],
+            z: v[2],
+        })
+    }
+    /// Colors (`rgb` is linear, `srgb` gamma corrected, also as
+    /// `#rrggbb`), uniform or sampled (wavelength:value pairs)
+    /// spectra, or floats.
+    fn spectrum(
+        &self,
+        element: &Element,
+        name: &str,
+        default: Spectrum,
+        api: &mut ApiCalls,
+    ) -> Result<Spectrum, ParseError> {
+        let property: &Element = match element.property(name) {
+            Some(property) => property,
+            None => return Ok(default),
+        };
+        let value: String = match property.tag.as_str() {
+            "rgb" | "srgb" | "spectrum" | "float" => self.value(property)?,
+            _ => {
+                self.unsupported(property, api)?;
+                return Ok(default);
+            }
+        };
+        if property.tag == "spectrum" && value.contains(':') {
+            let mut lambda: Vec<Float> = Vec::new();
+            let mut v: Vec<Float> = Vec::new();
+            for pair in value.split(',') {
+                let numbers: Vec<Float> = self.numbers(property, &pair.replace(':', " "))?;
+                if numbers.len() != 2 {
+                    return Err(self.error(property, format!("invalid spectrum {:?}", value)));
+                }
+                lambda.push(numbers[0]);
+                v.push(numbers[1]);
+            }
+            return Ok(Spectrum::from_sampled(&lambda, &v, lambda.len() as i32));
+        }
+        if property.tag == "srgb" && value.starts_with('#') {
+            let hex: &str = value.trim_start_matches('#');
+            let mut rgb: [u8; 3] = [0; 3];
+            for (i, c) in rgb.iter_mut().enumerate() {
+                *c = match hex.get(2 * i..2 * i + 2).map(|c| u8::from_str_radix(c, 16)) {
+                    Some(Ok(c)) if hex.len() == 6 => c,
+                    _ => return Err(self.error(property, format!("invalid srgb {:?}", value))),
+                };
+            }
+            return Ok(Spectrum::from_srgb(&rgb));
+        }
+        let numbers: Vec<Float> = self.numbers(property, &value)?;
+        let color: Spectrum = match numbers.len() {
+            1 => Spectrum::new(numbers[0]),
+            3 if property.tag != "spectrum" && property.tag != "float" => {
+                Spectrum::rgb(numbers[0], numbers[1], numbers[2])
+            }
+            _ => return Err(self.error(property, format!("invalid {} {:?}", property.tag, value))),
+        };
+        if property.tag == "srgb" {
+            return Ok(color.inverse_gamma_correct());
+        }
+        Ok(color)
+    }
+    /// Mitsuba applies the operations in the given order.
+    fn transform(&self, element: &Element, name: &str) -> Result<Transform, ParseError> {
+        let property: &Element = match element.property(name) {
+            Some(property) => property,
+            None => return Ok(Transform::default()),
+        };
+        let mut transform: Transform = Transform::default();
+        for operation in &property.children {
+            let t: Transform = match operation.tag.to_lowercase().as_str() {
+                "translate" => Transform::translate(&self.xyz(operation, 0.0)?),
+                "scale" => {
+                    let s: Vector3f = self.xyz(operation, 1.0)?;
+                    Transform::scale(s.x, s.y, s.z)
+                }
+                "rotate" => {
+                    let axis: Vector3f = self.xyz(operation, 0.0)?;
+                    let angle: Float = match operation.attribute("angle") {
+                        Some(angle) => self
+                            .numbers(operation, angle)?
+                            .first()
+                            .cloned()
+                            .unwrap_or(0.0),
+                        None => 0.0,
+                    };
+                    Transform::rotate(angle, &axis)
+                }
+                "matrix" => {
+                    let value: String = self.value(operation)?;
+                    let m: Vec<Float> = self.numbers(operation, &value)?;
+                    match m.len() {
+                        16 => Transform::new(
+                            m[0], m[1], m[2], m[3], m[4], m[5], m[6], m[7], m[8], m[9], m[10],
+                            m[11], m[12], m[13], m[14], m[15],
+                        ),
+                        9 => Transform::new(
+                            m[0], m[1], 0.0, m[2], m[3], m[4], 0.0, m[5], 0.0, 0.0, 1.0, 0.0, m[6],
+                            m[7], 0.0, m[8],
+                        ),
+                        _ => {
+                            return Err(self.error(
+                                operation,
+                                format!("16 numbers expected, found {:?}", value),
+                            ))
+                        }
+                    }
+                }
+                "lookat" => {
+                    let point = |attribute: &str, default: &str| -> Result<Point3f, ParseError> {
+                        let value: &str = operation.attribute(attribute).unwrap_or(default);
+                        let v: Vec<Float> = self.numbers(operation, value)?;
+                        if v.len() != 3 {
+                            return Err(self.error(
+                                operation,
+                                format!("3 numbers expected for {:?}", attribute),
+                            ));
+                        }
+                        Ok(Point3f {
+                            x: v[0],
+                            y: v[1],
+                            z: v[2],
+                        })
+                    };
+                    let origin: Point3f = point("origin", "0, 0, 0")?;
+                    let target: Point3f = point("target", "0, 0, 1")?;
+                    let up: Point3f = point("up", "0, 1, 0")?;
+                    let up: Vector3f = Vector3f {
+                        x: up.x,
+                        y: up.y,
+                        z: up.z,
+                    };
+                    Transform::inverse(&Transform::look_at(&origin, &target, &up))
+                }
+                _ => {
+                    return Err(self.error(
+                        operation,
+                        format!("unknown transformation <{}>", operation.tag),
+                    ))
+                }
+            };
+            transform = t * transform;
+        }
+        Ok(transform)
+    }
+}
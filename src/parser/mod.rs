@@ -0,0 +1,1153 @@
+//! The parser reads a scene description in **pbrt**'s file format,
+//! as defined by the [pest][pest] grammar in `pbrt.pest`, and calls
+//! the *pbrt_* functions of the **core::api** module for each
+//! statement found.
+//!
+//! Other files can be pulled in via `Include` or `Import`
+//! statements. Their paths are resolved relative to the directory of
+//! the including file, and recursive includes are reported as
+//! errors. Files ending in **.gz** are decompressed while being read,
+//! and the statements are handed to the grammar in chunks, so even
+//! huge scenes never have to be kept in memory as a whole.
+//!
+//! Parsing stops at the final `WorldEnd` statement. Rendering is
+//! left to the caller, which calls **pbrt_cleanup()** once
+//! **parse_file()** or **parse_str()** returned successfully:
+//!
+//! ```rust,no_run
+//! use pbrt::core::api::{pbrt_cleanup, pbrt_init};
+//! use pbrt::parser::parse_file;
+//! use std::path::Path;
+//!
+//! let (mut api_state, mut bsdf_state) = pbrt_init(0_u8);
+//! match parse_file(Path::new("scene.pbrt"), &mut api_state, &mut bsdf_state) {
+//!     Ok(()) => pbrt_cleanup(&mut api_state).unwrap(),
+//!     Err(e) => println!("{}", e),
+//! }
+//! ```
+//!
+//! Scenes in Arnold's **.ass** format are imported by the **ass**
+//! submodule the same way, glTF 2.0 scenes (**.gltf** or **.glb**)
+//! by the **gltf** submodule, and Mitsuba scenes (**.xml**) by the
+//! **mitsuba** submodule.
+//!
+//! [pest]: https://pest.rs
+
+// std
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+// others
+use pest::error::{Error as PestError, ErrorVariant, LineColLocation};
+use pest::iterators::Pair;
+use pest::{Parser, RuleType};
+// pbrt
+use core::api::{
+    pbrt_accelerator, pbrt_active_transform_all, pbrt_active_transform_end_time,
+    pbrt_active_transform_start_time, pbrt_area_light_source, pbrt_attribute_begin,
+    pbrt_attribute_end, pbrt_camera, pbrt_concat_transform, pbrt_coord_sys_transform, pbrt_film,
+    pbrt_integrator, pbrt_light_source, pbrt_look_at, pbrt_make_named_material,
+    pbrt_make_named_medium, pbrt_material, pbrt_medium_interface, pbrt_named_material,
+    pbrt_object_begin, pbrt_object_end, pbrt_object_instance, pbrt_pixel_filter,
+    pbrt_reverse_orientation, pbrt_rotate, pbrt_sampler, pbrt_scale, pbrt_shape, pbrt_texture,
+    pbrt_transform, pbrt_transform_begin, pbrt_transform_end, pbrt_transform_times, pbrt_translate,
+    pbrt_world_begin,
+};
+use core::api::{ApiError, ApiState, BsdfState};
+use core::fileutil::open_file;
+use core::geometry::{vec3_coordinate_system, Normal3f, Point2f, Point3f, Vector3f};
+use core::paramset::ParamSet;
+use core::pbrt::{Float, Spectrum};
+use core::spectrum::{sort_spectrum_samples, spectrum_samples_sorted};
+use core::transform::Transform;
+
+pub mod ass;
+pub mod gltf;
+pub mod mitsuba;
+
+#[derive(Parser)]
+#[grammar = "parser/pbrt.pest"]
+struct PbrtParser;
+
+/// Name used for the file in error messages if the scene description
+/// was handed over as a string.
+const STRING_INPUT: &str = "<string>";
+
+/// Statements are handed to the pest parser in chunks of (roughly)
+/// this many bytes.
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// A chunk can only be split before a line starting with one of these
+/// keywords.
+const STATEMENT_KEYWORDS: [&str; 38] = [
+    "Accelerator",
+    "ActiveTransform",
+    "AreaLightSource",
+    "AttributeBegin",
+    "AttributeEnd",
+    "Camera",
+    "ConcatTransform",
+    "CoordSysTransform",
+    "CoordinateSystem",
+    "Film",
+    "Identity",
+    "Import",
+    "Include",
+    "Integrator",
+    "LightSource",
+    "LookAt",
+    "MakeNamedMaterial",
+    "MakeNamedMedium",
+    "Material",
+    "MediumInterface",
+    "NamedMaterial",
+    "ObjectBegin",
+    "ObjectEnd",
+    "ObjectInstance",
+    "PixelFilter",
+    "ReverseOrientation",
+    "Rotate",
+    "Sampler",
+    "Scale",
+    "Shape",
+    "Texture",
+    "Transform",
+    "TransformBegin",
+    "TransformEnd",
+    "TransformTimes",
+    "Translate",
+    "WorldBegin",
+    "WorldEnd",
+];
+
+/// Describes what went wrong while reading a scene description and
+/// where (file, line, and column) it happened.
+///
+/// Errors within included files list the `Include` statements which
+/// led to them (innermost first).
+#[derive(Debug)]
+pub struct ParseError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+    pub included_from: Vec<String>,
+}
+
+impl ParseError {
+    fn new(file: &str, line: usize, column: usize, message: String) -> Self {
+        ParseError {
+            file: String::from(file),
+            line: line,
+            column: column,
+            message: message,
+            included_from: Vec::new(),
+        }
+    }
+    fn at<R: RuleType>(chunk: &Chunk, pair: &Pair<R>, message: String) -> Self {
+        let (line, column) = chunk.line_col(pair);
+        ParseError::new(chunk.file, line, column, message)
+    }
+    fn from_pest<R: RuleType>(chunk: &Chunk, error: PestError<R>) -> Self {
+        let (line, column) = match error.line_col {
+            LineColLocation::Pos(line_col) => line_col,
+            LineColLocation::Span(start, _end) => start,
+        };
+        let message: String = match error.variant {
+            ErrorVariant::ParsingError {
+                positives,
+                negatives,
+            } => {
+                if positives.is_empty() && negatives.is_empty() {
+                    String::from("unknown parsing error")
+                } else if negatives.is_empty() {
+                    format!("expected {:?}", positives)
+                } else if positives.is_empty() {
+                    format!("unexpected {:?}", negatives)
+                } else {
+                    format!("unexpected {:?}, expected {:?}", negatives, positives)
+                }
+            }
+            ErrorVariant::CustomError { message } => message,
+        };
+        ParseError::new(chunk.file, chunk.first_line + line - 1, column, message)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}: {}", self.file, self.message)?;
+        } else {
+            write!(
+                f,
+                "{}:{}:{}: {}",
+                self.file, self.line, self.column, self.message
+            )?;
+        }
+        for location in &self.included_from {
+            write!(f, "\n  included from {}", location)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+/// Keeps track of the files currently being parsed (innermost
+/// last) to resolve relative `Include` paths and to detect recursive
+/// includes.
+struct ParseContext {
+    files: Vec<PathBuf>,
+    directories: Vec<PathBuf>,
+    world_end: bool,
+}
+
+impl ParseContext {
+    fn new(directory: PathBuf) -> Self {
+        ParseContext {
+            files: Vec::new(),
+            directories: vec![directory],
+            world_end: false,
+        }
+    }
+    /// Relative paths are interpreted relative to the directory of
+    /// the including file.
+    fn resolve_filename(&self, filename: &str) -> PathBuf {
+        let path: &Path = Path::new(filename);
+        if path.is_relative() {
+            match self.directories.last() {
+                Some(directory) => directory.join(path),
+                None => PathBuf::from(path),
+            }
+        } else {
+            PathBuf::from(path)
+        }
+    }
+    fn push(&mut self, path: &Path) {
+        let canonical: PathBuf = fs::canonicalize(path).unwrap_or(PathBuf::from(path));
+        let directory: PathBuf = match path.parent() {
+            Some(parent) => PathBuf::from(parent),
+            None => PathBuf::new(),
+        };
+        self.files.push(canonical);
+        self.directories.push(directory);
+    }
+    fn pop(&mut self) {
+        self.files.pop();
+        self.directories.pop();
+    }
+    fn is_being_parsed(&self, path: &Path) -> bool {
+        let canonical: PathBuf = fs::canonicalize(path).unwrap_or(PathBuf::from(path));
+        self.files.contains(&canonical)
+    }
+}
+
+/// The part of a file handed to the pest parser in one go. Lines are
+/// counted relative to the chunk, so error locations need the line
+/// number the chunk started with within the whole file.
+struct Chunk<'a> {
+    file: &'a str,
+    first_line: usize,
+}
+
+impl<'a> Chunk<'a> {
+    fn line_col<R: RuleType>(&self, pair: &Pair<R>) -> (usize, usize) {
+        let (line, column) = pair.as_span().start_pos().line_col();
+        (self.first_line + line - 1, column)
+    }
+}
+
+/// Rotates the z axis onto **dir**, e.g. for shapes defined along
+/// the z axis (used by the importers of other scene formats).
+fn frame(dir: &Vector3f) -> Transform {
+    let z: Vector3f = dir.normalize();
+    let mut x: Vector3f = Vector3f::default();
+    let mut y: Vector3f = Vector3f::default();
+    vec3_coordinate_system(&z, &mut x, &mut y);
+    Transform::new(
+        x.x, y.x, z.x, 0.0, x.y, y.y, z.y, 0.0, x.z, y.z, z.z, 0.0, 0.0, 0.0, 0.0, 1.0,
+    )
+}
+
+/// Reads the scene description from the file found at `path` and
+/// feeds it into `api_state`. The directory containing the file
+/// becomes the search directory for other files (meshes, textures,
+/// etc.) referenced by the scene.
+pub fn parse_file(
+    path: &Path,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let file: String = path.display().to_string();
+    let absolute_path: PathBuf = if path.is_relative() {
+        match env::current_dir() {
+            Ok(cp) => cp.join(path),
+            Err(e) => return Err(ParseError::new(&file, 0, 0, e.to_string())),
+        }
+    } else {
+        PathBuf::from(path)
+    };
+    if let Some(search_directory) = absolute_path.parent() {
+        api_state.search_directory = Some(Box::new(PathBuf::from(search_directory)));
+    }
+    let mut reader = match open_file(path) {
+        Ok(reader) => reader,
+        Err(e) => return Err(ParseError::new(&file, 0, 0, e.to_string())),
+    };
+    let mut context: ParseContext = ParseContext::new(PathBuf::new());
+    context.push(&absolute_path);
+    parse_reader(&mut reader, &file, &mut context, api_state, bsdf_state)?;
+    check_world_end(&file, &context)
+}
+
+/// Reads the scene description from a string and feeds it into
+/// `api_state`. Relative file names within the scene are resolved
+/// against the search directory already set in `api_state` (if any).
+pub fn parse_str(
+    source: &str,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let directory: PathBuf = match api_state.search_directory {
+        Some(ref search_directory) => PathBuf::from(search_directory.as_ref()),
+        None => env::current_dir().unwrap_or(PathBuf::new()),
+    };
+    let mut context: ParseContext = ParseContext::new(directory);
+    let mut reader: &[u8] = source.as_bytes();
+    parse_reader(
+        &mut reader,
+        STRING_INPUT,
+        &mut context,
+        api_state,
+        bsdf_state,
+    )?;
+    check_world_end(STRING_INPUT, &context)
+}
+
+fn check_world_end(file: &str, context: &ParseContext) -> Result<(), ParseError> {
+    if context.world_end {
+        Ok(())
+    } else {
+        Err(ParseError::new(
+            file,
+            0,
+            0,
+            String::from("missing WorldEnd statement"),
+        ))
+    }
+}
+
+/// Does the line start a new statement?
+fn starts_statement(line: &str) -> bool {
+    let word: &str = match line
+        .trim_start()
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+    {
+        Some(word) => word,
+        None => return false,
+    };
+    STATEMENT_KEYWORDS.contains(&word)
+}
+
+/// Reads statements line by line and hands them to the pest parser
+/// whenever more than **CHUNK_SIZE** bytes were collected and the
+/// next line starts a new statement.
+fn parse_reader(
+    reader: &mut BufRead,
+    file: &str,
+    context: &mut ParseContext,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let mut source: String = String::new();
+    let mut line: String = String::new();
+    let mut first_line: usize = 1;
+    let mut n_lines: usize = 0;
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(_num_bytes) => {}
+            Err(e) => {
+                return Err(ParseError::new(
+                    file,
+                    first_line + n_lines,
+                    0,
+                    e.to_string(),
+                ))
+            }
+        }
+        if source.len() >= CHUNK_SIZE && starts_statement(&line) {
+            let chunk: Chunk = Chunk {
+                file: file,
+                first_line: first_line,
+            };
+            parse_chunk(&source, &chunk, context, api_state, bsdf_state)?;
+            source.clear();
+            first_line += n_lines;
+            n_lines = 0;
+        }
+        source.push_str(&line);
+        n_lines += 1;
+    }
+    let chunk: Chunk = Chunk {
+        file: file,
+        first_line: first_line,
+    };
+    parse_chunk(&source, &chunk, context, api_state, bsdf_state)
+}
+
+fn parse_chunk(
+    source: &str,
+    chunk: &Chunk,
+    context: &mut ParseContext,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let pairs = match PbrtParser::parse(Rule::pbrt, source) {
+        Ok(pairs) => pairs,
+        Err(e) => return Err(ParseError::from_pest(chunk, e)),
+    };
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::statement => {
+                if context.world_end {
+                    return Err(ParseError::at(
+                        chunk,
+                        &pair,
+                        String::from("statement after WorldEnd"),
+                    ));
+                }
+                for inner_pair in pair.into_inner() {
+                    parse_statement(inner_pair, chunk, context, api_state, bsdf_state)?;
+                }
+            }
+            Rule::last_statement => {
+                // WorldEnd
+                if context.world_end {
+                    return Err(ParseError::at(
+                        chunk,
+                        &pair,
+                        String::from("statement after WorldEnd"),
+                    ));
+                }
+                context.world_end = true;
+            }
+            Rule::EOI => {}
+            _ => return Err(unexpected_rule(chunk, &pair)),
+        }
+    }
+    Ok(())
+}
+
+/// Parses the file named by an `Include` or `Import` statement as if
+/// its statements were found in place of the including statement.
+fn parse_included_file(
+    pair: Pair<Rule>,
+    chunk: &Chunk,
+    context: &mut ParseContext,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let location: Pair<Rule> = pair.clone();
+    let filename: String = match pair.into_inner().next() {
+        Some(string) => string_value(string),
+        None => String::new(),
+    };
+    let path: PathBuf = context.resolve_filename(&filename);
+    if context.is_being_parsed(&path) {
+        return Err(ParseError::at(
+            chunk,
+            &location,
+            format!("recursive Include of {:?}", filename),
+        ));
+    }
+    let mut reader = match open_file(&path) {
+        Ok(reader) => reader,
+        Err(e) => {
+            return Err(ParseError::at(
+                chunk,
+                &location,
+                format!("unable to read {:?}: {}", path.display().to_string(), e),
+            ))
+        }
+    };
+    let included_file: String = path.display().to_string();
+    context.push(&path);
+    let result = parse_reader(&mut reader, &included_file, context, api_state, bsdf_state);
+    context.pop();
+    match result {
+        Ok(()) => Ok(()),
+        Err(mut e) => {
+            let (line, column) = chunk.line_col(&location);
+            e.included_from
+                .push(format!("{}:{}:{}", chunk.file, line, column));
+            Err(e)
+        }
+    }
+}
+
+fn parse_statement(
+    pair: Pair<Rule>,
+    chunk: &Chunk,
+    context: &mut ParseContext,
+    api_state: &mut ApiState,
+    bsdf_state: &mut BsdfState,
+) -> Result<(), ParseError> {
+    let location: Pair<Rule> = pair.clone();
+    match pair.as_rule() {
+        Rule::active_transform => {
+            // ActiveTransform [ StartTime | EndTime | All ]
+            for rule_pair in pair.into_inner() {
+                match rule_pair.as_rule() {
+                    Rule::all => {
+                        api_result(pbrt_active_transform_all(api_state), chunk, &location)?
+                    }
+                    Rule::start_time => api_result(
+                        pbrt_active_transform_start_time(api_state),
+                        chunk,
+                        &location,
+                    )?,
+                    Rule::end_time => {
+                        api_result(pbrt_active_transform_end_time(api_state), chunk, &location)?
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+        Rule::concat_transform => {
+            // ConcatTransform m00 .. m33
+            let tr: Transform = parse_matrix(pair, chunk)?;
+            api_result(pbrt_concat_transform(api_state, &tr), chunk, &location)?;
+        }
+        Rule::include | Rule::import => {
+            // Include "filename" or Import "filename"
+            parse_included_file(pair, chunk, context, api_state, bsdf_state)?;
+        }
+        Rule::keyword => {
+            for rule_pair in pair.into_inner() {
+                match rule_pair.as_rule() {
+                    Rule::attribute_begin => {
+                        api_result(pbrt_attribute_begin(api_state), chunk, &location)?
+                    }
+                    Rule::attribute_end => {
+                        api_result(pbrt_attribute_end(api_state), chunk, &location)?
+                    }
+                    Rule::object_begin => {
+                        let params = extract_params(
+                            String::from("ObjectBegin"),
+                            rule_pair,
+                            chunk,
+                            api_state,
+                        )?;
+                        api_result(pbrt_object_begin(api_state, params), chunk, &location)?;
+                    }
+                    Rule::object_end => api_result(pbrt_object_end(api_state), chunk, &location)?,
+                    Rule::object_instance => {
+                        let params = extract_params(
+                            String::from("ObjectInstance"),
+                            rule_pair,
+                            chunk,
+                            api_state,
+                        )?;
+                        api_result(
+                            pbrt_object_instance(api_state, bsdf_state, params),
+                            chunk,
+                            &location,
+                        )?;
+                    }
+                    Rule::transform_begin => {
+                        api_result(pbrt_transform_begin(api_state), chunk, &location)?
+                    }
+                    Rule::transform_end => {
+                        api_result(pbrt_transform_end(api_state), chunk, &location)?
+                    }
+                    Rule::reverse_orientation => {
+                        api_result(pbrt_reverse_orientation(api_state), chunk, &location)?
+                    }
+                    Rule::world_begin => api_result(pbrt_world_begin(api_state), chunk, &location)?,
+                    _ => return Err(unexpected_rule(chunk, &rule_pair)),
+                }
+            }
+        }
+        Rule::look_at => {
+            // LookAt eye_x eye_y eye_z look_x look_y look_z up_x up_y up_z
+            let v: Vec<Float> = parse_numbers(pair, chunk)?;
+            api_result(
+                pbrt_look_at(
+                    api_state, v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7], v[8],
+                ),
+                chunk,
+                &location,
+            )?;
+        }
+        Rule::medium_interface => {
+            // MediumInterface "" "vol"
+            let mut strings: Vec<String> = Vec::new();
+            for rule_pair in pair.clone().into_inner() {
+                match rule_pair.as_rule() {
+                    Rule::empty_string => strings.push(String::from("")),
+                    Rule::string => strings.push(string_value(rule_pair)),
+                    _ => unreachable!(),
+                }
+            }
+            if strings.len() != 2_usize {
+                return Err(ParseError::at(
+                    chunk,
+                    &pair,
+                    format!("expected two strings, found {:?}", strings.len()),
+                ));
+            }
+            api_result(
+                pbrt_medium_interface(api_state, &strings[0], &strings[1]),
+                chunk,
+                &location,
+            )?;
+        }
+        Rule::named_statement => {
+            for rule_pair in pair.into_inner() {
+                match rule_pair.as_rule() {
+                    Rule::accelerator => {
+                        let params = extract_params(
+                            String::from("Accelerator"),
+                            rule_pair,
+                            chunk,
+                            api_state,
+                        )?;
+                        api_result(pbrt_accelerator(api_state, params), chunk, &location)?;
+                    }
+                    Rule::area_light_source => {
+                        let params = extract_params(
+                            String::from("AreaLightSource"),
+                            rule_pair,
+                            chunk,
+                            api_state,
+                        )?;
+                        api_result(pbrt_area_light_source(api_state, params), chunk, &location)?;
+                    }
+                    Rule::camera => {
+                        let params =
+                            extract_params(String::from("Camera"), rule_pair, chunk, api_state)?;
+                        api_result(pbrt_camera(api_state, params), chunk, &location)?;
+                    }
+                    Rule::coord_sys_transform => {
+                        let params = extract_params(
+                            String::from("CoordSysTransform"),
+                            rule_pair,
+                            chunk,
+                            api_state,
+                        )?;
+                        api_result(
+                            pbrt_coord_sys_transform(api_state, params),
+                            chunk,
+                            &location,
+                        )?;
+                    }
+                    Rule::film => {
+                        let params =
+                            extract_params(String::from("Film"), rule_pair, chunk, api_state)?;
+                        api_result(pbrt_film(api_state, params), chunk, &location)?;
+                    }
+                    Rule::integrator => {
+                        let params = extract_params(
+                            String::from("Integrator"),
+                            rule_pair,
+                            chunk,
+                            api_state,
+                        )?;
+                        api_result(pbrt_integrator(api_state, params), chunk, &location)?;
+                    }
+                    Rule::light_source => {
+                        let params = extract_params(
+                            String::from("LightSource"),
+                            rule_pair,
+                            chunk,
+                            api_state,
+                        )?;
+                        api_result(pbrt_light_source(api_state, params), chunk, &location)?;
+                    }
+                    Rule::make_named_material => {
+                        let params = extract_params(
+                            String::from("MakeNamedMaterial"),
+                            rule_pair,
+                            chunk,
+                            api_state,
+                        )?;
+                        api_result(
+                            pbrt_make_named_material(api_state, bsdf_state, params),
+                            chunk,
+                            &location,
+                        )?;
+                    }
+                    Rule::make_named_medium => {
+                        let params = extract_params(
+                            String::from("MakeNamedMedium"),
+                            rule_pair,
+                            chunk,
+                            api_state,
+                        )?;
+                        api_result(pbrt_make_named_medium(api_state, params), chunk, &location)?;
+                    }
+                    Rule::material => {
+                        let params =
+                            extract_params(String::from("Material"), rule_pair, chunk, api_state)?;
+                        api_result(pbrt_material(api_state, params), chunk, &location)?;
+                    }
+                    Rule::named_material => {
+                        let params = extract_params(
+                            String::from("NamedMaterial"),
+                            rule_pair,
+                            chunk,
+                            api_state,
+                        )?;
+                        api_result(pbrt_named_material(api_state, params), chunk, &location)?;
+                    }
+                    Rule::pixel_filter => {
+                        let params = extract_params(
+                            String::from("PixelFilter"),
+                            rule_pair,
+                            chunk,
+                            api_state,
+                        )?;
+                        api_result(pbrt_pixel_filter(api_state, params), chunk, &location)?;
+                    }
+                    Rule::sampler => {
+                        let params =
+                            extract_params(String::from("Sampler"), rule_pair, chunk, api_state)?;
+                        api_result(pbrt_sampler(api_state, params), chunk, &location)?;
+                    }
+                    Rule::shape => {
+                        let params =
+                            extract_params(String::from("Shape"), rule_pair, chunk, api_state)?;
+                        api_result(pbrt_shape(api_state, bsdf_state, params), chunk, &location)?;
+                    }
+                    Rule::texture => {
+                        let params =
+                            extract_params(String::from("Texture"), rule_pair, chunk, api_state)?;
+                        api_result(pbrt_texture(api_state, params), chunk, &location)?;
+                    }
+                    _ => return Err(unexpected_rule(chunk, &rule_pair)),
+                }
+            }
+        }
+        Rule::rotate => {
+            // Rotate angle x y z
+            let v: Vec<Float> = parse_numbers(pair, chunk)?;
+            api_result(
+                pbrt_rotate(api_state, v[0], v[1], v[2], v[3]),
+                chunk,
+                &location,
+            )?;
+        }
+        Rule::scale => {
+            // Scale x y z
+            let v: Vec<Float> = parse_numbers(pair, chunk)?;
+            api_result(pbrt_scale(api_state, v[0], v[1], v[2]), chunk, &location)?;
+        }
+        Rule::transform => {
+            // Transform m00 .. m33
+            let tr: Transform = parse_matrix(pair, chunk)?;
+            api_result(pbrt_transform(api_state, &tr), chunk, &location)?;
+        }
+        Rule::transform_times => {
+            // TransformTimes start end
+            let v: Vec<Float> = parse_numbers(pair, chunk)?;
+            api_result(
+                pbrt_transform_times(api_state, v[0], v[1]),
+                chunk,
+                &location,
+            )?;
+        }
+        Rule::translate => {
+            // Translate x y z
+            let v: Vec<Float> = parse_numbers(pair, chunk)?;
+            api_result(
+                pbrt_translate(api_state, v[0], v[1], v[2]),
+                chunk,
+                &location,
+            )?;
+        }
+        _ => return Err(unexpected_rule(chunk, &pair)),
+    }
+    Ok(())
+}
+
+/// Reports a rule the grammar produced but the parser does not handle.
+fn unexpected_rule(chunk: &Chunk, pair: &Pair<Rule>) -> ParseError {
+    ParseError::at(chunk, pair, format!("unexpected {:?}", pair.as_rule()))
+}
+
+/// Attaches the location of the statement to an error reported by
+/// one of the *pbrt_* API functions.
+fn api_result(
+    result: Result<(), ApiError>,
+    chunk: &Chunk,
+    location: &Pair<Rule>,
+) -> Result<(), ParseError> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => Err(ParseError::at(chunk, location, e.to_string())),
+    }
+}
+
+fn parse_float(pair: &Pair<Rule>, chunk: &Chunk) -> Result<Float, ParseError> {
+    match Float::from_str(pair.as_str()) {
+        Ok(float) => Ok(float),
+        Err(e) => Err(ParseError::at(
+            chunk,
+            pair,
+            format!("invalid number {:?}: {}", pair.as_str(), e),
+        )),
+    }
+}
+
+fn parse_integer(pair: &Pair<Rule>, chunk: &Chunk) -> Result<i32, ParseError> {
+    match i32::from_str(pair.as_str()) {
+        Ok(integer) => Ok(integer),
+        Err(e) => Err(ParseError::at(
+            chunk,
+            pair,
+            format!("invalid integer {:?}: {}", pair.as_str(), e),
+        )),
+    }
+}
+
+/// Collects all numbers of a statement (or parameter), ignoring
+/// brackets.
+fn parse_numbers(pair: Pair<Rule>, chunk: &Chunk) -> Result<Vec<Float>, ParseError> {
+    let mut numbers: Vec<Float> = Vec::new();
+    for rule_pair in pair.into_inner() {
+        match rule_pair.as_rule() {
+            Rule::lbrack | Rule::rbrack => {}
+            _ => numbers.push(parse_float(&rule_pair, chunk)?),
+        }
+    }
+    Ok(numbers)
+}
+
+/// Converts the 16 numbers of a `Transform` or `ConcatTransform`
+/// statement (given in column-major order) into a **Transform**.
+fn parse_matrix(pair: Pair<Rule>, chunk: &Chunk) -> Result<Transform, ParseError> {
+    let m: Vec<Float> = parse_numbers(pair, chunk)?;
+    Ok(Transform::new(
+        m[0], m[4], m[8], m[12], m[1], m[5], m[9], m[13], m[2], m[6], m[10], m[14], m[3], m[7],
+        m[11], m[15],
+    ))
+}
+
+/// Returns the content of a quoted string (without the quotes).
+fn string_value(pair: Pair<Rule>) -> String {
+    match pair.into_inner().next() {
+        Some(ident) => String::from(ident.as_str()),
+        None => String::new(),
+    }
+}
+
+/// Splits a parameter like `"float fov" [ 45 ]` into its name
+/// (`fov`) and the pairs holding the values (without brackets).
+fn parameter_name_and_values<'i>(pair: Pair<'i, Rule>) -> (String, Vec<Pair<'i, Rule>>) {
+    let mut name: String = String::new();
+    let mut values: Vec<Pair<'i, Rule>> = Vec::new();
+    for rule_pair in pair.into_inner() {
+        match rule_pair.as_rule() {
+            Rule::ident => name = String::from(rule_pair.as_str()),
+            Rule::lbrack | Rule::rbrack => {}
+            _ => values.push(rule_pair),
+        }
+    }
+    (name, values)
+}
+
+fn parameter_floats(pair: Pair<Rule>, chunk: &Chunk) -> Result<(String, Vec<Float>), ParseError> {
+    let (name, values) = parameter_name_and_values(pair);
+    let mut floats: Vec<Float> = Vec::with_capacity(values.len());
+    for value in &values {
+        floats.push(parse_float(value, chunk)?);
+    }
+    Ok((name, floats))
+}
+
+fn parameter_integers(pair: Pair<Rule>, chunk: &Chunk) -> Result<(String, Vec<i32>), ParseError> {
+    let (name, values) = parameter_name_and_values(pair);
+    let mut integers: Vec<i32> = Vec::with_capacity(values.len());
+    for value in &values {
+        integers.push(parse_integer(value, chunk)?);
+    }
+    Ok((name, integers))
+}
+
+fn parameter_string(pair: Pair<Rule>) -> (String, String) {
+    let (name, mut values) = parameter_name_and_values(pair);
+    match values.pop() {
+        Some(value) => (name, string_value(value)),
+        None => (name, String::new()),
+    }
+}
+
+fn extract_params(
+    key_word: String,
+    pairs: Pair<Rule>,
+    chunk: &Chunk,
+    api_state: &mut ApiState,
+) -> Result<ParamSet, ParseError> {
+    let mut params: ParamSet = ParamSet::default();
+    params.key_word = key_word;
+    let (line, column) = chunk.line_col(&pairs);
+    params.location = format!("{}:{}:{}", chunk.file, line, column);
+    let mut counter: u8 = 0_u8;
+    for pair in pairs.into_inner() {
+        match pair.as_rule() {
+            Rule::empty_string => {}
+            Rule::string => {
+                match counter {
+                    // name
+                    0 => params.name = string_value(pair),
+                    // tex_type
+                    1 => params.tex_type = string_value(pair),
+                    // tex_name
+                    2 => params.tex_name = string_value(pair),
+                    _ => unreachable!(),
+                };
+                counter += 1_u8;
+            }
+            Rule::parameter => {
+                for parameter_pair in pair.into_inner() {
+                    add_parameter(&mut params, parameter_pair, chunk, api_state)?;
+                }
+            }
+            _ => return Err(unexpected_rule(chunk, &pair)),
+        }
+    }
+    Ok(params)
+}
+
+fn add_parameter(
+    params: &mut ParamSet,
+    parameter_pair: Pair<Rule>,
+    chunk: &Chunk,
+    api_state: &mut ApiState,
+) -> Result<(), ParseError> {
+    let location: Pair<Rule> = parameter_pair.clone();
+    match parameter_pair.as_rule() {
+        Rule::bool_param => {
+            let (string, value) = parameter_string(parameter_pair);
+            // return boolean (instead of string)
+            let b: bool;
+            if value == "true" {
+                b = true;
+            } else if value == "false" {
+                b = false;
+            } else {
+                println!(
+                    "WARNING: parameter {:?} not well defined, defaulting to false",
+                    string
+                );
+                b = false;
+            }
+            params.add_bool(string, b);
+        }
+        Rule::blackbody_param => {
+            let (string, floats) = parameter_floats(parameter_pair, chunk)?;
+            if floats.len() % 2 != 0 {
+                return Err(ParseError::at(
+                    chunk,
+                    &location,
+                    format!(
+                        "blackbody parameter {:?} needs pairs of temperature and scale",
+                        string
+                    ),
+                ));
+            }
+            params.add_blackbody_spectrum(string, floats);
+        }
+        Rule::float_param => {
+            let (string, floats) = parameter_floats(parameter_pair, chunk)?;
+            if floats.len() == 1 {
+                params.add_float(string, floats[0]);
+            } else {
+                params.add_floats(string, floats);
+            }
+        }
+        Rule::integer_param => {
+            let (string, integers) = parameter_integers(parameter_pair, chunk)?;
+            if integers.len() == 1 {
+                params.add_int(string, integers[0]);
+            } else {
+                params.add_ints(string, integers);
+            }
+        }
+        Rule::point_param => {
+            let (string, floats) = parameter_floats(parameter_pair, chunk)?;
+            check_tuples(&location, chunk, &string, floats.len(), 3)?;
+            if floats.len() == 3 {
+                params.add_point3f(
+                    string,
+                    Point3f {
+                        x: floats[0],
+                        y: floats[1],
+                        z: floats[2],
+                    },
+                );
+            } else {
+                params.add_point3fs(string, floats);
+            }
+        }
+        Rule::point2_param => {
+            let (string, floats) = parameter_floats(parameter_pair, chunk)?;
+            check_tuples(&location, chunk, &string, floats.len(), 2)?;
+            if floats.len() == 2 {
+                params.add_point2f(
+                    string,
+                    Point2f {
+                        x: floats[0],
+                        y: floats[1],
+                    },
+                );
+            } else {
+                params.add_point2fs(string, floats);
+            }
+        }
+        Rule::normal_param => {
+            let (string, floats) = parameter_floats(parameter_pair, chunk)?;
+            check_tuples(&location, chunk, &string, floats.len(), 3)?;
+            if floats.len() == 3 {
+                params.add_normal3f(
+                    string,
+                    Normal3f {
+                        x: floats[0],
+                        y: floats[1],
+                        z: floats[2],
+                    },
+                );
+            } else {
+                params.add_normal3fs(string, floats);
+            }
+        }
+        Rule::rgb_param => {
+            let (string, floats) = parameter_floats(parameter_pair, chunk)?;
+            params.add_rgb_spectrum(
+                string,
+                Spectrum {
+                    c: [floats[0], floats[1], floats[2]],
+                },
+            );
+        }
+        Rule::spectrum_param => {
+            let is_filename: bool = parameter_pair
+                .clone()
+                .into_inner()
+                .any(|rule_pair| rule_pair.as_rule() == Rule::string);
+            if is_filename {
+                // "spectrum Kd" "filename"
+                let (string1, string2) = parameter_string(parameter_pair);
+                let mut strings: Vec<String> = Vec::with_capacity(1_usize);
+                strings.push(string2);
+                params.add_sampled_spectrum_files(string1, strings);
+            } else {
+                // "spectrum Kd" [ 300 .3  400 .6   410 .65  415 .8  500 .2  600 .1 ]
+                let (string, floats) = parameter_floats(parameter_pair, chunk)?;
+                if floats.len() % 2 != 0 {
+                    return Err(ParseError::at(
+                        chunk,
+                        &location,
+                        format!(
+                            "spectrum parameter {:?} needs pairs of wavelength and value, found {} values",
+                            string,
+                            floats.len()
+                        ),
+                    ));
+                }
+                let n: usize = floats.len() / 2;
+                let mut lambda: Vec<Float> = Vec::with_capacity(n);
+                let mut vals: Vec<Float> = Vec::with_capacity(n);
+                for i in 0..n {
+                    lambda.push(floats[2 * i]);
+                    vals.push(floats[2 * i + 1]);
+                }
+                if !spectrum_samples_sorted(&lambda, &vals, n as i32) {
+                    // the samples get sorted when the spectrum is created
+                    let warning: ApiError = ApiError::InvalidParameter {
+                        directive: params.key_word.clone(),
+                        message: format!(
+                            "spectrum parameter {:?} has unsorted wavelengths, sorting them",
+                            string
+                        ),
+                    };
+                    api_result(api_state.report(warning), chunk, &location)?;
+                    sort_spectrum_samples(&mut lambda, &mut vals);
+                }
+                for i in 1..lambda.len() {
+                    if lambda[i - 1] == lambda[i] {
+                        return Err(ParseError::at(
+                            chunk,
+                            &location,
+                            format!(
+                                "spectrum parameter {:?} has more than one value for wavelength {}",
+                                string, lambda[i]
+                            ),
+                        ));
+                    }
+                }
+                if let Err(message) = params.add_sampled_spectrum(string, floats) {
+                    return Err(ParseError::at(chunk, &location, message));
+                }
+            }
+        }
+        Rule::string_param => {
+            // "string aovs" [ "depth" "normal" ]
+            let (name, values) = parameter_name_and_values(parameter_pair);
+            let strings: Vec<String> = values.into_iter().map(string_value).collect();
+            params.add_strings(name, strings);
+        }
+        Rule::texture_param => {
+            let (string1, string2) = parameter_string(parameter_pair);
+            params.add_texture(string1, string2);
+        }
+        Rule::vector_param => {
+            let (string, floats) = parameter_floats(parameter_pair, chunk)?;
+            params.add_vector3f(
+                string,
+                Vector3f {
+                    x: floats[0],
+                    y: floats[1],
+                    z: floats[2],
+                },
+            );
+        }
+        _ => return Err(unexpected_rule(chunk, &parameter_pair)),
+    }
+    Ok(())
+}
+
+/// Point and normal parameters need a multiple of their number of
+/// coordinates.
+fn check_tuples(
+    pair: &Pair<Rule>,
+    chunk: &Chunk,
+    name: &String,
+    n_values: usize,
+    n_coordinates: usize,
+) -> Result<(), ParseError> {
+    if n_values % n_coordinates != 0 {
+        Err(ParseError::at(
+            chunk,
+            pair,
+            format!(
+                "parameter {:?} needs {} coordinates per value ({} numbers found)",
+                name, n_coordinates, n_values
+            ),
+        ))
+    } else {
+        Ok(())
+    }
+}
@@ -9,6 +9,7 @@ use core::lowdiscrepancy::{
 use core::lowdiscrepancy::{PRIME_SUMS, PRIME_TABLE_SIZE};
 use core::pbrt::mod_t;
 use core::pbrt::Float;
+use core::profiler::{Prof, ProfilePhase};
 use core::rng::Rng;
 use core::sampler::{GlobalSampler, Sampler};
 
@@ -185,7 +186,7 @@ impl HaltonSampler {
 
 impl Sampler for HaltonSampler {
     fn start_pixel(&mut self, p: &Point2i) {
-        // TODO: ProfilePhase _(Prof::StartPixel);
+        let _p = ProfilePhase::new(Prof::StartPixel);
         // Sampler::StartPixel(p);
         self.current_pixel = *p;
         self.current_pixel_sample_index = 0_i64;
@@ -222,7 +223,7 @@ impl Sampler for HaltonSampler {
         assert!(self.array_end_dim == dim);
     }
     fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _p = ProfilePhase::new(Prof::GetSample);
         if self.dimension >= self.array_start_dim && self.dimension < self.array_end_dim {
             self.dimension = self.array_end_dim;
         }
@@ -233,7 +234,7 @@ impl Sampler for HaltonSampler {
         ret
     }
     fn get_2d(&mut self) -> Point2f {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _p = ProfilePhase::new(Prof::GetSample);
         if self.dimension + 1 >= self.array_start_dim && self.dimension < self.array_end_dim {
             self.dimension = self.array_end_dim;
         }
@@ -1,6 +1,7 @@
 // pbrt
 use core::geometry::{Point2f, Point2i};
 use core::pbrt::Float;
+use core::profiler::{Prof, ProfilePhase};
 use core::rng::Rng;
 use core::sampler::Sampler;
 
@@ -39,7 +40,7 @@ impl RandomSampler {
 
 impl Sampler for RandomSampler {
     fn start_pixel(&mut self, p: &Point2i) {
-        // TODO: ProfilePhase _(Prof::StartPixel);
+        let _p = ProfilePhase::new(Prof::StartPixel);
         for i in 0..self.sample_array_1d.len() {
             for j in 0..self.sample_array_1d[i].len() {
                 self.sample_array_1d[i][j] = self.rng.uniform_float();
@@ -61,12 +62,12 @@ impl Sampler for RandomSampler {
         self.array_2d_offset = 0_usize;
     }
     fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _p = ProfilePhase::new(Prof::GetSample);
         assert!(self.current_pixel_sample_index < self.samples_per_pixel);
         self.rng.uniform_float()
     }
     fn get_2d(&mut self) -> Point2f {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _p = ProfilePhase::new(Prof::GetSample);
         assert!(self.current_pixel_sample_index < self.samples_per_pixel);
         // C++: call x first
         let x = self.rng.uniform_float();
@@ -3,6 +3,7 @@ use core::geometry::{Bounds2i, Point2f, Point2i, Vector2i};
 use core::lowdiscrepancy::{sobol_interval_to_index, sobol_sample};
 use core::pbrt::Float;
 use core::pbrt::{clamp_t, is_power_of_2, log_2_int_u32, round_up_pow2_32, round_up_pow2_64};
+use core::profiler::{Prof, ProfilePhase};
 use core::rng::FLOAT_ONE_MINUS_EPSILON;
 use core::sampler::{GlobalSampler, Sampler};
 use core::sobolmatrices::NUM_SOBOL_DIMENSIONS;
@@ -96,7 +97,7 @@ impl SobolSampler {
 
 impl Sampler for SobolSampler {
     fn start_pixel(&mut self, p: &Point2i) {
-        // TODO: ProfilePhase _(Prof::StartPixel);
+        let _p = ProfilePhase::new(Prof::StartPixel);
         // Sampler::StartPixel(p);
         self.current_pixel = *p;
         self.current_pixel_sample_index = 0_i64;
@@ -133,7 +134,7 @@ impl Sampler for SobolSampler {
         assert!(self.array_end_dim == dim);
     }
     fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _p = ProfilePhase::new(Prof::GetSample);
         if self.dimension >= self.array_start_dim && self.dimension < self.array_end_dim {
             self.dimension = self.array_end_dim;
         }
@@ -144,7 +145,7 @@ impl Sampler for SobolSampler {
         ret
     }
     fn get_2d(&mut self) -> Point2f {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _p = ProfilePhase::new(Prof::GetSample);
         if self.dimension + 1 >= self.array_start_dim && self.dimension < self.array_end_dim {
             self.dimension = self.array_end_dim;
         }
@@ -3,6 +3,7 @@ use core::geometry::{Point2f, Point2i};
 use core::lowdiscrepancy::{sobol_2d, van_der_corput};
 use core::pbrt::round_up_pow2_32;
 use core::pbrt::Float;
+use core::profiler::{Prof, ProfilePhase};
 use core::rng::Rng;
 use core::sampler::{PixelSampler, Sampler};
 
@@ -94,7 +95,7 @@ impl ZeroTwoSequenceSampler {
 
 impl Sampler for ZeroTwoSequenceSampler {
     fn start_pixel(&mut self, p: &Point2i) {
-        // TODO: ProfilePhase _(Prof::StartPixel);
+        let _p = ProfilePhase::new(Prof::StartPixel);
         // generate 1D and 2D pixel sample components using $(0,2)$-sequence
         for samples in &mut self.samples_1d {
             van_der_corput(1, self.samples_per_pixel as i32, samples, &mut self.rng);
@@ -129,7 +130,7 @@ impl Sampler for ZeroTwoSequenceSampler {
         self.array_2d_offset = 0_usize;
     }
     fn get_1d(&mut self) -> Float {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _p = ProfilePhase::new(Prof::GetSample);
         assert!(
             self.current_pixel_sample_index < self.samples_per_pixel,
             "current_pixel_sample_index = {}, samples_per_pixel = {}",
@@ -146,7 +147,7 @@ impl Sampler for ZeroTwoSequenceSampler {
         }
     }
     fn get_2d(&mut self) -> Point2f {
-        // TODO: ProfilePhase _(Prof::GetSample);
+        let _p = ProfilePhase::new(Prof::GetSample);
         assert!(
             self.current_pixel_sample_index < self.samples_per_pixel,
             "current_pixel_sample_index = {}, samples_per_pixel = {}",
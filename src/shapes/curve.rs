@@ -1,4 +1,5 @@
 // std
+use std::mem;
 use std::sync::Arc;
 // pbrt
 use core::geometry::{
@@ -11,11 +12,21 @@ use core::material::Material;
 use core::paramset::ParamSet;
 use core::pbrt::Float;
 use core::pbrt::{clamp_t, float_to_bits, lerp};
+use core::profiler::{Prof, ProfilePhase};
 use core::shape::Shape;
+use core::stats::{StatCounter, StatIntDistribution, StatMemoryCounter, StatPercent};
 use core::transform::Transform;
 
 // see curve.h
 
+static CURVE_BYTES: StatMemoryCounter = StatMemoryCounter::new("Memory/Curves");
+static RAY_CURVE_TESTS: StatPercent =
+    StatPercent::new("Intersections/Ray-curve intersection tests");
+static REFINEMENT_LEVEL: StatIntDistribution =
+    StatIntDistribution::new("Intersections/Curve refinement level");
+static N_CURVES: StatCounter = StatCounter::new("Scene/Curves");
+static N_SPLIT_CURVES: StatCounter = StatCounter::new("Scene/Split curves");
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum CurveType {
     Flat,
@@ -74,8 +85,8 @@ pub struct Curve {
     pub u_min: Float,
     pub u_max: Float,
     // inherited from class Shape (see shape.h)
-    object_to_world: Transform,
-    world_to_object: Transform,
+    object_to_world: Arc<Transform>,
+    world_to_object: Arc<Transform>,
     reverse_orientation: bool,
     transform_swaps_handedness: bool,
     pub material: Option<Arc<Material + Send + Sync>>,
@@ -83,13 +94,14 @@ pub struct Curve {
 
 impl Curve {
     pub fn new(
-        object_to_world: Transform,
-        world_to_object: Transform,
+        object_to_world: Arc<Transform>,
+        world_to_object: Arc<Transform>,
         reverse_orientation: bool,
         common: Arc<CurveCommon>,
         u_min: Float,
         u_max: Float,
     ) -> Self {
+        N_CURVES.inc();
         Curve {
             // Curve
             common: common,
@@ -104,8 +116,8 @@ impl Curve {
         }
     }
     pub fn create(
-        o2w: Transform,
-        w2o: Transform,
+        o2w: Arc<Transform>,
+        w2o: Arc<Transform>,
         reverse_orientation: bool,
         c: &[Point3f; 4],
         w0: Float,
@@ -123,17 +135,17 @@ impl Curve {
             // segments.push_back(std::make_shared<Curve>(o2w, w2o, reverseOrientation,
             //                                            common, u_min, u_max));
             let curve: Arc<Curve> = Arc::new(Curve::new(
-                o2w,
-                w2o,
+                o2w.clone(),
+                w2o.clone(),
                 reverse_orientation,
                 common.clone(),
                 u_min,
                 u_max,
             ));
             segments.push(curve.clone());
-            // TODO: ++nSplitCurves;
+            N_SPLIT_CURVES.inc();
         }
-        // TODO: curveBytes += sizeof(CurveCommon) + n_segments * sizeof(Curve);
+        CURVE_BYTES.add(mem::size_of::<CurveCommon>() + n_segments * mem::size_of::<Curve>());
         segments
     }
     fn recursive_intersect(
@@ -337,7 +349,7 @@ impl Curve {
                 isect.shape = si.shape;
             }
             // }
-            // TODO: ++n_hits;
+            RAY_CURVE_TESTS.add(1, 0);
             // return true;
             hit = Some((isect, t_hit));
         }
@@ -368,8 +380,8 @@ impl Shape for Curve {
         self.object_to_world.transform_bounds(&self.object_bound())
     }
     fn intersect(&self, r: &Ray) -> Option<(SurfaceInteraction, Float)> {
-        // TODO: ProfilePhase p(isect ? Prof::CurveIntersect : Prof::CurveIntersectP);
-        // TODO: ++nTests;
+        let _p = ProfilePhase::new(Prof::CurveIntersect);
+        RAY_CURVE_TESTS.add(0, 1);
         // transform _Ray_ to object space
         let mut o_err: Vector3f = Vector3f::default();
         let mut d_err: Vector3f = Vector3f::default();
@@ -466,7 +478,7 @@ impl Shape for Curve {
         let r0: i32 =
             log2(1.41421356237 as Float * 6.0 as Float * l0 / (8.0 as Float * eps)) / 2_i32;
         let max_depth: i32 = clamp_t(r0, 0_i32, 10_i32);
-        // TODO: ReportValue(refinementLevel, maxDepth);
+        REFINEMENT_LEVEL.report_value(max_depth as i64);
         self.recursive_intersect(
             &ray,
             &[cp[0], cp[1], cp[2], cp[3]],
@@ -477,6 +489,7 @@ impl Shape for Curve {
         )
     }
     fn intersect_p(&self, r: &Ray) -> bool {
+        let _p = ProfilePhase::new(Prof::CurveIntersectP);
         if let Some((_isect, _t_hit)) = self.intersect(r) {
             true
         } else {
@@ -551,8 +564,8 @@ impl Shape for Curve {
 }
 
 pub fn create_curve_shape(
-    o2w: &Transform,
-    w2o: &Transform,
+    o2w: &Arc<Transform>,
+    w2o: &Arc<Transform>,
     reverse_orientation: bool,
     params: &ParamSet,
 ) -> Vec<Arc<Shape + Send + Sync>> {
@@ -598,8 +611,8 @@ pub fn create_curve_shape(
     }
     if n.is_empty() {
         Curve::create(
-            *o2w,
-            *w2o,
+            o2w.clone(),
+            w2o.clone(),
             reverse_orientation,
             &[cp[0], cp[1], cp[2], cp[3]],
             width0,
@@ -610,8 +623,8 @@ pub fn create_curve_shape(
         )
     } else {
         Curve::create(
-            *o2w,
-            *w2o,
+            o2w.clone(),
+            w2o.clone(),
             reverse_orientation,
             &[cp[0], cp[1], cp[2], cp[3]],
             width0,
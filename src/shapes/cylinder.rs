@@ -10,6 +10,7 @@ use core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
 use core::material::Material;
 use core::pbrt::Float;
 use core::pbrt::{clamp_t, gamma, lerp, radians};
+use core::profiler::{Prof, ProfilePhase};
 use core::shape::Shape;
 use core::transform::Transform;
 
@@ -22,8 +23,8 @@ pub struct Cylinder {
     pub z_max: Float,
     pub phi_max: Float,
     // inherited from class Shape (see shape.h)
-    object_to_world: Transform,
-    world_to_object: Transform,
+    object_to_world: Arc<Transform>,
+    world_to_object: Arc<Transform>,
     reverse_orientation: bool,
     transform_swaps_handedness: bool,
     pub material: Option<Arc<Material + Send + Sync>>,
@@ -33,8 +34,8 @@ impl Default for Cylinder {
     fn default() -> Self {
         Cylinder {
             // Shape
-            object_to_world: Transform::default(),
-            world_to_object: Transform::default(),
+            object_to_world: Arc::new(Transform::default()),
+            world_to_object: Arc::new(Transform::default()),
             reverse_orientation: false,
             transform_swaps_handedness: false,
             // Cylinder
@@ -49,8 +50,8 @@ impl Default for Cylinder {
 
 impl Cylinder {
     pub fn new(
-        object_to_world: Transform,
-        world_to_object: Transform,
+        object_to_world: Arc<Transform>,
+        world_to_object: Arc<Transform>,
         reverse_orientation: bool,
         radius: Float,
         z_min: Float,
@@ -93,7 +94,7 @@ impl Shape for Cylinder {
         self.object_to_world.transform_bounds(&self.object_bound())
     }
     fn intersect(&self, r: &Ray) -> Option<(SurfaceInteraction, Float)> {
-        // TODO: ProfilePhase p(Prof::ShapeIntersect);
+        let _p = ProfilePhase::new(Prof::ShapeIntersect);
         // transform _Ray_ to object space
         let mut o_err: Vector3f = Vector3f::default();
         let mut d_err: Vector3f = Vector3f::default();
@@ -242,7 +243,7 @@ impl Shape for Cylinder {
         Some((isect, t_shape_hit.v as Float))
     }
     fn intersect_p(&self, r: &Ray) -> bool {
-        // TODO: ProfilePhase p(Prof::ShapeIntersect);
+        let _p = ProfilePhase::new(Prof::ShapeIntersectP);
         // transform _Ray_ to object space
         let mut o_err: Vector3f = Vector3f::default();
         let mut d_err: Vector3f = Vector3f::default();
@@ -8,6 +8,7 @@ use core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
 use core::material::Material;
 use core::pbrt::Float;
 use core::pbrt::{clamp_t, radians};
+use core::profiler::{Prof, ProfilePhase};
 use core::sampling::concentric_sample_disk;
 use core::shape::Shape;
 use core::transform::Transform;
@@ -21,8 +22,8 @@ pub struct Disk {
     pub inner_radius: Float,
     pub phi_max: Float,
     // inherited from class Shape (see shape.h)
-    object_to_world: Transform,
-    world_to_object: Transform,
+    object_to_world: Arc<Transform>,
+    world_to_object: Arc<Transform>,
     reverse_orientation: bool,
     transform_swaps_handedness: bool,
     pub material: Option<Arc<Material + Send + Sync>>,
@@ -32,8 +33,8 @@ impl Default for Disk {
     fn default() -> Self {
         Disk {
             // Shape
-            object_to_world: Transform::default(),
-            world_to_object: Transform::default(),
+            object_to_world: Arc::new(Transform::default()),
+            world_to_object: Arc::new(Transform::default()),
             reverse_orientation: false,
             transform_swaps_handedness: false,
             // Disk
@@ -48,8 +49,8 @@ impl Default for Disk {
 
 impl Disk {
     pub fn new(
-        object_to_world: Transform,
-        world_to_object: Transform,
+        object_to_world: Arc<Transform>,
+        world_to_object: Arc<Transform>,
         reverse_orientation: bool,
         transform_swaps_handedness: bool,
         height: Float,
@@ -93,7 +94,7 @@ impl Shape for Disk {
         self.object_to_world.transform_bounds(&self.object_bound())
     }
     fn intersect(&self, r: &Ray) -> Option<(SurfaceInteraction, Float)> {
-        // TODO: ProfilePhase p(Prof::ShapeIntersect);
+        let _p = ProfilePhase::new(Prof::ShapeIntersect);
         // transform _Ray_ to object space
         let mut o_err: Vector3f = Vector3f::default();
         let mut d_err: Vector3f = Vector3f::default();
@@ -163,7 +164,7 @@ impl Shape for Disk {
         Some((isect, t_shape_hit))
     }
     fn intersect_p(&self, r: &Ray) -> bool {
-        // TODO: ProfilePhase p(Prof::ShapeIntersectP);
+        let _p = ProfilePhase::new(Prof::ShapeIntersectP);
         // transform _Ray_ to object space
         let mut o_err: Vector3f = Vector3f::default();
         let mut d_err: Vector3f = Vector3f::default();
@@ -266,8 +266,8 @@ fn loop_gamma(valence: i32) -> Float {
 }
 
 pub fn loop_subdivide(
-    object_to_world: &Transform,
-    world_to_object: &Transform,
+    object_to_world: &Arc<Transform>,
+    world_to_object: &Arc<Transform>,
     reverse_orientation: bool,
     n_levels: i32,
     vertex_indices: &Vec<i32>,
@@ -643,8 +643,8 @@ pub fn loop_subdivide(
         n_ws.push(object_to_world.transform_normal(&ns[i]));
     }
     Arc::new(TriangleMesh::new(
-        *object_to_world,
-        *world_to_object,
+        object_to_world.clone(),
+        world_to_object.clone(),
         reverse_orientation,
         false, // transform_swaps_handedness
         ntris,
@@ -64,6 +64,7 @@ pub mod cylinder;
 pub mod disk;
 pub mod loopsubdiv;
 pub mod nurbs;
+pub mod objmesh;
 pub mod plymesh;
 pub mod sphere;
 pub mod triangle;
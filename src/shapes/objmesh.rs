@@ -0,0 +1,564 @@
+//! Loads polygon meshes from Wavefront OBJ files (**objmesh**
+//! shape). Faces are triangulated as fans and split into one
+//! **TriangleMesh** per material (`usemtl`). Materials found in the
+//! MTL files (`mtllib`) are mapped onto pbrt's materials:
+//!
+//! * `illum` 4, 6, 7, or 9 (refraction): **glass** with `Ks` as `Kr`,
+//!   `Tf` as `Kt`, and `Ni` as index of refraction
+//! * `illum` 3 or 5 (reflection) with a specular color: **metal**
+//!   reflecting `Ks` at normal incidence
+//! * dissolve `d` (or `Tr`) below one: **uber** with `d` as opacity
+//! * a specular color `Ks`: **plastic**
+//! * otherwise: **matte** with `Kd`
+//!
+//! The Phong exponent `Ns` becomes the roughness, and the images of
+//! `map_Kd` and `map_Ks` become textures for `Kd` and `Ks`. Faces
+//! without a material (or with one not defined in any MTL file) use
+//! the current material of the scene description, as do all faces if
+//! `"bool usemtl" [false]` is given.
+
+// std
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+// pbrt
+use core::api::ApiError;
+use core::fileutil::open_file;
+use core::geometry::{Normal3f, Point2f, Point3f, Vector3f};
+use core::material::Material;
+use core::mipmap::ImageWrap;
+use core::paramset::{ParamSet, TextureParams};
+use core::pbrt::{Float, Spectrum};
+use core::shape::Shape;
+use core::texture::{Texture, UVMapping2D};
+use core::transform::Transform;
+use materials::glass::GlassMaterial;
+use materials::matte::MatteMaterial;
+use materials::metal::MetalMaterial;
+use materials::plastic::PlasticMaterial;
+use materials::uber::UberMaterial;
+use shapes::triangle::{Triangle, TriangleMesh};
+use textures::imagemap::{convert_to_spectrum, ImageTexture};
+
+/// The statements of a `newmtl` block in an MTL file.
+#[derive(Debug, Clone)]
+pub struct ObjMaterial {
+    pub name: String,
+    pub kd: Spectrum,
+    pub ks: Spectrum,
+    pub tf: Spectrum,
+    pub ns: Option<Float>,
+    pub ni: Float,
+    pub d: Float,
+    pub illum: i32,
+    pub map_kd: Option<String>,
+    pub map_ks: Option<String>,
+}
+
+impl ObjMaterial {
+    pub fn new(name: &str) -> Self {
+        ObjMaterial {
+            name: String::from(name),
+            kd: Spectrum::new(0.5 as Float),
+            ks: Spectrum::new(0.0 as Float),
+            tf: Spectrum::new(1.0 as Float),
+            ns: None,
+            ni: 1.5 as Float,
+            d: 1.0 as Float,
+            illum: 2,
+            map_kd: None,
+            map_ks: None,
+        }
+    }
+    /// Creates the pbrt material (see module description), using
+    /// **textures** to share image textures between materials.
+    pub fn create(
+        &self,
+        textures: &mut HashMap<String, Arc<Texture<Spectrum> + Send + Sync>>,
+    ) -> Arc<Material + Send + Sync> {
+        let mut mp: TextureParams = TextureParams::new(
+            ParamSet::default(),
+            ParamSet::default(),
+            HashMap::new(),
+            HashMap::new(),
+        );
+        let has_specular: bool = !self.ks.is_black() || self.map_ks.is_some();
+        match self.illum {
+            4 | 6 | 7 | 9 => {
+                if has_specular {
+                    add_color(&mut mp, textures, "Kr", &self.ks, &self.map_ks);
+                } else {
+                    let kr: Spectrum = Spectrum::new(1.0 as Float);
+                    add_color(&mut mp, textures, "Kr", &kr, &None);
+                }
+                add_color(&mut mp, textures, "Kt", &self.tf, &None);
+                mp.material_params.add_float(String::from("index"), self.ni);
+                GlassMaterial::create(&mut mp)
+            }
+            3 | 5 if !self.ks.is_black() => {
+                // conductor with eta = 1 reflecting Ks at normal
+                // incidence: Ks = k^2 / (4 + k^2)
+                let mut rgb: [Float; 3] = [0.0 as Float; 3];
+                self.ks.to_rgb(&mut rgb);
+                for c in rgb.iter_mut() {
+                    let r: Float = c.min(0.999 as Float).max(0.0 as Float);
+                    *c = 2.0 as Float * (r / (1.0 as Float - r)).sqrt();
+                }
+                let eta: Spectrum = Spectrum::new(1.0 as Float);
+                add_color(&mut mp, textures, "eta", &eta, &None);
+                add_color(&mut mp, textures, "k", &Spectrum::from_rgb(&rgb), &None);
+                self.add_roughness(&mut mp);
+                MetalMaterial::create(&mut mp)
+            }
+            _ => {
+                add_color(&mut mp, textures, "Kd", &self.kd, &self.map_kd);
+                if self.d < 1.0 as Float {
+                    add_color(&mut mp, textures, "Ks", &self.ks, &self.map_ks);
+                    let opacity: Spectrum = Spectrum::new(self.d);
+                    add_color(&mut mp, textures, "opacity", &opacity, &None);
+                    mp.material_params.add_float(String::from("index"), self.ni);
+                    self.add_roughness(&mut mp);
+                    UberMaterial::create(&mut mp)
+                } else if has_specular {
+                    add_color(&mut mp, textures, "Ks", &self.ks, &self.map_ks);
+                    self.add_roughness(&mut mp);
+                    PlasticMaterial::create(&mut mp)
+                } else {
+                    MatteMaterial::create(&mut mp)
+                }
+            }
+        }
+    }
+    /// Converts the Phong exponent to a microfacet roughness.
+    fn add_roughness(&self, mp: &mut TextureParams) {
+        if let Some(ns) = self.ns {
+            let roughness: Float = (2.0 as Float / (ns.max(0.0 as Float) + 2.0 as Float)).sqrt();
+            mp.material_params
+                .add_float(String::from("roughness"), roughness);
+            mp.material_params
+                .add_bool(String::from("remaproughness"), false);
+        }
+    }
+}
+
+/// Adds a color parameter, or an image texture (named after its
+/// file), if a map is given.
+fn add_color(
+    mp: &mut TextureParams,
+    textures: &mut HashMap<String, Arc<Texture<Spectrum> + Send + Sync>>,
+    name: &str,
+    color: &Spectrum,
+    map: &Option<String>,
+) {
+    if let Some(ref filename) = *map {
+        let texture: Arc<Texture<Spectrum> + Send + Sync> = textures
+            .entry(filename.clone())
+            .or_insert_with(|| {
+                Arc::new(ImageTexture::new(
+                    Box::new(UVMapping2D {
+                        su: 1.0,
+                        sv: 1.0,
+                        du: 0.0,
+                        dv: 0.0,
+                    }),
+                    filename.clone(),
+                    false, // do_trilinear
+                    8.0,   // max_aniso
+                    ImageWrap::Repeat,
+                    1.0,  // scale
+                    true, // gamma
+                    convert_to_spectrum,
+                ))
+            })
+            .clone();
+        mp.spectrum_textures.insert(filename.clone(), texture);
+        mp.material_params
+            .add_texture(String::from(name), filename.clone());
+    } else {
+        mp.material_params
+            .add_rgb_spectrum(String::from(name), *color);
+    }
+}
+
+/// The shapes (triangles) sharing a material; no material means the
+/// current material should be used.
+pub struct ObjMeshPart {
+    pub shapes: Vec<Arc<Shape + Send + Sync>>,
+    pub material: Option<Arc<Material + Send + Sync>>,
+}
+
+/// Triangles of one material, with indices into the position, uv,
+/// and normal lists of the OBJ file for each corner.
+#[derive(Default)]
+struct FaceGroup {
+    material: String,
+    corners: Vec<(usize, Option<usize>, Option<usize>)>,
+}
+
+pub fn create_obj_mesh(
+    o2w: &Arc<Transform>,
+    w2o: &Arc<Transform>,
+    reverse_orientation: bool,
+    params: &ParamSet,
+    search_directory: Option<&Box<PathBuf>>,
+    warnings: &mut Vec<ApiError>,
+) -> Result<Vec<ObjMeshPart>, String> {
+    let mut path: PathBuf = PathBuf::from(params.find_one_string("filename", String::new()));
+    if let Some(ref search_directory) = search_directory {
+        path = search_directory.join(path);
+    }
+    let source: String = read_file(&path)?;
+    let use_mtl: bool = params.find_one_bool("usemtl", true);
+    let directory: PathBuf = match path.parent() {
+        Some(parent) => PathBuf::from(parent),
+        None => PathBuf::new(),
+    };
+    let mut p: Vec<Point3f> = Vec::new();
+    let mut uvs: Vec<Point2f> = Vec::new();
+    let mut n: Vec<Normal3f> = Vec::new();
+    let mut groups: Vec<FaceGroup> = vec![FaceGroup::default()];
+    let mut current: usize = 0;
+    let mut obj_materials: HashMap<String, ObjMaterial> = HashMap::new();
+    for (line_number, line) in logical_lines(&source) {
+        let location = |message: String| format!("{}:{}: {}", path.display(), line_number, message);
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        match tokens[0] {
+            "v" => {
+                let v: Vec<Float> = floats(&tokens[1..], 3).map_err(&location)?;
+                p.push(Point3f {
+                    x: v[0],
+                    y: v[1],
+                    z: v[2],
+                });
+            }
+            "vt" => {
+                let v: Vec<Float> = floats(&tokens[1..], 1).map_err(&location)?;
+                uvs.push(Point2f {
+                    x: v[0],
+                    y: *v.get(1).unwrap_or(&0.0),
+                });
+            }
+            "vn" => {
+                let v: Vec<Float> = floats(&tokens[1..], 3).map_err(&location)?;
+                n.push(Normal3f {
+                    x: v[0],
+                    y: v[1],
+                    z: v[2],
+                });
+            }
+            "f" => {
+                let mut face: Vec<(usize, Option<usize>, Option<usize>)> = Vec::new();
+                for token in &tokens[1..] {
+                    let mut indices = token.split('/');
+                    let vi: usize = match indices.next() {
+                        Some(index) => resolve_index(index, p.len()).map_err(&location)?,
+                        None => return Err(location(format!("invalid face vertex {:?}", token))),
+                    };
+                    let ti: Option<usize> = match indices.next() {
+                        Some(index) if index != "" => {
+                            Some(resolve_index(index, uvs.len()).map_err(&location)?)
+                        }
+                        _ => None,
+                    };
+                    let ni: Option<usize> = match indices.next() {
+                        Some(index) if index != "" => {
+                            Some(resolve_index(index, n.len()).map_err(&location)?)
+                        }
+                        _ => None,
+                    };
+                    face.push((vi, ti, ni));
+                }
+                if face.len() < 3 {
+                    return Err(location(format!(
+                        "face with {} vertices (at least 3 expected)",
+                        face.len()
+                    )));
+                }
+                for i in 1..face.len() - 1 {
+                    groups[current].corners.push(face[0]);
+                    groups[current].corners.push(face[i]);
+                    groups[current].corners.push(face[i + 1]);
+                }
+            }
+            "usemtl" if use_mtl => {
+                let name: String = tokens[1..].join(" ");
+                current = match groups.iter().position(|g| g.material == name) {
+                    Some(index) => index,
+                    None => {
+                        groups.push(FaceGroup {
+                            material: name,
+                            corners: Vec::new(),
+                        });
+                        groups.len() - 1
+                    }
+                };
+            }
+            "mtllib" if use_mtl => {
+                for filename in &tokens[1..] {
+                    read_mtl_file(&directory.join(filename), &mut obj_materials, warnings)?;
+                }
+            }
+            // groups, objects, smoothing groups, lines, points, etc.
+            _ => {}
+        }
+    }
+    let mut textures: HashMap<String, Arc<Texture<Spectrum> + Send + Sync>> = HashMap::new();
+    let mut parts: Vec<ObjMeshPart> = Vec::new();
+    for group in groups {
+        if group.corners.is_empty() {
+            continue;
+        }
+        let material: Option<Arc<Material + Send + Sync>> = match obj_materials.get(&group.material)
+        {
+            Some(obj_material) => Some(obj_material.create(&mut textures)),
+            None => {
+                // using the current material
+                if group.material != "" {
+                    warnings.push(ApiError::Unknown {
+                        directive: format!("{}: usemtl", path.display()),
+                        name: group.material.clone(),
+                    });
+                }
+                None
+            }
+        };
+        let shapes: Vec<Arc<Shape + Send + Sync>> =
+            create_triangles(o2w, w2o, reverse_orientation, &group, &p, &uvs, &n);
+        parts.push(ObjMeshPart {
+            shapes: shapes,
+            material: material,
+        });
+    }
+    Ok(parts)
+}
+
+/// Vertices are duplicated, where corners share a position but not
+/// uv coordinates or normals. Normals are only used, if all corners
+/// have one.
+fn create_triangles(
+    o2w: &Arc<Transform>,
+    w2o: &Arc<Transform>,
+    reverse_orientation: bool,
+    group: &FaceGroup,
+    p: &Vec<Point3f>,
+    uvs: &Vec<Point2f>,
+    n: &Vec<Normal3f>,
+) -> Vec<Arc<Shape + Send + Sync>> {
+    let has_uvs: bool = group.corners.iter().any(|c| c.1.is_some());
+    let has_normals: bool = group.corners.iter().all(|c| c.2.is_some());
+    let mut vertices: HashMap<(usize, Option<usize>, Option<usize>), usize> = HashMap::new();
+    let mut vertex_indices: Vec<usize> = Vec::with_capacity(group.corners.len());
+    let mut p_ws: Vec<Point3f> = Vec::new();
+    let mut uv: Vec<Point2f> = Vec::new();
+    let mut n_ws: Vec<Normal3f> = Vec::new();
+    for corner in &group.corners {
+        let next: usize = p_ws.len();
+        let index: usize = *vertices.entry(*corner).or_insert(next);
+        if index == next {
+            // transform mesh vertices (and normals) to world space
+            let (vi, ti, ni) = *corner;
+            p_ws.push(o2w.transform_point(&p[vi]));
+            if has_uvs {
+                match ti {
+                    Some(ti) => uv.push(uvs[ti]),
+                    None => uv.push(Point2f::default()),
+                }
+            }
+            if has_normals {
+                if let Some(ni) = ni {
+                    n_ws.push(o2w.transform_normal(&n[ni]));
+                }
+            }
+        }
+        vertex_indices.push(index);
+    }
+    let s_ws: Vec<Vector3f> = Vec::new();
+    let n_vertices: usize = p_ws.len();
+    let mesh = Arc::new(TriangleMesh::new(
+        o2w.clone(),
+        w2o.clone(),
+        reverse_orientation,
+        false, // transform_swaps_handedness
+        vertex_indices.len() / 3,
+        vertex_indices,
+        n_vertices,
+        p_ws, // in world space
+        s_ws, // in world space
+        n_ws, // in world space
+        uv,
+    ));
+    let mut shapes: Vec<Arc<Shape + Send + Sync>> = Vec::new();
+    for id in 0..mesh.n_triangles {
+        let triangle = Arc::new(Triangle::new(
+            mesh.object_to_world.clone(),
+            mesh.world_to_object.clone(),
+            mesh.reverse_orientation,
+            mesh.clone(),
+            id,
+        ));
+        shapes.push(triangle.clone());
+    }
+    shapes
+}
+
+fn read_mtl_file(
+    path: &Path,
+    materials: &mut HashMap<String, ObjMaterial>,
+    warnings: &mut Vec<ApiError>,
+) -> Result<(), String> {
+    let source: String = read_file(path)?;
+    let directory: PathBuf = match path.parent() {
+        Some(parent) => PathBuf::from(parent),
+        None => PathBuf::new(),
+    };
+    let mut material: Option<ObjMaterial> = None;
+    for (line_number, line) in logical_lines(&source) {
+        let location = |message: String| format!("{}:{}: {}", path.display(), line_number, message);
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.is_empty() {
+            continue;
+        }
+        if tokens[0] == "newmtl" {
+            if let Some(material) = material.take() {
+                materials.insert(material.name.clone(), material);
+            }
+            material = Some(ObjMaterial::new(&tokens[1..].join(" ")));
+            continue;
+        }
+        let material: &mut ObjMaterial = match material {
+            Some(ref mut material) => material,
+            None => return Err(location(format!("{:?} before any newmtl", tokens[0]))),
+        };
+        let values: &[&str] = &tokens[1..];
+        match tokens[0] {
+            "Kd" => material.kd = color(values).map_err(&location)?,
+            "Ks" => material.ks = color(values).map_err(&location)?,
+            "Tf" => material.tf = color(values).map_err(&location)?,
+            "Ns" => material.ns = Some(floats(values, 1).map_err(&location)?[0]),
+            "Ni" => material.ni = floats(values, 1).map_err(&location)?[0],
+            "d" => material.d = floats(values, 1).map_err(&location)?[0],
+            "Tr" => material.d = 1.0 as Float - floats(values, 1).map_err(&location)?[0],
+            "illum" => {
+                material.illum = match i32::from_str(values.get(0).unwrap_or(&"")) {
+                    Ok(illum) => illum,
+                    Err(_) => return Err(location(format!("invalid illum {:?}", values))),
+                }
+            }
+            // the file name follows the options
+            "map_Kd" | "map_Ks" => {
+                let filename: PathBuf = match values.last() {
+                    Some(filename) => directory.join(filename),
+                    None => return Err(location(format!("no file name for {}", tokens[0]))),
+                };
+                if !filename.is_file() {
+                    return Err(location(format!(
+                        "can't find image {:?}",
+                        filename.display()
+                    )));
+                }
+                let filename: Option<String> = Some(filename.display().to_string());
+                if tokens[0] == "map_Kd" {
+                    material.map_kd = filename;
+                } else {
+                    material.map_ks = filename;
+                }
+            }
+            statement if statement.starts_with("map_") || statement == "bump" => {
+                warnings.push(ApiError::Unsupported {
+                    directive: format!("{}:{}: MTL", path.display(), line_number),
+                    name: String::from(statement),
+                })
+            }
+            // ambient and emissive colors, etc.
+            _ => {}
+        }
+    }
+    if let Some(material) = material.take() {
+        materials.insert(material.name.clone(), material);
+    }
+    Ok(())
+}
+
+fn read_file(path: &Path) -> Result<String, String> {
+    let mut reader = match open_file(path) {
+        Ok(reader) => reader,
+        Err(e) => return Err(format!("can't open {:?}: {}", path.display(), e)),
+    };
+    let mut source: String = String::new();
+    if let Err(e) = reader.read_to_string(&mut source) {
+        return Err(format!("can't read {:?}: {}", path.display(), e));
+    }
+    Ok(source)
+}
+
+/// Joins lines ending in a backslash and removes comments. Returns
+/// each statement with the (1-based) number of its first line.
+fn logical_lines(source: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut continued: Option<(usize, String)> = None;
+    for (i, line) in source.lines().enumerate() {
+        let line: &str = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let (number, mut text) = continued.take().unwrap_or((i + 1, String::new()));
+        let line: &str = line.trim_end();
+        if line.ends_with('\\') {
+            text.push_str(&line[..line.len() - 1]);
+            text.push(' ');
+            continued = Some((number, text));
+        } else {
+            text.push_str(line);
+            lines.push((number, text));
+        }
+    }
+    if let Some(line) = continued {
+        lines.push(line);
+    }
+    lines
+}
+
+fn floats(values: &[&str], n_min: usize) -> Result<Vec<Float>, String> {
+    let mut numbers: Vec<Float> = Vec::with_capacity(values.len());
+    for value in values {
+        match Float::from_str(value) {
+            Ok(number) => numbers.push(number),
+            Err(_) => return Err(format!("invalid number {:?}", value)),
+        }
+    }
+    if numbers.len() < n_min {
+        return Err(format!("{} number(s) expected", n_min));
+    }
+    Ok(numbers)
+}
+
+/// A single value stands for a grey color.
+fn color(values: &[&str]) -> Result<Spectrum, String> {
+    let v: Vec<Float> = floats(values, 1)?;
+    if v.len() < 3 {
+        Ok(Spectrum::new(v[0]))
+    } else {
+        Ok(Spectrum::rgb(v[0], v[1], v[2]))
+    }
+}
+
+/// OBJ indices start at one, negative ones count backwards from the
+/// last element defined so far.
+fn resolve_index(index: &str, len: usize) -> Result<usize, String> {
+    let i: i64 = match i64::from_str(index) {
+        Ok(i) => i,
+        Err(_) => return Err(format!("invalid index {:?}", index)),
+    };
+    let resolved: i64 = if i < 0 { len as i64 + i } else { i - 1 };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(format!(
+            "out of bounds index {} ({} elements defined)",
+            i, len
+        ));
+    }
+    Ok(resolved as usize)
+}
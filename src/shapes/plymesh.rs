@@ -3,15 +3,17 @@ extern crate ply_rs;
 // std
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::BufReader;
-use std::path::PathBuf;
+use std::io::{BufWriter, Result, Write};
+use std::path::{Path, PathBuf};
 use std::string::String;
 use std::sync::Arc;
 use std::vec::Vec;
 // others
+use byteorder::{LittleEndian, WriteBytesExt};
 use ply_rs::parser;
 use ply_rs::ply;
 // pbrt
+use core::fileutil::open_file;
 use core::geometry::{Normal3f, Point2f, Point3f, Vector3f};
 use core::paramset::ParamSet;
 use core::pbrt::Float;
@@ -20,41 +22,147 @@ use core::texture::Texture;
 use core::transform::Transform;
 use shapes::triangle::{Triangle, TriangleMesh};
 
+/// Creates the triangles of a PLY file given by the *filename*
+/// parameter. An optional second PLY file *filename1* with the same
+/// number of vertices (e.g. the same mesh exported at another frame)
+/// provides the vertex positions (and normals) at **time1** for
+/// deformation motion blur, the first file is used at **time0**.
+/// Problems which don't prevent creating the mesh (like a mismatching
+/// *filename1*) are added to **warnings**.
 pub fn create_ply_mesh(
-    o2w: &Transform,
-    w2o: &Transform,
+    o2w: &Arc<Transform>,
+    w2o: &Arc<Transform>,
     reverse_orientation: bool,
     params: &ParamSet,
     _float_textures: HashMap<String, Arc<Texture<Float> + Send + Sync>>,
     search_directory: Option<&Box<PathBuf>>,
-) -> Vec<Arc<Shape + Send + Sync>> {
-    let mut filename: String = params.find_one_string("filename", String::new());
+    time0: Float,
+    time1: Float,
+    warnings: &mut Vec<String>,
+) -> std::result::Result<Vec<Arc<Shape + Send + Sync>>, String> {
+    let filename: String = ply_path(
+        params.find_one_string("filename", String::new()),
+        search_directory,
+    );
+    let (p, n, uvs, tm_vertex_indices) = read_ply_file(&filename)?;
+    if let Some(vi) = tm_vertex_indices.iter().find(|vi| **vi >= p.len()) {
+        return Err(format!(
+            "PLY file {:?} has vertex index {} out of range (only {} vertices)",
+            filename,
+            vi,
+            p.len()
+        ));
+    }
+    let mut n_ws: Vec<Normal3f> = Vec::new();
+    if !n.is_empty() {
+        assert!(n.len() == p.len());
+        // transform normals to world space
+        let n_normals: usize = n.len();
+        for i in 0..n_normals {
+            n_ws.push(o2w.transform_normal(&n[i]));
+        }
+    }
+    // transform mesh vertices to world space
+    let mut p_ws: Vec<Point3f> = Vec::new();
+    let n_vertices: usize = p.len();
+    for i in 0..n_vertices {
+        p_ws.push(o2w.transform_point(&p[i]));
+    }
+    let s_ws: Vec<Vector3f> = Vec::new(); // TODO
+    let mut mesh: TriangleMesh = TriangleMesh::new(
+        o2w.clone(),
+        w2o.clone(),
+        reverse_orientation,
+        false,                       // transform_swaps_handedness
+        tm_vertex_indices.len() / 3, // n_triangles
+        tm_vertex_indices,
+        n_vertices,
+        p_ws, // in world space
+        s_ws, // in world space
+        n_ws, // in world space
+        uvs,
+    );
+    let filename1: String = params.find_one_string("filename1", String::new());
+    if filename1 != "" {
+        let filename1: String = ply_path(filename1, search_directory);
+        let (p1, n1, _uvs1, _vertex_indices1) = read_ply_file(&filename1)?;
+        if p1.len() != n_vertices {
+            warnings.push(format!(
+                "{:?} has {} vertices instead of {}, ignoring \"filename1\"",
+                filename1,
+                p1.len(),
+                n_vertices
+            ));
+        } else {
+            let p1_ws: Vec<Point3f> = p1.iter().map(|p| o2w.transform_point(p)).collect();
+            let mut n1_ws: Vec<Normal3f> = Vec::new();
+            if n1.len() == mesh.n.len() {
+                for i in 0..n1.len() {
+                    n1_ws.push(o2w.transform_normal(&n1[i]));
+                }
+            }
+            mesh.set_motion(p1_ws, n1_ws, time0, time1);
+        }
+    }
+    let mesh = Arc::new(mesh);
+    let mut shapes: Vec<Arc<Shape + Send + Sync>> = Vec::new();
+    for id in 0..mesh.n_triangles {
+        let triangle = Arc::new(Triangle::new(
+            mesh.object_to_world.clone(),
+            mesh.world_to_object.clone(),
+            mesh.transform_swaps_handedness,
+            mesh.clone(),
+            id,
+        ));
+        shapes.push(triangle.clone());
+    }
+    Ok(shapes)
+}
+
+fn ply_path(filename: String, search_directory: Option<&Box<PathBuf>>) -> String {
     if let Some(ref search_directory) = search_directory {
         let mut path_buf: PathBuf = PathBuf::from("/");
         path_buf.push(search_directory.as_ref());
         path_buf.push(filename);
-        filename = String::from(path_buf.to_str().unwrap());
-    }
-    let result = File::open(&filename);
-    if result.is_err() {
-        panic!("Couldn't open PLY file {:?}", filename);
+        String::from(path_buf.to_str().unwrap())
+    } else {
+        filename
     }
-    let f = result.unwrap();
-    let mut buf_reader = BufReader::new(f);
+}
+
+/// Reads vertex positions, normals, uv coordinates, and the vertex
+/// indices of all triangles (quads get split) from a PLY file. Other
+/// properties are ignored.
+fn read_ply_file(
+    filename: &String,
+) -> std::result::Result<(Vec<Point3f>, Vec<Normal3f>, Vec<Point2f>, Vec<usize>), String> {
+    // compressed PLY files (*.ply.gz) are decompressed while reading
+    let mut buf_reader = match open_file(Path::new(&filename)) {
+        Ok(buf_reader) => buf_reader,
+        Err(e) => return Err(format!("Couldn't open PLY file {:?}: {}", filename, e)),
+    };
     let p = parser::Parser::<ply::DefaultElement>::new();
     // header
-    let result = p.read_header(&mut buf_reader);
-    if result.is_err() {
-        panic!("Unable to read the header of PLY file  {:?}", filename);
-    }
-    let header = result.unwrap();
+    let header = match p.read_header(&mut buf_reader) {
+        Ok(header) => header,
+        Err(e) => {
+            return Err(format!(
+                "Unable to read the header of PLY file {:?}: {}",
+                filename, e
+            ))
+        }
+    };
     // println!("header = {:?}", header);
     // payload
-    let result = p.read_payload(&mut buf_reader, &header);
-    if result.is_err() {
-        panic!("Unable to read the payload of PLY file  {:?}", filename);
-    }
-    let payload = result.unwrap();
+    let payload = match p.read_payload(&mut buf_reader, &header) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return Err(format!(
+                "Unable to read the payload of PLY file {:?}: {}",
+                filename, e
+            ))
+        }
+    };
     // println!("payload = {:?}", payload);
     let mut p: Vec<Point3f> = Vec::new();
     let mut n: Vec<Normal3f> = Vec::new();
@@ -116,10 +224,7 @@ pub fn create_ply_mesh(
                                     pt2.y = y;
                                 }
                             }
-                            _ => {
-                                println!("name2 = {:?}", name2);
-                                unreachable!();
-                            }
+                            _ => {}
                         }
                     }
                     p.push(pnt);
@@ -137,9 +242,7 @@ pub fn create_ply_mesh(
                         match name2.as_ref() {
                             "vertex_indices" => {
                                 if let ply::Property::ListInt(li) = list2 {
-                                    let mut vertex_indices: Vec<
-                                        usize,
-                                    > = Vec::new();
+                                    let mut vertex_indices: Vec<usize> = Vec::new();
                                     for i in li.into_iter() {
                                         vertex_indices.push(i as usize);
                                     }
@@ -154,8 +257,11 @@ pub fn create_ply_mesh(
                                             vertex_indices.push(v3);
                                             vertex_indices.push(v4);
                                         } else {
-                                            panic!("plymesh: Ignoring face with {} vertices (only triangles and quads are supported!)",
-                                                   vertex_indices.len());
+                                            return Err(format!(
+                                                "PLY file {:?} has a face with {} vertices (only triangles and quads are supported)",
+                                                filename,
+                                                vertex_indices.len()
+                                            ));
                                         }
                                     }
                                     // now we can add the indices to the triangle mesh vertex indices
@@ -164,57 +270,75 @@ pub fn create_ply_mesh(
                                     }
                                 }
                             }
-                            _ => unreachable!(),
+                            _ => {}
                         }
                     }
                 }
             }
-            _ => unreachable!(),
+            _ => {}
         }
     }
     // for i in 0..p.len() {
     //     println!("{:?}: {:?}", i, p[i]);
     // }
     // println!("tm_vertex_indices = {:?}", tm_vertex_indices);
-    let mut n_ws: Vec<Normal3f> = Vec::new();
-    if !n.is_empty() {
-        assert!(n.len() == p.len());
-        // transform normals to world space
-        let n_normals: usize = n.len();
-        for i in 0..n_normals {
-            n_ws.push(o2w.transform_normal(&n[i]));
-        }
+    Ok((p, n, uvs, tm_vertex_indices))
+}
+
+/// Writes a triangle mesh as binary (little endian) PLY file, which
+/// can be read back via **create_ply_mesh()** (see WritePlyFile() in
+/// triangle.cpp). Normals and uv coordinates are optional (pass empty
+/// vectors), tangents are not supported by the PLY format used here.
+pub fn write_ply_file(
+    path: &Path,
+    vertex_indices: &Vec<i32>,
+    p: &Vec<Point3f>,
+    n: &Vec<Normal3f>,
+    uv: &Vec<Point2f>,
+) -> Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    let has_normals: bool = !n.is_empty() && n.len() == p.len();
+    let has_uvs: bool = !uv.is_empty() && uv.len() == p.len();
+    // header
+    write!(out, "ply\nformat binary_little_endian 1.0\n")?;
+    write!(out, "element vertex {}\n", p.len())?;
+    write!(
+        out,
+        "property float x\nproperty float y\nproperty float z\n"
+    )?;
+    if has_normals {
+        write!(
+            out,
+            "property float nx\nproperty float ny\nproperty float nz\n"
+        )?;
     }
-    // transform mesh vertices to world space
-    let mut p_ws: Vec<Point3f> = Vec::new();
-    let n_vertices: usize = p.len();
-    for i in 0..n_vertices {
-        p_ws.push(o2w.transform_point(&p[i]));
+    if has_uvs {
+        write!(out, "property float u\nproperty float v\n")?;
     }
-    let s_ws: Vec<Vector3f> = Vec::new(); // TODO
-    let mesh = Arc::new(TriangleMesh::new(
-        *o2w,
-        *w2o,
-        reverse_orientation,
-        false,                       // transform_swaps_handedness
-        tm_vertex_indices.len() / 3, // n_triangles
-        tm_vertex_indices,
-        n_vertices,
-        p_ws, // in world space
-        s_ws, // in world space
-        n_ws, // in world space
-        uvs,
-    ));
-    let mut shapes: Vec<Arc<Shape + Send + Sync>> = Vec::new();
-    for id in 0..mesh.n_triangles {
-        let triangle = Arc::new(Triangle::new(
-            mesh.object_to_world,
-            mesh.world_to_object,
-            mesh.transform_swaps_handedness,
-            mesh.clone(),
-            id,
-        ));
-        shapes.push(triangle.clone());
+    write!(out, "element face {}\n", vertex_indices.len() / 3)?;
+    write!(out, "property list uchar int vertex_indices\nend_header\n")?;
+    // payload
+    for i in 0..p.len() {
+        out.write_f32::<LittleEndian>(p[i].x as f32)?;
+        out.write_f32::<LittleEndian>(p[i].y as f32)?;
+        out.write_f32::<LittleEndian>(p[i].z as f32)?;
+        if has_normals {
+            out.write_f32::<LittleEndian>(n[i].x as f32)?;
+            out.write_f32::<LittleEndian>(n[i].y as f32)?;
+            out.write_f32::<LittleEndian>(n[i].z as f32)?;
+        }
+        if has_uvs {
+            out.write_f32::<LittleEndian>(uv[i].x as f32)?;
+            out.write_f32::<LittleEndian>(uv[i].y as f32)?;
+        }
+    }
+    for triangle in vertex_indices.chunks(3) {
+        if triangle.len() == 3 {
+            out.write_u8(3_u8)?;
+            for vi in triangle {
+                out.write_i32::<LittleEndian>(*vi)?;
+            }
+        }
     }
-    shapes
+    out.flush()
 }
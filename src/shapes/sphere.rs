@@ -13,6 +13,7 @@ use core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
 use core::material::Material;
 use core::pbrt::Float;
 use core::pbrt::{clamp_t, gamma, radians};
+use core::profiler::{Prof, ProfilePhase};
 use core::sampling::{uniform_cone_pdf, uniform_sample_sphere};
 use core::shape::Shape;
 use core::transform::Transform;
@@ -28,8 +29,8 @@ pub struct Sphere {
     pub theta_max: Float,
     pub phi_max: Float,
     // inherited from class Shape (see shape.h)
-    object_to_world: Transform,
-    world_to_object: Transform,
+    object_to_world: Arc<Transform>,
+    world_to_object: Arc<Transform>,
     reverse_orientation: bool,
     transform_swaps_handedness: bool,
     pub material: Option<Arc<Material + Send + Sync>>,
@@ -39,8 +40,8 @@ impl Default for Sphere {
     fn default() -> Self {
         Sphere {
             // Shape
-            object_to_world: Transform::default(),
-            world_to_object: Transform::default(),
+            object_to_world: Arc::new(Transform::default()),
+            world_to_object: Arc::new(Transform::default()),
             reverse_orientation: false,
             transform_swaps_handedness: false,
             // Sphere
@@ -57,8 +58,8 @@ impl Default for Sphere {
 
 impl Sphere {
     pub fn new(
-        object_to_world: Transform,
-        world_to_object: Transform,
+        object_to_world: Arc<Transform>,
+        world_to_object: Arc<Transform>,
         reverse_orientation: bool,
         transform_swaps_handedness: bool,
         radius: Float,
@@ -104,6 +105,7 @@ impl Shape for Sphere {
         self.object_to_world.transform_bounds(&self.object_bound())
     }
     fn intersect(&self, r: &Ray) -> Option<(SurfaceInteraction, Float)> {
+        let _p = ProfilePhase::new(Prof::ShapeIntersect);
         // transform _Ray_ to object space
         let mut o_err: Vector3f = Vector3f::default();
         let mut d_err: Vector3f = Vector3f::default();
@@ -268,6 +270,7 @@ impl Shape for Sphere {
         Some((isect, t_shape_hit.v as Float))
     }
     fn intersect_p(&self, r: &Ray) -> bool {
+        let _p = ProfilePhase::new(Prof::ShapeIntersectP);
         // transform _Ray_ to object space
         let mut o_err: Vector3f = Vector3f::default();
         let mut d_err: Vector3f = Vector3f::default();
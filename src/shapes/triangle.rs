@@ -4,20 +4,27 @@ use std::sync::Arc;
 // pbrt
 use core::geometry::{
     bnd3_union_pnt3, nrm_abs_dot_vec3, nrm_faceforward_nrm, pnt3_abs, pnt3_distance_squared,
-    pnt3_permute, vec3_coordinate_system, vec3_cross_nrm, vec3_cross_vec3, vec3_max_component,
+    pnt3_lerp, pnt3_permute, vec3_coordinate_system, vec3_cross_nrm, vec3_cross_vec3, vec3_max_component,
     vec3_max_dimension, vec3_permute,
 };
 use core::geometry::{Bounds3f, Normal3, Normal3f, Point2f, Point3f, Ray, Vector2f, Vector3f};
 use core::interaction::{Interaction, InteractionCommon, SurfaceInteraction};
 use core::material::Material;
-use core::pbrt::gamma;
 use core::pbrt::Float;
+use core::pbrt::{clamp_t, gamma, lerp};
+use core::profiler::{Prof, ProfilePhase};
 use core::sampling::uniform_sample_triangle;
 use core::shape::Shape;
+use core::stats::{StatMemoryCounter, StatPercent, StatRatio};
 use core::transform::Transform;
 
 // see triangle.h
 
+static TRIANGLE_MESH_BYTES: StatMemoryCounter = StatMemoryCounter::new("Memory/Triangle meshes");
+static TRIANGLES_PER_MESH: StatRatio = StatRatio::new("Scene/Triangles per triangle mesh");
+static RAY_TRIANGLE_TESTS: StatPercent =
+    StatPercent::new("Intersections/Ray-triangle intersection tests");
+
 #[derive(Debug, Clone)]
 pub struct TriangleMesh {
     /// the total number of triangles in the mesh
@@ -34,18 +41,26 @@ pub struct TriangleMesh {
     pub s: Vec<Vector3f>,
     /// an optional vector of paramtric (u, v) values (texture coordinates)
     pub uv: Vec<Point2f>,
+    /// optional vertex positions at *time1* for deformation motion
+    /// blur (empty for a static mesh)
+    pub p1: Vec<Point3f>,
+    /// optional normal vectors at *time1* (can be empty)
+    pub n1: Vec<Normal3f>,
+    /// the times *p* and *p1* belong to
+    pub time0: Float,
+    pub time1: Float,
     // TODO: std::shared_ptr<Texture<Float>> alphaMask, shadowAlphaMask;
     // inherited from class Shape (see shape.h)
-    pub object_to_world: Transform, // TODO: not pub?
-    pub world_to_object: Transform, // TODO: not pub?
+    pub object_to_world: Arc<Transform>, // TODO: not pub?
+    pub world_to_object: Arc<Transform>, // TODO: not pub?
     pub reverse_orientation: bool,
     pub transform_swaps_handedness: bool, // TODO: not pub?
 }
 
 impl TriangleMesh {
     pub fn new(
-        object_to_world: Transform,
-        world_to_object: Transform,
+        object_to_world: Arc<Transform>,
+        world_to_object: Arc<Transform>,
         reverse_orientation: bool,
         transform_swaps_handedness: bool,
         n_triangles: usize,
@@ -56,6 +71,15 @@ impl TriangleMesh {
         n: Vec<Normal3f>,
         uv: Vec<Point2f>,
     ) -> Self {
+        TRIANGLES_PER_MESH.add(n_triangles as i64, 1);
+        TRIANGLE_MESH_BYTES.add(
+            mem::size_of::<TriangleMesh>()
+                + vertex_indices.len() * mem::size_of::<usize>()
+                + p.len() * mem::size_of::<Point3f>()
+                + n.len() * mem::size_of::<Normal3f>()
+                + s.len() * mem::size_of::<Vector3f>()
+                + uv.len() * mem::size_of::<Point2f>(),
+        );
         TriangleMesh {
             // Shape
             object_to_world: object_to_world,
@@ -70,6 +94,37 @@ impl TriangleMesh {
             n: n,
             s: s,
             uv: uv,
+            p1: Vec::new(),
+            n1: Vec::new(),
+            time0: 0.0 as Float,
+            time1: 1.0 as Float,
+        }
+    }
+    /// Turns a static mesh into a deforming one: vertex positions
+    /// (and optionally normals) are given for **time0** (*p* and *n*)
+    /// and **time1** (*p1* and *n1*) and get interpolated linearly at
+    /// the time of each ray.
+    pub fn set_motion(&mut self, p1: Vec<Point3f>, n1: Vec<Normal3f>, time0: Float, time1: Float) {
+        assert!(p1.len() == self.p.len());
+        assert!(n1.is_empty() || n1.len() == self.n.len());
+        TRIANGLE_MESH_BYTES.add(
+            p1.len() * mem::size_of::<Point3f>() + n1.len() * mem::size_of::<Normal3f>(),
+        );
+        self.p1 = p1;
+        self.n1 = n1;
+        self.time0 = time0;
+        self.time1 = time1;
+    }
+    pub fn is_deforming(&self) -> bool {
+        !self.p1.is_empty()
+    }
+    /// Maps **time** to an interpolation parameter between *time0*
+    /// (0.0) and *time1* (1.0).
+    fn motion_t(&self, time: Float) -> Float {
+        if self.time1 > self.time0 {
+            clamp_t((time - self.time0) / (self.time1 - self.time0), 0.0, 1.0)
+        } else {
+            0.0 as Float
         }
     }
 }
@@ -79,8 +134,8 @@ pub struct Triangle {
     mesh: Arc<TriangleMesh>,
     pub id: usize,
     // inherited from class Shape (see shape.h)
-    object_to_world: Transform,
-    world_to_object: Transform,
+    object_to_world: Arc<Transform>,
+    world_to_object: Arc<Transform>,
     reverse_orientation: bool,
     transform_swaps_handedness: bool,
     pub material: Option<Arc<Material + Send + Sync>>,
@@ -88,8 +143,8 @@ pub struct Triangle {
 
 impl Triangle {
     pub fn new(
-        object_to_world: Transform,
-        world_to_object: Transform,
+        object_to_world: Arc<Transform>,
+        world_to_object: Arc<Transform>,
         reverse_orientation: bool,
         mesh: Arc<TriangleMesh>,
         tri_number: usize,
@@ -119,6 +174,78 @@ impl Triangle {
             ]
         }
     }
+    /// Returns the triangle's vertex positions at **time** (which only
+    /// matters for deforming meshes).
+    pub fn get_positions(&self, time: Float) -> [Point3f; 3] {
+        let i0: usize = self.mesh.vertex_indices[self.id * 3 + 0];
+        let i1: usize = self.mesh.vertex_indices[self.id * 3 + 1];
+        let i2: usize = self.mesh.vertex_indices[self.id * 3 + 2];
+        if self.mesh.is_deforming() {
+            let dt: Float = self.mesh.motion_t(time);
+            [
+                pnt3_lerp(dt, &self.mesh.p[i0], &self.mesh.p1[i0]),
+                pnt3_lerp(dt, &self.mesh.p[i1], &self.mesh.p1[i1]),
+                pnt3_lerp(dt, &self.mesh.p[i2], &self.mesh.p1[i2]),
+            ]
+        } else {
+            [self.mesh.p[i0], self.mesh.p[i1], self.mesh.p[i2]]
+        }
+    }
+    /// Returns the triangle's (unnormalized) vertex normals at
+    /// **time**. The mesh has to provide normals.
+    fn get_normals(&self, time: Float) -> [Normal3f; 3] {
+        let i0: usize = self.mesh.vertex_indices[self.id * 3 + 0];
+        let i1: usize = self.mesh.vertex_indices[self.id * 3 + 1];
+        let i2: usize = self.mesh.vertex_indices[self.id * 3 + 2];
+        if self.mesh.n1.is_empty() {
+            [self.mesh.n[i0], self.mesh.n[i1], self.mesh.n[i2]]
+        } else {
+            let dt: Float = self.mesh.motion_t(time);
+            [
+                lerp(dt, self.mesh.n[i0], self.mesh.n1[i0]),
+                lerp(dt, self.mesh.n[i1], self.mesh.n1[i1]),
+                lerp(dt, self.mesh.n[i2], self.mesh.n1[i2]),
+            ]
+        }
+    }
+    /// Samples a point uniformly by area on the triangle as it is at
+    /// **time**.
+    fn sample_at(&self, u: &Point2f, time: Float, pdf: &mut Float) -> InteractionCommon {
+        let b: Point2f = uniform_sample_triangle(u);
+        // get triangle vertices in _p0_, _p1_, and _p2_
+        let [p0, p1, p2] = self.get_positions(time);
+        let mut it: InteractionCommon = InteractionCommon::default();
+        it.p = p0 * b[0] + p1 * b[1] + p2 * (1.0 as Float - b[0] - b[1]);
+        it.time = time;
+        // compute surface normal for sampled point on triangle
+        it.n = Normal3f::from(vec3_cross_vec3(&(p1 - p0), &(p2 - p0))).normalize();
+        // ensure correct orientation of the geometric normal; follow
+        // the same approach as was used in Triangle::Intersect().
+        if !self.mesh.n.is_empty() {
+            let [n0, n1, n2] = self.get_normals(time);
+            let ns: Normal3f =
+                Normal3f::from(n0 * b[0] + n1 * b[1] + n2 * (1.0 as Float - b[0] - b[1]));
+            it.n = nrm_faceforward_nrm(&it.n, &ns);
+        } else if self.reverse_orientation ^ self.transform_swaps_handedness {
+            it.n *= -1.0 as Float;
+        }
+        // compute error bounds for sampled point on triangle
+        let p_abs_sum: Point3f = pnt3_abs(&(p0 * b[0]))
+            + pnt3_abs(&(p1 * b[1]))
+            + pnt3_abs(&(p2 * (1.0 as Float - b[0] - b[1])));
+        it.p_error = Vector3f {
+            x: p_abs_sum.x,
+            y: p_abs_sum.y,
+            z: p_abs_sum.z,
+        } * gamma(6);
+        *pdf = 1.0 as Float / self.area_at(time);
+        it
+    }
+    /// Returns the surface area of the triangle at **time**.
+    fn area_at(&self, time: Float) -> Float {
+        let [p0, p1, p2] = self.get_positions(time);
+        0.5 as Float * vec3_cross_vec3(&(p1 - p0), &(p2 - p0)).length()
+    }
 }
 
 impl Shape for Triangle {
@@ -126,25 +253,42 @@ impl Shape for Triangle {
         let p0: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 0]];
         let p1: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 1]];
         let p2: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 2]];
-        bnd3_union_pnt3(
+        let mut bounds: Bounds3f = bnd3_union_pnt3(
             &Bounds3f::new(
                 self.world_to_object.transform_point(&p0),
                 self.world_to_object.transform_point(&p1),
             ),
             &self.world_to_object.transform_point(&p2),
-        )
+        );
+        if self.mesh.is_deforming() {
+            // vertices move linearly, so the bounds at both ends of
+            // the motion cover the triangle at any time in between
+            let [p0, p1, p2] = self.get_positions(self.mesh.time1);
+            bounds = bnd3_union_pnt3(&bounds, &self.world_to_object.transform_point(&p0));
+            bounds = bnd3_union_pnt3(&bounds, &self.world_to_object.transform_point(&p1));
+            bounds = bnd3_union_pnt3(&bounds, &self.world_to_object.transform_point(&p2));
+        }
+        bounds
     }
     fn world_bound(&self) -> Bounds3f {
         let p0: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 0]];
         let p1: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 1]];
         let p2: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 2]];
-        bnd3_union_pnt3(&Bounds3f::new(p0, p1), &p2)
+        let mut bounds: Bounds3f = bnd3_union_pnt3(&Bounds3f::new(p0, p1), &p2);
+        if self.mesh.is_deforming() {
+            // see object_bound()
+            let [p0, p1, p2] = self.get_positions(self.mesh.time1);
+            bounds = bnd3_union_pnt3(&bounds, &p0);
+            bounds = bnd3_union_pnt3(&bounds, &p1);
+            bounds = bnd3_union_pnt3(&bounds, &p2);
+        }
+        bounds
     }
     fn intersect(&self, ray: &Ray) -> Option<(SurfaceInteraction, Float)> {
-        // get triangle vertices in _p0_, _p1_, and _p2_
-        let p0: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 0]];
-        let p1: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 1]];
-        let p2: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 2]];
+        let _p = ProfilePhase::new(Prof::TriIntersect);
+        RAY_TRIANGLE_TESTS.add(0, 1);
+        // get triangle vertices in _p0_, _p1_, and _p2_ (at the ray's time)
+        let [p0, p1, p2] = self.get_positions(ray.time);
         // translate vertices based on ray origin
         let mut p0t: Point3f = p0 - Vector3f {
             x: ray.o.x,
@@ -341,9 +485,7 @@ impl Shape for Triangle {
             // compute shading normal _ns_ for triangle
             let mut ns: Normal3f;
             if !self.mesh.n.is_empty() {
-                let n0 = self.mesh.n[self.mesh.vertex_indices[self.id * 3 + 0]];
-                let n1 = self.mesh.n[self.mesh.vertex_indices[self.id * 3 + 1]];
-                let n2 = self.mesh.n[self.mesh.vertex_indices[self.id * 3 + 2]];
+                let [n0, n1, n2] = self.get_normals(ray.time);
                 ns = Normal3::from(n0) * b0 + Normal3::from(n1) * b1 + Normal3::from(n2) * b2;
                 if ns.length_squared() > 0.0 {
                     ns = ns.normalize();
@@ -383,12 +525,9 @@ impl Shape for Triangle {
                 // compute deltas for triangle partial derivatives of normal
                 let duv02: Vector2f = uv[0] - uv[2];
                 let duv12: Vector2f = uv[1] - uv[2];
-                let dn1: Normal3f =
-                    Normal3::from(self.mesh.n[self.mesh.vertex_indices[self.id * 3 + 0]])
-                        - Normal3::from(self.mesh.n[self.mesh.vertex_indices[self.id * 3 + 2]]);
-                let dn2: Normal3f =
-                    Normal3::from(self.mesh.n[self.mesh.vertex_indices[self.id * 3 + 1]])
-                        - Normal3::from(self.mesh.n[self.mesh.vertex_indices[self.id * 3 + 2]]);
+                let [n0, n1, n2] = self.get_normals(ray.time);
+                let dn1: Normal3f = n0 - n2;
+                let dn2: Normal3f = n1 - n2;
                 let determinant: Float = duv02.x * duv12.y - duv02.y * duv12.x;
                 let degenerate_uv: bool = determinant.abs() < 1e-8;
                 if degenerate_uv {
@@ -412,15 +551,14 @@ impl Shape for Triangle {
             si.shading.n = -si.n;
             si.n = -si.n;
         }
+        RAY_TRIANGLE_TESTS.add(1, 0);
         Some((si, t as Float))
     }
     fn intersect_p(&self, ray: &Ray) -> bool {
-        // TODO: ProfilePhase p(Prof::TriIntersectP);
-        // TODO: ++nTests;
-        // get triangle vertices in _p0_, _p1_, and _p2_
-        let p0: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 0]];
-        let p1: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 1]];
-        let p2: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 2]];
+        let _p = ProfilePhase::new(Prof::TriIntersectP);
+        RAY_TRIANGLE_TESTS.add(0, 1);
+        // get triangle vertices in _p0_, _p1_, and _p2_ (at the ray's time)
+        let [p0, p1, p2] = self.get_positions(ray.time);
         // translate vertices based on ray origin
         let mut p0t: Point3f = p0 - Vector3f {
             x: ray.o.x,
@@ -548,7 +686,7 @@ impl Shape for Triangle {
             return false;
         }
         // TODO: if (testAlphaTexture && (mesh->alphaMask || mesh->shadowAlphaMask)) { ... }
-        // TODO: ++nHits;
+        RAY_TRIANGLE_TESTS.add(1, 0);
         true
     }
     fn get_reverse_orientation(&self) -> bool {
@@ -558,46 +696,10 @@ impl Shape for Triangle {
         self.transform_swaps_handedness
     }
     fn area(&self) -> Float {
-        // get triangle vertices in _p0_, _p1_, and _p2_
-        let p0: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 0]];
-        let p1: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 1]];
-        let p2: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 2]];
-        0.5 as Float * vec3_cross_vec3(&(p1 - p0), &(p2 - p0)).length()
+        self.area_at(self.mesh.time0)
     }
     fn sample(&self, u: &Point2f, pdf: &mut Float) -> InteractionCommon {
-        let b: Point2f = uniform_sample_triangle(u);
-        // get triangle vertices in _p0_, _p1_, and _p2_
-        let p0: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 0]];
-        let p1: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 1]];
-        let p2: Point3f = self.mesh.p[self.mesh.vertex_indices[self.id * 3 + 2]];
-        let mut it: InteractionCommon = InteractionCommon::default();
-        it.p = p0 * b[0] + p1 * b[1] + p2 * (1.0 as Float - b[0] - b[1]);
-        // compute surface normal for sampled point on triangle
-        it.n = Normal3f::from(vec3_cross_vec3(&(p1 - p0), &(p2 - p0))).normalize();
-        // ensure correct orientation of the geometric normal; follow
-        // the same approach as was used in Triangle::Intersect().
-        if !self.mesh.n.is_empty() {
-            let ns: Normal3f = Normal3f::from(
-                self.mesh.n[self.mesh.vertex_indices[self.id * 3 + 0]] * b[0]
-                    + self.mesh.n[self.mesh.vertex_indices[self.id * 3 + 1]] * b[1]
-                    + self.mesh.n[self.mesh.vertex_indices[self.id * 3 + 2]]
-                        * (1.0 as Float - b[0] - b[1]),
-            );
-            it.n = nrm_faceforward_nrm(&it.n, &ns);
-        } else if self.reverse_orientation ^ self.transform_swaps_handedness {
-            it.n *= -1.0 as Float;
-        }
-        // compute error bounds for sampled point on triangle
-        let p_abs_sum: Point3f = pnt3_abs(&(p0 * b[0]))
-            + pnt3_abs(&(p1 * b[1]))
-            + pnt3_abs(&(p2 * (1.0 as Float - b[0] - b[1])));
-        it.p_error = Vector3f {
-            x: p_abs_sum.x,
-            y: p_abs_sum.y,
-            z: p_abs_sum.z,
-        } * gamma(6);
-        *pdf = 1.0 as Float / self.area();
-        it
+        self.sample_at(u, self.mesh.time0, pdf)
     }
     fn sample_with_ref_point(
         &self,
@@ -605,7 +707,7 @@ impl Shape for Triangle {
         u: &Point2f,
         pdf: &mut Float,
     ) -> InteractionCommon {
-        let intr: InteractionCommon = self.sample(u, pdf);
+        let intr: InteractionCommon = self.sample_at(u, iref.time, pdf);
         let mut wi: Vector3f = intr.p - iref.p;
         if wi.length_squared() == 0.0 as Float {
             *pdf = 0.0 as Float;
@@ -629,7 +731,7 @@ impl Shape for Triangle {
         if let Some((isect_light, _t_hit)) = self.intersect(&ray) {
             // convert light sample weight to solid angle measure
             let mut pdf: Float = pnt3_distance_squared(&iref.get_p(), &isect_light.p)
-                / (nrm_abs_dot_vec3(&isect_light.n, &-(*wi)) * self.area());
+                / (nrm_abs_dot_vec3(&isect_light.n, &-(*wi)) * self.area_at(ray.time));
             if pdf.is_infinite() {
                 pdf = 0.0 as Float;
             }
@@ -51,6 +51,29 @@ where
             panic!("Error reading \"{}\"", filename);
         }
         let buf = img_result.unwrap();
+        ImageTexture::from_image(
+            mapping,
+            &buf,
+            do_trilinear,
+            max_aniso,
+            wrap_mode,
+            scale,
+            gamma,
+            convert,
+        )
+    }
+    /// Like **new()**, but for an image which was already decoded
+    /// (e.g. from memory).
+    pub fn from_image<F: Fn(&Spectrum) -> T>(
+        mapping: Box<TextureMapping2D + Send + Sync>,
+        buf: &DynamicImage,
+        do_trilinear: bool,
+        max_aniso: Float,
+        wrap_mode: ImageWrap,
+        scale: Float,
+        gamma: bool,
+        convert: F,
+    ) -> ImageTexture<T> {
         let rgb = buf.to_rgb();
         let res = Point2i {
             x: rgb.width() as i32,
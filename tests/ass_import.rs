@@ -0,0 +1,56 @@
+extern crate pbrt;
+
+// std
+use std::fs;
+use std::path::PathBuf;
+// pbrt
+use pbrt::core::api::{pbrt_init, ApiError, ErrorMode};
+use pbrt::parser::ass::parse_file;
+
+fn ass_assets() -> Vec<PathBuf> {
+    let mut directory: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    directory.push("assets");
+    directory.push("ass");
+    let mut paths: Vec<PathBuf> = fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "ass"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// The Arnold scenes shipped in `assets/ass` may only use nodes which
+/// are either imported or known to be unsupported. In strict mode the
+/// first unsupported node stops the import, otherwise it has to
+/// succeed.
+#[test]
+fn parse_assets_strict() {
+    let paths: Vec<PathBuf> = ass_assets();
+    assert!(!paths.is_empty());
+    for path in paths {
+        let (mut api_state, mut bsdf_state) = pbrt_init(0_u8);
+        if let Err(e) = parse_file(&path, &mut api_state, &mut bsdf_state) {
+            panic!("{}", e);
+        }
+        let unsupported: Vec<String> = api_state
+            .warnings()
+            .iter()
+            .map(|warning| match *warning {
+                ApiError::Unsupported { .. } => warning.to_string(),
+                _ => panic!("{}: {}", path.display(), warning),
+            })
+            .collect();
+        let (mut api_state, mut bsdf_state) = pbrt_init(0_u8);
+        api_state.error_mode = ErrorMode::Strict;
+        match parse_file(&path, &mut api_state, &mut bsdf_state) {
+            Ok(()) => assert!(unsupported.is_empty()),
+            Err(e) => {
+                let message: String = e.to_string();
+                if unsupported.is_empty() || !message.ends_with(&unsupported[0]) {
+                    panic!("{}", message);
+                }
+            }
+        }
+    }
+}
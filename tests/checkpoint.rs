@@ -0,0 +1,208 @@
+extern crate pbrt;
+
+// std
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+// pbrt
+use pbrt::core::aov::AovType;
+use pbrt::core::api::{pbrt_cleanup, pbrt_init};
+use pbrt::core::checkpoint::Checkpoint;
+use pbrt::core::film::{Film, FilmTile};
+use pbrt::core::filter::Filter;
+use pbrt::core::geometry::{Bounds2f, Bounds2i, Point2f, Point2i, Vector2i};
+use pbrt::core::paramset::ParamSet;
+use pbrt::core::pbrt::{Float, Spectrum};
+use pbrt::filters::boxfilter::BoxFilter;
+use pbrt::parser::parse_str;
+
+/// 3 x 2 tiles of 16 x 16 pixels, the box filter keeps the pixels of
+/// each tile apart.
+const SCENE: &str = r#"
+LookAt 0 0 5  0 0 0  0 1 0
+Camera "perspective" "float fov" [ 45 ]
+Film "image" "string filename" [ "IMAGE" ]
+  "integer xresolution" [ 40 ] "integer yresolution" [ 24 ]
+PixelFilter "box"
+Sampler "random" "integer pixelsamples" [ 4 ]
+Integrator "directlighting"
+WorldBegin
+  LightSource "point" "point from" [ 0 2 4 ] "rgb I" [ 20 20 20 ]
+  Material "matte" "rgb Kd" [ 0.5 0.5 0.5 ]
+  Shape "sphere" "float radius" [ 1 ]
+WorldEnd
+"#;
+
+/// Creates an empty film like the one of **SCENE**.
+fn scene_film(image: &Path, aovs: Vec<AovType>) -> Film {
+    let filter: Arc<Filter + Sync + Send> = BoxFilter::create(&ParamSet::default());
+    Film::new(
+        Point2i { x: 40, y: 24 },
+        Bounds2f {
+            p_min: Point2f { x: 0.0, y: 0.0 },
+            p_max: Point2f { x: 1.0, y: 1.0 },
+        },
+        filter,
+        35.0,
+        image.to_string_lossy().into_owned(),
+        8_u8,
+        1.0,
+        std::f32::INFINITY,
+        aovs,
+    )
+}
+
+fn n_tiles(film: &Film) -> Point2i {
+    let extent: Vector2i = film.get_sample_bounds().diagonal();
+    Point2i {
+        x: (extent.x + 15) / 16,
+        y: (extent.y + 15) / 16,
+    }
+}
+
+fn render(image: &Path, interval: Option<Duration>, resume: bool) {
+    let (mut api_state, mut bsdf_state) = pbrt_init(2_u8);
+    api_state.checkpoint_interval = interval;
+    api_state.resume = resume;
+    let source: String = SCENE.replace("IMAGE", &image.to_string_lossy());
+    if let Err(e) = parse_str(&source, &mut api_state, &mut bsdf_state) {
+        panic!("{}", e);
+    }
+    pbrt_cleanup(&mut api_state).unwrap();
+}
+
+/// Reads the floats of a PFM image (as written by **write_image()**).
+fn read_pfm(image: &Path) -> Vec<f32> {
+    let data: Vec<u8> = fs::read(image).unwrap();
+    // the header has three lines
+    let start: usize = data
+        .iter()
+        .enumerate()
+        .filter(|&(_, b)| *b == b'\n')
+        .nth(2)
+        .unwrap()
+        .0
+        + 1;
+    data[start..]
+        .chunks(4)
+        .map(|c| {
+            f32::from_bits(
+                u32::from(c[0])
+                    | u32::from(c[1]) << 8
+                    | u32::from(c[2]) << 16
+                    | u32::from(c[3]) << 24,
+            )
+        })
+        .collect()
+}
+
+fn checkpoint_bytes(film: &Film) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    film.write_checkpoint(&mut bytes).unwrap();
+    bytes
+}
+
+/// A render which gets interrupted and resumed from its checkpoint
+/// ends up with the same image as an uninterrupted render.
+#[test]
+fn resume_interrupted_render() {
+    let directory: PathBuf = env::temp_dir().join("pbrt_checkpoint_resume");
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    let full: PathBuf = directory.join("full.pfm");
+    render(&full, None, false);
+    // start from a checkpoint which claims the upper row of tiles to
+    // be finished, without their pixels ...
+    let image: PathBuf = directory.join("resumed.pfm");
+    let film: Film = scene_film(&image, Vec::new());
+    let tiles: Point2i = n_tiles(&film);
+    assert_eq!(tiles, Point2i { x: 3, y: 2 });
+    let upper: Vec<(u32, u32)> = (0..3).map(|x| (x, 0)).collect();
+    let lower: Vec<(u32, u32)> = (0..3).map(|x| (x, 1)).collect();
+    let checkpoint: Checkpoint = Checkpoint::new(&image.to_string_lossy(), None, true);
+    checkpoint.write(&film, &tiles, 4, &upper);
+    // ... and render the lower row, with a checkpoint after each tile,
+    // but fail to write the image, which keeps the checkpoint
+    fs::create_dir(&image).unwrap();
+    render(&image, Some(Duration::from_secs(0)), true);
+    assert!(Path::new(&checkpoint.filename).is_file());
+    fs::remove_dir(&image).unwrap();
+    // remove the upper row from the finished tiles, so the checkpoint
+    // holds exactly the lower row of an interrupted render
+    let film: Film = scene_film(&image, Vec::new());
+    let mut finished: Vec<(u32, u32)> = checkpoint.resume(&film, &tiles, 4);
+    finished.sort();
+    let mut all: Vec<(u32, u32)> = upper.iter().chain(lower.iter()).cloned().collect();
+    all.sort();
+    assert_eq!(finished, all);
+    checkpoint.write(&film, &tiles, 4, &lower);
+    // resume
+    render(&image, Some(Duration::from_secs(0)), true);
+    assert!(!Path::new(&checkpoint.filename).exists());
+    let expected: Vec<f32> = read_pfm(&full);
+    let resumed: Vec<f32> = read_pfm(&image);
+    assert_eq!(expected.len(), 3 * 40 * 24);
+    assert_eq!(resumed.len(), expected.len());
+    let sum: f32 = expected.iter().sum();
+    assert!(sum > 0.0);
+    for (e, r) in expected.iter().zip(resumed.iter()) {
+        assert!((e - r).abs() <= 1e-5 * e.abs().max(1.0), "{} != {}", e, r);
+    }
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+/// Reading a checkpoint restores the film written to it.
+#[test]
+fn read_written_checkpoint() {
+    let directory: PathBuf = env::temp_dir().join("pbrt_checkpoint_read");
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    let image: PathBuf = directory.join("film.pfm");
+    let film: Film = scene_film(&image, vec![AovType::Depth]);
+    let sample_bounds: Bounds2i = film.get_sample_bounds();
+    let mut tile: FilmTile = film.get_film_tile(&sample_bounds);
+    for y in 0..24 {
+        for x in 0..40 {
+            let p_film: Point2f = Point2f {
+                x: x as Float + 0.5,
+                y: y as Float + 0.5,
+            };
+            let mut l: Spectrum = Spectrum::new((x + 40 * y) as Float / 960.0);
+            tile.add_sample(&p_film, &mut l, 1.0);
+        }
+    }
+    film.merge_film_tile(&tile);
+    let written: Vec<u8> = checkpoint_bytes(&film);
+    let checkpoint: Checkpoint = Checkpoint::new(&image.to_string_lossy(), None, true);
+    let tiles: Point2i = n_tiles(&film);
+    let finished: Vec<(u32, u32)> = vec![(2, 0), (0, 1)];
+    checkpoint.write(&film, &tiles, 4, &finished);
+    // a different film doesn't get changed
+    let smaller: Film = Film::new(
+        Point2i { x: 20, y: 24 },
+        Bounds2f {
+            p_min: Point2f { x: 0.0, y: 0.0 },
+            p_max: Point2f { x: 1.0, y: 1.0 },
+        },
+        BoxFilter::create(&ParamSet::default()),
+        35.0,
+        image.to_string_lossy().into_owned(),
+        8_u8,
+        1.0,
+        std::f32::INFINITY,
+        vec![AovType::Depth],
+    );
+    let before: Vec<u8> = checkpoint_bytes(&smaller);
+    assert!(smaller.read_checkpoint(&mut &written[..]).is_err());
+    assert_eq!(checkpoint_bytes(&smaller), before);
+    // the same film gets restored
+    let restored: Film = scene_film(&image, vec![AovType::Depth]);
+    assert_eq!(checkpoint.resume(&restored, &tiles, 4), finished);
+    assert_eq!(checkpoint_bytes(&restored), written);
+    // the number of samples has to match
+    let other: Film = scene_film(&image, vec![AovType::Depth]);
+    assert!(checkpoint.resume(&other, &tiles, 8).is_empty());
+    fs::remove_dir_all(&directory).unwrap();
+}
@@ -0,0 +1,61 @@
+extern crate pbrt;
+
+// std
+use std::path::PathBuf;
+// pbrt
+use pbrt::core::api::RenderSetup;
+use pbrt::core::geometry::Bounds3f;
+use pbrt::core::pbrt::Spectrum;
+use pbrt::core::scenebuilder::{MaterialKind, SceneBuilder, ShapeKind};
+use pbrt::core::transform::Transform;
+
+fn matte() -> MaterialKind {
+    MaterialKind::Matte {
+        kd: Spectrum::new(0.5),
+        sigma: 0.0,
+    }
+}
+
+fn assert_not_empty(bounds: &Bounds3f) {
+    assert!(bounds.p_min.x < bounds.p_max.x);
+    assert!(bounds.p_min.y < bounds.p_max.y);
+    assert!(bounds.p_min.z < bounds.p_max.z);
+}
+
+/// PLY files are found relative to the current directory (which is
+/// the crate's directory for tests).
+#[test]
+fn ply_mesh_relative_to_current_dir() {
+    let mut builder: SceneBuilder = SceneBuilder::new(1_u8);
+    builder
+        .shape(
+            &Transform::default(),
+            ShapeKind::PlyMesh {
+                filename: String::from("assets/scenes/veach-mis/geometry/floor.ply"),
+            },
+            matte(),
+        )
+        .unwrap();
+    let render_setup: RenderSetup = builder.build().unwrap();
+    assert_not_empty(&render_setup.scene.world_bound());
+}
+
+/// ... or relative to the search directory, if one is given.
+#[test]
+fn ply_mesh_in_search_directory() {
+    let mut directory: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    directory.push("assets/scenes/veach-mis/geometry");
+    let mut builder: SceneBuilder = SceneBuilder::new(1_u8);
+    builder.search_directory(&directory);
+    builder
+        .shape(
+            &Transform::default(),
+            ShapeKind::PlyMesh {
+                filename: String::from("plate1.ply"),
+            },
+            matte(),
+        )
+        .unwrap();
+    let render_setup: RenderSetup = builder.build().unwrap();
+    assert_not_empty(&render_setup.scene.world_bound());
+}
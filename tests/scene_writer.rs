@@ -0,0 +1,140 @@
+extern crate pbrt;
+
+// std
+use std::env;
+use std::fs::{self, File};
+use std::path::PathBuf;
+// pbrt
+use pbrt::core::api::{pbrt_init, ErrorMode};
+use pbrt::core::geometry::{Point3f, Vector3f};
+use pbrt::core::pbrt::Spectrum;
+use pbrt::core::scenebuilder::{CameraKind, FilmSettings, MaterialKind};
+use pbrt::core::scenebuilder::{SceneBuilder, ShapeKind};
+use pbrt::core::scenewriter::SceneWriter;
+use pbrt::core::transform::Transform;
+use pbrt::parser::parse_str;
+
+const SCENE: &str = r#"
+LookAt 0 0 5  0 0 0  0 1 0
+Camera "perspective" "float fov" [ 45 ]
+Film "image" "integer xresolution" [ 32 ] "integer yresolution" [ 32 ]
+WorldBegin
+  LightSource "point" "blackbody I" [ 6500 2 ]
+  Texture "checks" "spectrum" "checkerboard" "float uscale" [ 4 ]
+  MakeNamedMaterial "red"
+    "string type" [ "matte" ]
+    "spectrum Kd" [ 400 0.1 500 0.2 700 0.8 ]
+  AttributeBegin
+    Translate 1 0 0
+    NamedMaterial "red"
+    Shape "sphere" "float radius" [ 0.5 ]
+  AttributeEnd
+  ObjectBegin "ball"
+    Material "matte" "texture Kd" "checks"
+    Shape "sphere" "float radius" [ 0.25 ]
+  ObjectEnd
+  AttributeBegin
+    Translate -1 0 0
+    ObjectInstance "ball"
+  AttributeEnd
+WorldEnd
+"#;
+
+/// Parses **source** with the scene being recorded and returns the
+/// resolved scene as written by the **SceneWriter**.
+fn write_resolved(source: &str, file_name: &str) -> String {
+    let (mut api_state, mut bsdf_state) = pbrt_init(1_u8);
+    api_state.error_mode = ErrorMode::Strict;
+    api_state.scene_record = Some(Vec::new());
+    if let Err(e) = parse_str(source, &mut api_state, &mut bsdf_state) {
+        panic!("{}", e);
+    }
+    let path: PathBuf = env::temp_dir().join(file_name);
+    {
+        let out: File = File::create(&path).unwrap();
+        let mut writer: SceneWriter = SceneWriter::new(Box::new(out), None);
+        writer.write_state(&api_state).unwrap();
+    }
+    let resolved: String = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    resolved
+}
+
+/// Sampled and blackbody spectra keep their values, named materials
+/// get resolved for each shape, and writing the resolved scene again
+/// doesn't change it.
+#[test]
+fn write_resolved_state() {
+    let resolved: String = write_resolved(SCENE, "pbrt_scene_writer_1.pbrt");
+    assert!(resolved.contains("\"blackbody I\" [ 6500 2 ]"));
+    assert!(resolved.contains("\"spectrum Kd\" [ 400 0.1 500 0.2 700 0.8 ]"));
+    assert!(resolved.contains("Material \"matte\"\n"));
+    assert!(resolved.contains("ObjectInstance \"ball\""));
+    assert!(!resolved
+        .lines()
+        .any(|line| line.trim_start().starts_with("NamedMaterial")));
+    let rewritten: String = write_resolved(&resolved, "pbrt_scene_writer_2.pbrt");
+    assert_eq!(resolved, rewritten);
+}
+
+/// Scenes built by a **SceneBuilder** have no scene file to echo,
+/// but can be written as well.
+#[test]
+fn write_builder_scene() {
+    let mut builder: SceneBuilder = SceneBuilder::new(1_u8);
+    builder.record_scene();
+    let world_to_camera: Transform = Transform::look_at(
+        &Point3f {
+            x: 0.0,
+            y: 0.0,
+            z: 5.0,
+        },
+        &Point3f {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        &Vector3f {
+            x: 0.0,
+            y: 1.0,
+            z: 0.0,
+        },
+    );
+    builder
+        .camera(CameraKind::Perspective { fov: 45.0 }, &world_to_camera)
+        .unwrap();
+    builder
+        .film(FilmSettings {
+            x_resolution: 32,
+            y_resolution: 32,
+            ..Default::default()
+        })
+        .unwrap();
+    builder
+        .shape(
+            &Transform::translate(&Vector3f {
+                x: 0.0,
+                y: 1.0,
+                z: 0.0,
+            }),
+            ShapeKind::Sphere { radius: 1.0 },
+            MaterialKind::Matte {
+                kd: Spectrum::new(0.5),
+                sigma: 0.0,
+            },
+        )
+        .unwrap();
+    let path: PathBuf = env::temp_dir().join("pbrt_scene_writer_3.pbrt");
+    {
+        let out: File = File::create(&path).unwrap();
+        let mut writer: SceneWriter = SceneWriter::new(Box::new(out), None);
+        builder.write_scene(&mut writer).unwrap();
+    }
+    let resolved: String = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert!(resolved.contains("Camera \"perspective\""));
+    assert!(resolved.contains("\"integer xresolution\" [ 32 ]"));
+    assert!(resolved.contains("Shape \"sphere\""));
+    let rewritten: String = write_resolved(&resolved, "pbrt_scene_writer_4.pbrt");
+    assert_eq!(resolved, rewritten);
+}